@@ -0,0 +1,328 @@
+//! A stable library API, for embedding versio in other Rust tools without shelling out to the executable.
+//!
+//! Open a monorepo with the [`Versio`] builder, and then inspect its projects, plan a release, or perform one:
+//!
+//! ```no_run
+//! use versio::api::Versio;
+//! use versio::commands::Engagement;
+//! use versio::vcs::VcsLevel;
+//!
+//! # async fn example() -> versio::errors::Result<()> {
+//! let mut versio = Versio::open(".").vcs(VcsLevel::Smart).build()?;
+//! for proj in versio.plan().await? {
+//!   println!("{} needs a {} increment", proj.name(), proj.size());
+//! }
+//! versio.release(Engagement::Dry).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The types returned here are plain data, disconnected from the monorepo that produced them.
+
+use crate::commands::Engagement;
+pub use crate::config::{ProjectId, Size};
+use crate::errors::Result;
+use crate::events::Events;
+pub use crate::mono::Released;
+use crate::git::Repo;
+use crate::mono::{ChangelogEntry, Mono};
+use crate::vcs::{VcsLevel, VcsState};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// An open monorepo. Use [`Versio::open`] to construct one.
+pub struct Versio {
+  mono: Mono
+}
+
+impl Versio {
+  /// Start building a `Versio` for the monorepo that contains `path`.
+  pub fn open<P: AsRef<Path>>(path: P) -> VersioBuilder { VersioBuilder::new(path.as_ref().to_path_buf()) }
+
+  /// The projects of the monorepo, with their current versions.
+  pub fn projects(&self) -> Result<Vec<ProjectInfo>> {
+    let config = self.mono.config();
+    config
+      .projects()
+      .iter()
+      .map(|p| {
+        Ok(ProjectInfo {
+          id: p.id().clone(),
+          name: p.name().to_string(),
+          root: p.root().cloned(),
+          tag_prefix: p.tag_prefix().clone(),
+          version: config.get_value(p.id())?
+        })
+      })
+      .collect()
+  }
+
+  /// Plan the next release: the increment size and changes of every project that has changed since the last
+  /// release.
+  pub async fn plan(&self) -> Result<Vec<ProjectPlan>> {
+    let plan = self.mono.build_plan(None).await?;
+    plan
+      .incrs()
+      .iter()
+      .map(|(id, (size, changelog))| {
+        let changes = changelog
+          .entries()
+          .iter()
+          .filter_map(|entry| match entry {
+            ChangelogEntry::Pr(pr, size) => Some(ChangeInfo {
+              pr_number: pr.number(),
+              pr_title: pr.title().to_string(),
+              size: *size,
+              commits: pr
+                .commits()
+                .iter()
+                .filter(|c| c.included())
+                .map(|c| CommitInfo { oid: c.oid().to_string(), summary: c.summary().to_string(), size: c.size() })
+                .collect()
+            }),
+            ChangelogEntry::Dep(..) => None
+          })
+          .collect();
+        let name = self.mono.get_project(id)?.name().to_string();
+        Ok(ProjectPlan { id: id.clone(), name, size: *size, changes })
+      })
+      .collect()
+  }
+
+  /// Release the monorepo: update the version of each changed project, and write its changelog. How much is
+  /// actually written is controlled by `engagement`, just like the `release` command's `--dry-run`,
+  /// `--changelog-only`, and `--tag-only` options.
+  pub async fn release(&mut self, engagement: Engagement) -> Result<Vec<ProjectRelease>> {
+    let mut plan = self.mono.build_plan(None).await?;
+    self.mono.bump_deps(&mut plan)?;
+
+    self.mono.check_branch()?;
+
+    if plan.incrs().is_empty() {
+      return Ok(Vec::new());
+    }
+
+    let targets = self.mono.release_targets(&plan)?;
+    let mut releases = Vec::new();
+    let mut final_sizes = HashMap::new();
+    for id in plan.incrs().keys() {
+      let name = self.mono.get_project(id)?.name().to_string();
+      let (released, changelog) = self.mono.release_project(&plan, id, &targets, false).await?;
+      final_sizes.insert(id.clone(), released.version().to_string());
+      releases.push(ProjectRelease { id: id.clone(), name, released, changelog });
+    }
+
+    self.mono.write_chains(plan.chain_writes(), &final_sizes)?;
+
+    match engagement {
+      Engagement::Full => self.mono.commit(self.mono.advances_prev(), None, &Events::none())?,
+      Engagement::Changelog => self.mono.write_changelogs()?,
+      Engagement::Tags => self.mono.tag_only(self.mono.advances_prev(), &Events::none())?,
+      Engagement::Dry => ()
+    }
+
+    Ok(releases)
+  }
+}
+
+/// Options for opening a monorepo.
+pub struct VersioBuilder {
+  path: PathBuf,
+  vcs: VcsLevel,
+  ignore_current: bool,
+  only: Vec<String>
+}
+
+impl VersioBuilder {
+  fn new(path: PathBuf) -> VersioBuilder {
+    VersioBuilder { path, vcs: VcsLevel::Smart, ignore_current: false, only: Vec::new() }
+  }
+
+  /// The highest VCS level to use: the actual level may be lower, if the repository doesn't support it. By
+  /// default, this is `VcsLevel::Smart`.
+  pub fn vcs(mut self, level: VcsLevel) -> VersioBuilder {
+    self.vcs = level;
+    self
+  }
+
+  /// Ignore uncommitted changes in the working directory.
+  pub fn ignore_current(mut self, ignore: bool) -> VersioBuilder {
+    self.ignore_current = ignore;
+    self
+  }
+
+  /// Open only the projects selected by ID, name, or label (and their dependents), like the `--only` option.
+  pub fn only<I: IntoIterator<Item = S>, S: ToString>(mut self, only: I) -> VersioBuilder {
+    self.only = only.into_iter().map(|s| s.to_string()).collect();
+    self
+  }
+
+  /// Open the monorepo. The VCS level is detected from the repository at the builder's path, not from the current
+  /// directory.
+  pub fn build(self) -> Result<Versio> {
+    let vcs = std::cmp::min(self.vcs, Repo::detect(&self.path, None)?);
+    let mono = Mono::open_only(&self.path, VcsState::new(vcs, self.ignore_current), &self.only)?;
+    Ok(Versio { mono })
+  }
+}
+
+/// A project in the monorepo.
+pub struct ProjectInfo {
+  id: ProjectId,
+  name: String,
+  root: Option<String>,
+  tag_prefix: Option<String>,
+  version: Option<String>
+}
+
+impl ProjectInfo {
+  pub fn id(&self) -> &ProjectId { &self.id }
+  pub fn name(&self) -> &str { &self.name }
+
+  /// The root directory of the project, relative to the monorepo; `None` if the project isn't rooted.
+  pub fn root(&self) -> Option<&str> { self.root.as_deref() }
+
+  /// The prefix of the project's version tags, if the project is tagged.
+  pub fn tag_prefix(&self) -> Option<&str> { self.tag_prefix.as_deref() }
+
+  /// The current version of the project, or `None` if its version can't be found.
+  pub fn version(&self) -> Option<&str> { self.version.as_deref() }
+}
+
+/// The planned release of a single project.
+pub struct ProjectPlan {
+  id: ProjectId,
+  name: String,
+  size: Size,
+  changes: Vec<ChangeInfo>
+}
+
+impl ProjectPlan {
+  pub fn id(&self) -> &ProjectId { &self.id }
+  pub fn name(&self) -> &str { &self.name }
+
+  /// The size of the increment that the project's changes call for.
+  pub fn size(&self) -> Size { self.size }
+
+  /// The PRs that changed the project. Changes to dependencies are not included.
+  pub fn changes(&self) -> &[ChangeInfo] { &self.changes }
+}
+
+/// A PR (or a pseudo-PR of commits made directly on the branch) that changed a project.
+pub struct ChangeInfo {
+  pr_number: u32,
+  pr_title: String,
+  size: Size,
+  commits: Vec<CommitInfo>
+}
+
+impl ChangeInfo {
+  /// The number of the PR, or zero for commits that weren't part of any PR.
+  pub fn pr_number(&self) -> u32 { self.pr_number }
+  pub fn pr_title(&self) -> &str { &self.pr_title }
+
+  /// The largest size of the PR's commits that changed the project.
+  pub fn size(&self) -> Size { self.size }
+
+  /// The commits of the PR that changed the project.
+  pub fn commits(&self) -> &[CommitInfo] { &self.commits }
+}
+
+/// A commit that changed a project.
+pub struct CommitInfo {
+  oid: String,
+  summary: String,
+  size: Size
+}
+
+impl CommitInfo {
+  pub fn oid(&self) -> &str { &self.oid }
+
+  /// The first line of the commit message.
+  pub fn summary(&self) -> &str { &self.summary }
+
+  /// The size of the increment that the commit calls for, by its conventional commit type.
+  pub fn size(&self) -> Size { self.size }
+}
+
+/// The release of a single project.
+pub struct ProjectRelease {
+  id: ProjectId,
+  name: String,
+  released: Released,
+  changelog: Option<PathBuf>
+}
+
+impl ProjectRelease {
+  pub fn id(&self) -> &ProjectId { &self.id }
+  pub fn name(&self) -> &str { &self.name }
+
+  /// What the release did to the project's version.
+  pub fn released(&self) -> &Released { &self.released }
+
+  /// The changelog that was written for the project, if any.
+  pub fn changelog(&self) -> Option<&Path> { self.changelog.as_deref() }
+}
+
+#[cfg(test)]
+mod test {
+  use super::{Released, Versio};
+  use crate::errors::Result;
+  use crate::vcs::VcsLevel;
+  use git2::{Repository, Signature};
+  use std::fs::{create_dir_all, write};
+  use std::path::Path;
+
+  const CONFIG: &str = r#"
+projects:
+  - name: proj
+    id: 1
+    root: "proj"
+    tag_prefix: "proj"
+    version:
+      file: "VERSION"
+"#;
+
+  #[test]
+  fn test_open_projects() {
+    let dir = std::env::temp_dir().join(format!("versio-api-{}", std::process::id()));
+    let result = (|| -> Result<_> {
+      create_dir_all(dir.join("proj"))?;
+      write(dir.join(".versio.yaml"), CONFIG)?;
+      write(dir.join("proj/VERSION"), "1.2.3\n")?;
+      let git = Repository::init(&dir)?;
+      let mut index = git.index()?;
+      index.add_path(Path::new(".versio.yaml"))?;
+      index.add_path(Path::new("proj/VERSION"))?;
+      index.write()?;
+      let tree = git.find_tree(index.write_tree()?)?;
+      let sig = Signature::now("Test", "test@example.com")?;
+      git.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])?;
+
+      let versio = Versio::open(&dir).vcs(VcsLevel::Local).build()?;
+      let projects = versio.projects()?;
+      Ok(projects.iter().map(|p| (p.id().to_string(), p.name().to_string(), p.root().map(|r| r.to_string()),
+                                  p.tag_prefix().map(|t| t.to_string()), p.version().map(|v| v.to_string())))
+                   .collect::<Vec<_>>())
+    })();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let some = |s: &str| Some(s.to_string());
+    assert_eq!(result.unwrap(), vec![("1".to_string(), "proj".to_string(), some("proj"), some("proj"), some("1.2.3"))]);
+  }
+
+  #[test]
+  fn test_released_versions() {
+    let changed = Released::Changed { prev: "1.0.0".into(), curt: "1.0.0".into(), target: "1.1.0".into() };
+    assert_eq!((changed.version(), changed.prev_version()), ("1.1.0", Some("1.0.0")));
+
+    let forward = Released::Forward { prev: "1.0.0".into(), curt: "2.0.0".into(), target: "1.1.0".into() };
+    assert_eq!((forward.version(), forward.prev_version()), ("2.0.0", Some("1.0.0")));
+
+    let new = Released::New { curt: "0.1.0".into() };
+    assert_eq!((new.version(), new.prev_version()), ("0.1.0", None));
+
+    let locked = Released::NoChange { locked: true, prev: None, curt: "0.1.0".into() };
+    assert_eq!((locked.version(), locked.prev_version()), ("0.1.0", None));
+  }
+}
@@ -0,0 +1,162 @@
+//! Native integration with CI systems: workflow annotations and step summaries for GitHub Actions, and service
+//! messages for TeamCity (which Jenkins plugins also understand), so that CI runs show Versio's results in the run
+//! page instead of only in the log.
+
+use crate::errors::Result;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// The environment variable that selects the CI integration: `github`, `teamcity`, or `none`. If it's unset, the
+/// CI system is detected from `GITHUB_ACTIONS` or `TEAMCITY_VERSION`.
+pub const CI_VAR: &str = "VERSIO_CI";
+
+/// The CI system that Versio is running in.
+pub enum Ci {
+  Github(GithubActions),
+  Teamcity
+}
+
+impl Ci {
+  /// The CI system that Versio is running in, or `None` if it isn't running in a supported one. The `choice` (from
+  /// `--ci`) overrides `CI_VAR`, which overrides the detected system.
+  pub fn detect(choice: Option<&str>) -> Option<Ci> {
+    let ci = match choice.map(|c| c.to_string()).or_else(|| std::env::var(CI_VAR).ok()) {
+      Some(ci) => ci,
+      None if std::env::var("GITHUB_ACTIONS").map(|v| v == "true").unwrap_or(false) => "github".into(),
+      None if std::env::var_os("TEAMCITY_VERSION").is_some() => "teamcity".into(),
+      None => return None
+    };
+
+    match ci.as_str() {
+      "github" => {
+        let summary = std::env::var_os("GITHUB_STEP_SUMMARY").filter(|s| !s.is_empty()).map(PathBuf::from);
+        Some(Ci::Github(GithubActions { summary }))
+      }
+      "teamcity" => Some(Ci::Teamcity),
+      _ => None
+    }
+  }
+
+  /// A notice attached to a line of a file, relative to the repository root.
+  pub fn notice_at(&self, file: &str, line: usize, message: &str) {
+    match self {
+      Ci::Github(_) => {
+        println!("::notice file={},line={}::{}", escape_property(file), line, escape_data(message));
+      }
+      Ci::Teamcity => {
+        let text = format!("{}:{}: {}", file, line, message);
+        println!("##teamcity[message text='{}' status='WARNING']", escape_teamcity(&text));
+      }
+    }
+  }
+
+  /// A problem that fails the build.
+  pub fn error(&self, message: &str) {
+    match self {
+      Ci::Github(_) => println!("::error::{}", escape_data(message)),
+      Ci::Teamcity => println!("##teamcity[buildProblem description='{}']", escape_teamcity(message))
+    }
+  }
+
+  /// Set the number of the build to the version that it released. Only TeamCity has build numbers.
+  pub fn build_number(&self, number: &str) {
+    if let Ci::Teamcity = self {
+      println!("##teamcity[buildNumber '{}']", escape_teamcity(number));
+    }
+  }
+
+  /// Report a table of results: in the step summary of GitHub Actions (if the runner provides one), or as a
+  /// message per row in TeamCity.
+  pub fn summarize(&self, title: &str, headers: &[&str], rows: &[Vec<String>]) -> Result<()> {
+    match self {
+      Ci::Github(gh) => gh.summarize(title, headers, rows),
+      Ci::Teamcity => {
+        for row in rows {
+          let cells: Vec<_> = headers.iter().zip(row).map(|(h, c)| format!("{}: {}", h, c)).collect();
+          let text = format!("{}: {}", title, cells.join(", "));
+          println!("##teamcity[message text='{}']", escape_teamcity(&text));
+        }
+        Ok(())
+      }
+    }
+  }
+}
+
+/// A GitHub Actions runner, to which step summaries are written.
+pub struct GithubActions {
+  summary: Option<PathBuf>
+}
+
+impl GithubActions {
+  fn summarize(&self, title: &str, headers: &[&str], rows: &[Vec<String>]) -> Result<()> {
+    let path = match &self.summary {
+      Some(path) => path,
+      None => return Ok(())
+    };
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(summary_table(title, headers, rows).as_bytes())?;
+    Ok(())
+  }
+}
+
+fn summary_table(title: &str, headers: &[&str], rows: &[Vec<String>]) -> String {
+  let cell = |s: &str| s.replace('|', "\\|").replace('\n', " ");
+  let mut table = format!("### {}\n\n", title);
+  if rows.is_empty() {
+    table.push_str("No projects.\n\n");
+    return table;
+  }
+
+  table.push_str(&format!("| {} |\n", headers.join(" | ")));
+  table.push_str(&format!("|{}\n", " --- |".repeat(headers.len())));
+  for row in rows {
+    table.push_str(&format!("| {} |\n", row.iter().map(|c| cell(c)).collect::<Vec<_>>().join(" | ")));
+  }
+  table.push('\n');
+  table
+}
+
+fn escape_data(data: &str) -> String { data.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A") }
+fn escape_property(prop: &str) -> String { escape_data(prop).replace(':', "%3A").replace(',', "%2C") }
+
+fn escape_teamcity(value: &str) -> String {
+  let mut escaped = String::new();
+  for c in value.chars() {
+    match c {
+      '|' => escaped.push_str("||"),
+      '\'' => escaped.push_str("|'"),
+      '\n' => escaped.push_str("|n"),
+      '\r' => escaped.push_str("|r"),
+      '[' => escaped.push_str("|["),
+      ']' => escaped.push_str("|]"),
+      c => escaped.push(c)
+    }
+  }
+  escaped
+}
+
+#[cfg(test)]
+mod test {
+  use super::{escape_data, escape_teamcity, summary_table};
+
+  #[test]
+  fn test_escape_data() {
+    assert_eq!(escape_data("100% done\nnext"), "100%25 done%0Anext");
+  }
+
+  #[test]
+  fn test_escape_teamcity() {
+    assert_eq!(escape_teamcity("can't [1|2]\n"), "can|'t |[1||2|]|n");
+  }
+
+  #[test]
+  fn test_summary_table() {
+    let rows = vec![vec!["proj".to_string(), "minor".to_string()]];
+    assert_eq!(
+      summary_table("Plan", &["Project", "Size"], &rows),
+      "### Plan\n\n| Project | Size |\n| --- | --- |\n| proj | minor |\n\n"
+    );
+  }
+}
@@ -1,15 +1,20 @@
 //! The command-line options for the executable.
 
+use clap::builder::PossibleValuesParser;
 use clap::error::ErrorKind;
-use clap::{ArgGroup, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap::{ArgGroup, Command, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 use versio::commands::*;
 use versio::errors::Result;
 use versio::init::init;
-use versio::vcs::{VcsLevel, VcsRange};
+use versio::vcs::{VcsLevel, VcsPrefs, VcsRange};
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
-struct Cli {
+pub struct Cli {
   /// The VCS level
   #[arg(short = 'l', long, value_enum)]
   vcs_level: Option<VcsLevelArg>,
@@ -26,6 +31,43 @@ struct Cli {
   #[arg(short = 'c', long)]
   no_current: bool,
 
+  /// Fail if the prev tag annotation is malformed, instead of ignoring it
+  #[arg(long)]
+  strict_prev: bool,
+
+  /// The git remote to use, if the repo has more than one
+  #[arg(long)]
+  remote: Option<String>,
+
+  /// Don't use the network: work at the local VCS level, and read url versions from the responses cached by
+  /// earlier runs
+  #[arg(long)]
+  offline: bool,
+
+  /// Always ask the GitHub (or Gitea) API for PRs, instead of re-using the responses cached by earlier runs
+  #[arg(long)]
+  no_cache: bool,
+
+  /// The number of projects to scan, GitHub queries to send, or hooks to run at once (default: the number of CPUs)
+  #[arg(short = 'j', long)]
+  jobs: Option<NonZeroUsize>,
+
+  /// Emit a stream of events
+  #[arg(long, value_enum)]
+  events: Option<EventsFormat>,
+
+  /// Write events to a file instead of stdout
+  #[arg(long)]
+  events_file: Option<PathBuf>,
+
+  /// The format of log messages: json writes one JSON object per line to stderr
+  #[arg(long, value_enum, default_value_t = LogFormat::Pretty)]
+  log_format: LogFormat,
+
+  /// Write annotations and step summaries for a CI system (detected from the environment by default)
+  #[arg(long, value_enum)]
+  ci: Option<CiArg>,
+
   #[command(subcommand)]
   command: Commands
 }
@@ -53,6 +95,130 @@ impl VcsLevelArg {
   }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, ValueEnum)]
+enum InfoFormatArg {
+  Json,
+  Yaml,
+  Toml,
+  Table
+}
+
+impl InfoFormatArg {
+  fn to_info_format(self) -> InfoFormat {
+    match self {
+      Self::Json => InfoFormat::Json,
+      Self::Yaml => InfoFormat::Yaml,
+      Self::Toml => InfoFormat::Toml,
+      Self::Table => InfoFormat::Table
+    }
+  }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, ValueEnum)]
+enum ReleaseFormatArg {
+  Text,
+  Json
+}
+
+impl ReleaseFormatArg {
+  fn to_release_format(self) -> ReleaseFormat {
+    match self {
+      Self::Text => ReleaseFormat::Text,
+      Self::Json => ReleaseFormat::Json
+    }
+  }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, ValueEnum)]
+enum ShowFormatArg {
+  Text,
+  Json
+}
+
+impl ShowFormatArg {
+  fn to_show_format(self) -> ShowFormat {
+    match self {
+      Self::Text => ShowFormat::Text,
+      Self::Json => ShowFormat::Json
+    }
+  }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, ValueEnum)]
+enum DiffFormatArg {
+  Text,
+  Json
+}
+
+impl DiffFormatArg {
+  fn to_diff_format(self) -> DiffFormat {
+    match self {
+      Self::Text => DiffFormat::Text,
+      Self::Json => DiffFormat::Json
+    }
+  }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, ValueEnum)]
+enum FilesFormatArg {
+  Text,
+  Json
+}
+
+impl FilesFormatArg {
+  fn to_files_format(self) -> FilesFormat {
+    match self {
+      Self::Text => FilesFormat::Text,
+      Self::Json => FilesFormat::Json
+    }
+  }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, ValueEnum)]
+enum BumpArg {
+  Major,
+  Minor,
+  Patch
+}
+
+impl BumpArg {
+  fn as_str(self) -> &'static str {
+    match self {
+      Self::Major => "major",
+      Self::Minor => "minor",
+      Self::Patch => "patch"
+    }
+  }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, ValueEnum)]
+enum EventsFormat {
+  Ndjson
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, ValueEnum)]
+enum CiArg {
+  Github,
+  Teamcity,
+  None
+}
+
+impl CiArg {
+  fn as_str(self) -> &'static str {
+    match self {
+      Self::Github => "github",
+      Self::Teamcity => "teamcity",
+      Self::None => "none"
+    }
+  }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, ValueEnum)]
+enum LogFormat {
+  Pretty,
+  Json
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, ValueEnum)]
 enum VcsLevelBound {
   None,
@@ -75,7 +241,15 @@ impl VcsLevelBound {
 #[derive(Debug, Subcommand)]
 enum Commands {
   /// Check current config
-  Check {},
+  Check {
+    /// Repair common problems in the config file
+    #[arg(long)]
+    fix: bool,
+
+    /// Fail if the includes of two projects overlap, unless one is nested in the other
+    #[arg(long)]
+    strict: bool
+  },
 
   /// Show all versions
   Show {
@@ -85,7 +259,11 @@ enum Commands {
 
     /// Wide output shows IDs
     #[arg(short, long)]
-    wide: bool
+    wide: bool,
+
+    /// The output format: json includes previous versions and pending sizes
+    #[arg(short = 'o', long, value_enum, default_value_t = ShowFormatArg::Text)]
+    format: ShowFormatArg
   },
 
   /// Get one or more versions
@@ -95,6 +273,10 @@ enum Commands {
     #[arg(short, long)]
     prev: bool,
 
+    /// Show the versions at a tag, branch, or commit
+    #[arg(long, value_name = "REF")]
+    at: Option<String>,
+
     /// Only show the version number
     #[arg(short, long)]
     version_only: bool,
@@ -118,6 +300,7 @@ enum Commands {
 
   /// Set a version.
   #[command(group(ArgGroup::new("ident").args(["name", "id", "exact"]),))]
+  #[command(group(ArgGroup::new("target").required(true).args(["value", "bump"])))]
   Set {
     /// The name to set.
     #[arg(short, long)]
@@ -133,14 +316,41 @@ enum Commands {
 
     /// The new value
     #[arg(short, long)]
-    value: String
+    value: Option<String>,
+
+    /// Increment the current version instead
+    #[arg(short, long, value_enum)]
+    bump: Option<BumpArg>
   },
 
   /// View changes from previous
-  Diff {},
+  Diff {
+    /// The output format: json describes the added, removed, and changed projects
+    #[arg(short = 'o', long, value_enum, default_value_t = DiffFormatArg::Text)]
+    format: DiffFormatArg,
+
+    /// Also list the changed files of each project (json only)
+    #[arg(short, long)]
+    files: bool
+  },
 
   /// Stream changed files
-  Files {},
+  Files {
+    /// The output format: json also lists the projects that cover each file
+    #[arg(short = 'o', long, value_enum, default_value_t = FilesFormatArg::Text)]
+    format: FilesFormatArg
+  },
+
+  /// Show which projects cover each changed file
+  Coverage {
+    /// The output format
+    #[arg(short = 'o', long, value_enum, default_value_t = FilesFormatArg::Text)]
+    format: FilesFormatArg,
+
+    /// Only show files covered by no project, or by more than one
+    #[arg(short, long)]
+    problems: bool
+  },
 
   /// Find versions that need to change
   Plan {
@@ -150,7 +360,23 @@ enum Commands {
 
     /// Plan only a single project
     #[arg(short, long)]
-    id: Option<u32>
+    id: Option<u32>,
+
+    /// Plan from a tag, commit, or ISO date instead of the prev tag
+    #[arg(long)]
+    since: Option<String>,
+
+    /// Plan only these projects (by ID, name, or label) and their dependents
+    #[arg(long, num_args = 1..)]
+    only: Vec<String>,
+
+    /// Exit with an error if any project would get a bump of this size or larger
+    #[arg(long, value_enum)]
+    fail_on: Option<BumpArg>,
+
+    /// Show why each commit has its size, and which of its files each project covers
+    #[arg(long, conflicts_with = "template")]
+    explain: bool
   },
 
   /// Change and commit version numbers
@@ -160,36 +386,149 @@ enum Commands {
     #[arg(short = 'a', long)]
     show_all: bool,
 
-    /// Pause the release
+    /// Pause the release before this stage
     #[arg(short, long, value_enum)]
-    pause: Option<PauseStage>,
+    pause: Option<PauseStageArg>,
 
     /// Resume after pausing
     #[arg(long)]
     resume: bool,
 
+    /// Resume even if the repo has changed since pausing
+    #[arg(long)]
+    force: bool,
+
     /// Abort after pausing
     #[arg(long)]
     abort: bool,
 
+    /// Keep the paused state in this file, instead of .versio-paused at the base of the repo
+    #[arg(long, value_name = "PATH")]
+    paused_file: Option<PathBuf>,
+
     #[arg(short, long)]
     dry_run: bool,
 
+    /// With dry-run, render every changelog and version file into this directory instead
+    #[arg(long, value_name = "DIR")]
+    preview_dir: Option<PathBuf>,
+
     #[arg(short, long)]
     changelog_only: bool,
 
+    /// Only create and push tags: don't write any files or run any hooks
+    #[arg(long)]
+    tag_only: bool,
+
     #[arg(short, long)]
-    lock_tags: bool
+    lock_tags: bool,
+
+    /// Warn about local modifications and untracked files instead of failing, and don't commit them
+    #[arg(long)]
+    allow_dirty: bool,
+
+    /// Release only these projects (by ID, name, or label) and their dependents
+    #[arg(long, num_args = 1..)]
+    only: Vec<String>,
+
+    /// Also write the release notes of every released project to RELEASE_NOTES.md in this directory
+    #[arg(long)]
+    changelog_dir: Option<String>,
+
+    /// Only regenerate the release notes of this already-released version, from its tags
+    #[arg(long, value_name = "VERSION")]
+    notes_only: Option<String>,
+
+    /// The output format: json (with dry-run) describes every action the release would take
+    #[arg(short = 'o', long, value_enum, default_value_t = ReleaseFormatArg::Text)]
+    format: ReleaseFormatArg,
+
+    /// Don't use the remote: make all local changes, and queue the pushes for `versio push`
+    #[arg(long)]
+    offline: bool
+  },
+
+  /// Push the branch and tags queued by offline releases
+  Push {},
+
+  /// Undo the last release
+  Rollback {},
+
+  /// Compare tags with file versions
+  Audit {},
+
+  /// Run a command in the root of each project that the plan will change
+  Exec {
+    /// Only run for projects with a planned bump of this size or larger
+    #[arg(long, value_enum, default_value_t = BumpArg::Patch)]
+    size_min: BumpArg,
+
+    /// Run only for these projects (by ID, name, or label) and their dependents
+    #[arg(long, num_args = 1..)]
+    only: Vec<String>,
+
+    /// The command to run, and its arguments
+    #[arg(last = true, required = true)]
+    command: Vec<String>
+  },
+
+  /// Compare local tags with the remote
+  VerifyTags {
+    /// Push the tags that are missing or different on the remote
+    #[arg(long)]
+    push: bool
+  },
+
+  /// Manage version tags
+  Tags {
+    #[command(subcommand)]
+    command: TagsCommands
+  },
+
+  /// Manage the config file
+  Config {
+    #[command(subcommand)]
+    command: ConfigCommands
+  },
+
+  /// Inspect or repair the annotation of the prev tag
+  Prev {
+    #[command(subcommand)]
+    command: PrevCommands
   },
 
   /// Print true changes
-  Changes {},
+  Changes {
+    /// Find changes from a tag, commit, or ISO date instead of the prev tag
+    #[arg(long)]
+    since: Option<String>,
+
+    /// Show only changes to these projects (by ID, name, or label) and their dependents
+    #[arg(long, num_args = 1..)]
+    only: Vec<String>,
+
+    /// Show only commits whose author name or email contains this
+    #[arg(long)]
+    author: Option<String>,
+
+    /// Show only commits made on or after this ISO date
+    #[arg(long)]
+    since_date: Option<String>,
+
+    /// Show only commits made on or before this ISO date
+    #[arg(long)]
+    until_date: Option<String>
+  },
 
   /// Search for projects and write a config
   Init {
     /// Max descent to search
     #[arg(short = 'd', long, default_value_t = 5)]
-    max_depth: u16
+    max_depth: u16,
+
+    /// Confirm, rename, or skip each project, and pick tag prefixes and sizes
+    #[arg(short, long)]
+    interactive: bool
   },
 
   /// Print info about projects
@@ -240,75 +579,303 @@ enum Commands {
 
     /// Show the project(s) tag prefix
     #[arg(short = 'T', long)]
-    show_tag_prefix: bool
+    show_tag_prefix: bool,
+
+    /// Show the last commit of the project(s) since the prev tag
+    #[arg(long)]
+    show_last_commit: bool,
+
+    /// Show the files of the project(s) changed since the prev tag
+    #[arg(long)]
+    show_changed_files: bool,
+
+    /// The output format
+    #[arg(short = 'o', long, value_enum, default_value_t = InfoFormatArg::Json)]
+    format: InfoFormatArg
   },
 
-  /// Output a changelog template
+  /// Output, list, or validate changelog templates
+  #[command(group(ArgGroup::new("which").required(true).args(["template", "list", "init"])))]
   Template {
     /// The changelog template to output
     #[arg(short, long)]
-    template: String
+    template: Option<String>,
+
+    /// List the builtin templates
+    #[arg(short, long)]
+    list: bool,
+
+    /// Output the named builtin template, as a starting point for your own
+    #[arg(long, value_name = "NAME")]
+    init: Option<String>,
+
+    /// Check the template against the available variables, instead of outputting it
+    #[arg(long, requires = "template")]
+    validate: bool
   },
 
   /// Output a JSON schema for the config file
-  Schema {}
+  Schema {
+    /// Validate this config file against the schema, instead of outputting it
+    #[arg(long)]
+    check: Option<PathBuf>,
+
+    /// Read the checked file as of this git ref (path is relative to the repo root)
+    #[arg(long, requires = "check")]
+    at: Option<String>
+  },
+
+  /// Output a shell completion script
+  Completions {
+    /// The shell to complete for
+    #[arg(value_enum)]
+    shell: Shell
+  }
+}
+
+#[derive(Subcommand, Debug)]
+enum TagsCommands {
+  /// Copy a project's version tags to a new tag prefix
+  Migrate {
+    /// The ID of the project
+    #[arg(short, long)]
+    project: u32,
+
+    /// The old tag prefix
+    #[arg(long)]
+    from: String,
+
+    /// The new tag prefix
+    #[arg(long)]
+    to: String,
+
+    /// Delete the old tags, locally and on the remote
+    #[arg(long)]
+    delete_old: bool
+  },
+
+  /// Delete old version tags, locally and on the remote
+  Prune {
+    /// The ID of the project; every project if not given
+    #[arg(short, long)]
+    project: Option<u32>,
+
+    /// Keep the tags of this many of the newest releases of each project
+    #[arg(long, default_value = "1")]
+    keep: NonZeroUsize,
+
+    /// Only delete prerelease tags
+    #[arg(long)]
+    prereleases: bool,
+
+    /// List the tags that would be deleted, without deleting them
+    #[arg(short, long)]
+    dry_run: bool
+  }
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommands {
+  /// Rewrite the config file in the current config version
+  Migrate {}
+}
+
+#[derive(Subcommand, Debug)]
+enum PrevCommands {
+  /// Print the annotation, and check that it's valid
+  Get {},
+
+  /// Replace the annotation, after checking that it's valid
+  Set {
+    /// Read the annotation from this file, instead of from stdin
+    #[arg(short, long)]
+    file: Option<PathBuf>
+  }
 }
 
 impl Commands {
   fn requires_sanity(&self) -> bool {
     match self {
       Self::Release { abort, resume, .. } => !*abort && !*resume,
+      Self::Completions { .. } => false,
       _ => true
     }
   }
+
+  /// The file that holds the paused state of a release. A `--paused-file` is relative to the directory that
+  /// Versio was started in.
+  fn paused_file(&self, early_info: &EarlyInfo) -> PathBuf {
+    match self {
+      Self::Release { paused_file: Some(file), .. } => early_info.orig_dir().join(file),
+      _ => PathBuf::from(PAUSED_FILENAME)
+    }
+  }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, ValueEnum)]
-enum PauseStage {
-  Commit
+enum PauseStageArg {
+  Commit,
+  Tag,
+  Push
 }
 
-pub async fn execute(early_info: &EarlyInfo) -> Result<()> {
-  let id_required = early_info.project_count() != 1;
+impl PauseStageArg {
+  fn to_pause_stage(self) -> PauseStage {
+    match self {
+      Self::Commit => PauseStage::Commit,
+      Self::Tag => PauseStage::Tag,
+      Self::Push => PauseStage::Push
+    }
+  }
+}
+
+/// Log to stdout for people, or as JSON lines on stderr for tools that follow the progress events.
+fn init_logging(log_format: LogFormat) {
+  match log_format {
+    LogFormat::Pretty => {
+      let format = fmt::format()
+        .with_level(true)
+        .with_target(false)
+        .with_thread_ids(false)
+        .with_thread_names(false)
+        .with_source_location(false)
+        .pretty()
+        .with_source_location(false);
+
+      tracing_subscriber::registry().with(fmt::layer().event_format(format)).with(EnvFilter::from_default_env()).init();
+    }
+    LogFormat::Json => {
+      let layer = fmt::layer().json().with_current_span(true).with_span_list(false).with_writer(std::io::stderr);
+      tracing_subscriber::registry().with(layer).with(EnvFilter::from_default_env()).init();
+    }
+  }
+}
+
+/// Parse the command line and start logging in the chosen format. This is done before anything else, so that
+/// nothing (such as the logs of `early_info`) is logged before the format is known.
+pub fn parse() -> Cli {
   let cli = Cli::parse();
+  init_logging(cli.log_format);
+  cli
+}
+
+pub async fn execute(cli: Cli, early_info: &EarlyInfo) -> Result<()> {
+  let id_required = early_info.project_count() > 1;
   verify_cli(&cli, id_required)?;
 
+  let paused_file = cli.command.paused_file(early_info);
   if cli.command.requires_sanity() {
-    sanity_check()?;
+    sanity_check(&paused_file)?;
   }
 
-  let pref_vcs = parse_vcs(&cli);
+  // An offline release works at the local level, and queues its pushes instead of making them.
+  let offline = cli.offline || matches!(&cli.command, Commands::Release { offline: true, .. });
+  let pref_vcs = if offline { Some(VcsRange::exact(VcsLevel::Local)) } else { parse_vcs(&cli) };
+  let jobs = cli.jobs.or_else(|| std::env::var(JOBS_VAR).ok().and_then(|j| j.parse().ok()));
+  let pref_vcs = &VcsPrefs::new(pref_vcs)
+    .with_strict_prev(cli.strict_prev)
+    .with_remote(cli.remote.clone())
+    .with_offline(offline)
+    .with_no_cache(cli.no_cache)
+    .with_jobs(jobs);
   let no_current = cli.no_current;
+  let events = parse_events(&cli)?;
+  let ci = cli.ci.map(|ci| ci.as_str());
 
   match &cli.command {
-    Commands::Check {} => check(pref_vcs, no_current)?,
-    Commands::Get { prev, version_only, wide, name, exact, id } => {
+    Commands::Check { fix, strict } => check(pref_vcs, *fix, *strict, no_current, ci)?,
+    Commands::Get { prev, at, version_only, wide, name, exact, id } => {
       let name_match = NameMatch::from(name, exact);
-      get(pref_vcs, *wide, *version_only, *prev, id.as_ref(), &name_match, no_current)?
+      get(pref_vcs, *wide, *version_only, *prev, at.as_deref(), id.as_ref(), &name_match, no_current)?
     }
-    Commands::Show { prev, wide } => show(pref_vcs, *wide, *prev, no_current)?,
-    Commands::Set { name, exact, id, value } => {
+    Commands::Show { prev, wide, format } => show(pref_vcs, *wide, *prev, no_current, format.to_show_format()).await?,
+    Commands::Set { name, exact, id, value, bump } => {
       let name_match = NameMatch::from(name, exact);
-      set(pref_vcs, id.as_ref(), &name_match, value)?
-    }
-    Commands::Diff {} => diff(pref_vcs, no_current)?,
-    Commands::Files {} => files(pref_vcs, no_current).await?,
-    Commands::Changes {} => changes(pref_vcs, no_current).await?,
-    Commands::Plan { template, id } => plan(early_info, pref_vcs, id.as_ref(), template.as_deref(), no_current).await?,
-    Commands::Release { abort: a, .. } if *a => abort()?,
-    Commands::Release { resume: r, .. } if *r => resume(pref_vcs)?,
-    Commands::Release { show_all, pause, dry_run, changelog_only, lock_tags, .. } => {
+      let bump = bump.map(|b| b.as_str());
+      set(pref_vcs, id.as_ref(), &name_match, value.as_deref(), bump)?
+    }
+    Commands::Diff { format, files } => diff(pref_vcs, no_current, format.to_diff_format(), *files).await?,
+    Commands::Files { format } => files(pref_vcs, no_current, format.to_files_format()).await?,
+    Commands::Coverage { format, problems } => {
+      coverage(pref_vcs, no_current, format.to_files_format(), *problems).await?
+    }
+    Commands::Changes { since, only, author, since_date, until_date } => {
+      let filter = ChangesFilter::new(author.as_deref(), since_date.as_deref(), until_date.as_deref())?;
+      changes(pref_vcs, since.as_deref(), only, &filter, no_current).await?
+    }
+    Commands::Plan { template, id, since, only, fail_on, explain } => {
+      let (since, template) = (since.as_deref(), template.as_deref());
+      let fail_on = fail_on.map(|b| b.as_str());
+      plan(early_info, pref_vcs, id.as_ref(), template, since, only, fail_on, *explain, no_current, &events, ci).await?
+    }
+    Commands::Release { abort: a, .. } if *a => abort(&paused_file)?,
+    Commands::Release { resume: r, force, pause, .. } if *r => {
+      let pause = pause.map(|p| p.to_pause_stage());
+      resume(pref_vcs, *force, &paused_file, pause, &events)?
+    }
+    Commands::Release { notes_only: Some(version), only, changelog_dir, .. } => {
+      release_notes(pref_vcs, version, only, changelog_dir.as_deref()).await?
+    }
+    Commands::Release {
+      show_all,
+      pause,
+      dry_run,
+      changelog_only,
+      tag_only,
+      lock_tags,
+      allow_dirty,
+      only,
+      changelog_dir,
+      preview_dir,
+      format,
+      ..
+    } => {
       let dry = if *dry_run {
         Engagement::Dry
       } else if *changelog_only {
         Engagement::Changelog
+      } else if *tag_only {
+        Engagement::Tags
       } else {
         Engagement::Full
       };
 
-      release(pref_vcs, *show_all, &dry, *lock_tags, pause.is_some()).await?
+      let format = format.to_release_format();
+      let pause = pause.map(|p| Pause::new(p.to_pause_stage(), &paused_file));
+      let changelog_dir = changelog_dir.as_deref();
+      let preview_dir = preview_dir.as_ref().map(|dir| early_info.orig_dir().join(dir));
+      let preview_dir = preview_dir.as_deref();
+      release(
+        pref_vcs,
+        *show_all,
+        &dry,
+        *lock_tags,
+        pause,
+        *allow_dirty,
+        only,
+        changelog_dir,
+        preview_dir,
+        format,
+        &events,
+        ci
+      )
+      .await?
+    }
+    Commands::Push {} => push(pref_vcs)?,
+    Commands::Rollback {} => rollback(pref_vcs)?,
+    Commands::Audit {} => audit(pref_vcs, no_current)?,
+    Commands::Exec { size_min, only, command } => exec(pref_vcs, size_min.as_str(), only, command, no_current).await?,
+    Commands::VerifyTags { push } => verify_tags(pref_vcs, *push)?,
+    Commands::Tags { command: TagsCommands::Migrate { project, from, to, delete_old } } => {
+      migrate_tags(pref_vcs, *project, from, to, *delete_old)?
+    }
+    Commands::Tags { command: TagsCommands::Prune { project, keep, prereleases, dry_run } } => {
+      prune_tags(pref_vcs, *project, *keep, *prereleases, *dry_run)?
     }
-    Commands::Init { max_depth } => init(*max_depth)?,
+    Commands::Config { command: ConfigCommands::Migrate {} } => config_migrate()?,
+    Commands::Prev { command: PrevCommands::Get {} } => prev_get(pref_vcs, no_current)?,
+    Commands::Prev { command: PrevCommands::Set { file } } => prev_set(pref_vcs, file.as_deref())?,
+    Commands::Init { max_depth, interactive } => init(*max_depth, *interactive)?,
     Commands::Info {
       id,
       name,
@@ -321,26 +888,66 @@ pub async fn execute(early_info: &EarlyInfo) -> Result<()> {
       show_id,
       show_full_version,
       show_version,
-      show_tag_prefix
+      show_tag_prefix,
+      show_last_commit,
+      show_changed_files,
+      format
     } => {
       let show = InfoShow::new()
+        .with_format(format.to_info_format())
         .pick_all(*all)
         .show_name(*show_name || *show_all)
         .show_root(*show_root || *show_all)
         .show_id(*show_id || *show_all)
         .show_full_version(*show_full_version || *show_all)
         .show_version(*show_version || *show_all)
-        .show_tag_prefix(*show_tag_prefix || *show_all);
+        .show_tag_prefix(*show_tag_prefix || *show_all)
+        .show_last_commit(*show_last_commit)
+        .show_changed_files(*show_changed_files);
 
-      info(pref_vcs, id, name, exact, label, show, no_current)?
+      info(pref_vcs, id, name, exact, label, show, no_current).await?
     }
-    Commands::Template { template: t } => template(early_info, t).await?,
-    Commands::Schema {} => schema()?
+    Commands::Template { template: t, list, init, validate } => {
+      template(early_info, t.as_deref(), *list, init.as_deref(), *validate).await?
+    }
+    Commands::Schema { check: None, .. } => schema()?,
+    Commands::Schema { check: Some(check), at } => schema_check(check, at.as_deref())?,
+    Commands::Completions { shell } => completions(early_info, *shell)
   }
 
   Ok(())
 }
 
+/// Write a completion script to stdout. The IDs and names of the configured projects are written into the script, so
+/// it should be regenerated after projects are added or renamed.
+fn completions(early_info: &EarlyInfo, shell: Shell) {
+  let mut cmd = with_project_values(Cli::command(), early_info.project_ids(), early_info.project_names());
+  clap_complete::generate(shell, &mut cmd, "versio", &mut std::io::stdout());
+}
+
+/// Offer the project IDs and names as the values of every `id`, `project`, `name`, and `exact` argument.
+fn with_project_values(mut cmd: Command, ids: &[String], names: &[String]) -> Command {
+  if ids.is_empty() {
+    return cmd;
+  }
+
+  let args: Vec<String> = cmd.get_arguments().map(|a| a.get_id().to_string()).collect();
+  for arg in args {
+    let values = match arg.as_str() {
+      "id" | "project" => ids,
+      "name" | "exact" => names,
+      _ => continue
+    };
+    cmd = cmd.mut_arg(arg, |a| a.value_parser(PossibleValuesParser::new(values)));
+  }
+
+  let subs: Vec<String> = cmd.get_subcommands().map(|s| s.get_name().to_string()).collect();
+  for sub in subs {
+    cmd = cmd.mut_subcommand(sub, |s| with_project_values(s, ids, names));
+  }
+  cmd
+}
+
 fn verify_cli(cli: &Cli, id_required: bool) -> Result<()> {
   if cli.vcs_level.is_some() && (cli.vcs_level_min.is_some() || cli.vcs_level_max.is_some()) {
     let mut cmd = Cli::command();
@@ -352,13 +959,28 @@ fn verify_cli(cli: &Cli, id_required: bool) -> Result<()> {
     cmd.error(ErrorKind::ValueValidation, "vcs-level-min and vcs-level-max must both be set, or neither.").exit();
   }
 
-  if let Commands::Get { prev, name, id, exact, .. } = &cli.command {
+  if cli.offline && (cli.vcs_level.is_some() || cli.vcs_level_min.is_some()) {
+    let mut cmd = Cli::command();
+    cmd.error(ErrorKind::ValueValidation, "offline can't be used with a VCS level: it's always local").exit();
+  }
+
+  if cli.events_file.is_some() && cli.events.is_none() {
+    let mut cmd = Cli::command();
+    cmd.error(ErrorKind::ValueValidation, "events-file can only be used with events.").exit();
+  }
+
+  if let Commands::Get { prev, at, name, id, exact, .. } = &cli.command {
     let is_idented = name.is_some() || id.is_some() || exact.is_some();
     if *prev && !is_idented {
       let mut cmd = Cli::command();
       cmd.error(ErrorKind::ValueValidation, "Unable to use `prev` without a name or ID.").exit();
     }
 
+    if *prev && at.is_some() {
+      let mut cmd = Cli::command();
+      cmd.error(ErrorKind::ValueValidation, "Unable to use both `prev` and `at`.").exit();
+    }
+
     if !is_idented && id_required {
       let mut cmd = Cli::command();
       cmd.error(ErrorKind::ValueValidation, "Name or ID required for multi-project config.").exit();
@@ -373,30 +995,124 @@ fn verify_cli(cli: &Cli, id_required: bool) -> Result<()> {
     }
   }
 
-  if let Commands::Plan { id, template } = &cli.command {
+  if let Commands::Diff { format, files } = &cli.command {
+    if *files && *format != DiffFormatArg::Json {
+      let mut cmd = Cli::command();
+      cmd.error(ErrorKind::ValueValidation, "files can only be used with json format").exit();
+    }
+  }
+
+  if let Commands::Plan { id, template, .. } = &cli.command {
     if template.is_some() && id.is_none() && id_required {
       let mut cmd = Cli::command();
       cmd.error(ErrorKind::ValueValidation, "Choose an ID for template plan.").exit();
     }
   }
 
-  if let Commands::Release { dry_run, changelog_only, lock_tags, pause, resume, abort, .. } = &cli.command {
-    if *dry_run && (pause.is_some() || *resume || *abort || *changelog_only) {
+  if let Commands::Show { prev, format, .. } = &cli.command {
+    if *prev && *format == ShowFormatArg::Json {
+      let mut cmd = Cli::command();
+      cmd.error(ErrorKind::ValueValidation, "prev can't be used with json format, which already shows prev").exit();
+    }
+  }
+
+  if let Commands::Release {
+    dry_run,
+    changelog_only,
+    tag_only,
+    lock_tags,
+    allow_dirty,
+    changelog_dir,
+    preview_dir,
+    notes_only,
+    pause,
+    resume,
+    abort,
+    force,
+    only,
+    format,
+    offline,
+    ..
+  } = &cli.command
+  {
+    if *dry_run && (pause.is_some() || *resume || *abort || *changelog_only || *tag_only) {
       let mut cmd = Cli::command();
       cmd
-        .error(ErrorKind::ValueValidation, "dry-run can't be used with pause, resume, abort, or changelog-only")
+        .error(
+          ErrorKind::ValueValidation,
+          "dry-run can't be used with pause, resume, abort, changelog-only, or tag-only"
+        )
         .exit();
     }
 
-    if *changelog_only && (pause.is_some() || *resume || *abort) {
+    if *changelog_only && (pause.is_some() || *resume || *abort || *tag_only) {
       let mut cmd = Cli::command();
-      cmd.error(ErrorKind::ValueValidation, "changelog-only can't be used with pause, resume, or abort").exit();
+      cmd
+        .error(ErrorKind::ValueValidation, "changelog-only can't be used with pause, resume, abort, or tag-only")
+        .exit();
+    }
+
+    if *tag_only && (pause.is_some() || *resume || *abort) {
+      let mut cmd = Cli::command();
+      cmd.error(ErrorKind::ValueValidation, "tag-only can't be used with pause, resume, or abort").exit();
     }
 
     if *lock_tags && (pause.is_some() || *resume || *abort) {
       let mut cmd = Cli::command();
       cmd.error(ErrorKind::ValueValidation, "lock-tags can't be used with pause, resume, or abort").exit();
     }
+
+    if *allow_dirty && (*resume || *abort) {
+      let mut cmd = Cli::command();
+      cmd.error(ErrorKind::ValueValidation, "allow-dirty can't be used with resume or abort").exit();
+    }
+
+    if changelog_dir.is_some() && (*resume || *abort || *tag_only) {
+      let mut cmd = Cli::command();
+      cmd.error(ErrorKind::ValueValidation, "changelog-dir can't be used with resume, abort, or tag-only").exit();
+    }
+
+    if notes_only.is_some()
+      && (*dry_run || pause.is_some() || *resume || *abort || *changelog_only || *tag_only || *lock_tags)
+    {
+      let mut cmd = Cli::command();
+      cmd
+        .error(
+          ErrorKind::ValueValidation,
+          "notes-only can't be used with dry-run, pause, resume, abort, changelog-only, tag-only, or lock-tags"
+        )
+        .exit();
+    }
+
+    if preview_dir.is_some() && !*dry_run {
+      let mut cmd = Cli::command();
+      cmd.error(ErrorKind::ValueValidation, "preview-dir can only be used with dry-run").exit();
+    }
+
+    if *force && !*resume {
+      let mut cmd = Cli::command();
+      cmd.error(ErrorKind::ValueValidation, "force can only be used with resume").exit();
+    }
+
+    if !only.is_empty() && (*resume || *abort) {
+      let mut cmd = Cli::command();
+      cmd.error(ErrorKind::ValueValidation, "only can't be used with resume or abort").exit();
+    }
+
+    if *format == ReleaseFormatArg::Json && !*dry_run {
+      let mut cmd = Cli::command();
+      cmd.error(ErrorKind::ValueValidation, "json format can only be used with dry-run").exit();
+    }
+
+    if (*offline || cli.offline) && (*abort || notes_only.is_some()) {
+      let mut cmd = Cli::command();
+      cmd.error(ErrorKind::ValueValidation, "offline can't be used with abort or notes-only").exit();
+    }
+
+    if *offline && (cli.vcs_level.is_some() || cli.vcs_level_min.is_some()) {
+      let mut cmd = Cli::command();
+      cmd.error(ErrorKind::ValueValidation, "offline can't be used with a VCS level: it's always local").exit();
+    }
   }
 
   Ok(())
@@ -412,3 +1128,10 @@ fn parse_vcs(cli: &Cli) -> Option<VcsRange> {
     None
   }
 }
+
+fn parse_events(cli: &Cli) -> Result<Events> {
+  match cli.events {
+    Some(EventsFormat::Ndjson) => Events::ndjson(cli.events_file.as_deref()),
+    None => Ok(Events::none())
+  }
+}
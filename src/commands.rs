@@ -2,71 +2,161 @@
 
 use crate::bail;
 use crate::config::{Config, ConfigFile, ProjectId, Size};
+pub use crate::config::JOBS_VAR;
 use crate::errors::{Context as _, Result};
-use crate::git::Repo;
-use crate::mono::{Mono, Plan};
+pub use crate::events::Events;
+use crate::fix::fix_config;
+use crate::git::{CommitInfoBuf, FromTagBuf, Repo};
+use crate::migrate::migrate_config;
+use crate::mono::{parse_date, read_user_auth, read_user_proxy, Mono, Plan, PrevTag, Released, SetTo};
 use crate::output::{Output, ProjLine};
-use crate::state::{CommitState, StateRead};
-use crate::template::read_template;
-use crate::vcs::{VcsLevel, VcsRange, VcsState};
+use crate::state::{read_from_slice, CommitState, PrevTagMessage, StateRead};
+pub use crate::state::{Pause, PauseStage, PAUSED_FILENAME};
+use crate::template::{read_template, validate_template, BUILTIN_TEMPLATES};
+use crate::validate::validate_config;
+use crate::vcs::{VcsLevel, VcsPrefs, VcsRange, VcsState};
+use chrono::{DateTime, FixedOffset};
 use schemars::schema_for;
 use std::collections::HashMap;
 use std::fs::{remove_file, File};
-use std::io::BufReader;
+use std::io::{read_to_string, stdin, BufReader};
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 
 pub fn early_info() -> Result<EarlyInfo> {
-  let vcs = VcsRange::detect()?.max();
+  let vcs = VcsRange::detect(None)?.max();
   let root = Repo::find_working_dir(".", vcs, true)?;
-  let file = ConfigFile::from_dir(&root)?;
-  let project_count = file.projects().len();
+  // A config that can't be read (say, because it uses an unset environment variable) is reported by the commands
+  // that need it, so that the others (such as `completions`) still work.
+  let (project_ids, project_names) = match ConfigFile::from_dir(&root) {
+    Ok(file) => file.projects().iter().map(|p| (p.id().to_string(), p.name().to_string())).unzip(),
+    Err(_) => (Vec::new(), Vec::new())
+  };
   let orig_dir = std::env::current_dir()?;
   assert_ok!(orig_dir.is_absolute(), "Couldn't find current working directory.");
 
-  Ok(EarlyInfo::new(project_count, root, orig_dir))
+  Ok(EarlyInfo::new(project_ids, project_names, root, orig_dir))
 }
 
 pub enum Engagement {
   Dry,
   Changelog,
+  Tags,
   Full
 }
 
+/// The format of `release` output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReleaseFormat {
+  Text,
+  Json
+}
+
+/// The format of `show` output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShowFormat {
+  Text,
+  Json
+}
+
+/// The format of `diff` output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffFormat {
+  Text,
+  Json
+}
+
+/// The format of `files` output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilesFormat {
+  Text,
+  Json
+}
+
 /// Environment information gathered even before we set the CLI options.
 pub struct EarlyInfo {
-  project_count: usize,
+  project_ids: Vec<String>,
+  project_names: Vec<String>,
   working_dir: PathBuf,
   orig_dir: PathBuf
 }
 
 impl EarlyInfo {
-  pub fn new(project_count: usize, working_dir: PathBuf, orig_dir: PathBuf) -> EarlyInfo {
-    EarlyInfo { project_count, working_dir, orig_dir }
+  pub fn new(
+    project_ids: Vec<String>, project_names: Vec<String>, working_dir: PathBuf, orig_dir: PathBuf
+  ) -> EarlyInfo {
+    EarlyInfo { project_ids, project_names, working_dir, orig_dir }
   }
 
-  pub fn project_count(&self) -> usize { self.project_count }
+  pub fn project_count(&self) -> usize { self.project_ids.len() }
+  pub fn project_ids(&self) -> &[String] { &self.project_ids }
+  pub fn project_names(&self) -> &[String] { &self.project_names }
   pub fn working_dir(&self) -> &Path { &self.working_dir }
   pub fn orig_dir(&self) -> &Path { &self.orig_dir }
 }
 
-pub fn check(pref_vcs: Option<VcsRange>, ignore_current: bool) -> Result<()> {
-  let mono = with_opts(pref_vcs, VcsLevel::None, VcsLevel::Local, VcsLevel::None, VcsLevel::Smart, ignore_current)?;
-  let output = Output::new();
+/// Check the config and its projects. If `strict`, projects that cover the same files (other than nested projects)
+/// are also reported, and fail the check: this walks every file in the repo, so it's not done otherwise.
+pub fn check(pref_vcs: &VcsPrefs, fix: bool, strict: bool, ignore_current: bool, ci: Option<&str>) -> Result<()> {
+  let output = Output::new().with_ci(ci);
   let mut output = output.check();
 
-  mono.check()?;
+  if fix {
+    // Fix the config file before it's loaded, since some problems prevent it from being loaded at all.
+    let root = Repo::find_working_dir(".", VcsLevel::None, true)?;
+    output.write_fixes(&fix_config(&root)?)?;
+  }
+
+  let mono = with_opts(pref_vcs, VcsLevel::None, VcsLevel::Local, VcsLevel::None, VcsLevel::Smart, ignore_current);
+  let overlaps = mono.and_then(|mono| {
+    mono.check()?;
+    if strict {
+      mono.overlaps()
+    } else {
+      Ok(Vec::new())
+    }
+  });
+  let overlaps = match overlaps {
+    Ok(overlaps) => overlaps,
+    Err(e) => {
+      output.write_failed(&e);
+      return Err(e);
+    }
+  };
+
+  output.write_overlaps(&overlaps);
+  if !overlaps.is_empty() {
+    let e = bad!("Found {} overlapping pairs of projects.", overlaps.len());
+    output.write_failed(&e);
+    return Err(e);
+  }
   output.write_done()?;
 
   output.commit()
 }
 
+/// Rewrite the config file in the current config version, if it's in an older one.
+pub fn config_migrate() -> Result<()> {
+  let root = Repo::find_working_dir(".", VcsLevel::None, true)?;
+  let output = Output::new();
+  let mut output = output.config_migrate();
+
+  output.write_migrated(migrate_config(&root)?);
+  output.commit()
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn get(
-  pref_vcs: Option<VcsRange>, wide: bool, versonly: bool, prev: bool, id: Option<&u32>, name: &NameMatch,
-  ignore_current: bool
+  pref_vcs: &VcsPrefs, wide: bool, versonly: bool, prev: bool, at: Option<&str>, id: Option<&u32>,
+  name: &NameMatch, ignore_current: bool
 ) -> Result<()> {
-  let mono = with_opts(pref_vcs, VcsLevel::None, VcsLevel::Local, VcsLevel::None, VcsLevel::Smart, ignore_current)?;
+  let pref_hi = prev_pref_hi(prev, ignore_current);
+  let req_lo = if at.is_some() { VcsLevel::Local } else { VcsLevel::None };
+  let mono = with_opts(pref_vcs, VcsLevel::None, pref_hi, req_lo, VcsLevel::Smart, ignore_current)?;
 
-  if prev {
+  if let Some(at) = at {
+    get_using_cfg(&mono.config().slice_to(mono.repo(), at)?, wide, versonly, id, name)
+  } else if prev {
     get_using_cfg(&mono.config().slice_to_prev(mono.repo())?, wide, versonly, id, name)
   } else {
     get_using_cfg(mono.config(), wide, versonly, id, name)
@@ -102,13 +192,47 @@ fn get_using_cfg<R: StateRead>(
   output.commit()
 }
 
-pub fn show(pref_vcs: Option<VcsRange>, wide: bool, prev: bool, ignore_current: bool) -> Result<()> {
-  let mono = with_opts(pref_vcs, VcsLevel::None, VcsLevel::Local, VcsLevel::None, VcsLevel::Smart, ignore_current)?;
+pub async fn show(
+  pref_vcs: &VcsPrefs, wide: bool, prev: bool, ignore_current: bool, format: ShowFormat
+) -> Result<()> {
+  if format == ShowFormat::Json {
+    return show_json(pref_vcs, ignore_current).await;
+  }
+
+  let pref_hi = prev_pref_hi(prev, ignore_current);
+  let mono = with_opts(pref_vcs, VcsLevel::None, pref_hi, VcsLevel::None, VcsLevel::Smart, ignore_current)?;
 
   if prev {
     show_using_cfg(&mono.config().slice_to_prev(mono.repo())?, wide)
   } else {
-    show_using_cfg(mono.config(), wide)
+    // The current files can be read concurrently, which the prev files (read from git) can't.
+    let output = Output::new();
+    let mut output = output.projects(wide, false);
+    output.write_projects(mono.config().proj_lines(mono.repo().jobs())?.into_iter().map(Ok))?;
+    output.commit()
+  }
+}
+
+/// Show the current and previous versions of every project, along with the size of its pending increment, in a
+/// single JSON document.
+async fn show_json(pref_vcs: &VcsPrefs, ignore_current: bool) -> Result<()> {
+  let mono = with_opts(pref_vcs, VcsLevel::None, VcsLevel::Smart, VcsLevel::Local, VcsLevel::Smart, ignore_current)?;
+  let output = Output::new();
+  let mut output = output.show();
+
+  let prev = mono.config().slice_to_prev(mono.repo())?;
+  let plan = mono.build_plan(None).await?;
+  output.write_show(mono.config(), &prev, &plan)?;
+  output.commit()
+}
+
+/// Reading the prev state prefers to fetch the prev tag from the remote, so that it isn't stale. Fetching requires
+/// a clean repo, though, so don't prefer it when ignoring local changes.
+fn prev_pref_hi(prev: bool, ignore_current: bool) -> VcsLevel {
+  if prev && !ignore_current {
+    VcsLevel::Remote
+  } else {
+    VcsLevel::Local
   }
 }
 
@@ -120,84 +244,229 @@ fn show_using_cfg<R: StateRead>(cfg: &Config<R>, wide: bool) -> Result<()> {
   output.commit()
 }
 
-pub fn set(pref_vcs: Option<VcsRange>, id: Option<&u32>, name: &NameMatch, value: &str) -> Result<()> {
+pub fn set(
+  pref_vcs: &VcsPrefs, id: Option<&u32>, name: &NameMatch, value: Option<&str>, bump: Option<&str>
+) -> Result<()> {
   let mut mono = build(pref_vcs, VcsLevel::None, VcsLevel::None, VcsLevel::None, VcsLevel::Smart)?;
 
+  let to = match (value, bump) {
+    (Some(value), None) => SetTo::Value(value.to_string()),
+    (None, Some(bump)) => SetTo::Bump(bump.parse()?),
+    _ => bail!("Exactly one of value or bump is required.")
+  };
+
   if let Some(id) = id {
     let id = ProjectId::from_id(*id);
-    mono.set_by_id(&id, value)?;
+    mono.set_by_id(&id, &to)?;
   } else if let NameMatch::Partial(name) = name {
-    mono.set_by_name(name, value)?;
+    mono.set_by_name(name, &to)?;
   } else if let NameMatch::Exact(name) = name {
-    mono.set_by_exact_name(name, value)?;
+    mono.set_by_exact_name(name, &to)?;
   } else {
-    mono.set_by_only(value)?;
+    mono.set_by_only(&to)?;
   }
 
-  mono.commit(false, false)
+  mono.commit(false, None, &Events::none())
 }
 
-pub fn diff(pref_vcs: Option<VcsRange>, ignore_current: bool) -> Result<()> {
+pub async fn diff(pref_vcs: &VcsPrefs, ignore_current: bool, format: DiffFormat, files: bool) -> Result<()> {
   let mono = with_opts(pref_vcs, VcsLevel::None, VcsLevel::Local, VcsLevel::Local, VcsLevel::Smart, ignore_current)?;
   let output = Output::new();
-  let mut output = output.diff();
+  let mut output = output.diff(format);
 
   let analysis = mono.diff()?;
-
   output.write_analysis(analysis)?;
+  if files {
+    output.write_files(mono.keyed_files().await?, &mono)?;
+  }
+
   output.commit()
 }
 
-pub async fn files(pref_vcs: Option<VcsRange>, ignore_current: bool) -> Result<()> {
+pub async fn files(pref_vcs: &VcsPrefs, ignore_current: bool, format: FilesFormat) -> Result<()> {
   let mono = with_opts(pref_vcs, VcsLevel::None, VcsLevel::Smart, VcsLevel::Local, VcsLevel::Smart, ignore_current)?;
   let output = Output::new();
-  let mut output = output.files();
+  let mut output = output.files(format);
 
-  output.write_files(mono.keyed_files().await?)?;
+  output.write_files(mono.keyed_files().await?, &mono)?;
   output.commit()
 }
 
-pub async fn changes(pref_vcs: Option<VcsRange>, ignore_current: bool) -> Result<()> {
+/// List every file changed since the prev tag, with the projects that cover it. If `problems`, only list the files
+/// that are covered by no project or by more than one.
+pub async fn coverage(
+  pref_vcs: &VcsPrefs, ignore_current: bool, format: FilesFormat, problems: bool
+) -> Result<()> {
   let mono = with_opts(pref_vcs, VcsLevel::None, VcsLevel::Smart, VcsLevel::Local, VcsLevel::Smart, ignore_current)?;
   let output = Output::new();
+  let mut output = output.coverage(format, problems);
+
+  output.write_coverage(mono.coverage().await?, &mono);
+  output.commit()
+}
+
+/// Restricts the commits listed by `changes` to an author and a span of dates.
+#[derive(Default)]
+pub struct ChangesFilter {
+  author: Option<String>,
+  since_date: Option<DateTime<FixedOffset>>,
+  until_date: Option<DateTime<FixedOffset>>
+}
+
+impl ChangesFilter {
+  /// The author matches any part of the commit's `name <email>`, ignoring case. The dates are inclusive, and are
+  /// either ISO dates or RFC 3339 timestamps.
+  pub fn new(author: Option<&str>, since_date: Option<&str>, until_date: Option<&str>) -> Result<ChangesFilter> {
+    let date = |d: &str, end_of_day| parse_date(d, end_of_day).ok_or_else(|| bad!("Can't parse date \"{}\".", d));
+    Ok(ChangesFilter {
+      author: author.map(|a| a.to_lowercase()),
+      since_date: since_date.map(|d| date(d, false)).transpose()?,
+      until_date: until_date.map(|d| date(d, true)).transpose()?
+    })
+  }
+
+  fn matches(&self, commit: &CommitInfoBuf) -> bool {
+    self.author.as_ref().map(|a| commit.author().to_lowercase().contains(a.as_str())).unwrap_or(true)
+      && self.since_date.map(|d| commit.time() >= &d).unwrap_or(true)
+      && self.until_date.map(|d| commit.time() <= &d).unwrap_or(true)
+  }
+}
+
+pub async fn changes(
+  pref_vcs: &VcsPrefs, since: Option<&str>, only: &[String], filter: &ChangesFilter, ignore_current: bool
+) -> Result<()> {
+  let mono =
+    with_only(pref_vcs, VcsLevel::None, VcsLevel::Smart, VcsLevel::Local, VcsLevel::Smart, ignore_current, only)?;
+  let output = Output::new();
   let mut output = output.changes();
 
-  output.write_changes(mono.changes().await?)?;
+  let since = since.map(|s| mono.since_base(s)).transpose()?;
+  let mut changes = mono.changes(since.as_ref()).await?;
+  changes.retain_commits(|commit| filter.matches(commit));
+  output.write_changes(changes)?;
   output.commit();
   Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn plan(
-  early_info: &EarlyInfo, pref_vcs: Option<VcsRange>, id: Option<&u32>, template: Option<&str>, ignore_current: bool
+  early_info: &EarlyInfo, pref_vcs: &VcsPrefs, id: Option<&u32>, template: Option<&str>, since: Option<&str>,
+  only: &[String], fail_on: Option<&str>, explain: bool, ignore_current: bool, events: &Events, ci: Option<&str>
 ) -> Result<()> {
-  let mono = with_opts(pref_vcs, VcsLevel::None, VcsLevel::Smart, VcsLevel::Local, VcsLevel::Smart, ignore_current)?;
-  let output = Output::new();
+  let fail_on: Option<Size> = fail_on.map(|f| f.parse()).transpose()?;
+  let mono =
+    with_only(pref_vcs, VcsLevel::None, VcsLevel::Smart, VcsLevel::Local, VcsLevel::Smart, ignore_current, only)?;
+  let output = Output::with_events(events).with_ci(ci);
   let mut output = output.plan();
-  let plan = mono.build_plan().await?;
+  output.write_started()?;
+  let since = since.map(|s| mono.since_base(s)).transpose()?;
+  let mut plan = mono.build_plan(since.as_ref()).await?;
+  mono.bump_deps(&mut plan)?;
   let id = id.map(|i| ProjectId::from_id(*i));
   let orig_dir = early_info.orig_dir();
 
+  // Find the gate failures before the plan is handed off to the output.
+  let mut failures: Vec<_> = match fail_on {
+    Some(fail_on) => plan
+      .incrs()
+      .iter()
+      .filter(|(pid, (size, _))| *size >= fail_on && id.as_ref().map(|id| id == *pid).unwrap_or(true))
+      .map(|(pid, (size, _))| Ok(format!("{} ({})", mono.get_project(pid)?.name(), size)))
+      .collect::<Result<_>>()?,
+    None => Vec::new()
+  };
+  failures.sort();
+
+  output.write_explain(explain);
   output.write_plan(plan, id, template, orig_dir)?;
-  output.commit(&mono).await
+  output.commit(&mono).await?;
+
+  if let Some(fail_on) = fail_on.filter(|_| !failures.is_empty()) {
+    bail!("Planned bumps at or above {}: {}", fail_on, failures.join(", "));
+  }
+  Ok(())
+}
+
+/// Run a command in the root of each project whose planned increment is at least `size_min`, with the project's
+/// planned version in the environment. Stops at the first command that fails.
+pub async fn exec(
+  pref_vcs: &VcsPrefs, size_min: &str, only: &[String], command: &[String], ignore_current: bool
+) -> Result<()> {
+  let size_min: Size = size_min.parse()?;
+  let mono =
+    with_only(pref_vcs, VcsLevel::None, VcsLevel::Smart, VcsLevel::Local, VcsLevel::Smart, ignore_current, only)?;
+  let output = Output::new();
+  let mut output = output.exec();
+  let plan = mono.build_plan(None).await?;
+
+  let (program, args) = command.split_first().ok_or_else(|| bad!("No command to run."))?;
+  for (proj, size, prev, target) in mono.changing_projects(&plan, size_min)? {
+    output.write_running(proj.name(), prev.as_deref(), &target);
+    let mut cmd = std::process::Command::new(program);
+    if let Some(root) = proj.root() {
+      cmd.current_dir(root);
+    }
+    cmd
+      .args(args)
+      .env("VERSIO_PROJECT_ID", proj.id().to_string())
+      .env("VERSIO_PROJECT_NAME", proj.name())
+      .env("VERSIO_SIZE", size.to_string())
+      .env("VERSIO_PREV_VERSION", prev.as_deref().unwrap_or(""))
+      .env("VERSIO_VERSION", &target)
+      .env("VERSIO_FULL_VERSION", proj.full_version(&target).unwrap_or_default());
+    let status = cmd.status().with_context(|| format!("Unable to run {} for {}.", program, proj.name()))?;
+    if !status.success() {
+      bail!("Command {} failed for {}: {}.", program, proj.name(), status);
+    }
+  }
+  output.commit()
 }
 
-pub async fn template(early_info: &EarlyInfo, template: &str) -> Result<()> {
+pub async fn template(
+  early_info: &EarlyInfo, template: Option<&str>, list: bool, init: Option<&str>, validate: bool
+) -> Result<()> {
+  if list {
+    for (name, description, _) in BUILTIN_TEMPLATES {
+      println!("builtin:{:<10} {}", name, description);
+    }
+    return Ok(());
+  }
+
+  let url = match (template, init) {
+    (Some(template), _) => template.to_string(),
+    (None, Some(init)) => format!("builtin:{}", init),
+    (None, None) => bail!("No template given.")
+  };
+
   let orig_dir = early_info.orig_dir();
-  let template = read_template(template, Some(orig_dir), false).await?;
-  println!("{}", template);
+  let template = read_template(&url, Some(orig_dir), false).await?;
+  if validate {
+    validate_template(&template).with_context(|| format!("Invalid template {}.", url))?;
+    println!("Template {} is valid.", url);
+  } else {
+    println!("{}", template);
+  }
   Ok(())
 }
 
-pub fn info(
-  pref_vcs: Option<VcsRange>, ids: &[u32], names: &[String], exacts: &[String], labels: &[String], show: InfoShow,
+pub async fn info(
+  pref_vcs: &VcsPrefs, ids: &[u32], names: &[String], exacts: &[String], labels: &[String], show: InfoShow,
   ignore_current: bool
 ) -> Result<()> {
   let ids = ids.iter().map(|i| ProjectId::from_id(*i)).collect::<Vec<_>>();
   let mono = with_opts(pref_vcs, VcsLevel::None, VcsLevel::Smart, VcsLevel::None, VcsLevel::Smart, ignore_current)?;
   let output = Output::new();
   let all = show.all();
+  let (last_commit, changed_files) = (show.last_commit(), show.changed_files());
   let mut output = output.info(show);
 
+  if last_commit {
+    output.write_last_commits(mono.last_commits().clone());
+  }
+  if changed_files {
+    output.write_changed_files(mono.changed_files().await?);
+  }
+
   let cfg = mono.config();
   let reader = cfg.state_read();
 
@@ -228,14 +497,49 @@ pub fn schema() -> Result<()> {
   Ok(())
 }
 
+/// Validate a config file against the schema, reading it from the working directory or from the git ref `at`.
+pub fn schema_check(path: &Path, at: Option<&str>) -> Result<()> {
+  let data = match at {
+    Some(at) => {
+      let repo = Repo::open(".", VcsState::new(VcsLevel::Local, false), Default::default())?;
+      read_from_slice(&repo.slice(FromTagBuf::new(at.to_string(), false)), path)
+        .with_context(|| format!("Can't read {} at {}", path.display(), at))?
+    }
+    None => std::fs::read_to_string(path).with_context(|| format!("Can't read {}", path.display()))?
+  };
+
+  let output = Output::new();
+  let mut output = output.schema_check(path, at);
+  let errors = validate_config(&data)?;
+  output.write_errors(&errors);
+  output.commit()?;
+
+  if !errors.is_empty() {
+    bail!("{} doesn't match the schema.", path.display());
+  }
+  Ok(())
+}
+
+/// The format of `info` output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InfoFormat {
+  Json,
+  Yaml,
+  Toml,
+  Table
+}
+
 pub struct InfoShow {
+  format: InfoFormat,
   pick_all: bool,
   show_id: bool,
   show_root: bool,
   show_name: bool,
   show_tag_prefix: bool,
   show_full_version: bool,
-  show_version: bool
+  show_version: bool,
+  show_last_commit: bool,
+  show_changed_files: bool
 }
 
 impl Default for InfoShow {
@@ -245,16 +549,20 @@ impl Default for InfoShow {
 impl InfoShow {
   pub fn new() -> InfoShow {
     InfoShow {
+      format: InfoFormat::Json,
       pick_all: false,
       show_id: false,
       show_root: false,
       show_name: false,
       show_version: false,
       show_tag_prefix: false,
-      show_full_version: false
+      show_full_version: false,
+      show_last_commit: false,
+      show_changed_files: false
     }
   }
 
+  pub fn format(&self) -> InfoFormat { self.format }
   pub fn all(&self) -> bool { self.pick_all }
   pub fn id(&self) -> bool { self.show_id }
   pub fn name(&self) -> bool { self.show_name }
@@ -262,6 +570,13 @@ impl InfoShow {
   pub fn tag_prefix(&self) -> bool { self.show_tag_prefix }
   pub fn full_version(&self) -> bool { self.show_full_version }
   pub fn version(&self) -> bool { self.show_version }
+  pub fn last_commit(&self) -> bool { self.show_last_commit }
+  pub fn changed_files(&self) -> bool { self.show_changed_files }
+
+  pub fn with_format(mut self, format: InfoFormat) -> InfoShow {
+    self.format = format;
+    self
+  }
 
   pub fn pick_all(mut self, v: bool) -> InfoShow {
     self.pick_all = v;
@@ -297,87 +612,82 @@ impl InfoShow {
     self.show_version = v;
     self
   }
+
+  pub fn show_last_commit(mut self, v: bool) -> InfoShow {
+    self.show_last_commit = v;
+    self
+  }
+
+  pub fn show_changed_files(mut self, v: bool) -> InfoShow {
+    self.show_changed_files = v;
+    self
+  }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn release(
-  pref_vcs: Option<VcsRange>, all: bool, dry: &Engagement, locktags: bool, pause: bool
+  pref_vcs: &VcsPrefs, all: bool, dry: &Engagement, locktags: bool, pause: Option<Pause<'_>>, allow_dirty: bool,
+  only: &[String], changelog_dir: Option<&str>, preview_dir: Option<&Path>, format: ReleaseFormat, events: &Events,
+  ci: Option<&str>
 ) -> Result<()> {
-  let mut mono = build(pref_vcs, VcsLevel::None, VcsLevel::Smart, VcsLevel::Local, VcsLevel::Smart)?;
-  let output = Output::new();
-  let mut output = output.release();
-  let plan = mono.build_plan().await?;
+  let vcs = combine_vcs(pref_vcs, VcsLevel::None, VcsLevel::Smart, VcsLevel::Local, VcsLevel::Smart)?;
+  let mut mono = Mono::here_only(pref_vcs.state(vcs.max(), false).with_allow_dirty(allow_dirty), only)?;
+  let output = Output::with_events(events).with_ci(ci);
+  let mut output = output.release(format);
+  output.write_started()?;
+  let mut plan = mono.build_plan(None).await?;
+  mono.bump_deps(&mut plan)?;
 
-  if let Err((should, is)) = mono.check_branch() {
-    bail!("Branch name \"{}\"\" doesn't match \"{}\".", is, should);
-  }
+  mono.check_branch()?;
 
   if plan.incrs().is_empty() {
     output.write_empty()?;
-    output.commit();
-    return Ok(());
+    return output.commit();
   }
 
+  let targets = mono.release_targets(&plan)?;
   let mut final_sizes = HashMap::new();
-  for (id, (size, changelog)) in plan.incrs() {
-    let proj = mono.get_project(id)?;
-    let name = proj.name().to_string();
-    let curt_config = mono.config();
-    let prev_config = curt_config.slice_to_prev(mono.repo())?;
-
-    let curt_vers = curt_config
-      .get_value(id)
-      .with_context(|| format!("Unable to find project {} value.", id))?
-      .unwrap_or_else(|| panic!("No such project {}.", id));
-    let prev_vers = prev_config.get_value(id).with_context(|| format!("Unable to find prev {} value.", id))?;
-    let new_vers = if size == &Size::Empty {
-      output.write_no_change(all, false, name.clone(), prev_vers.clone(), curt_vers.clone());
-      curt_vers
-    } else if let Some(prev_vers) = prev_vers {
-      if size.is_failure() {
-        bail!("Couldn't parse conventional commit(s): {}", failed_hashes(&plan));
+  let mut all_released = Vec::new();
+  for id in plan.incrs().keys() {
+    let name = mono.get_project(id)?.name().to_string();
+    let (released, wrote) = mono.release_project(&plan, id, &targets, locktags).await?;
+
+    match &released {
+      Released::Changed { prev, curt, target } => {
+        output.write_changed(id, name, prev.clone(), curt.clone(), target.clone())?
       }
-      let target = size.apply(&prev_vers)?;
-
-      if Size::less_than(&curt_vers, &target)? {
-        proj.verify_restrictions(&target)?;
-        mono.set_by_id(id, &target)?;
-        output.write_changed(name.clone(), prev_vers.clone(), curt_vers.clone(), target.clone());
-        target
-      } else {
-        proj.verify_restrictions(&curt_vers)?;
-        if locktags {
-          output.write_no_change(all, true, name.clone(), Some(prev_vers.clone()), curt_vers.clone());
-        } else {
-          mono.forward_by_id(id, &curt_vers)?;
-          output.write_forward(all, name.clone(), prev_vers.clone(), curt_vers.clone(), target.clone());
-        }
-        curt_vers
+      Released::Forward { prev, curt, target } => {
+        output.write_forward(all, id, name, prev.clone(), curt.clone(), target.clone())?
       }
-    } else {
-      proj.verify_restrictions(&curt_vers)?;
-      if locktags {
-        output.write_no_change(all, true, name.clone(), prev_vers.clone(), curt_vers.clone());
-      } else {
-        mono.forward_by_id(id, &curt_vers)?;
-        output.write_new(all, name.clone(), curt_vers.clone());
+      Released::New { curt } => output.write_new(all, id, name, curt.clone())?,
+      Released::NoChange { locked, prev, curt } => {
+        output.write_no_change(all, *locked, id, name, prev.clone(), curt.clone())?
       }
-      curt_vers
-    };
+    }
 
-    if let Some(wrote) = mono.write_changelog(id, changelog, &new_vers).await? {
+    if let Some(wrote) = wrote.filter(|_| !matches!(dry, Engagement::Tags)) {
       output.write_logged(wrote);
     }
 
-    final_sizes.insert(id.clone(), new_vers);
+    final_sizes.insert(id.clone(), released.version().to_string());
+    all_released.push((id.clone(), released));
+  }
+
+  if let Some(dir) = changelog_dir {
+    let wrote = mono.write_release_notes(&plan, &all_released, &targets, dir).await?;
+    if let Some(wrote) = wrote.filter(|_| !matches!(dry, Engagement::Tags)) {
+      output.write_logged(wrote);
+    }
   }
 
   mono.write_chains(plan.chain_writes(), &final_sizes)?;
 
   match dry {
     Engagement::Full => {
-      mono.commit(true, pause)?;
-      if pause {
-        output.write_pause();
+      // A partial release leaves the prev tag alone, since the projects that were left out haven't been released.
+      mono.commit(mono.advances_prev(), pause, events)?;
+      if let Some(pause) = pause {
+        output.write_pause(pause.stage())?;
       } else {
         output.write_commit();
         output.write_done();
@@ -387,46 +697,183 @@ pub async fn release(
       mono.write_changelogs()?;
       output.write_wrote_changelogs();
     }
+    Engagement::Tags => {
+      mono.tag_only(mono.advances_prev(), events)?;
+      output.write_tags_only();
+      output.write_done();
+    }
     Engagement::Dry => {
+      output.write_actions(mono.planned_actions());
+      if let Some(dir) = preview_dir {
+        output.write_previewed(mono.preview(dir)?);
+      }
       output.write_dry();
     }
   }
 
-  output.commit();
-  Ok(())
+  output.commit()
 }
 
-pub fn resume(user_pref_vcs: Option<VcsRange>) -> Result<()> {
+pub fn resume(
+  user_pref_vcs: &VcsPrefs, force: bool, paused_file: &Path, pause: Option<PauseStage>, events: &Events
+) -> Result<()> {
   let vcs = combine_vcs(user_pref_vcs, VcsLevel::None, VcsLevel::Smart, VcsLevel::Local, VcsLevel::Smart)?;
-  let output = Output::new();
+  let output = Output::with_events(events);
   let mut output = output.resume();
+  output.write_started()?;
 
   let mut commit: CommitState = {
-    let file = File::open(".versio-paused")?;
+    let file = File::open(paused_file).with_context(|| format!("Can't read {}.", paused_file.display()))?;
     let reader = BufReader::new(file);
-    let commit: CommitState = serde_json::from_reader(reader)?;
-
-    // We must remove the pausefile before resuming, or else it will be committed.
-    remove_file(".versio-paused")?;
-    commit
+    serde_json::from_reader(reader)?
   };
-  let repo = Repo::open(".", VcsState::new(vcs.max(), false), commit.commit_config().clone())?;
-  commit.resume(&repo)?;
-
-  output.write_done()?;
+  if pause.map(|stage| stage <= commit.stage()).unwrap_or(false) {
+    bail!("Release is paused before its {} stage: it can only pause again at a later stage.", commit.stage().as_str());
+  }
+  let repo = Repo::open(".", user_pref_vcs.state(vcs.max(), false), commit.commit_config().clone())?;
+  let repo = repo.with_auth(read_user_auth()?).with_proxy(read_user_proxy()?);
+  commit.verify_paused(&repo, force)?;
+  commit.restore_paused(&repo, paused_file)?;
+
+  // We must remove the pausefile before resuming, or else it will be committed.
+  remove_file(paused_file)?;
+  commit.resume_until(&repo, pause, events)?;
+
+  match pause {
+    Some(stage) => {
+      commit.pause(&repo, paused_file)?;
+      output.write_pause(stage)?;
+    }
+    None => output.write_done()?
+  }
   output.commit()?;
 
   Ok(())
 }
 
-pub fn abort() -> Result<()> {
-  remove_file(".versio-paused")?;
+pub fn rollback(pref_vcs: &VcsPrefs) -> Result<()> {
+  let mono = build(pref_vcs, VcsLevel::Local, VcsLevel::Smart, VcsLevel::Local, VcsLevel::Smart)?;
+  let output = Output::new();
+  let mut output = output.rollback();
+
+  let record = mono.rollback()?;
+  if let Some(commit) = record.commit() {
+    output.write_reverted(commit);
+  }
+  for tag in record.tags() {
+    output.write_deleted(tag);
+  }
+
+  output.commit()
+}
+
+pub fn migrate_tags(pref_vcs: &VcsPrefs, id: u32, from: &str, to: &str, delete_old: bool) -> Result<()> {
+  let mono = build(pref_vcs, VcsLevel::Local, VcsLevel::Smart, VcsLevel::Local, VcsLevel::Smart)?;
+  let output = Output::new();
+  let mut output = output.migrate(delete_old);
+
+  output.write_moved(mono.migrate_tags(&ProjectId::from_id(id), from, to, delete_old)?);
+  output.commit()
+}
+
+pub fn prune_tags(
+  pref_vcs: &VcsPrefs, id: Option<u32>, keep: NonZeroUsize, prereleases: bool, dry: bool
+) -> Result<()> {
+  let mono = build(pref_vcs, VcsLevel::Local, VcsLevel::Smart, VcsLevel::Local, VcsLevel::Smart)?;
+  let output = Output::new();
+  let mut output = output.prune(dry);
+
+  let id = id.map(ProjectId::from_id);
+  output.write_pruned(mono.prune_tags(id.as_ref(), keep, prereleases, dry)?);
+  output.commit()
+}
+
+pub fn audit(pref_vcs: &VcsPrefs, ignore_current: bool) -> Result<()> {
+  let mono = with_opts(pref_vcs, VcsLevel::Local, VcsLevel::Local, VcsLevel::Local, VcsLevel::Smart, ignore_current)?;
+  let output = Output::new();
+  let mut output = output.audit();
+
+  output.write_drifts(mono.audit()?);
+  output.commit()
+}
+
+/// Regenerate the release notes of an already-released version, printing them or writing them to `changelog_dir`.
+pub async fn release_notes(
+  pref_vcs: &VcsPrefs, version: &str, only: &[String], changelog_dir: Option<&str>
+) -> Result<()> {
+  let vcs = combine_vcs(pref_vcs, VcsLevel::Local, VcsLevel::Smart, VcsLevel::Local, VcsLevel::Smart)?;
+  let mono = Mono::here_only(pref_vcs.state(vcs.max(), false), only)?;
+  let output = Output::new();
+  let mut output = output.notes(version);
+
+  if let Some(notes) = mono.notes_of(version).await? {
+    match changelog_dir {
+      Some(dir) => output.write_wrote(mono.write_notes_file(dir, &notes)?),
+      None => output.write_notes(notes)
+    }
+  }
+  output.commit()
+}
+
+pub fn verify_tags(pref_vcs: &VcsPrefs, push: bool) -> Result<()> {
+  let mono = build(pref_vcs, VcsLevel::Remote, VcsLevel::Smart, VcsLevel::Remote, VcsLevel::Smart)?;
+  let output = Output::new();
+  let mut output = output.verify_tags(push);
+
+  output.write_unsynced(mono.verify_tags(push)?);
+  output.commit()
+}
+
+/// Push the branch and tags that offline releases have queued.
+pub fn push(pref_vcs: &VcsPrefs) -> Result<()> {
+  let vcs = combine_vcs(pref_vcs, VcsLevel::Remote, VcsLevel::Smart, VcsLevel::Remote, VcsLevel::Smart)?;
+  let repo = Repo::open(".", pref_vcs.state(vcs.max(), false), Default::default())?;
+  let repo = repo.with_auth(read_user_auth()?).with_proxy(read_user_proxy()?);
+  let output = Output::new();
+  let mut output = output.push();
+
+  output.write_pushed(repo.push_queued()?);
+  output.commit()
+}
+
+pub fn prev_get(pref_vcs: &VcsPrefs, ignore_current: bool) -> Result<()> {
+  let vcs = combine_vcs(pref_vcs, VcsLevel::Local, VcsLevel::Smart, VcsLevel::Local, VcsLevel::Smart)?;
+  let prev = PrevTag::here(pref_vcs.state(vcs.max(), ignore_current))?;
+  let output = Output::new();
+  let mut output = output.prev();
+
+  let anno = prev.annotation()?;
+  output.write_annotation(&anno);
+  output.commit()?;
+  PrevTagMessage::parse(&anno).with_context(|| format!("Tag \"{}\" has a malformed annotation.", prev.tag()))?;
+  Ok(())
+}
+
+pub fn prev_set(pref_vcs: &VcsPrefs, file: Option<&Path>) -> Result<()> {
+  let anno = match file {
+    Some(file) => std::fs::read_to_string(file).with_context(|| format!("Can't read {}", file.display()))?,
+    None => read_to_string(stdin())?
+  };
+  let msg = PrevTagMessage::parse(&anno).context("Refusing to write a malformed annotation.")?;
+
+  let vcs = combine_vcs(pref_vcs, VcsLevel::Local, VcsLevel::Smart, VcsLevel::Local, VcsLevel::Smart)?;
+  let prev = PrevTag::here(pref_vcs.state(vcs.max(), false))?;
+  let output = Output::new();
+  let mut output = output.prev();
+
+  prev.set_annotation(&msg)?;
+  output.write_set(prev.tag());
+  output.commit()
+}
+
+pub fn abort(paused_file: &Path) -> Result<()> {
+  remove_file(paused_file)?;
   println!("Release aborted. You may need to rollback your VCS \n(i.e `git checkout -- .`)");
   Ok(())
 }
 
-pub fn sanity_check() -> Result<()> {
-  if Path::new(".versio-paused").exists() {
+pub fn sanity_check(paused_file: &Path) -> Result<()> {
+  if paused_file.exists() {
     bail!("versio is paused: use `release --resume` or `--abort`.")
   } else {
     Ok(())
@@ -434,27 +881,36 @@ pub fn sanity_check() -> Result<()> {
 }
 
 fn with_opts(
-  user_pref_vcs: Option<VcsRange>, my_pref_lo: VcsLevel, my_pref_hi: VcsLevel, my_reqd_lo: VcsLevel,
+  user_pref_vcs: &VcsPrefs, my_pref_lo: VcsLevel, my_pref_hi: VcsLevel, my_reqd_lo: VcsLevel,
   my_reqd_hi: VcsLevel, ignore_current: bool
 ) -> Result<Mono> {
   let vcs = combine_vcs(user_pref_vcs, my_pref_lo, my_pref_hi, my_reqd_lo, my_reqd_hi)?;
-  Mono::here(VcsState::new(vcs.max(), ignore_current))
+  Mono::here(user_pref_vcs.state(vcs.max(), ignore_current))
+}
+
+/// Like `with_opts`, but only the selected projects (and their dependents) are opened: see `Mono::open_only`.
+fn with_only(
+  user_pref_vcs: &VcsPrefs, my_pref_lo: VcsLevel, my_pref_hi: VcsLevel, my_reqd_lo: VcsLevel,
+  my_reqd_hi: VcsLevel, ignore_current: bool, only: &[String]
+) -> Result<Mono> {
+  let vcs = combine_vcs(user_pref_vcs, my_pref_lo, my_pref_hi, my_reqd_lo, my_reqd_hi)?;
+  Mono::here_only(user_pref_vcs.state(vcs.max(), ignore_current), only)
 }
 
 fn build(
-  user_pref_vcs: Option<VcsRange>, my_pref_lo: VcsLevel, my_pref_hi: VcsLevel, my_reqd_lo: VcsLevel,
+  user_pref_vcs: &VcsPrefs, my_pref_lo: VcsLevel, my_pref_hi: VcsLevel, my_reqd_lo: VcsLevel,
   my_reqd_hi: VcsLevel
 ) -> Result<Mono> {
   with_opts(user_pref_vcs, my_pref_lo, my_pref_hi, my_reqd_lo, my_reqd_hi, false)
 }
 
 fn combine_vcs(
-  user_pref_vcs: Option<VcsRange>, my_pref_lo: VcsLevel, my_pref_hi: VcsLevel, my_reqd_lo: VcsLevel,
+  user_pref_vcs: &VcsPrefs, my_pref_lo: VcsLevel, my_pref_hi: VcsLevel, my_reqd_lo: VcsLevel,
   my_reqd_hi: VcsLevel
 ) -> Result<VcsRange> {
-  let pref_vcs = user_pref_vcs.unwrap_or_else(move || VcsRange::new(my_pref_lo, my_pref_hi));
+  let pref_vcs = user_pref_vcs.range().unwrap_or_else(move || VcsRange::new(my_pref_lo, my_pref_hi));
   let reqd_vcs = VcsRange::new(my_reqd_lo, my_reqd_hi);
-  VcsRange::detect_and_combine(&pref_vcs, &reqd_vcs)
+  VcsRange::detect_and_combine(&pref_vcs, &reqd_vcs, user_pref_vcs.remote())
 }
 
 pub fn failed_hashes(plan: &Plan) -> String {
@@ -488,3 +944,38 @@ impl NameMatch {
     }
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::ChangesFilter;
+  use crate::git::CommitInfoBuf;
+  use chrono::DateTime;
+
+  fn commit(author: &str, time: &str) -> CommitInfoBuf {
+    let time = DateTime::parse_from_rfc3339(time).unwrap();
+    CommitInfoBuf::new("abc".into(), "feat".into(), "-".into(), "".into(), author.into(), Vec::new(), time)
+  }
+
+  #[test]
+  fn test_changes_filter_author() {
+    let filter = ChangesFilter::new(Some("Jane"), None, None).unwrap();
+    assert!(filter.matches(&commit("Jane Doe <jane@example.com>", "2021-03-04T10:00:00Z")));
+    assert!(filter.matches(&commit("JANE DOE <jd@example.com>", "2021-03-04T10:00:00Z")));
+    assert!(!filter.matches(&commit("John Doe <john@example.com>", "2021-03-04T10:00:00Z")));
+
+    let filter = ChangesFilter::new(Some("@example.org"), None, None).unwrap();
+    assert!(filter.matches(&commit("Jane Doe <jane@Example.org>", "2021-03-04T10:00:00Z")));
+    assert!(!filter.matches(&commit("Jane Doe <jane@example.com>", "2021-03-04T10:00:00Z")));
+  }
+
+  #[test]
+  fn test_changes_filter_dates() {
+    let filter = ChangesFilter::new(None, Some("2021-03-04"), Some("2021-03-05")).unwrap();
+    assert!(filter.matches(&commit("Jane", "2021-03-04T00:00:00Z")));
+    assert!(filter.matches(&commit("Jane", "2021-03-05T23:59:59Z")));
+    assert!(!filter.matches(&commit("Jane", "2021-03-03T23:59:59Z")));
+    assert!(!filter.matches(&commit("Jane", "2021-03-06T00:00:00Z")));
+
+    assert!(ChangesFilter::new(None, Some("last week"), None).is_err());
+  }
+}
@@ -3,18 +3,21 @@
 use crate::analyze::AnnotatedMark;
 use crate::bail;
 use crate::either::IterEither2 as E2;
-use crate::errors::{Context as _, Result};
-use crate::git::{FromTagBuf, Repo, Slice};
-use crate::mark::{FilePicker, LinePicker, Picker, ScanningPicker};
-use crate::mono::Changelog;
+use crate::errors::{Context as _, Result, VersioError};
+use crate::fetch::fetch_url;
+use crate::git::{FromTagBuf, GithubHost, Repo, Slice};
+use crate::mark::{FilePicker, ImageTagPicker, LinePicker, Picker, ScanningPicker};
+use crate::migrate::{migrate_value, CONFIG_VERSION};
+use crate::mono::{find_old_tags_at, Changelog};
 use crate::output::ProjLine;
-use crate::scan::parts::{deserialize_parts, Part};
-use crate::state::{CurrentFiles, CurrentState, FilesRead, OldTags, PickPath, PrevFiles, PrevState, StateRead,
-                   StateWrite};
-use crate::template::{construct_changelog_html, extract_old_content, read_template};
-use glob::{glob_with, MatchOptions, Pattern};
+use crate::scan::parts::{deserialize_parts, IntoPartVec as _, Part};
+use crate::state::{split_range, unignored_walk, CurrentFiles, CurrentState, FilesRead, OldTags, PickPath, PrevFiles,
+                   PrevState, StateRead, StateWrite};
+use crate::template::{construct_changelog_html, construct_tag_message, extract_old_content, read_template, DateFormat,
+                      DateZone, IssueLinks, ReleaseLinks, SharedPrs, DEFAULT_DATE_FORMAT, DEFAULT_ISSUE_PATTERN};
+use glob::{MatchOptions, Pattern};
 use liquid::ParserBuilder;
-use path_slash::PathBufExt as _;
+use path_slash::{PathBufExt as _, PathExt as _};
 use regex::{escape, Regex};
 use schemars::gen::SchemaGenerator;
 use schemars::schema::{ArrayValidation, InstanceType, ObjectValidation, Schema, SchemaObject, SingleOrVec};
@@ -24,16 +27,20 @@ use serde::ser::Serializer;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::cmp::{Ord, Ordering};
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt;
 use std::iter::once;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use tracing::trace;
+use tracing::{trace, warn};
 
 pub const CONFIG_FILENAME: &str = ".versio.yaml";
 
-#[derive(Hash, Debug, Eq, PartialEq, Clone)]
+/// The environment variable that holds the number of jobs (threads, queries, or hooks) to run at once.
+pub const JOBS_VAR: &str = "VERSIO_JOBS";
+
+#[derive(Hash, Debug, Eq, PartialEq, Ord, PartialOrd, Clone)]
 pub struct ProjectId {
   id: u32,
   majors: Vec<u32>
@@ -140,17 +147,142 @@ pub struct Config<S: StateRead> {
 
 impl Config<CurrentState> {
   pub fn prev_tag(&self) -> &str { self.file.prev_tag() }
+  pub fn release_notes_template(&self) -> &str { self.file.release_notes_template() }
 
   pub fn slice_to_prev<'r>(&self, repo: &'r Repo) -> Result<Config<PrevState<'r>>> {
+    repo.fetch_tag(self.prev_tag())?;
     let spec = FromTagBuf::new(self.prev_tag().to_string(), true);
     let old_tags = self.state.old_tags().slice_to_prev()?;
-    let prev_state = PrevState::new(repo.slice(spec), old_tags);
-    Config::from_state(prev_state)
+    self.slice_with(repo, spec, old_tags)
+  }
+
+  /// The config as it was at any commit-ish `spec`, such as a tag, branch, or commit ID. Versions from tags are
+  /// those of the latest version tags at or before that commit.
+  pub fn slice_to<'r>(&self, repo: &'r Repo, spec: &str) -> Result<Config<PrevState<'r>>> {
+    let old_tags = find_old_tags_at(self.projects().iter(), repo, spec)?;
+    self.slice_with(repo, FromTagBuf::new(spec.to_string(), false), old_tags)
+  }
+
+  fn slice_with<'r>(&self, repo: &'r Repo, spec: FromTagBuf, old_tags: OldTags) -> Result<Config<PrevState<'r>>> {
+    let prev_state = PrevState::new(repo.slice(spec.clone()), old_tags);
+    let file = prev_config_file(&prev_state, &self.file, spec.tag())?;
+    Ok(Config::new(prev_state, file))
   }
 
   pub fn old_tags(&self) -> &OldTags { self.state.old_tags() }
 
   pub fn hooks(&self) -> HashMap<ProjectId, (Option<&String>, &HookSet)> { self.file.hooks() }
+
+  /// Like `annotate`, but the projects' files are read and scanned by up to `jobs` threads.
+  pub fn annotate_par(&self, jobs: usize) -> Result<Vec<AnnotatedMark>> {
+    par_map(self.projects(), jobs, |p| p.annotate(&self.state))
+  }
+
+  /// Check every project, reading and scanning the projects' files by up to `jobs` threads.
+  pub fn check(&self, jobs: usize) -> Result<()> {
+    par_map(self.projects(), jobs, |p| p.check(&self.state)).map(|_| ())
+  }
+
+  /// Every pair of projects that both cover some unignored file, except for projects whose roots are nested one
+  /// inside the other. Each pair is reported once, with the first such file found.
+  pub fn overlaps(&self) -> Result<Vec<Overlap>> {
+    let mut found: Vec<Overlap> = Vec::new();
+    for entry in unignored_walk(Path::new("."), None) {
+      let entry = entry?;
+      if !entry.file_type().map(|t| t.is_file()).unwrap_or(false) {
+        continue;
+      }
+      let path = entry.path().strip_prefix(".").unwrap_or_else(|_| entry.path()).to_slash_lossy().into_owned();
+
+      let mut covering = Vec::new();
+      for p in self.projects() {
+        if let Some(pattern) = p.covering_include(&path)? {
+          covering.push((p, pattern));
+        }
+      }
+
+      for (i, (a, a_pat)) in covering.iter().enumerate() {
+        for (b, b_pat) in &covering[i + 1 ..] {
+          if a.nests_with(b) || found.iter().any(|o| o.first.0 == a.id && o.second.0 == b.id) {
+            continue;
+          }
+          found.push(Overlap {
+            first: (a.id.clone(), a_pat.clone()),
+            second: (b.id.clone(), b_pat.clone()),
+            file: path.clone()
+          });
+        }
+      }
+    }
+    Ok(found)
+  }
+
+  /// The output line of every project, reading and scanning the projects' files by up to `jobs` threads.
+  pub fn proj_lines(&self, jobs: usize) -> Result<Vec<ProjLine>> {
+    par_map(self.projects(), jobs, |p| ProjLine::from(p, &self.state))
+  }
+}
+
+/// The config file in an earlier state. A config there that can't be parsed would otherwise block every release
+/// until the tag is moved, so the `current` config is used instead; but any other failure (such as being unable to
+/// read the file at all) is returned.
+fn prev_config_file<R: FilesRead>(read: &R, current: &ConfigFile, at: &str) -> Result<ConfigFile> {
+  if !read.has_file(CONFIG_FILENAME.as_ref())? {
+    return Ok(Default::default());
+  }
+
+  match ConfigFile::read(&read.read_file(CONFIG_FILENAME.as_ref())?) {
+    Ok(file) => file.expand(read),
+    Err(e) => {
+      warn!("Can't read the config at \"{}\", using the current config instead: {:#}", at, e);
+      Ok(current.clone())
+    }
+  }
+}
+
+/// Two projects that cover the same file, so that a change to it bumps both.
+pub struct Overlap {
+  first: (ProjectId, String),
+  second: (ProjectId, String),
+  file: String
+}
+
+impl Overlap {
+  /// The ID of the first project, and its include pattern that covers the file.
+  pub fn first(&self) -> (&ProjectId, &str) { (&self.first.0, &self.first.1) }
+
+  /// The ID of the second project, and its include pattern that covers the file.
+  pub fn second(&self) -> (&ProjectId, &str) { (&self.second.0, &self.second.1) }
+
+  /// An example of a file that both projects cover.
+  pub fn file(&self) -> &str { &self.file }
+}
+
+/// The number of jobs to run at once: the given number, or else the number of CPUs.
+pub fn jobs_or_cpus(jobs: Option<NonZeroUsize>) -> usize {
+  jobs.map(|j| j.get()).unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1))
+}
+
+/// Apply `f` to every item, splitting the items among up to `jobs` threads, and keep the results in order. Reading
+/// version files is mostly waiting on I/O, which adds up in large monorepos (especially on network filesystems).
+pub fn par_map<I, T, F>(items: &[I], jobs: usize, f: F) -> Result<Vec<T>>
+where
+  I: Sync,
+  T: Send,
+  F: Fn(&I) -> Result<T> + Sync
+{
+  let threads = jobs;
+  if threads <= 1 || items.len() <= 1 {
+    return items.iter().map(f).collect();
+  }
+
+  let f = &f;
+  let chunk_size = items.len().div_ceil(threads);
+  std::thread::scope(|scope| {
+    let handles: Vec<_> =
+      items.chunks(chunk_size).map(|chunk| scope.spawn(move || chunk.iter().map(f).collect::<Vec<_>>())).collect();
+    handles.into_iter().flat_map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e))).collect()
+  })
 }
 
 impl<S: StateRead> Config<S> {
@@ -190,6 +322,7 @@ impl<S: StateRead> Config<S> {
   }
 
   pub fn get_value(&self, id: &ProjectId) -> Result<Option<String>> { self.do_project_read(id, |p, s| p.get_value(s)) }
+  pub fn latest_tag(&self, id: &ProjectId) -> Option<&String> { self.state.latest_tag(id) }
 
   fn do_project_read<F, T>(&self, id: &ProjectId, f: F) -> Result<Option<T>>
   where
@@ -205,12 +338,52 @@ pub struct FsConfig<F: FilesRead> {
 }
 
 impl<'r> FsConfig<PrevFiles<'r>> {
-  pub fn slice_to(&self, spec: FromTagBuf) -> Result<FsConfig<PrevFiles<'r>>> {
-    FsConfig::from_read(self.files.slice_to(spec)?)
+  /// Read the config of a slice, reusing an earlier parse of the same config file from the cache.
+  pub fn from_slice(slice: Slice<'r>, cache: &mut ConfigCache) -> Result<FsConfig<PrevFiles<'r>>> {
+    let files = PrevFiles::from_slice(slice)?;
+    let file = cache.read(&files)?;
+    Ok(FsConfig::new(files, file))
   }
+}
+
+/// A small LRU cache of parsed config files, keyed by the blob ID of the config file. The config file usually
+/// doesn't change from one commit to the next, so this saves walks over many commits from re-parsing it each
+/// time. Only the parse is cached: a config is still expanded against the files of its own commit.
+pub struct ConfigCache {
+  entries: VecDeque<(String, ConfigFile)>
+}
+
+impl Default for ConfigCache {
+  fn default() -> ConfigCache { ConfigCache::new() }
+}
 
-  pub fn from_slice(slice: Slice<'r>) -> Result<FsConfig<PrevFiles<'r>>> {
-    FsConfig::from_read(PrevFiles::from_slice(slice)?)
+impl ConfigCache {
+  const CAPACITY: usize = 8;
+
+  pub fn new() -> ConfigCache { ConfigCache { entries: VecDeque::new() } }
+
+  fn read(&mut self, files: &PrevFiles) -> Result<ConfigFile> {
+    let blob_id = match files.blob_id(CONFIG_FILENAME)? {
+      Some(blob_id) => blob_id,
+      None => return Ok(Default::default())
+    };
+
+    let file = match self.entries.iter().position(|(id, _)| id == &blob_id) {
+      Some(i) => {
+        let entry = self.entries.remove(i).unwrap();
+        let file = entry.1.clone();
+        self.entries.push_front(entry);
+        file
+      }
+      None => {
+        let data = files.read_file(CONFIG_FILENAME.as_ref())?;
+        let file = ConfigFile::read(&data).context(VersioError::MalformedConfig)?;
+        self.entries.push_front((blob_id, file.clone()));
+        self.entries.truncate(Self::CAPACITY);
+        file
+      }
+    };
+    file.expand(files)
   }
 }
 
@@ -223,16 +396,25 @@ impl<F: FilesRead> FsConfig<F> {
   }
 
   pub fn file(&self) -> &ConfigFile { &self.file }
+  pub fn into_file(self) -> ConfigFile { self.file }
 }
 
-#[derive(Deserialize, JsonSchema, Debug)]
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
 pub struct ConfigFile {
+  // Only here for the schema: the version is checked (and older configs migrated) before deserializing.
+  #[allow(dead_code)]
+  #[serde(default = "default_config_version")]
+  config_version: u64,
+
   #[serde(default)]
   options: Options,
 
   #[serde(default)]
   projects: Vec<Project>,
 
+  #[serde(default)]
+  projects_from: Vec<ProjectsFrom>,
+
   #[serde(default)]
   commit: CommitConfig,
 
@@ -247,7 +429,14 @@ impl Default for ConfigFile {
     insert_angular(&mut sizes);
     sizes.insert("*".into(), Size::Fail);
 
-    ConfigFile { options: Default::default(), projects: Default::default(), commit: Default::default(), sizes }
+    ConfigFile {
+      config_version: CONFIG_VERSION,
+      options: Default::default(),
+      projects: Default::default(),
+      projects_from: Default::default(),
+      commit: Default::default(),
+      sizes
+    }
   }
 }
 
@@ -265,23 +454,115 @@ impl ConfigFile {
   }
 
   fn read(data: &str) -> Result<ConfigFile> {
-    let file: ConfigFile = serde_yaml::from_str(data)?;
+    // Only go through a YAML value when it had to be changed, since it loses the location of errors.
+    let mut value: serde_yaml::Value = serde_yaml::from_str(data)?;
+    let file: ConfigFile = if preprocess_config(&mut value)? {
+      serde_yaml::from_value(value)?
+    } else {
+      serde_yaml::from_str(data)?
+    };
     file.validate()?;
     Ok(file)
   }
 
   fn expand<R: FilesRead>(self, read: &R) -> Result<ConfigFile> {
     let iters: Vec<_> = self.projects.into_iter().map(move |p| p.expand(read)).collect::<Result<_>>()?;
-    let projects = iters.into_iter().flatten().collect();
+    let mut projects: Vec<Project> = iters.into_iter().flatten().collect();
+
+    for from in &self.projects_from {
+      for found in from.discover(read)? {
+        if let Some(p) = projects.iter().find(|p| p.id == found.id || p.name == found.name) {
+          bail!("Discovered project {} ({}) conflicts with project {} ({}).", found.name, found.id, p.name, p.id);
+        }
+        projects.push(found);
+      }
+    }
+
+    check_tag_majors(&projects)?;
+
+    let roots: HashMap<_, _> = projects.iter().map(|p| (p.id.clone(), p.root().cloned())).collect();
+    for project in &mut projects {
+      project.resolve_also(&roots)?;
+    }
+
+    Ok(ConfigFile { projects, ..self })
+  }
+
+  /// Keep only the selected projects (by ID, name, or label), along with their dependents and the other members of
+  /// their version groups. Dependencies on projects that aren't kept are dropped.
+  pub fn restrict(self, only: &[String]) -> Result<ConfigFile> {
+    let mut keep = HashSet::new();
+    for sel in only {
+      let found: Vec<_> = self.projects.iter().filter(|p| p.selected_by(sel)).map(|p| p.id.clone()).collect();
+      if found.is_empty() {
+        bail!("No project matches \"{}\".", sel);
+      }
+      keep.extend(found);
+    }
+
+    loop {
+      let before = keep.len();
+      for p in &self.projects {
+        if p.depends.keys().any(|id| keep.contains(id)) {
+          keep.insert(p.id.clone());
+        }
+      }
+      for group in self.options.version_groups() {
+        if group.iter().any(|id| keep.contains(id)) {
+          keep.extend(group.iter().cloned());
+        }
+      }
+      if keep.len() == before {
+        break;
+      }
+    }
 
+    let projects = self
+      .projects
+      .into_iter()
+      .filter(|p| keep.contains(&p.id))
+      .map(|mut p| {
+        p.depends.retain(|id, _| keep.contains(id));
+        p
+      })
+      .collect();
     Ok(ConfigFile { projects, ..self })
   }
 
+  /// Resolve any `{branch}` in the `prev_tag` option to the given branch name, so that each branch can keep its
+  /// own release history.
+  pub fn for_branch(mut self, branch: Option<&str>) -> Result<ConfigFile> {
+    if self.options.prev_tag.contains("{branch}") {
+      match branch {
+        Some(branch) => self.options.prev_tag = self.options.prev_tag.replace("{branch}", branch),
+        None => bail!("The prev_tag \"{}\" requires a branch, but there is none.", self.options.prev_tag)
+      }
+    }
+    Ok(self)
+  }
+
   pub fn prev_tag(&self) -> &str { self.options.prev_tag() }
   pub fn projects(&self) -> &[Project] { &self.projects }
   pub fn get_project(&self, id: &ProjectId) -> Option<&Project> { self.projects.iter().find(|p| p.id() == id) }
+
+  /// The current project that a project from an older config has become. A project with the same ID and name is
+  /// always a match; otherwise a project that lists the old ID or name in its `aliases`, and otherwise a project
+  /// with the same ID.
+  pub fn current_project(&self, prev: &Project) -> Option<&Project> {
+    self
+      .projects
+      .iter()
+      .find(|p| p.id == prev.id && p.name == prev.name)
+      .or_else(|| self.projects.iter().find(|p| p.aliases.matches(prev)))
+      .or_else(|| self.get_project(prev.id()))
+  }
+
   pub fn sizes(&self) -> &HashMap<String, Size> { &self.sizes }
   pub fn branch(&self) -> &Option<String> { self.options.branch() }
+  pub fn version_groups(&self) -> &[Vec<ProjectId>] { self.options.version_groups() }
+  pub fn github_host(&self) -> GithubHost { self.options.github_host() }
+  pub fn release_notes_template(&self) -> &str { self.options.release_notes_template() }
+  pub fn remote(&self) -> Option<&str> { self.options.remote() }
 
   pub fn hooks(&self) -> HashMap<ProjectId, (Option<&String>, &HookSet)> {
     self.projects.iter().map(|p| (p.id().clone(), (p.root(), p.hooks()))).collect()
@@ -293,7 +574,9 @@ impl ConfigFile {
   fn validate(&self) -> Result<()> {
     let mut ids = HashSet::new();
     let mut names = HashSet::new();
-    let mut prefs = HashSet::new();
+    let mut prefs = HashMap::new();
+    let mut old_ids = HashSet::new();
+    let mut old_names = HashSet::new();
 
     for p in &self.projects {
       if ids.contains(&p.id) {
@@ -307,13 +590,59 @@ impl ConfigFile {
       names.insert(p.name.clone());
 
       if let Some(pref) = &p.tag_prefix {
-        if prefs.contains(pref) {
-          bail!("tag_prefix {} is duplicated", pref);
+        // Projects can share a prefix only if they all claim majors (directly, or through their subs): after
+        // expansion, `check_tag_majors` ensures those don't overlap.
+        let majored = p.subs.is_some() || p.tag_majors().is_some();
+        if let Some(others_majored) = prefs.insert(pref.clone(), majored) {
+          if !(majored && others_majored) {
+            bail!("tag_prefix {} is duplicated", pref);
+          }
         }
         if !legal_tag(pref) {
-          bail!("illegal tag_prefix \"{}\"", pref);
+          return Err(VersioError::IllegalPrefix(pref.clone(), true).into());
+        }
+      }
+
+      if let Some(sep) = &p.tag_prefix_separator {
+        if !legal_separator(sep) {
+          bail!("illegal tag_prefix_separator \"{}\"", sep);
+        }
+      }
+
+      if let Some(min) = &p.min_version {
+        if Size::parts(min).is_err() {
+          bail!("illegal min_version \"{}\"", min);
+        }
+      }
+
+      if let Some(format) = &p.tag_format {
+        if format.matches("{version}").count() != 1 {
+          bail!("tag_format \"{}\" must have exactly one {{version}}", format);
+        }
+      }
+
+      for old_id in &p.aliases.old_ids {
+        if !old_ids.insert(old_id.clone()) {
+          bail!("old id {} is an alias of more than one project", old_id);
+        }
+      }
+      for old_name in &p.aliases.old_names {
+        if !old_names.insert(old_name.clone()) {
+          bail!("old name {} is an alias of more than one project", old_name);
+        }
+      }
+    }
+
+    let mut grouped = HashSet::new();
+    for group in self.options.version_groups() {
+      for id in group {
+        if !ids.contains(id) {
+          bail!("version group member {} is not a listed project (discovered projects can't be grouped)", id);
+        }
+        if grouped.contains(id) {
+          bail!("project {} is in more than one version group", id);
         }
-        prefs.insert(pref.clone());
+        grouped.insert(id.clone());
       }
     }
 
@@ -321,30 +650,281 @@ impl ConfigFile {
   }
 }
 
-#[derive(Deserialize, JsonSchema, Debug)]
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
 struct Options {
   #[serde(default = "default_prev_tag")]
   prev_tag: String,
   #[serde(default = "default_branch")]
-  branch: Option<String>
+  branch: Option<String>,
+  #[serde(default)]
+  version_groups: Vec<Vec<ProjectId>>,
+  github_host: Option<String>,
+  github_api_url: Option<String>,
+  gitea_host: Option<String>,
+  gitea_api_url: Option<String>,
+  release_notes_template: Option<String>,
+  remote: Option<String>
 }
 
 impl Default for Options {
-  fn default() -> Options { Options { prev_tag: default_prev_tag(), branch: default_branch() } }
+  fn default() -> Options {
+    Options {
+      prev_tag: default_prev_tag(),
+      branch: default_branch(),
+      version_groups: Vec::new(),
+      github_host: None,
+      github_api_url: None,
+      gitea_host: None,
+      gitea_api_url: None,
+      release_notes_template: None,
+      remote: None
+    }
+  }
 }
 
 impl Options {
   pub fn prev_tag(&self) -> &str { &self.prev_tag }
   pub fn branch(&self) -> &Option<String> { &self.branch }
+  pub fn version_groups(&self) -> &[Vec<ProjectId>] { &self.version_groups }
+
+  pub fn release_notes_template(&self) -> &str { self.release_notes_template.as_deref().unwrap_or("builtin:notes") }
+  pub fn remote(&self) -> Option<&str> { self.remote.as_deref() }
+
+  pub fn github_host(&self) -> GithubHost {
+    match &self.gitea_host {
+      Some(gitea_host) => GithubHost::gitea(gitea_host, self.gitea_api_url.as_deref()),
+      None => GithubHost::new(self.github_host.as_deref(), self.github_api_url.as_deref())
+    }
+  }
+}
+
+/// Bring a parsed config file into the shape that's deserialized: migrate it to the current version, merge the
+/// project defaults, and replace environment variables. Returns `false` if nothing was changed.
+pub fn preprocess_config(value: &mut serde_yaml::Value) -> Result<bool> {
+  let migrated = migrate_value(value)?;
+  if let Some((from, _)) = migrated {
+    trace!("Migrated config from version {} to {}.", from, CONFIG_VERSION);
+  }
+  let defaulted = with_project_defaults(value)?;
+  let interpolated = with_env_vars(value)?;
+  Ok(migrated.map(|(_, changed)| changed).unwrap_or(false) || defaulted || interpolated)
+}
+
+/// Merge the top-level `project_defaults` mapping (if any) into every listed project: each default key is added
+/// to each project that doesn't already have that key. Returns `false` if there are no defaults.
+fn with_project_defaults(value: &mut serde_yaml::Value) -> Result<bool> {
+  let config = match value.as_mapping_mut() {
+    Some(config) => config,
+    None => return Ok(false)
+  };
+  let defaults = match config.remove("project_defaults") {
+    Some(serde_yaml::Value::Mapping(defaults)) => defaults,
+    Some(serde_yaml::Value::Null) => return Ok(true),
+    Some(_) => bail!("project_defaults must be a mapping."),
+    None => return Ok(false)
+  };
+
+  if let Some(projects) = config.get_mut("projects").and_then(|p| p.as_sequence_mut()) {
+    for project in projects.iter_mut().filter_map(|p| p.as_mapping_mut()) {
+      for (key, default) in &defaults {
+        if !project.contains_key(key) {
+          project.insert(key.clone(), default.clone());
+        }
+      }
+    }
+  }
+  Ok(true)
+}
+
+/// Replace every `${VAR}` in the string values of the config with the value of the environment variable `VAR`,
+/// failing if it's not set. A `$${` is kept as a literal `${`. Returns `false` if nothing was replaced.
+fn with_env_vars(value: &mut serde_yaml::Value) -> Result<bool> { env_vars_at(value, "") }
+
+fn env_vars_at(value: &mut serde_yaml::Value, path: &str) -> Result<bool> {
+  match value {
+    serde_yaml::Value::String(text) => match interpolate_env(text, path)? {
+      Some(replaced) => {
+        *text = replaced;
+        Ok(true)
+      }
+      None => Ok(false)
+    },
+    serde_yaml::Value::Sequence(seq) => seq.iter_mut().enumerate().try_fold(false, |changed, (i, v)| {
+      Ok(env_vars_at(v, &format!("{}/{}", path, i))? || changed)
+    }),
+    serde_yaml::Value::Mapping(map) => map.iter_mut().try_fold(false, |changed, (k, v)| {
+      let key = k.as_str().map(|k| k.to_string()).unwrap_or_else(|| serde_yaml::to_string(k).unwrap_or_default());
+      Ok(env_vars_at(v, &format!("{}/{}", path, key.trim()))? || changed)
+    }),
+    serde_yaml::Value::Tagged(tagged) => env_vars_at(&mut tagged.value, path),
+    _ => Ok(false)
+  }
+}
+
+/// Replace the `${VAR}` references in a config value, or `None` if it has none. The `path` of the value is only
+/// used in errors.
+fn interpolate_env(text: &str, path: &str) -> Result<Option<String>> {
+  if !text.contains("${") {
+    return Ok(None);
+  }
+
+  let mut result = String::new();
+  let mut rest = text;
+  while let Some(i) = rest.find("${") {
+    if rest[.. i].ends_with('$') {
+      result.push_str(&rest[.. i - 1]);
+      result.push_str("${");
+      rest = &rest[i + 2 ..];
+      continue;
+    }
+
+    result.push_str(&rest[.. i]);
+    let len = rest[i ..].find('}').ok_or_else(|| bad!("Unclosed \"${{\" in config value at {}.", path))?;
+    let name = &rest[i + 2 .. i + len];
+    if name.is_empty() {
+      bail!("Empty \"${{}}\" in config value at {}.", path);
+    }
+    let val =
+      std::env::var(name).map_err(|_| bad!("Environment variable {} is not set, but is used at {}.", name, path))?;
+    result.push_str(&val);
+    rest = &rest[i + len + 1 ..];
+  }
+  result.push_str(rest);
+  Ok(Some(result))
+}
+
+/// A template for projects that are discovered, rather than listed: every file that matches `glob` marks the
+/// root directory of a new project, which is named after that directory.
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+struct ProjectsFrom {
+  glob: String,
+  version: Location,
+  changelog: Option<ChangelogConfig>,
+  #[serde(default, deserialize_with = "deser_labels")]
+  #[schemars(schema_with = "schema_labels")]
+  labels: Vec<String>,
+  #[serde(default)]
+  tag_prefix: bool
+}
+
+impl ProjectsFrom {
+  fn discover<R: FilesRead>(&self, read: &R) -> Result<Vec<Project>> {
+    let pattern = Pattern::new(&self.glob)?;
+    read
+      .files_matching(&pattern)?
+      .into_iter()
+      .map(|file| {
+        let root = match file.rfind('/') {
+          Some(slash) => file[.. slash].to_string(),
+          None => bail!("Can't discover a project at the base of the repo from {}: list it in \"projects\".", file)
+        };
+        let name = root.rsplit('/').next().unwrap().to_string();
+        let tag_prefix = if self.tag_prefix { Some(name.clone()) } else { None };
+        if let Some(pref) = &tag_prefix {
+          if !legal_tag(pref) {
+            return Err(VersioError::IllegalPrefix(pref.clone(), false))
+              .with_context(|| format!("Can't discover project at {}.", root));
+          }
+        }
+
+        Ok(Project {
+          id: ProjectId::from_id(discovered_id(&root)),
+          name,
+          root: Some(root),
+          includes: default_includes(),
+          excludes: Vec::new(),
+          depends: HashMap::new(),
+          changelog: self.changelog.clone(),
+          version: self.version.clone(),
+          derived: None,
+          also: Vec::new(),
+          mirror_version_file: None,
+          labels: self.labels.clone(),
+          tag_prefix,
+          tag_prefix_separator: None,
+          tag_format: None,
+          tag_message: None,
+          branch: None,
+          min_version: None,
+          scopes: Vec::new(),
+          subs: None,
+          aliases: Default::default(),
+          submodule: false,
+          hooks: Default::default()
+        })
+      })
+      .collect()
+  }
+}
+
+/// Generate a stable ID for a discovered project from its root, so that IDs (which are recorded in the prev
+/// tag) don't change as other projects come and go. This is 32-bit FNV-1a, kept out of the range of small
+/// hand-assigned IDs.
+fn discovered_id(root: &str) -> u32 {
+  let hash = root.bytes().fold(0x811c_9dc5u32, |h, b| (h ^ b as u32).wrapping_mul(0x0100_0193));
+  hash | 0x8000_0000
 }
 
-fn legal_tag(prefix: &str) -> bool {
+/// True if the tag prefix can start a version tag. Other than being plain ASCII and not starting with `-`, this
+/// follows git's rules for ref names (see `git check-ref-format`), so prefixes like `my.proj` or `libs/core` are
+/// fine.
+pub fn legal_tag(prefix: &str) -> bool {
   prefix.is_empty()
-    || ((prefix.starts_with('_') || prefix.chars().next().unwrap().is_alphabetic())
-      && (prefix.chars().all(|c| c.is_ascii() && (c == '/' || c == '_' || c == '-' || c.is_alphanumeric()))))
+    || (prefix.chars().all(|c| c.is_ascii_graphic() && !matches!(c, '~' | '^' | ':' | '?' | '*' | '[' | '\\'))
+      && !prefix.starts_with('-')
+      && !prefix.ends_with('.')
+      && !prefix.contains("..")
+      && !prefix.contains("@{")
+      && prefix != "@"
+      && prefix.split('/').all(|part| !part.is_empty() && !part.starts_with('.') && !part.ends_with(".lock")))
+}
+
+/// Escape the characters that are special to the fnmatch patterns used to find tags.
+fn fnmatch_escape(literal: &str) -> String {
+  let mut escaped = String::with_capacity(literal.len());
+  for c in literal.chars() {
+    if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+      escaped.push('\\');
+    }
+    escaped.push(c);
+  }
+  escaped
+}
+
+/// True if the separator can join a tag prefix to the rest of a tag: it can't be empty, and can't have any
+/// characters that are special to git refs or to the patterns that find tags.
+pub fn legal_separator(sep: &str) -> bool {
+  !sep.is_empty()
+    && !sep.contains("..")
+    && sep.chars().all(|c| c.is_ascii() && (matches!(c, '/' | '_' | '-' | '.' | '+' | '@') || c.is_alphanumeric()))
+}
+
+/// Convert a tag prefix into a legal one, by replacing illegal characters with `-`. The `@` of a scoped package name
+/// is dropped, so that `@scope/pkg` becomes `scope/pkg`.
+pub fn tag_sanitize(prefix: &str) -> String {
+  let legal = |c: char| c.is_ascii() && (c == '/' || c == '_' || c == '-' || c.is_alphanumeric());
+  let prefix = prefix.strip_prefix('@').unwrap_or(prefix);
+  let sanitized: String = prefix.chars().map(|c| if legal(c) { c } else { '-' }).collect();
+  if sanitized.is_empty() || sanitized.starts_with('_') || sanitized.chars().next().unwrap().is_alphabetic() {
+    sanitized
+  } else {
+    format!("_{}", sanitized)
+  }
+}
+
+/// True if the branch name matches the pattern. A pattern surrounded by slashes (`/release-\d+/`) is a regular
+/// expression which must match the entire branch name; any other pattern is a glob (`release/*`), which
+/// includes plain branch names.
+pub fn branch_matches(pattern: &str, branch: &str) -> Result<bool> {
+  if pattern.len() > 1 && pattern.starts_with('/') && pattern.ends_with('/') {
+    let regex = Regex::new(&format!("^(?:{})$", &pattern[1 .. pattern.len() - 1]))?;
+    Ok(regex.is_match(branch))
+  } else {
+    Ok(Pattern::new(pattern)?.matches_with(branch, match_opts()))
+  }
 }
 
-#[derive(Deserialize, JsonSchema, Debug)]
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
 pub struct Project {
   name: String,
   id: ProjectId,
@@ -356,17 +936,30 @@ pub struct Project {
   #[serde(default)]
   depends: HashMap<ProjectId, Depends>,
   changelog: Option<ChangelogConfig>,
+  #[serde(alias = "version_from")]
   version: Location,
+  derived: Option<Derived>,
   #[serde(default)]
-  also: Vec<Location>,
+  also: Vec<AlsoLocation>,
+  mirror_version_file: Option<String>,
   #[serde(default, deserialize_with = "deser_labels")]
   #[schemars(schema_with = "schema_labels")]
   labels: Vec<String>,
   tag_prefix: Option<String>,
   tag_prefix_separator: Option<String>,
+  tag_format: Option<String>,
+  tag_message: Option<String>,
+  branch: Option<String>,
+  min_version: Option<String>,
+  #[serde(default)]
+  scopes: Vec<String>,
   #[serde(default)]
   subs: Option<Subs>,
   #[serde(default)]
+  aliases: Aliases,
+  #[serde(default)]
+  submodule: bool,
+  #[serde(default)]
   hooks: HookSet
 }
 
@@ -378,6 +971,31 @@ impl Project {
   pub fn hooks(&self) -> &HookSet { &self.hooks }
   pub fn labels(&self) -> &[String] { &self.labels }
 
+  /// How the version is derived from the versions of the projects it depends on, if it is.
+  pub fn derived(&self) -> Option<&Derived> { self.derived.as_ref() }
+
+  /// The pattern of branches that this project may be released from, if it's restricted.
+  pub fn branch(&self) -> Option<&str> { self.branch.as_deref() }
+
+  /// The lowest version that a release of this project may have, if there is one.
+  pub fn min_version(&self) -> Option<&str> { self.min_version.as_deref() }
+
+  /// Raise a planned version to the `min_version`, if it's lower.
+  pub fn floor_version(&self, vers: String) -> Result<String> {
+    match &self.min_version {
+      Some(min) if Size::less_than(&vers, min)? => Ok(min.clone()),
+      _ => Ok(vers)
+    }
+  }
+
+  /// True if the project declares the scope of a conventional commit, such as the `api` of `feat(api): ...`.
+  pub fn has_scope(&self, scope: &str) -> bool { self.scopes.iter().any(|s| s.eq_ignore_ascii_case(scope)) }
+
+  /// True if the selector is the ID, name, or one of the labels of this project.
+  fn selected_by(&self, sel: &str) -> bool {
+    sel.parse::<u32>().ok() == Some(self.id.id) || self.name == sel || self.labels.iter().any(|l| l == sel)
+  }
+
   fn annotate<S: StateRead>(&self, state: &S) -> Result<AnnotatedMark> {
     Ok(AnnotatedMark::new(self.id.clone(), self.name.clone(), self.get_value(state)?))
   }
@@ -410,10 +1028,26 @@ impl Project {
   pub fn tag_prefix(&self) -> &Option<String> { &self.tag_prefix }
   pub fn tag_prefix_separator(&self) -> &str { self.tag_prefix_separator.as_deref().unwrap_or("-") }
   pub fn tag_majors(&self) -> Option<&[u32]> { self.version.tag_majors() }
+  pub fn reads_files(&self) -> bool { self.version.reads_files() }
+
+  /// True if the version is fetched from a URL: the project has no files of its own, and is released only to tag
+  /// the latest version that it has seen.
+  pub fn is_external(&self) -> bool { self.version.is_url() }
+
+  /// The links for a release of this project: `compare` is the URL of a comparison with the previous release.
+  pub fn release_links(&self, compare: Option<String>) -> ReleaseLinks {
+    ReleaseLinks::new(self.changelog.as_ref().and_then(|c| c.issues()), compare, self.shared_prs())
+      .with_notes(self.changelog.as_ref().map(|c| c.pr_notes()).unwrap_or(false))
+      .with_dates(self.changelog.as_ref().map(|c| c.dates().clone()).unwrap_or_default())
+  }
+
+  /// How the changelog lists PRs that are also released in other projects.
+  pub fn shared_prs(&self) -> SharedPrs { self.changelog.as_ref().map(|c| c.shared_prs()).unwrap_or_default() }
 
   pub async fn write_changelog(
-    &self, write: &mut StateWrite, cl: &Changelog, new_vers: &str
+    &self, write: &mut StateWrite, cl: &Changelog, new_vers: &str, links: &ReleaseLinks<'_>
   ) -> Result<Option<PathBuf>> {
+    self.annotate_tag(write, cl, new_vers, links)?;
     if cl.is_empty() {
       return Ok(None);
     }
@@ -422,9 +1056,10 @@ impl Project {
       let log_path = PathBuf::from_slash(log_path.as_ref());
       let old_content = extract_old_content(&log_path)?;
       let tmpl = read_template(template, self.root().map(PathBuf::from_slash).as_deref(), true).await?;
+      let proj = ProjLine::from_version(self, new_vers.to_string())?;
       write.write_file(
         log_path.clone(),
-        construct_changelog_html(cl, ProjLine::from_version(self, new_vers.to_string())?, new_vers, old_content, tmpl)?,
+        construct_changelog_html(cl, proj, new_vers, links, old_content, tmpl)?,
         self.id(),
         true
       )?;
@@ -435,15 +1070,30 @@ impl Project {
   }
 
   pub fn size(&self, parent_sizes: &HashMap<String, Size>, kind: &str) -> Result<Size> {
+    self.size_entry(parent_sizes, kind).map(|(_, size)| size)
+  }
+
+  /// The size of a commit kind, along with the entry of the sizes map that gave it: the kind itself, or `*`.
+  pub fn size_entry<'a>(&self, parent_sizes: &'a HashMap<String, Size>, kind: &str) -> Result<(&'a str, Size)> {
     let kind = kind.trim();
     parent_sizes
-      .get(kind)
-      .copied()
-      .map(Ok)
-      .unwrap_or_else(|| parent_sizes.get("*").copied().map(Ok).unwrap_or_else(|| err!("Unknown kind \"{}\".", kind)))
+      .get_key_value(kind)
+      .or_else(|| parent_sizes.get_key_value("*"))
+      .map(|(entry, size)| (entry.as_str(), *size))
+      .ok_or_else(|| bad!("Unknown kind \"{}\".", kind))
   }
 
   pub fn does_cover(&self, path: &str) -> Result<bool> {
+    if self.is_external() {
+      return Ok(false);
+    }
+
+    // A changed gitlink at the root means that the submodule was moved to a different commit.
+    if self.submodule && self.root().map(|r| r.trim_end_matches('/') == path).unwrap_or(false) {
+      trace!("submodule {} bumped at {}", self.id(), path);
+      return Ok(true);
+    }
+
     let excludes = self.excludes.iter().try_fold::<_, _, Result<_>>(false, |val, cov| {
       Ok(
         val || {
@@ -471,6 +1121,35 @@ impl Project {
     })
   }
 
+  /// The rooted include pattern that covers the path, if the project covers it at all. A submodule bump is
+  /// covered by the root of the project.
+  fn covering_include(&self, path: &str) -> Result<Option<String>> {
+    if !self.does_cover(path)? {
+      return Ok(None);
+    }
+    for cov in &self.includes {
+      let rooted = self.rooted_pattern(cov);
+      if Pattern::new(&rooted)?.matches_with(path, match_opts()) {
+        return Ok(Some(rooted));
+      }
+    }
+    Ok(self.root().cloned())
+  }
+
+  /// True if the root of one project is a subdirectory of the other's root. Such projects are expected to cover
+  /// some of the same files. A project without a root isn't nested with anything, even though it is at the base of
+  /// the repo: otherwise it could never overlap.
+  fn nests_with(&self, other: &Project) -> bool {
+    match (self.root(), other.root()) {
+      (Some(mine), Some(theirs)) => {
+        let (mine, theirs) = (mine.trim_end_matches('/'), theirs.trim_end_matches('/'));
+        let inside = |inner: &str, outer: &str| inner.starts_with(&format!("{}/", outer));
+        mine != theirs && (inside(mine, theirs) || inside(theirs, mine))
+      }
+      _ => false
+    }
+  }
+
   pub fn check<S: StateRead>(&self, state: &S) -> Result<()> {
     // Check that we can find the given mark.
     self.get_value(state)?;
@@ -479,10 +1158,14 @@ impl Project {
 
     self.check_prefix()?;
 
-    // Check that each pattern includes at least one file.
-    for cov in &self.includes {
+    self.check_derived()?;
+
+    self.check_external()?;
+
+    // Check that each pattern includes at least one (non-ignored) file. An external project has no files.
+    for cov in self.includes.iter().filter(|_| !self.is_external()) {
       let pattern = self.rooted_pattern(cov);
-      if !glob_with(&pattern, match_opts())?.any(|_| true) {
+      if !self.covers_unignored(&pattern)? {
         return err!("No files in proj. {} covered by \"{}\".", self.id, pattern);
       }
     }
@@ -490,6 +1173,19 @@ impl Project {
     Ok(())
   }
 
+  fn covers_unignored(&self, pattern: &str) -> Result<bool> {
+    let pattern = Pattern::new(pattern)?;
+    let base = self.root().map(PathBuf::from_slash).unwrap_or_else(|| PathBuf::from("."));
+    for entry in unignored_walk(&base, None) {
+      let entry = entry?;
+      let path = entry.path().strip_prefix(".").unwrap_or_else(|_| entry.path()).to_slash_lossy();
+      if pattern.matches_with(&path, match_opts()) {
+        return Ok(true);
+      }
+    }
+    Ok(false)
+  }
+
   /// Ensure that we don't have excludes without includes.
   fn check_excludes(&self) -> Result<()> {
     if !self.excludes.is_empty() && self.includes.is_empty() {
@@ -506,6 +1202,32 @@ impl Project {
     Ok(())
   }
 
+  /// Ensure that a URL isn't written to, and that an external project has a tag to record the versions it has seen.
+  fn check_external(&self) -> Result<()> {
+    if self.is_external() {
+      if self.tag_prefix.is_none() {
+        bail!("Proj {} has a url version without tag_prefix.", self.id);
+      }
+      if self.changelog.is_some() || !self.also.is_empty() || self.mirror_version_file.is_some() {
+        bail!("Proj {} has a url version, so it can't have a changelog, also, or mirror_version_file.", self.id);
+      }
+    }
+
+    let mut writes = self.also.iter().map(|a| &a.location).chain(self.depends.values().flat_map(|d| &d.files));
+    if writes.any(|l| l.is_url()) {
+      bail!("Proj {} writes to a url in also or depends, which is read-only.", self.id);
+    }
+    Ok(())
+  }
+
+  /// Ensure that a derived version has dependencies to derive from.
+  fn check_derived(&self) -> Result<()> {
+    if self.derived.is_some() && self.depends.is_empty() {
+      bail!("Proj {} has a derived version, but no depends.", self.id);
+    }
+    Ok(())
+  }
+
   pub fn get_value<S: StateRead>(&self, read: &S) -> Result<String> {
     self.version.read_value(read, self.root(), self.id())
   }
@@ -513,33 +1235,104 @@ impl Project {
   pub fn set_value(&self, write: &mut StateWrite, vers: &str) -> Result<()> {
     self.version.write_value(write, self.root(), vers, &self.id)?;
     self.set_also(write, vers)?;
+    self.set_mirror(write, vers)?;
     self.forward_tag(write, vers)
   }
 
   fn set_also(&self, write: &mut StateWrite, vers: &str) -> Result<()> {
-    for loc in &self.also {
-      loc.write_value(write, self.root(), vers, &self.id)?;
+    for also in &self.also {
+      match &also.project {
+        Some(target) => {
+          also.location.write_value(write, also.project_root.as_ref(), vers, &self.id)?;
+          write.attribute_to(target);
+        }
+        None => also.location.write_value(write, self.root(), vers, &self.id)?
+      }
+    }
+    Ok(())
+  }
+
+  /// Find the roots of other projects that are targeted by `also` locations.
+  fn resolve_also(&mut self, roots: &HashMap<ProjectId, Option<String>>) -> Result<()> {
+    for also in &mut self.also {
+      if let Some(target) = &also.project {
+        let root = roots.get(target).ok_or_else(|| bad!("Proj {} has 'also' in unknown project {}.", self.id, target))?;
+        also.project_root = root.clone();
+      }
+    }
+    Ok(())
+  }
+
+  /// Write the plain version file, if any: the whole file is replaced with the version number.
+  fn set_mirror(&self, write: &mut StateWrite, vers: &str) -> Result<()> {
+    if let Some(mirror) = &self.mirror_version_file {
+      let path = match self.root() {
+        Some(root) => PathBuf::from_slash(root).join(PathBuf::from_slash(mirror)),
+        None => PathBuf::from_slash(mirror)
+      };
+      write.write_file(path, format!("{}\n", vers), &self.id, false)?;
     }
     Ok(())
   }
 
+  /// Move the project's tag to the version. Unless the changelog was already written to the tag's message, the
+  /// message is rendered without one.
   pub fn forward_tag(&self, write: &mut StateWrite, vers: &str) -> Result<()> {
     if let Some(full_tag) = self.full_version(vers) {
+      let annotated = write.has_tag_message(&full_tag);
       write.tag_head_or_last(vers, full_tag, &self.id)?;
+      if !annotated {
+        self.annotate_tag(write, &Changelog::empty(), vers, &self.release_links(None))?;
+      }
+    }
+    Ok(())
+  }
+
+  /// Render the `tag_message` template (if any) as the message of the project's tag for the given version.
+  fn annotate_tag(&self, write: &mut StateWrite, cl: &Changelog, vers: &str, links: &ReleaseLinks) -> Result<()> {
+    if let (Some(tmpl), Some(full_tag)) = (&self.tag_message, self.full_version(vers)) {
+      let msg = construct_tag_message(cl, ProjLine::from_version(self, vers.to_string())?, vers, links, tmpl)?;
+      write.annotate_tag(full_tag, msg)?;
     }
     Ok(())
   }
 
   pub fn full_version(&self, vers: &str) -> Option<String> {
-    let tag_prefix_separator = self.tag_prefix_separator();
+    self.tag_prefix.as_ref().map(|tag_prefix| self.format_tag(tag_prefix, vers))
+  }
 
-    self.tag_prefix.as_ref().map(|tag_prefix| {
-      if tag_prefix.is_empty() {
-        format!("v{}", vers)
-      } else {
-        format!("{}{}v{}", tag_prefix, tag_prefix_separator, vers)
-      }
-    })
+  /// The version of one of the project's version tags, or `None` if it isn't one.
+  pub fn tag_version(&self, tag: &str) -> Option<String> {
+    self.tag_prefix.as_ref().and_then(|tag_prefix| self.parse_tag(tag_prefix, tag))
+  }
+
+  /// The tag of a version under the given tag prefix, according to the `tag_format`.
+  pub fn format_tag(&self, tag_prefix: &str, vers: &str) -> String {
+    self.prefixed_format(tag_prefix).replace("{version}", vers)
+  }
+
+  /// An fnmatch pattern for tags under the given tag prefix, where `vers_pattern` (e.g. `*` or `1.*`) matches the
+  /// version. Everything else in the tag is escaped, so it's matched literally.
+  pub fn tag_pattern(&self, tag_prefix: &str, vers_pattern: &str) -> String {
+    fnmatch_escape(&self.prefixed_format(tag_prefix)).replace("{version}", vers_pattern)
+  }
+
+  /// The version of a tag under the given tag prefix, or `None` if the tag isn't a version tag.
+  pub fn parse_tag(&self, tag_prefix: &str, tag: &str) -> Option<String> {
+    let format = self.prefixed_format(tag_prefix);
+    let (head, tail) = format.split_once("{version}")?;
+    let vers = tag.strip_prefix(head)?.strip_suffix(tail)?;
+    Size::parts(vers).ok().map(|_| vers.to_string())
+  }
+
+  /// The `tag_format` with the given tag prefix filled in, which still has its `{version}`. Without a `tag_format`,
+  /// this is the prefix and separator (if the prefix isn't empty) followed by `v{version}`.
+  fn prefixed_format(&self, tag_prefix: &str) -> String {
+    match &self.tag_format {
+      Some(format) => format.replace("{prefix}", tag_prefix),
+      None if tag_prefix.is_empty() => "v{version}".to_string(),
+      None => format!("{}{}v{{version}}", tag_prefix, self.tag_prefix_separator())
+    }
   }
 
   fn rooted_pattern(&self, pat: &str) -> String {
@@ -565,11 +1358,20 @@ impl Project {
         depends: expand_depends(&self.depends, &sub),
         changelog: self.changelog.clone(),
         version: expand_version(&self.version, &sub),
+        derived: self.derived.clone(),
         also: expand_also(&self.also),
+        mirror_version_file: self.mirror_version_file.clone(),
         labels: Default::default(),
         tag_prefix: self.tag_prefix.clone(),
         tag_prefix_separator: self.tag_prefix_separator.clone(),
+        tag_format: self.tag_format.clone(),
+        tag_message: self.tag_message.clone(),
+        branch: self.branch.clone(),
+        min_version: self.min_version.clone(),
+        scopes: self.scopes.clone(),
         subs: None,
+        aliases: self.aliases.expand(&sub),
+        submodule: false,
         hooks: self.hooks.clone()
       })))
     } else {
@@ -580,7 +1382,9 @@ impl Project {
   fn read_subs<R: FilesRead>(&self, read: &R) -> Result<Option<Vec<SubExtent>>> {
     if let Some(subs) = &self.subs {
       let pattern = format!("^{}$", escape(subs.dirs()).replace("<>", "([0-9]+)"));
-      let dirs = read.subdirs(self.root(), &pattern)?;
+      let skips = subs.excludes()?;
+      let dirs: Vec<_> =
+        read.subdirs(self.root(), &pattern)?.into_iter().filter(|d| !skips.iter().any(|s| s.matches(d))).collect();
       let regex = Regex::new(&pattern)?;
       let extents: Vec<_> = dirs
         .iter()
@@ -614,17 +1418,30 @@ impl Project {
 #[derive(Clone, Debug)]
 pub struct ChangelogConfig {
   file: String,
-  template: String
+  template: String,
+  issues: Option<IssueLinks>,
+  shared_prs: SharedPrs,
+  pr_notes: bool,
+  dates: DateFormat
 }
 
 impl ChangelogConfig {
-  pub fn from_file(file: String) -> ChangelogConfig { ChangelogConfig { file, template: default_changelog_template() } }
+  pub fn from_file(file: String) -> ChangelogConfig {
+    let template = default_changelog_template();
+    let dates = DateFormat::default();
+    ChangelogConfig { file, template, issues: None, shared_prs: SharedPrs::Full, pr_notes: false, dates }
+  }
 
   pub fn file(&self) -> &str { &self.file }
   pub fn template(&self) -> &str { &self.template }
+  pub fn issues(&self) -> Option<&IssueLinks> { self.issues.as_ref() }
+  pub fn shared_prs(&self) -> SharedPrs { self.shared_prs }
+  pub fn pr_notes(&self) -> bool { self.pr_notes }
+  pub fn dates(&self) -> &DateFormat { &self.dates }
 }
 
 fn default_changelog_template() -> String { "builtin:html".to_string() }
+fn default_date_format() -> String { DEFAULT_DATE_FORMAT.to_string() }
 
 impl<'de> Deserialize<'de> for ChangelogConfig {
   fn deserialize<D: Deserializer<'de>>(desr: D) -> std::result::Result<ChangelogConfig, D::Error> {
@@ -645,17 +1462,40 @@ impl<'de> Deserialize<'de> for ChangelogConfig {
         struct InnerConfig {
           file: String,
           #[serde(default = "default_changelog_template")]
-          template: String
+          template: String,
+          issue_url: Option<String>,
+          issue_pattern: Option<String>,
+          #[serde(default)]
+          shared_prs: SharedPrs,
+          #[serde(default)]
+          pr_notes: bool,
+          #[serde(default = "default_date_format")]
+          date_format: String,
+          timezone: Option<String>
         }
 
         impl InnerConfig {
-          pub fn into_changelog(self) -> ChangelogConfig {
-            ChangelogConfig { file: self.file, template: self.template }
+          pub fn into_changelog(self) -> Result<ChangelogConfig> {
+            let issues = match (self.issue_url, self.issue_pattern) {
+              (Some(url), pattern) => Some(IssueLinks::new(url, pattern.as_deref().unwrap_or(DEFAULT_ISSUE_PATTERN))?),
+              (None, Some(_)) => bail!("Changelog issue_pattern requires an issue_url."),
+              (None, None) => None
+            };
+            let zone = self.timezone.as_deref().map(DateZone::parse).transpose()?.unwrap_or_default();
+            let dates = DateFormat::new(self.date_format, zone)?;
+            Ok(ChangelogConfig {
+              file: self.file,
+              template: self.template,
+              issues,
+              shared_prs: self.shared_prs,
+              pr_notes: self.pr_notes,
+              dates
+            })
           }
         }
 
         let i: InnerConfig = Deserialize::deserialize(de::value::MapAccessDeserializer::new(map))?;
-        Ok(i.into_changelog())
+        i.into_changelog().map_err(de::Error::custom)
       }
     }
 
@@ -673,14 +1513,30 @@ impl JsonSchema for ChangelogConfig {
     let mut properties = schemars::Map::new();
     let file_schema: SchemaObject = <String>::json_schema(gen).into();
     let template_schema: SchemaObject = <String>::json_schema(gen).into();
+    let issue_url_schema: SchemaObject = <String>::json_schema(gen).into();
+    let issue_pattern_schema: SchemaObject = <String>::json_schema(gen).into();
     properties.insert("file".into(), file_schema.into());
     properties.insert("template".into(), template_schema.into());
+    properties.insert("issue_url".into(), issue_url_schema.into());
+    properties.insert("issue_pattern".into(), issue_pattern_schema.into());
+    let shared_prs_schema = SchemaObject {
+      instance_type: Some(InstanceType::String.into()),
+      enum_values: Some(vec!["full".into(), "link".into(), "collapse".into()]),
+      ..Default::default()
+    };
+    properties.insert("shared_prs".into(), shared_prs_schema.into());
+    let pr_notes_schema: SchemaObject = <bool>::json_schema(gen).into();
+    properties.insert("pr_notes".into(), pr_notes_schema.into());
+    let date_format_schema: SchemaObject = <String>::json_schema(gen).into();
+    properties.insert("date_format".into(), date_format_schema.into());
+    let timezone_schema: SchemaObject = <String>::json_schema(gen).into();
+    properties.insert("timezone".into(), timezone_schema.into());
 
     Schema::Object(SchemaObject {
       instance_type: Some(SingleOrVec::Vec(vec![InstanceType::String, InstanceType::Object])),
       string: Some(Box::default()),
       object: Some(Box::new(ObjectValidation {
-        max_properties: Some(2),
+        max_properties: Some(8),
         min_properties: Some(1),
         required,
         properties,
@@ -691,6 +1547,27 @@ impl JsonSchema for ChangelogConfig {
   }
 }
 
+/// The IDs and names that a project had in older configs, so that its history is still attributed to it after it's
+/// renumbered or renamed.
+#[derive(Deserialize, JsonSchema, Debug, Clone, Default)]
+pub struct Aliases {
+  #[serde(default)]
+  old_ids: Vec<ProjectId>,
+  #[serde(default)]
+  old_names: Vec<String>
+}
+
+impl Aliases {
+  fn matches(&self, prev: &Project) -> bool { self.old_ids.contains(&prev.id) || self.old_names.contains(&prev.name) }
+
+  fn expand(&self, sub: &SubExtent) -> Aliases {
+    Aliases {
+      old_ids: self.old_ids.iter().map(|id| id.expand(sub)).collect(),
+      old_names: self.old_names.iter().map(|name| expand_name(name, sub)).collect()
+    }
+  }
+}
+
 #[derive(Deserialize, JsonSchema, Debug, Clone)]
 pub struct Depends {
   #[serde(default)]
@@ -714,8 +1591,103 @@ impl Depends {
 
 fn default_relative_size() -> RelativeSize { RelativeSize::Match }
 
+/// How the version of an umbrella project is derived from the versions of the projects it depends on.
 #[derive(Debug, Clone)]
-pub enum RelativeSize {
+pub enum Derived {
+  /// The largest version of any dependency: `max(children)`.
+  Max,
+  /// The sum of the majors of every dependency, and likewise for the minors and patches: `sum_majors(children)`.
+  SumMajors,
+  /// A Liquid template that renders the version from the `children` and their `max`.
+  Template(String)
+}
+
+impl<'de> Deserialize<'de> for Derived {
+  fn deserialize<D: Deserializer<'de>>(desr: D) -> std::result::Result<Derived, D::Error> {
+    struct StrVisitor;
+
+    impl<'de> Visitor<'de> for StrVisitor {
+      type Value = Derived;
+
+      fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("max(children), sum_majors(children), or a template")
+      }
+
+      fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<Derived, E> {
+        match v.trim() {
+          "max(children)" => Ok(Derived::Max),
+          "sum_majors(children)" => Ok(Derived::SumMajors),
+          t if t.contains("{{") => Ok(Derived::Template(t.to_string())),
+          _ => Err(E::invalid_value(Unexpected::Str(v), &self))
+        }
+      }
+    }
+
+    desr.deserialize_str(StrVisitor)
+  }
+}
+
+impl JsonSchema for Derived {
+  fn schema_name() -> String { "Derived".into() }
+
+  fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+    let string_schema: SchemaObject = <String>::json_schema(gen).into();
+    string_schema.into()
+  }
+}
+
+impl Derived {
+  /// Derive a version from the new version of each dependency. The derived version may not be lower than the
+  /// `current` version of the project, if it has one.
+  pub fn derive(&self, children: &[(&Project, String)], current: Option<&str>) -> Result<String> {
+    let mut max: Option<&str> = None;
+    let mut sums = [0; 3];
+    for (_, vers) in children {
+      for (sum, part) in sums.iter_mut().zip(Size::parts(vers)?) {
+        *sum += part;
+      }
+      if max.map(|max| Size::less_than(max, vers)).transpose()?.unwrap_or(true) {
+        max = Some(vers.as_str());
+      }
+    }
+    let max = max.ok_or_else(|| bad!("No dependency versions to derive a version from."))?;
+
+    let vers = match self {
+      Derived::Max => max.to_string(),
+      Derived::SumMajors => format!("{}.{}.{}", sums[0], sums[1], sums[2]),
+      Derived::Template(tmpl) => {
+        let children = children
+          .iter()
+          .map(|(proj, vers)| {
+            let [major, minor, patch] = Size::parts(vers)?;
+            Ok(liquid::object!({
+              "id": proj.id().to_string(),
+              "name": proj.name(),
+              "version": vers,
+              "major": major,
+              "minor": minor,
+              "patch": patch
+            }))
+          })
+          .collect::<Result<Vec<_>>>()?;
+        let tmpl = ParserBuilder::with_stdlib().build()?.parse(tmpl)?;
+        let vers = tmpl.render(&liquid::object!({ "children": children, "max": max }))?.trim().to_string();
+        Size::parts(&vers).with_context(|| format!("Derived version template rendered \"{}\".", vers))?;
+        vers
+      }
+    };
+
+    if let Some(current) = current {
+      if Size::less_than(&vers, current)? {
+        bail!("Derived version {} is lower than the current version {}.", vers, current);
+      }
+    }
+    Ok(vers)
+  }
+}
+
+#[derive(Debug, Clone)]
+pub enum RelativeSize {
   Match,
   Exact(Size)
 }
@@ -846,6 +1818,33 @@ impl JsonSchema for Hook {
   }
 }
 
+/// Ensure that no two projects (such as the subprojects of a `subs` project) claim the same major version under the
+/// same tag prefix, since their version tags would collide. A project whose version isn't in tags isn't restricted
+/// to any majors, so it isn't checked.
+fn check_tag_majors(projects: &[Project]) -> Result<()> {
+  let mut claimed: HashMap<(&str, u32), &Project> = HashMap::new();
+  for p in projects {
+    let (pref, majors) = match (p.tag_prefix(), p.tag_majors()) {
+      (Some(pref), Some(majors)) => (pref, majors),
+      _ => continue
+    };
+    for major in majors {
+      if let Some(other) = claimed.insert((pref.as_str(), *major), p) {
+        bail!(
+          "Subprojects {} ({}) and {} ({}) both use tag_prefix \"{}\" for major {}.",
+          other.name,
+          other.id,
+          p.name,
+          p.id,
+          pref,
+          major
+        );
+      }
+    }
+  }
+  Ok(())
+}
+
 fn expand_name(name: &str, sub: &SubExtent) -> String {
   match sub.dir() {
     Some(subdir) => format!("{}/{}", name, subdir),
@@ -885,7 +1884,9 @@ fn expand_version(version: &Location, sub: &SubExtent) -> Location {
   }
 }
 
-fn expand_also(also: &[Location]) -> Vec<Location> { also.iter().filter(|l| !l.is_tags()).cloned().collect() }
+fn expand_also(also: &[AlsoLocation]) -> Vec<AlsoLocation> {
+  also.iter().filter(|l| !l.location.is_tags()).cloned().collect()
+}
 
 struct SubExtent {
   // TODO: ensure `dir` will always be a single-level relative path (i.e. no slashes)
@@ -902,12 +1903,29 @@ impl SubExtent {
   pub fn majors(&self) -> &[u32] { &self.majors }
 }
 
+/// An additional location for a project's version, which is either in the project itself, or (if `project` is
+/// given) relative to the root of another project.
+#[derive(Clone, Deserialize, JsonSchema, Debug)]
+struct AlsoLocation {
+  project: Option<ProjectId>,
+  #[serde(skip)]
+  project_root: Option<String>,
+  #[serde(flatten)]
+  location: Location
+}
+
+/// The version in the `module(...)` call of a Bazel `MODULE.bazel` file, which may span several lines.
+pub const BAZEL_MODULE_PATTERN: &str = r#"\bmodule\s*\([^)]*?\bversion\s*=\s*"([^"]*)""#;
+
 #[derive(Clone, Debug)]
 // #[serde(untagged)]
 enum Location {
   File(FileLocation),
   Tag(TagLocation),
-  Cmd(Getter, Setter)
+  Cmd(Getter, Setter),
+  Helm(FileLocation, Option<FileLocation>),
+  NpmDependency(NpmDependency),
+  Url(UrlLocation)
 }
 
 #[derive(Clone, Debug)]
@@ -953,11 +1971,18 @@ impl Setter {
 
 impl Location {
   pub fn is_tags(&self) -> bool { matches!(self, Location::Tag(_)) }
+  pub fn is_url(&self) -> bool { matches!(self, Location::Url(_)) }
+  pub fn reads_files(&self) -> bool {
+    matches!(self, Location::File(_) | Location::Helm(..) | Location::NpmDependency(_))
+  }
 
   pub fn tag_majors(&self) -> Option<&[u32]> {
     match self {
       Location::File(_) => None,
       Location::Cmd(..) => None,
+      Location::Helm(..) => None,
+      Location::NpmDependency(_) => None,
+      Location::Url(_) => None,
       Location::Tag(tagl) => tagl.majors()
     }
   }
@@ -966,7 +1991,16 @@ impl Location {
     match self {
       Location::File(l) => l.write_value(write, root, vers, id),
       Location::Tag(_) => Ok(()),
-      Location::Cmd(_, setter) => setter.exec(write, root, vers, id)
+      Location::Cmd(_, setter) => setter.exec(write, root, vers, id),
+      Location::Helm(version, app_version) => {
+        version.write_value(write, root, vers, id)?;
+        if let Some(app_version) = app_version {
+          app_version.write_value(write, root, vers, id)?;
+        }
+        Ok(())
+      }
+      Location::NpmDependency(l) => l.write_value(write, root, vers, id),
+      Location::Url(l) => bail!("Can't write to read-only location {}.", l.url)
     }
   }
 
@@ -974,16 +2008,39 @@ impl Location {
     match self {
       Location::File(l) => l.read_value(read, root),
       Location::Tag(l) => Ok(l.read_value(read, proj)),
-      Location::Cmd(getter, _) => getter.exec(root)
+      Location::Cmd(getter, _) => getter.exec(root),
+      Location::Helm(version, _) => version.read_value(read, root),
+      Location::NpmDependency(l) => l.read_value(read, root),
+      Location::Url(l) => l.read_value(read, proj)
     }
   }
 
+  /// A Bazel module location: the `version` of the `module(...)` call in a `MODULE.bazel` file.
+  fn bazel(file: Option<String>, format: Option<String>) -> Location {
+    let file = file.unwrap_or_else(|| "MODULE.bazel".into());
+    let picker = Picker::Line(LinePicker::new(BAZEL_MODULE_PATTERN.into(), false));
+    Location::File(FileLocation { file, format, picker })
+  }
+
+  /// A Helm chart location: `version` and (optionally) `appVersion` in a `Chart.yaml` file.
+  fn helm(file: Option<String>, app_version: bool) -> Location {
+    let file = file.unwrap_or_else(|| "Chart.yaml".into());
+    let pick = |key: &str| FileLocation {
+      file: file.clone(),
+      format: None,
+      picker: Picker::Yaml(ScanningPicker::new(key.into_part_vec()))
+    };
+    Location::Helm(pick("version"), if app_version { Some(pick("appVersion")) } else { None })
+  }
+
   pub fn is_tag(&self) -> bool { matches!(self, Location::Tag(..)) }
 
   #[cfg(test)]
   pub fn picker(&self) -> &Picker {
     match self {
       Location::File(l) => &l.picker,
+      Location::Helm(l, _) => &l.picker,
+      Location::Url(l) => &l.picker,
       _ => panic!("Not a file location")
     }
   }
@@ -1016,17 +2073,33 @@ impl<'de> Deserialize<'de> for Location {
       {
         let mut file: Option<String> = None;
         let mut pattern: Option<String> = None;
+        let mut pattern_all: Option<bool> = None;
         let mut parts: Option<Vec<Part>> = None;
         let mut tags: Option<TagSpec> = None;
         let mut code: Option<String> = None;
         let mut format: Option<String> = None;
         let mut set: Option<String> = None;
         let mut get: Option<String> = None;
+        let mut get_key: Option<String> = None;
+        let mut helm: Option<bool> = None;
+        let mut bazel: Option<bool> = None;
+        let mut app_version: Option<bool> = None;
+        let mut image_tag: Option<ImageTagSpec> = None;
+        let mut npm_dependency: Option<String> = None;
+        let mut url: Option<String> = None;
 
         while let Some(key) = map.next_key::<String>()? {
           match key.as_str() {
-            "get" => {
+            "get" | "cmd" => {
+              match get_key {
+                Some(prev) if prev == key => {
+                  return Err(de::Error::custom(format!("duplicate '{}' for location", key)));
+                }
+                Some(_) => return Err(de::Error::custom("can't have both 'get' and 'cmd' for location")),
+                None => ()
+              }
               get = Some(map.next_value()?);
+              get_key = Some(key);
             }
             "set" => {
               set = Some(map.next_value()?);
@@ -1037,43 +2110,95 @@ impl<'de> Deserialize<'de> for Location {
             "tags" => {
               tags = Some(map.next_value()?);
             }
-            "json" | "yaml" | "toml" | "xml" => {
+            "json" | "yaml" | "toml" | "xml" | "hcl" => {
               code = Some(key);
               parts = Some(map.next_value_seed(VecPartSeed)?);
             }
             "pattern" => {
               pattern = Some(map.next_value()?);
             }
+            "pattern_all" => {
+              pattern_all = Some(map.next_value()?);
+            }
             "format" => {
               format = Some(map.next_value()?);
             }
+            "helm" => {
+              helm = Some(map.next_value()?);
+            }
+            "bazel" => {
+              bazel = Some(map.next_value()?);
+            }
+            "app_version" => {
+              app_version = Some(map.next_value()?);
+            }
+            "image_tag" => {
+              image_tag = Some(map.next_value()?);
+            }
+            "npm_dependency" => {
+              npm_dependency = Some(map.next_value()?);
+            }
+            "url" => {
+              url = Some(map.next_value()?);
+            }
             other => return Err(de::Error::invalid_value(Unexpected::Str(other), &"a location key"))
           }
         }
 
-        if let Some(file) = file {
+        if let Some(name) = npm_dependency {
+          let others = tags.is_some() || get.is_some() || set.is_some() || helm.is_some() || image_tag.is_some();
+          let others = others || bazel.is_some() || app_version.is_some();
+          if others || pattern.is_some() || pattern_all.is_some() || parts.is_some() {
+            Err(de::Error::custom("can only have 'file' with 'npm_dependency' for location"))
+          } else if format.is_some() {
+            Err(de::Error::custom("cant have 'format' in 'npm_dependency' location"))
+          } else {
+            let file = file.unwrap_or_else(|| "package.json".into());
+            Ok(Location::NpmDependency(NpmDependency { file, name }))
+          }
+        } else if let Some(ImageTagSpec { file: image_file, yaml, prefix }) = image_tag {
+          let others = file.is_some() || tags.is_some() || get.is_some() || set.is_some() || helm.is_some();
+          let others = others || bazel.is_some() || app_version.is_some();
+          if others || pattern.is_some() || pattern_all.is_some() || parts.is_some() {
+            Err(de::Error::custom("can only have 'format' with 'image_tag' for location"))
+          } else {
+            let picker = Picker::ImageTag(ImageTagPicker::new(yaml, prefix));
+            Ok(Location::File(FileLocation { file: image_file, format, picker }))
+          }
+        } else if let Some(url) = url {
+          let others = file.is_some() || tags.is_some() || get.is_some() || set.is_some() || helm.is_some();
+          if others || bazel.is_some() || app_version.is_some() || format.is_some() {
+            Err(de::Error::custom("can only have a pattern or parts field with 'url' for location"))
+          } else {
+            let picker = parse_picker(code, parts, pattern, pattern_all).map_err(de::Error::custom)?;
+            Ok(Location::Url(UrlLocation { url, picker }))
+          }
+        } else if bazel == Some(true) {
+          let has_picker = pattern.is_some() || pattern_all.is_some() || parts.is_some();
+          if tags.is_some() || get.is_some() || set.is_some() || helm.is_some() || app_version.is_some() || has_picker {
+            Err(de::Error::custom("can only have 'file' and 'format' with 'bazel' for location"))
+          } else {
+            Ok(Location::bazel(file, format))
+          }
+        } else if helm == Some(true) {
+          let has_picker = pattern.is_some() || pattern_all.is_some() || parts.is_some();
+          if tags.is_some() || get.is_some() || set.is_some() || has_picker {
+            Err(de::Error::custom("can only have 'file' and 'app_version' with 'helm' for location"))
+          } else if format.is_some() {
+            Err(de::Error::custom("cant have 'format' in 'helm' location"))
+          } else {
+            Ok(Location::helm(file, app_version.unwrap_or(true)))
+          }
+        } else if app_version.is_some() {
+          Err(de::Error::custom("must have 'helm' with 'app_version' for location"))
+        } else if let Some(file) = file {
           if tags.is_some() {
             Err(de::Error::custom("cant have both 'file' and 'tags' for location"))
           } else if get.is_some() || set.is_some() {
             Err(de::Error::custom("cant have both 'file' and 'get'/'set' for location"))
-          } else if pattern.is_none() && parts.is_none() {
-            Ok(Location::File(FileLocation { file, format, picker: Picker::File(FilePicker {}) }))
-          } else if let Some(pattern) = pattern {
-            if parts.is_some() {
-              Err(de::Error::custom("can't have both 'pattern' and parts field"))
-            } else {
-              Ok(Location::File(FileLocation { file, format, picker: Picker::Line(LinePicker::new(pattern)) }))
-            }
           } else {
-            let parts = parts.unwrap();
-            let loc = match code.unwrap().as_str() {
-              "json" => Location::File(FileLocation { file, format, picker: Picker::Json(ScanningPicker::new(parts)) }),
-              "yaml" => Location::File(FileLocation { file, format, picker: Picker::Yaml(ScanningPicker::new(parts)) }),
-              "toml" => Location::File(FileLocation { file, format, picker: Picker::Toml(ScanningPicker::new(parts)) }),
-              "xml" => Location::File(FileLocation { file, format, picker: Picker::Xml(ScanningPicker::new(parts)) }),
-              other => return Err(de::Error::custom(format!("unrecognized part {}", other)))
-            };
-            Ok(loc)
+            let picker = parse_picker(code, parts, pattern, pattern_all).map_err(de::Error::custom)?;
+            Ok(Location::File(FileLocation { file, format, picker }))
           }
         } else if let Some(tags) = tags {
           if format.is_some() {
@@ -1087,12 +2212,12 @@ impl<'de> Deserialize<'de> for Location {
           if let Some(set) = set {
             Ok(Location::Cmd(get.trim().into(), set.trim().into()))
           } else {
-            Err(de::Error::custom("must have 'set' with 'get' for location"))
+            Err(de::Error::custom("must have 'set' with 'get' or 'cmd' for location"))
           }
         } else if set.is_some() {
-          Err(de::Error::custom("must have 'get' with 'set' for location"))
+          Err(de::Error::custom("must have 'get' or 'cmd' with 'set' for location"))
         } else {
-          Err(de::Error::custom("must have 'file', 'tags', or 'get'/'set' for location"))
+          Err(de::Error::custom("must have 'file', 'url', 'tags', 'helm', 'bazel', or 'get'/'set' for location"))
         }
       }
     }
@@ -1123,6 +2248,14 @@ impl JsonSchema for Location {
       }
     }
 
+    #[derive(JsonSchema)]
+    #[allow(dead_code)]
+    struct InnerImageTag {
+      file: String,
+      yaml: PartSpec,
+      prefix: Option<String>
+    }
+
     #[derive(JsonSchema)]
     #[allow(dead_code)]
     struct InnerLoc {
@@ -1132,8 +2265,19 @@ impl JsonSchema for Location {
       yaml: Option<PartSpec>,
       toml: Option<PartSpec>,
       xml: Option<PartSpec>,
+      hcl: Option<PartSpec>,
       pattern: Option<String>,
-      format: Option<String>
+      pattern_all: Option<bool>,
+      format: Option<String>,
+      helm: Option<bool>,
+      app_version: Option<bool>,
+      bazel: Option<bool>,
+      image_tag: Option<InnerImageTag>,
+      npm_dependency: Option<String>,
+      url: Option<String>,
+      get: Option<String>,
+      cmd: Option<String>,
+      set: Option<String>
     }
 
     let my_schema: SchemaObject = <InnerLoc>::json_schema(gen).into();
@@ -1141,6 +2285,117 @@ impl JsonSchema for Location {
   }
 }
 
+/// The picker of a `file` or `url` location: a `pattern`, a parts field, or else the entire content.
+fn parse_picker(
+  code: Option<String>, parts: Option<Vec<Part>>, pattern: Option<String>, pattern_all: Option<bool>
+) -> std::result::Result<Picker, String> {
+  if pattern_all.is_some() && pattern.is_none() {
+    Err("must have 'pattern' with 'pattern_all' for location".into())
+  } else if let Some(pattern) = pattern {
+    if parts.is_some() {
+      Err("can't have both 'pattern' and parts field".into())
+    } else {
+      Ok(Picker::Line(LinePicker::new(pattern, pattern_all.unwrap_or(false))))
+    }
+  } else if let Some(parts) = parts {
+    match code.as_deref().unwrap_or_default() {
+      "json" => Ok(Picker::Json(ScanningPicker::new(parts))),
+      "yaml" => Ok(Picker::Yaml(ScanningPicker::new(parts))),
+      "toml" => Ok(Picker::Toml(ScanningPicker::new(parts))),
+      "xml" => Ok(Picker::Xml(ScanningPicker::new(parts))),
+      "hcl" => Ok(Picker::Hcl(ScanningPicker::new(parts))),
+      other => Err(format!("unrecognized part {}", other))
+    }
+  } else {
+    Ok(Picker::File(FilePicker {}))
+  }
+}
+
+/// A read-only version, fetched from a URL: such as the released version of an external service.
+#[derive(Clone, Debug)]
+struct UrlLocation {
+  url: String,
+  picker: Picker
+}
+
+impl UrlLocation {
+  /// The current value is fetched. An earlier value is the project's tag at the time, which was written by the
+  /// release that saw it; or, if there isn't one, the fetched value.
+  fn read_value<S: StateRead>(&self, read: &S, proj: &ProjectId) -> Result<String> {
+    match read.latest_tag(proj) {
+      Some(tag) if !read.is_current() => Ok(tag.clone()),
+      _ => {
+        let body = fetch_url(&self.url, &read.fetch_opts())?;
+        let mark = self.picker.find(&body).with_context(|| format!("Can't find the version at {}.", self.url))?;
+        Ok(mark.into_value())
+      }
+    }
+  }
+}
+
+/// The value of an `image_tag` location: the YAML file and the path to the image in it.
+#[derive(Deserialize)]
+struct ImageTagSpec {
+  file: String,
+  #[serde(deserialize_with = "deserialize_parts")]
+  yaml: Vec<Part>,
+  prefix: Option<String>
+}
+
+/// The version range of an npm package in any of the dependency sections of a `package.json`.
+#[derive(Clone, Debug)]
+struct NpmDependency {
+  file: String,
+  name: String
+}
+
+const NPM_DEPENDENCY_SECTIONS: &[&str] =
+  &["dependencies", "devDependencies", "peerDependencies", "optionalDependencies"];
+
+impl NpmDependency {
+  /// Write the version into the range of every section that has the dependency, keeping the range's style.
+  pub fn write_value(&self, write: &mut StateWrite, root: Option<&String>, vers: &str, id: &ProjectId) -> Result<()> {
+    let file = self.rooted(root);
+    let data =
+      std::fs::read_to_string(&file).with_context(|| format!("Can't read file {}.", file.to_string_lossy()))?;
+    for section in self.sections(&data)? {
+      write.update_mark(PickPath::ranged(file.clone(), self.picker(section)), vers, id)?;
+    }
+    Ok(())
+  }
+
+  pub fn read_value<S: StateRead>(&self, read: &S, root: Option<&String>) -> Result<String> {
+    let data: String = read.read_file(&self.rooted(root))?;
+    let section = self.sections(&data)?[0];
+    let range = self.picker(section).find(&data)?.into_value();
+    let (_, vers) =
+      split_range(&range).ok_or_else(|| bad!("No single version in range \"{}\" of {}.", range, self.name))?;
+    Ok(vers.to_string())
+  }
+
+  /// The dependency sections of the `package.json` data that have this dependency.
+  fn sections(&self, data: &str) -> Result<Vec<&'static str>> {
+    let json: serde_json::Value = serde_json::from_str(data)?;
+    let has_dep = |section: &&str| json.get(section).and_then(|deps| deps.get(&self.name)).map(|r| r.is_string());
+    let found: Vec<_> = NPM_DEPENDENCY_SECTIONS.iter().copied().filter(|s| has_dep(s) == Some(true)).collect();
+    if found.is_empty() {
+      bail!("No dependency on {} in {}.", self.name, self.file);
+    }
+    Ok(found)
+  }
+
+  fn picker(&self, section: &str) -> Picker {
+    Picker::Json(ScanningPicker::new(vec![Part::Map(section.into()), Part::Map(self.name.clone())]))
+  }
+
+  fn rooted(&self, root: Option<&String>) -> PathBuf {
+    match root {
+      Some(root) => PathBuf::from_slash(root).join(PathBuf::from_slash(&self.file)),
+      None => PathBuf::from_slash(&self.file)
+    }
+  }
+}
+
 #[derive(Clone, Deserialize, Debug)]
 struct TagLocation {
   tags: TagSpec
@@ -1240,13 +2495,40 @@ pub struct CommitConfig {
   #[serde(default = "CommitConfig::default_author")]
   author: String,
   #[serde(default = "CommitConfig::default_email")]
-  email: String
+  email: String,
+  #[serde(default)]
+  clean: CleanPolicy,
+  #[serde(default)]
+  ignore_paths: Vec<String>,
+  #[serde(default)]
+  hooks: Option<CommitHooks>
 }
 
 impl CommitConfig {
   pub fn message(&self) -> &str { &self.message }
   pub fn author(&self) -> &str { &self.author }
   pub fn email(&self) -> &str { &self.email }
+  pub fn clean(&self) -> CleanPolicy { self.clean }
+  pub fn hooks(&self) -> Option<CommitHooks> { self.hooks }
+
+  pub fn with_clean(mut self, clean: CleanPolicy) -> CommitConfig {
+    self.clean = clean;
+    self
+  }
+
+  /// Whether a dirty path may be left alone by the cleanliness check: i.e. it matches one of the `ignore_paths`
+  /// globs, or it is untracked and the policy ignores untracked files.
+  pub fn tolerates(&self, path: &str, untracked: bool) -> Result<bool> {
+    if untracked && self.clean == CleanPolicy::IgnoreUntracked {
+      return Ok(true);
+    }
+    for glob in &self.ignore_paths {
+      if Pattern::new(glob)?.matches_with(path, match_opts()) {
+        return Ok(true);
+      }
+    }
+    Ok(false)
+  }
 
   pub fn default_message() -> String { "build(deploy): Versio update versions".into() }
   pub fn default_author() -> String { "Versio".into() }
@@ -1258,22 +2540,52 @@ impl Default for CommitConfig {
     Self {
       message: CommitConfig::default_message(),
       author: CommitConfig::default_author(),
-      email: CommitConfig::default_email()
+      email: CommitConfig::default_email(),
+      clean: Default::default(),
+      ignore_paths: Vec::new(),
+      hooks: None
     }
   }
 }
 
-#[derive(Deserialize, JsonSchema, Debug)]
+/// What to do when the repository has local modifications or untracked files: `fail` (the default), `warn` and
+/// continue anyway, or `ignore-untracked` files while still failing on modifications.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CleanPolicy {
+  #[default]
+  Fail,
+  Warn,
+  IgnoreUntracked
+}
+
+/// Whether the release commit runs the repo's commit hooks (by committing with the system `git`), or bypasses them.
+/// If this isn't configured, hooks are bypassed with a warning.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CommitHooks {
+  Run,
+  Bypass
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
 struct Subs {
   #[serde(default)]
   dirs: Option<String>,
   #[serde(default)]
-  tops: Option<Vec<u32>>
+  tops: Option<Vec<u32>>,
+  #[serde(default)]
+  excludes: Vec<String>
 }
 
 impl Subs {
   fn dirs(&self) -> &str { self.dirs.as_deref().unwrap_or("v<>") }
   fn tops(&self) -> &[u32] { self.tops.as_deref().unwrap_or(&[0, 1]) }
+
+  /// The globs of subdirectories that should never be treated as major subdirectories.
+  fn excludes(&self) -> Result<Vec<Pattern>> {
+    self.excludes.iter().map(|e| Pattern::new(e).with_context(|| format!("Bad subs exclude \"{}\".", e))).collect()
+  }
 }
 
 /// The "size" of the commit is a measure of "how much" to increment a project's version number based on the
@@ -1308,18 +2620,6 @@ impl Size {
   pub fn is_size(v: &str) -> bool { Size::from_str(v).is_ok() }
   pub fn is_failure(&self) -> bool { matches!(self, Size::Fail) }
 
-  fn from_str(v: &str) -> Result<Size> {
-    match v {
-      "major" => Ok(Size::Major),
-      "minor" => Ok(Size::Minor),
-      "patch" => Ok(Size::Patch),
-      "none" => Ok(Size::None),
-      "empty" => Ok(Size::Empty),
-      "fail" => Ok(Size::Fail),
-      other => err!("Unknown size: {}", other)
-    }
-  }
-
   pub fn parts(v: &str) -> Result<[u32; 3]> {
     let parts: Vec<_> = v
       .split('.')
@@ -1332,6 +2632,30 @@ impl Size {
     Ok([parts[0], parts[1], parts[2]])
   }
 
+  /// The size of the change between two versions: the largest part that differs.
+  pub fn between(v1: &str, v2: &str) -> Result<Size> {
+    let (p1, p2) = (Size::parts(v1)?, Size::parts(v2)?);
+    if p1[0] != p2[0] {
+      Ok(Size::Major)
+    } else if p1[1] != p2[1] {
+      Ok(Size::Minor)
+    } else if p1[2] != p2[2] {
+      Ok(Size::Patch)
+    } else {
+      Ok(Size::Empty)
+    }
+  }
+
+  /// The size of the change to a version since its last release, or `None` if it hasn't moved forward. A version
+  /// that was never released is a change of size `none`, so that its dependents take it up as-is.
+  pub fn since(prev: Option<&str>, curt: &str) -> Result<Option<Size>> {
+    match prev {
+      Some(prev) if Size::less_than(prev, curt)? => Ok(Some(Size::between(prev, curt)?)),
+      Some(_) => Ok(None),
+      None => Ok(Some(Size::None))
+    }
+  }
+
   pub fn less_than(v1: &str, v2: &str) -> Result<bool> {
     let p1 = Size::parts(v1)?;
     let p2 = Size::parts(v2)?;
@@ -1355,6 +2679,21 @@ impl Size {
   }
 }
 
+impl FromStr for Size {
+  type Err = crate::errors::Error;
+  fn from_str(v: &str) -> Result<Size> {
+    match v {
+      "major" => Ok(Size::Major),
+      "minor" => Ok(Size::Minor),
+      "patch" => Ok(Size::Patch),
+      "none" => Ok(Size::None),
+      "empty" => Ok(Size::Empty),
+      "fail" => Ok(Size::Fail),
+      other => err!("Unknown size: {}", other)
+    }
+  }
+}
+
 impl fmt::Display for Size {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match self {
@@ -1408,6 +2747,7 @@ impl Ord for Size {
 }
 
 fn default_includes() -> Vec<String> { vec!["**/*".into()] }
+fn default_config_version() -> u64 { CONFIG_VERSION }
 fn default_prev_tag() -> String { "versio-prev".into() }
 fn default_branch() -> Option<String> { None }
 
@@ -1531,12 +2871,55 @@ fn insert_if_missing(result: &mut HashMap<String, Size>, key: &str, val: Size) {
   }
 }
 
-fn match_opts() -> MatchOptions { MatchOptions { require_literal_separator: true, ..Default::default() } }
+pub fn match_opts() -> MatchOptions { MatchOptions { require_literal_separator: true, ..Default::default() } }
 
 #[cfg(test)]
 mod test {
-  use super::{ConfigFile, FileLocation, HashMap, Location, Picker, Project, ProjectId, ScanningPicker, Size};
+  use super::{branch_matches, discovered_id, legal_tag, match_opts, par_map, prev_config_file, split_range,
+              tag_sanitize, ConfigFile, Derived, FileLocation, FilePicker, FilesRead, HashMap, Location, Path,
+              Pattern, Picker, Project, ProjectId, ScanningPicker, Size, StateRead, StateWrite, UrlLocation,
+              CONFIG_FILENAME};
+  use crate::errors::{Result, VersioError};
+  use crate::fetch::FetchOpts;
+  use crate::mono::Changelog;
   use crate::scan::parts::Part;
+  use regex::Regex;
+
+  #[test]
+  fn test_tag_sanitize() {
+    assert_eq!("my-proj", tag_sanitize("my proj"));
+    assert_eq!("_2fast", tag_sanitize("2fast"));
+    assert_eq!("a/b_c-d", tag_sanitize("a/b_c-d"));
+    assert_eq!("scope/pkg", tag_sanitize("@scope/pkg"));
+    assert_eq!("scope/my-pkg", tag_sanitize("@scope/my.pkg"));
+    assert!(legal_tag(&tag_sanitize("9 lives!")));
+  }
+
+  #[test]
+  fn test_legal_tag() {
+    assert!(legal_tag("example-versio.with-dot"));
+    assert!(legal_tag("libs/core"));
+    assert!(legal_tag("2fast"));
+    assert!(!legal_tag("a..b"));
+    assert!(!legal_tag("a/.b"));
+    assert!(!legal_tag("a//b"));
+    assert!(!legal_tag("/a"));
+    assert!(!legal_tag("a."));
+    assert!(!legal_tag("a.lock/b"));
+    assert!(!legal_tag("a@{b"));
+    assert!(!legal_tag("a b"));
+    assert!(!legal_tag("-a"));
+  }
+
+  #[test]
+  fn test_branch_matches() {
+    assert!(branch_matches("main", "main").unwrap());
+    assert!(!branch_matches("main", "maint").unwrap());
+    assert!(branch_matches("release/*", "release/1.2").unwrap());
+    assert!(!branch_matches("release/*", "release/1.2/fix").unwrap());
+    assert!(branch_matches("/release-\\d+/", "release-12").unwrap());
+    assert!(!branch_matches("/release-\\d+/", "prerelease-12").unwrap());
+  }
 
   #[test]
   fn test_both_file_and_tags() {
@@ -1590,88 +2973,446 @@ projects:
   }
 
   #[test]
-  fn test_validate() {
-    let config = r#"
+  fn test_project_defaults() {
+    let data = r#"
+project_defaults:
+  labels: npm
+  version:
+    file: "package.json"
+    json: "version"
+
 projects:
   - name: p1
     id: 1
-    version: { file: f1 }
+    root: "p1"
 
-  - name: project1
-    id: 1
-    version: { file: f2 }
-    "#;
+  - name: p2
+    id: 2
+    labels: [npm, ui]
+    version:
+      file: "VERSION"
+"#;
 
-    assert!(ConfigFile::read(config).is_err());
+    let config = ConfigFile::read(data).unwrap();
+    assert_eq!(config.projects[0].labels, vec!["npm".to_string()]);
+    assert_eq!("json", config.projects[0].version.picker().picker_type());
+    assert_eq!(config.projects[1].labels, vec!["npm".to_string(), "ui".to_string()]);
+    assert_eq!("file", config.projects[1].version.picker().picker_type());
   }
 
   #[test]
-  fn test_validate_names() {
-    let config = r#"
+  fn test_env_vars() {
+    let data = r#"
+config_version: 3
 projects:
   - name: p1
     id: 1
-    version: { file: f1 }
-
-  - name: p1
-    id: 2
-    version: { file: f2 }
-    "#;
+    root: "${PATH}/p1"
+    tag_prefix: "p1"
+    hooks:
+      post_write: echo $${HOME}
+    version:
+      file: "VERSION"
+"#;
 
-    assert!(ConfigFile::read(config).is_err());
+    let config = ConfigFile::read(data).unwrap();
+    assert_eq!(config.projects[0].root, Some(format!("{}/p1", std::env::var("PATH").unwrap())));
+    assert_eq!(config.projects[0].hooks.hooks["post_write"].cmd, "echo ${HOME}");
   }
 
   #[test]
-  fn test_validate_illegal_prefix() {
-    let config = r#"
+  fn test_env_vars_old_version() {
+    let data = r#"
+config_version: 2
 projects:
   - name: p1
     id: 1
-    tag_prefix: "ixth*&o"
-    version: { file: f1 }
-    "#;
+    hooks:
+      post_write: echo ${VERSIO_TEST_ENV_UNSET}
+    version:
+      file: "VERSION"
+"#;
 
-    assert!(ConfigFile::read(config).is_err());
+    let config = ConfigFile::read(data).unwrap();
+    assert_eq!(config.projects[0].hooks.hooks["post_write"].cmd, "echo ${VERSIO_TEST_ENV_UNSET}");
   }
 
   #[test]
-  fn test_validate_legal_prefix_slash() {
-    let config = r#"
+  fn test_env_vars_unset() {
+    let data = r#"
+config_version: 3
 projects:
   - name: p1
     id: 1
-    tag_prefix: "ixth/o"
-    tag_prefix_separator: "/"
-    version: { file: f1 }
-    "#;
-
-    let config = ConfigFile::read(config).unwrap();
+    root: "${VERSIO_TEST_ENV_UNSET}"
+    version:
+      file: "VERSION"
+"#;
 
-    assert_eq!(config.projects[0].id, ProjectId::from_id(1));
-    assert_eq!(config.projects[0].full_version("1.2.3"), Some("ixth/o/v1.2.3".to_string()));
+    let err = ConfigFile::read(data).unwrap_err();
+    assert!(err.to_string().contains("VERSIO_TEST_ENV_UNSET"));
+    assert!(err.to_string().contains("/projects/0/root"));
   }
 
   #[test]
-  fn test_validate_unascii_prefix() {
-    let config = r#"
+  fn test_min_version() {
+    let data = r#"
 projects:
   - name: p1
     id: 1
-    tag_prefix: "ixthïo"
-    version: { file: f1 }
-    "#;
+    min_version: "2.0.0"
+    version:
+      file: "VERSION"
+"#;
 
-    assert!(ConfigFile::read(config).is_err());
+    let config = ConfigFile::read(data).unwrap();
+    let proj = &config.projects[0];
+    assert_eq!(proj.floor_version("1.4.1".into()).unwrap(), "2.0.0");
+    assert_eq!(proj.floor_version("2.0.1".into()).unwrap(), "2.0.1");
+    assert!(ConfigFile::read(&data.replace("2.0.0", "two")).is_err());
   }
 
   #[test]
-  fn test_validate_prefix() {
-    let config = r#"
+  fn test_prev_tag_branch() {
+    let data = r#"
+options:
+  prev_tag: "versio-prev-{branch}"
+"#;
+
+    let config = ConfigFile::read(data).unwrap().for_branch(Some("release/1.x")).unwrap();
+    assert_eq!(config.prev_tag(), "versio-prev-release/1.x");
+    assert!(ConfigFile::read(data).unwrap().for_branch(None).is_err());
+  }
+
+  #[test]
+  fn test_cmd() {
+    let data = r#"
 projects:
-  - name: p1
+  - name: poetry
     id: 1
-    tag_prefix: proj
-    version: { file: f1 }
+    version:
+      cmd: "poetry version -s"
+      set: "poetry version {version}""#;
+
+    let config = ConfigFile::read(data).unwrap();
+    assert!(matches!(config.projects[0].version, Location::Cmd(..)));
+  }
+
+  #[test]
+  fn test_cmd_and_get() {
+    let data = r#"
+projects:
+  - name: poetry
+    id: 1
+    version:
+      cmd: "poetry version -s"
+      get: "poetry version -s"
+      set: "poetry version""#;
+
+    let err = ConfigFile::read(data).unwrap_err();
+    assert!(format!("{:#}", err).contains("both 'get' and 'cmd'"));
+  }
+
+  #[test]
+  fn test_duplicate_get() {
+    let data = r#"
+projects:
+  - name: poetry
+    id: 1
+    version:
+      get: "poetry version -s"
+      get: "poetry version -s"
+      set: "poetry version""#;
+
+    let err = ConfigFile::read(data).unwrap_err();
+    assert!(!format!("{:#}", err).contains("both 'get' and 'cmd'"));
+  }
+
+  #[test]
+  fn test_helm() {
+    let data = r#"
+projects:
+  - name: chart
+    id: 1
+    version:
+      helm: true"#;
+
+    let config = ConfigFile::read(data).unwrap();
+    assert_eq!("yaml", config.projects[0].version.picker().picker_type());
+  }
+
+  #[test]
+  fn test_helm_and_parts() {
+    let data = r#"
+projects:
+  - name: chart
+    id: 1
+    version:
+      helm: true
+      yaml: "version""#;
+
+    assert!(ConfigFile::read(data).is_err())
+  }
+
+  #[test]
+  fn test_version_from_url() {
+    let data = r#"
+projects:
+  - name: payments
+    id: 1
+    tag_prefix: payments
+    version_from:
+      url: "https://payments.example.com/version.json"
+      json: "version""#;
+
+    let config = ConfigFile::read(data).unwrap();
+    assert!(config.projects[0].is_external());
+    assert_eq!("json", config.projects[0].version.picker().picker_type());
+  }
+
+  #[test]
+  fn test_url_and_file() {
+    let data = r#"
+projects:
+  - name: payments
+    id: 1
+    version:
+      url: "https://payments.example.com/version.json"
+      file: "version.json""#;
+
+    assert!(ConfigFile::read(data).is_err())
+  }
+
+  #[test]
+  fn test_bazel() {
+    let data = r#"
+projects:
+  - name: module
+    id: 1
+    version:
+      bazel: true"#;
+
+    let config = ConfigFile::read(data).unwrap();
+    let module = r#"
+module(
+    name = "rules_x",
+    version = "1.4.2",
+)
+
+bazel_dep(name = "y", version = "0.9.0")
+"#;
+    let picker = config.projects[0].version.picker();
+    assert_eq!("line", picker.picker_type());
+    assert_eq!("1.4.2", picker.find(module).unwrap().value());
+  }
+
+  #[test]
+  fn test_bazel_and_helm() {
+    let data = r#"
+projects:
+  - name: module
+    id: 1
+    version:
+      bazel: true
+      helm: true"#;
+
+    assert!(ConfigFile::read(data).is_err())
+  }
+
+  #[test]
+  fn test_npm_dependency() {
+    let data = r#"
+projects:
+  - name: a
+    id: 1
+    version: { file: package.json, json: version }
+  - name: b
+    id: 2
+    version: { file: package.json, json: version }
+    depends:
+      1:
+        files:
+          - npm_dependency: "@scope/a"
+          - npm_dependency: "@scope/a"
+            json: "dependencies.a""#;
+
+    assert!(ConfigFile::read(data).is_err());
+    assert_eq!(split_range("^1.2.0"), Some(("^", "1.2.0")));
+    assert_eq!(split_range("workspace:~1.2.0-rc.1"), Some(("workspace:~", "1.2.0-rc.1")));
+    assert_eq!(split_range("1.2.0"), Some(("", "1.2.0")));
+    assert_eq!(split_range("1.x"), None);
+    assert_eq!(split_range(">=1.0.0 <2.0.0"), None);
+  }
+
+  #[test]
+  fn test_validate() {
+    let config = r#"
+projects:
+  - name: p1
+    id: 1
+    version: { file: f1 }
+
+  - name: project1
+    id: 1
+    version: { file: f2 }
+    "#;
+
+    assert!(ConfigFile::read(config).is_err());
+  }
+
+  #[test]
+  fn test_validate_names() {
+    let config = r#"
+projects:
+  - name: p1
+    id: 1
+    version: { file: f1 }
+
+  - name: p1
+    id: 2
+    version: { file: f2 }
+    "#;
+
+    assert!(ConfigFile::read(config).is_err());
+  }
+
+  #[test]
+  fn test_validate_illegal_prefix() {
+    let config = r#"
+projects:
+  - name: p1
+    id: 1
+    tag_prefix: "ixth*&o"
+    version: { file: f1 }
+    "#;
+
+    let err = ConfigFile::read(config).unwrap_err();
+    assert!(matches!(err.downcast_ref(), Some(VersioError::IllegalPrefix(p, true)) if p == "ixth*&o"));
+  }
+
+  #[test]
+  fn test_validate_legal_prefix_slash() {
+    let config = r#"
+projects:
+  - name: p1
+    id: 1
+    tag_prefix: "ixth/o"
+    tag_prefix_separator: "/"
+    version: { file: f1 }
+    "#;
+
+    let config = ConfigFile::read(config).unwrap();
+
+    assert_eq!(config.projects[0].id, ProjectId::from_id(1));
+    assert_eq!(config.projects[0].full_version("1.2.3"), Some("ixth/o/v1.2.3".to_string()));
+  }
+
+  #[test]
+  fn test_validate_legal_prefix_dot() {
+    let config = r#"
+projects:
+  - name: p1
+    id: 1
+    tag_prefix: "example-versio.with-dot"
+    version: { file: f1 }
+    "#;
+
+    let config = ConfigFile::read(config).unwrap();
+    let proj = &config.projects[0];
+
+    assert_eq!(proj.full_version("1.2.3"), Some("example-versio.with-dot-v1.2.3".to_string()));
+    assert_eq!(proj.tag_version("example-versio.with-dot-v1.2.3"), Some("1.2.3".to_string()));
+    assert_eq!(proj.tag_pattern("example-versio.with-dot", "*"), "example-versio.with-dot-v*");
+  }
+
+  #[test]
+  fn test_validate_illegal_separator() {
+    let config = r#"
+projects:
+  - name: p1
+    id: 1
+    tag_prefix: "proj"
+    tag_prefix_separator: "*"
+    version: { file: f1 }
+    "#;
+
+    assert!(ConfigFile::read(config).is_err());
+  }
+
+  #[test]
+  fn test_tag_separator() {
+    let config = r#"
+projects:
+  - name: p1
+    id: 1
+    tag_prefix: "proj"
+    tag_prefix_separator: "@"
+    version: { file: f1 }
+    "#;
+
+    let config = ConfigFile::read(config).unwrap();
+    let proj = &config.projects[0];
+
+    assert_eq!(proj.full_version("1.2.3"), Some("proj@v1.2.3".to_string()));
+    assert_eq!(proj.tag_version("proj@v1.2.3"), Some("1.2.3".to_string()));
+    assert_eq!(proj.tag_version("proj-v1.2.3"), None);
+  }
+
+  #[test]
+  fn test_tag_format() {
+    let config = r#"
+projects:
+  - name: p1
+    id: 1
+    tag_prefix: "proj"
+    tag_format: "{prefix}/{version}"
+    version: { file: f1 }
+    "#;
+
+    let config = ConfigFile::read(config).unwrap();
+    let proj = &config.projects[0];
+
+    assert_eq!(proj.full_version("1.2.3"), Some("proj/1.2.3".to_string()));
+    assert_eq!(proj.tag_version("proj/1.2.3"), Some("1.2.3".to_string()));
+    assert_eq!(proj.tag_version("proj/v1.2.3"), None);
+    assert_eq!(proj.format_tag("other", "*"), "other/*");
+  }
+
+  #[test]
+  fn test_validate_tag_format() {
+    let config = r#"
+projects:
+  - name: p1
+    id: 1
+    tag_prefix: "proj"
+    tag_format: "{prefix}-release"
+    version: { file: f1 }
+    "#;
+
+    assert!(ConfigFile::read(config).is_err());
+  }
+
+  #[test]
+  fn test_validate_unascii_prefix() {
+    let config = r#"
+projects:
+  - name: p1
+    id: 1
+    tag_prefix: "ixthïo"
+    version: { file: f1 }
+    "#;
+
+    assert!(ConfigFile::read(config).is_err());
+  }
+
+  #[test]
+  fn test_validate_prefix() {
+    let config = r#"
+projects:
+  - name: p1
+    id: 1
+    tag_prefix: proj
+    version: { file: f1 }
 
   - name: p2
     id: 2
@@ -1682,6 +3423,71 @@ projects:
     assert!(ConfigFile::read(config).is_err());
   }
 
+  #[test]
+  fn test_current_project() {
+    let prev = r#"
+projects:
+  - name: old_name
+    id: 1
+    version: { file: f1 }
+
+  - name: p2
+    id: 2
+    version: { file: f2 }
+
+  - name: p3
+    id: 3
+    version: { file: f3 }
+    "#;
+
+    let current = r#"
+projects:
+  - name: new_name
+    id: 4
+    aliases: { old_names: [old_name] }
+    version: { file: f1 }
+
+  - name: p2_moved
+    id: 5
+    aliases: { old_ids: [2] }
+    version: { file: f2 }
+
+  - name: p3
+    id: 3
+    version: { file: f3 }
+
+  - name: p2
+    id: 6
+    version: { file: f6 }
+    "#;
+
+    let prev = ConfigFile::read(prev).unwrap();
+    let current = ConfigFile::read(current).unwrap();
+    let current_id = |i: usize| current.current_project(&prev.projects[i]).map(|p| p.id().clone());
+
+    assert_eq!(current_id(0), Some(ProjectId::from_id(4)));
+    assert_eq!(current_id(1), Some(ProjectId::from_id(5)));
+    assert_eq!(current_id(2), Some(ProjectId::from_id(3)));
+  }
+
+  #[test]
+  fn test_validate_duplicate_alias() {
+    let config = r#"
+projects:
+  - name: p1
+    id: 1
+    aliases: { old_ids: [3] }
+    version: { file: f1 }
+
+  - name: p2
+    id: 2
+    aliases: { old_ids: [3] }
+    version: { file: f2 }
+    "#;
+
+    assert!(ConfigFile::read(config).is_err());
+  }
+
   #[test]
   fn test_validate_ok() {
     let config = r#"
@@ -1749,12 +3555,21 @@ sizes:
         picker: Picker::Json(ScanningPicker::new(vec![Part::Map("version".into())])),
         format: None
       }),
+      derived: None,
       also: Vec::new(),
+      mirror_version_file: None,
       tag_prefix: None,
       tag_prefix_separator: None,
+      tag_format: None,
+      tag_message: None,
+      branch: None,
+      min_version: None,
+      scopes: Vec::new(),
       labels: Default::default(),
       hooks: Default::default(),
-      subs: None
+      subs: None,
+      aliases: Default::default(),
+      submodule: false
     };
 
     assert!(proj.does_cover("base/somefile.txt").unwrap());
@@ -1776,17 +3591,128 @@ sizes:
         picker: Picker::Json(ScanningPicker::new(vec![Part::Map("version".into())])),
         format: None
       }),
+      derived: None,
       also: Vec::new(),
+      mirror_version_file: None,
       tag_prefix: None,
       tag_prefix_separator: None,
+      tag_format: None,
+      tag_message: None,
+      branch: None,
+      min_version: None,
+      scopes: Vec::new(),
       labels: Default::default(),
       hooks: Default::default(),
-      subs: None
+      subs: None,
+      aliases: Default::default(),
+      submodule: false
     };
 
     assert!(!proj.does_cover("base/internal/infile.txt").unwrap());
   }
 
+  #[test]
+  fn test_submodule_bump() {
+    let mut proj = Project {
+      name: "test".into(),
+      id: ProjectId::from_id(1),
+      root: Some("base".into()),
+      includes: vec!["**/*".into()],
+      excludes: Vec::new(),
+      depends: HashMap::new(),
+      changelog: None,
+      version: Location::File(FileLocation {
+        file: "package.json".into(),
+        picker: Picker::Json(ScanningPicker::new(vec![Part::Map("version".into())])),
+        format: None
+      }),
+      derived: None,
+      also: Vec::new(),
+      mirror_version_file: None,
+      tag_prefix: None,
+      tag_prefix_separator: None,
+      tag_format: None,
+      tag_message: None,
+      branch: None,
+      min_version: None,
+      scopes: Vec::new(),
+      labels: Default::default(),
+      hooks: Default::default(),
+      subs: None,
+      aliases: Default::default(),
+      submodule: false
+    };
+
+    assert!(!proj.does_cover("base").unwrap());
+    proj.submodule = true;
+    assert!(proj.does_cover("base").unwrap());
+    assert!(!proj.does_cover("based").unwrap());
+  }
+
+  #[test]
+  fn test_nested_overlap() {
+    let data = r#"
+projects:
+  - name: outer
+    id: 1
+    version: { file: "VERSION" }
+  - name: middle
+    id: 4
+    root: "app"
+    version: { file: "VERSION" }
+  - name: inner
+    id: 2
+    root: "app/base"
+    version: { file: "VERSION" }
+  - name: sibling
+    id: 3
+    root: "app/based/"
+    version: { file: "VERSION" }
+"#;
+    let config = ConfigFile::read(data).unwrap();
+    let projects = config.projects();
+    let (outer, middle, inner, sibling) = (&projects[0], &projects[1], &projects[2], &projects[3]);
+
+    assert!(middle.nests_with(inner));
+    assert!(inner.nests_with(middle));
+    assert!(!inner.nests_with(sibling));
+    assert!(!middle.nests_with(middle));
+    assert!(!outer.nests_with(inner));
+    assert!(!inner.nests_with(outer));
+    assert_eq!(inner.covering_include("app/base/a.txt").unwrap().as_deref(), Some("app/base/**/*"));
+    assert_eq!(inner.covering_include("app/based/a.txt").unwrap(), None);
+  }
+
+  #[test]
+  fn test_tag_majors_collide() {
+    let subs = |tops: &str, sub_prefix: &str| {
+      let config = format!(
+        r#"
+projects:
+  - name: proj
+    id: 1
+    tag_prefix: "proj"
+    subs: {{ tops: {} }}
+    version: {{ tags: {{ default: "0.0.0" }} }}
+  - name: other
+    id: 2
+    root: "other"
+    tag_prefix: "{}"
+    version: {{ tags: {{ majors: [ 3 ] }} }}
+"#,
+        tops, sub_prefix
+      );
+      ConfigFile::from_read(&Tree { config, files: &[], dirs: &["v2", "v3"] })
+    };
+
+    let err = subs("[ 0, 1, 2 ]", "other").unwrap_err().to_string();
+    assert!(err.contains("proj/v2") && err.contains("major 2"), "{}", err);
+    assert!(subs("[ 0, 1 ]", "other").is_ok());
+
+    let err = subs("[ 0, 1 ]", "proj").unwrap_err().to_string();
+    assert!(err.contains("proj/v3") && err.contains("other") && err.contains("major 3"), "{}", err);
+  }
+
   #[test]
   fn test_excludes_check() {
     let proj = Project {
@@ -1802,12 +3728,21 @@ sizes:
         picker: Picker::Json(ScanningPicker::new(vec![Part::Map("version".into())])),
         format: None
       }),
+      derived: None,
       also: Vec::new(),
+      mirror_version_file: None,
       tag_prefix: None,
       tag_prefix_separator: None,
+      tag_format: None,
+      tag_message: None,
+      branch: None,
+      min_version: None,
+      scopes: Vec::new(),
       labels: Default::default(),
       hooks: Default::default(),
-      subs: None
+      subs: None,
+      aliases: Default::default(),
+      submodule: false
     };
 
     assert!(proj.check_excludes().is_err());
@@ -1835,4 +3770,289 @@ sizes:
     assert_eq!(&Size::None, config.sizes.get("style").unwrap());
     assert_eq!(&Size::None, config.sizes.get("test").unwrap());
   }
+
+  #[test]
+  fn test_size_between() {
+    assert_eq!(Size::Major, Size::between("1.2.3", "2.0.0").unwrap());
+    assert_eq!(Size::Minor, Size::between("1.2.3", "1.3.0").unwrap());
+    assert_eq!(Size::Patch, Size::between("1.2.3", "1.2.4").unwrap());
+    assert_eq!(Size::Empty, Size::between("1.2.3", "1.2.3").unwrap());
+    assert!(Size::between("1.2", "1.2.3").is_err());
+  }
+
+  #[test]
+  fn test_size_apply() {
+    let bump = |size: &str, v: &str| size.parse::<Size>().and_then(|size| size.apply(v));
+    assert_eq!(bump("major", "1.2.3").unwrap(), "2.0.0");
+    assert_eq!(bump("minor", "1.2.3").unwrap(), "1.3.0");
+    assert_eq!(bump("patch", "1.2.3").unwrap(), "1.2.4");
+    assert_eq!(bump("minor", "0.9.9").unwrap(), "0.10.0");
+    assert_eq!(bump("none", "1.2.3").unwrap(), "1.2.3");
+    assert!(bump("patch", "1.2").is_err());
+    assert!(bump("patch", "1.2.3-rc.1").is_err());
+    assert!(bump("fail", "1.2.3").is_err());
+    assert!(bump("huge", "1.2.3").is_err());
+  }
+
+  #[test]
+  fn test_size_since() {
+    assert_eq!(Some(Size::Minor), Size::since(Some("1.2.3"), "1.3.0").unwrap());
+    assert_eq!(None, Size::since(Some("1.2.3"), "1.2.3").unwrap());
+    assert_eq!(None, Size::since(Some("1.3.0"), "1.2.3").unwrap());
+    assert_eq!(Some(Size::None), Size::since(None, "1.2.3").unwrap());
+  }
+
+  /// A state with no files, which might have a tag for every project.
+  struct TaggedState {
+    tag: Option<String>,
+    current: bool
+  }
+
+  impl FilesRead for TaggedState {
+    fn has_file(&self, _: &Path) -> Result<bool> { Ok(false) }
+    fn read_file(&self, path: &Path) -> Result<String> { Err(bad!("No file {}.", path.display())) }
+    fn subdirs(&self, _: Option<&String>, _: &str) -> Result<Vec<String>> { Ok(Vec::new()) }
+    fn files_matching(&self, _: &Pattern) -> Result<Vec<String>> { Ok(Vec::new()) }
+  }
+
+  impl StateRead for TaggedState {
+    fn latest_tag(&self, _: &ProjectId) -> Option<&String> { self.tag.as_ref() }
+    fn is_current(&self) -> bool { self.current }
+    fn fetch_opts(&self) -> FetchOpts { FetchOpts::new(true, None) }
+  }
+
+  #[test]
+  fn test_url_read_value() {
+    let url = "http://versio.invalid/test_url_read_value".to_string();
+    let loc = UrlLocation { url, picker: Picker::File(FilePicker {}) };
+    let id = ProjectId::from_id(1);
+
+    let prev = TaggedState { tag: Some("1.2.3".into()), current: false };
+    assert_eq!("1.2.3", loc.read_value(&prev, &id).unwrap());
+
+    // Offline, and never fetched: there's nothing to read.
+    let never = TaggedState { tag: None, current: false };
+    assert!(loc.read_value(&never, &id).is_err());
+    let current = TaggedState { tag: Some("1.2.3".into()), current: true };
+    assert!(loc.read_value(&current, &id).is_err());
+  }
+
+  /// The files of an earlier commit that has only a config file, or whose config file can't be read.
+  struct PrevConfig(Option<&'static str>);
+
+  impl FilesRead for PrevConfig {
+    fn has_file(&self, _: &Path) -> Result<bool> { Ok(true) }
+    fn read_file(&self, _: &Path) -> Result<String> { self.0.map(|d| d.to_string()).ok_or_else(|| bad!("No blob.")) }
+    fn subdirs(&self, _: Option<&String>, _: &str) -> Result<Vec<String>> { Ok(Vec::new()) }
+    fn files_matching(&self, _: &Pattern) -> Result<Vec<String>> { Ok(Vec::new()) }
+  }
+
+  #[test]
+  fn test_prev_config_file() {
+    let current = ConfigFile::read("projects: []\noptions:\n  prev_tag: current-prev\n").unwrap();
+
+    let prev = PrevConfig(Some("projects: []\noptions:\n  prev_tag: old-prev\n"));
+    assert_eq!("old-prev", prev_config_file(&prev, &current, "v1").unwrap().prev_tag());
+
+    let broken = PrevConfig(Some("projects: [\n"));
+    assert_eq!("current-prev", prev_config_file(&broken, &current, "v1").unwrap().prev_tag());
+
+    let unreadable = PrevConfig(None);
+    assert!(prev_config_file(&unreadable, &current, "v1").is_err());
+  }
+
+  #[test]
+  fn test_forward_tag_keeps_changelog() {
+    let data = r#"
+projects:
+  - name: proj
+    id: 1
+    version:
+      file: "VERSION"
+    tag_prefix: "proj"
+    tag_message: "{{ release.version }}{% for dep in release.deps %} {{ dep.name }}{% endfor %}"
+"#;
+
+    let config = ConfigFile::read(data).unwrap();
+    let proj = &config.projects[0];
+    let message = |write: &StateWrite| write.planned_actions(&HashMap::new(), None)["tags"][0]["message"].clone();
+
+    let mut changelog = Changelog::empty();
+    changelog.add_dep(ProjectId::from_id(2), "lib");
+    let mut write = StateWrite::new();
+    proj.annotate_tag(&mut write, &changelog, "1.2.0", &proj.release_links(None)).unwrap();
+    proj.forward_tag(&mut write, "1.2.0").unwrap();
+    assert_eq!("1.2.0 lib", message(&write));
+
+    let mut write = StateWrite::new();
+    proj.forward_tag(&mut write, "1.2.0").unwrap();
+    assert_eq!("1.2.0", message(&write));
+  }
+
+  /// A tree of empty files, along with a config file.
+  struct Tree {
+    config: String,
+    files: &'static [&'static str],
+    dirs: &'static [&'static str]
+  }
+
+  impl FilesRead for Tree {
+    fn has_file(&self, path: &Path) -> Result<bool> {
+      Ok(path == Path::new(CONFIG_FILENAME) || self.files.iter().any(|f| Path::new(f) == path))
+    }
+
+    fn read_file(&self, path: &Path) -> Result<String> {
+      Ok(if path == Path::new(CONFIG_FILENAME) { self.config.clone() } else { String::new() })
+    }
+
+    fn subdirs(&self, _: Option<&String>, regex: &str) -> Result<Vec<String>> {
+      let filter = Regex::new(regex)?;
+      Ok(self.dirs.iter().filter(|d| filter.is_match(d)).map(|d| d.to_string()).collect())
+    }
+
+    fn files_matching(&self, pattern: &Pattern) -> Result<Vec<String>> {
+      Ok(self.files.iter().filter(|f| pattern.matches_with(f, match_opts())).map(|f| f.to_string()).collect())
+    }
+  }
+
+  fn discover(glob: &str, groups: &str, files: &'static [&'static str]) -> Result<ConfigFile> {
+    let config = format!(
+      r#"
+options:
+  version_groups: {}
+projects:
+  - name: listed
+    id: 1
+    version:
+      file: "VERSION"
+projects_from:
+  - glob: "{}"
+    tag_prefix: true
+    version:
+      file: "package.json"
+      json: "version"
+"#,
+      groups, glob
+    );
+    ConfigFile::from_read(&Tree { config, files, dirs: &[] })
+  }
+
+  #[test]
+  fn test_projects_from() {
+    let files = &["packages/a/package.json", "packages/b-c/package.json", "other/d/package.json"];
+    let config = discover("packages/*/package.json", "[]", files).unwrap();
+
+    let found: Vec<_> = config
+      .projects()
+      .iter()
+      .map(|p| (p.name(), p.root().map(|r| r.as_str()), p.tag_prefix().as_deref()))
+      .collect();
+    assert_eq!(
+      found,
+      [("listed", None, None), ("a", Some("packages/a"), Some("a")), ("b-c", Some("packages/b-c"), Some("b-c"))]
+    );
+    let id = discovered_id("packages/a");
+    assert!(id >= 0x8000_0000);
+    assert_eq!(&ProjectId::from_id(id), config.projects()[1].id());
+  }
+
+  #[test]
+  fn test_projects_from_bad() {
+    // A project at the base of the repo would have no name.
+    assert!(discover("package.json", "[]", &["package.json"]).is_err());
+
+    // Discovered names must be unique, and usable as tag prefixes.
+    assert!(discover("*/*/package.json", "[]", &["a/x/package.json", "b/x/package.json"]).is_err());
+    let illegal = discover("*/*/package.json", "[]", &["a/x..y/package.json"]).unwrap_err();
+    assert!(matches!(illegal.downcast_ref::<VersioError>(), Some(VersioError::IllegalPrefix(_, false))));
+
+    let grouped = format!("[[1, {}]]", discovered_id("packages/a"));
+    assert!(discover("packages/*/package.json", &grouped, &["packages/a/package.json"]).is_err());
+  }
+
+  #[test]
+  fn test_commit_tolerates() {
+    let data = r#"
+commit:
+  clean: ignore-untracked
+  ignore_paths: [ "dist/**", "*.log" ]
+"#;
+    let commit = ConfigFile::read(data).unwrap().commit_config().clone();
+    assert!(commit.tolerates("dist/bundle.js", false).unwrap());
+    assert!(commit.tolerates("build.log", false).unwrap());
+    assert!(commit.tolerates("notes.txt", true).unwrap());
+    assert!(!commit.tolerates("notes.txt", false).unwrap());
+    assert!(!commit.tolerates("src/dist/bundle.js", false).unwrap());
+
+    let commit = ConfigFile::read("commit:\n  ignore_paths: [ \"dist/**\" ]\n").unwrap().commit_config().clone();
+    assert!(commit.tolerates("dist/bundle.js", true).unwrap());
+    assert!(!commit.tolerates("notes.txt", true).unwrap());
+
+    let commit = ConfigFile::read("commit:\n  ignore_paths: [ \"[\" ]\n").unwrap().commit_config().clone();
+    assert!(commit.tolerates("x", false).is_err());
+  }
+
+  #[test]
+  fn test_par_map_order() {
+    // Later items finish first, so the results are only in order if `par_map` puts them back.
+    let items: Vec<u64> = (0 .. 23).collect();
+    let slow = |i: &u64| -> Result<u64> {
+      std::thread::sleep(std::time::Duration::from_millis(23 - i));
+      Ok(i * 2)
+    };
+    let expected: Vec<u64> = items.iter().map(|i| i * 2).collect();
+    for jobs in [0, 1, 2, 4, 23, 100] {
+      assert_eq!(par_map(&items, jobs, slow).unwrap(), expected, "jobs = {}", jobs);
+    }
+
+    let failing = |i: &u64| -> Result<u64> { if *i == 17 { crate::err!("failed on {}", i) } else { Ok(*i) } };
+    assert!(par_map(&items, 4, failing).is_err());
+  }
+
+  #[test]
+  fn test_derive() {
+    let data = r#"
+projects:
+  - name: proj1
+    id: 1
+    root: "proj1"
+    version:
+      file: "VERSION"
+
+  - name: proj2
+    id: 2
+    root: "proj2"
+    version:
+      file: "VERSION""#;
+
+    let config = ConfigFile::read(data).unwrap();
+    let children = [(&config.projects[0], "1.2.3".to_string()), (&config.projects[1], "2.0.10".to_string())];
+
+    assert_eq!(Derived::Max.derive(&children, None).unwrap(), "2.0.10");
+    assert_eq!(Derived::SumMajors.derive(&children, None).unwrap(), "3.2.13");
+    let tmpl = Derived::Template("{{ children[0].major | plus: 1 }}.{{ children[1].patch }}.0 ".into());
+    assert_eq!(tmpl.derive(&children, None).unwrap(), "2.10.0");
+
+    assert!(Derived::Max.derive(&[], None).is_err());
+    assert!(Derived::Template("v{{ max }}".into()).derive(&children, None).is_err());
+  }
+
+  #[test]
+  fn test_derive_not_lower() {
+    let data = r#"
+projects:
+  - name: proj1
+    id: 1
+    root: "proj1"
+    version:
+      file: "VERSION""#;
+
+    let config = ConfigFile::read(data).unwrap();
+    let children = [(&config.projects[0], "1.2.3".to_string())];
+
+    assert_eq!(Derived::Max.derive(&children, Some("1.2.3")).unwrap(), "1.2.3");
+    assert_eq!(Derived::Max.derive(&children, Some("1.0.9")).unwrap(), "1.2.3");
+    assert!(Derived::Max.derive(&children, Some("1.10.0")).is_err());
+    assert!(Derived::SumMajors.derive(&children, Some("2.0.0")).is_err());
+  }
 }
@@ -1,6 +1,50 @@
 //! Error handling for Versio is all based on `error-chain`.
 
+use crate::config::ProjectId;
 pub use anyhow::{Context, Error, Result};
+use std::fmt;
+
+/// Errors that callers can recognize with `downcast_ref`, each with a message that suggests how to fix it.
+#[derive(Debug)]
+pub enum VersioError {
+  /// The current version of a project can't be read from its version location.
+  MissingVersion(ProjectId),
+  /// A project is expected, but isn't covered by the current config.
+  UncoveredProject(ProjectId),
+  /// A tag prefix can't be used in a git tag; and whether the prefix is from `.versio.yaml`, where `check --fix`
+  /// can repair it.
+  IllegalPrefix(String, bool),
+  /// The config file of an earlier commit can't be parsed.
+  MalformedConfig
+}
+
+impl fmt::Display for VersioError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      VersioError::MissingVersion(id) => write!(
+        f,
+        "Can't find the current version of project {}: check its \"version\" location, or run \"versio check\".",
+        id
+      ),
+      VersioError::UncoveredProject(id) => write!(
+        f,
+        "No project {} in the current config: if it was renumbered or renamed, add its old ID to its \"aliases\".",
+        id
+      ),
+      VersioError::IllegalPrefix(prefix, true) => write!(
+        f,
+        "Illegal tag prefix \"{}\": it must be usable in a git tag name; \"versio check --fix\" can repair it.",
+        prefix
+      ),
+      VersioError::IllegalPrefix(prefix, false) => {
+        write!(f, "Illegal tag prefix \"{}\": it must be usable in a git tag name.", prefix)
+      }
+      VersioError::MalformedConfig => write!(f, "Malformed config")
+    }
+  }
+}
+
+impl std::error::Error for VersioError {}
 
 // impl<'a, T: ?Sized> From<std::sync::PoisonError<std::sync::MutexGuard<'a, T>>> for Error {
 //   fn from(err: std::sync::PoisonError<std::sync::MutexGuard<'a, T>>) -> Error {
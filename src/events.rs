@@ -0,0 +1,54 @@
+//! A stream of machine-readable events, for tools that follow along with long-running commands.
+
+use crate::errors::Result;
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{stdout, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// The `tracing` target of progress events during planning and release. These have stable field names, such as
+/// `project_id`, `size`, `file`, and `tag`, so that wrappers can show progress or collect telemetry from them.
+pub const PROGRESS: &str = "versio::progress";
+
+/// A sink for newline-delimited JSON events. Each event is a single JSON object on its own line, with an
+/// `"event"` field naming the event, and other fields specific to that event. A disabled sink ignores all
+/// events.
+#[derive(Clone)]
+pub struct Events {
+  sink: Option<Arc<Mutex<Box<dyn Write + Send>>>>,
+  to_stdout: bool
+}
+
+impl Events {
+  pub fn none() -> Events { Events { sink: None, to_stdout: false } }
+
+  /// Write events to the given file, or to stdout if no file is given.
+  pub fn ndjson(path: Option<&Path>) -> Result<Events> {
+    let (out, to_stdout): (Box<dyn Write + Send>, bool) = match path {
+      Some(path) => (Box::new(File::create(path)?), false),
+      None => (Box::new(stdout()), true)
+    };
+    Ok(Events { sink: Some(Arc::new(Mutex::new(out))), to_stdout })
+  }
+
+  /// True if events are written to stdout, in which case normal human-readable output should be suppressed.
+  pub fn owns_stdout(&self) -> bool { self.to_stdout }
+
+  pub fn emit(&self, event: &str, data: Value) -> Result<()> {
+    if let Some(sink) = &self.sink {
+      let mut line = json!({ "event": event });
+      if let Value::Object(fields) = data {
+        line.as_object_mut().unwrap().extend(fields);
+      }
+
+      let mut sink = sink.lock().unwrap();
+      writeln!(sink, "{}", line)?;
+      sink.flush()?;
+    }
+    Ok(())
+  }
+
+  pub fn phase_started(&self, phase: &str) -> Result<()> { self.emit("phase_started", json!({ "phase": phase })) }
+  pub fn phase_finished(&self, phase: &str) -> Result<()> { self.emit("phase_finished", json!({ "phase": phase })) }
+}
@@ -0,0 +1,82 @@
+//! Read-only content fetched over HTTP, such as the released version of an external service.
+
+use crate::bail;
+use crate::errors::{Context as _, Result};
+use crate::git::{blob_hash, http_client, Proxy};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::warn;
+
+const CACHE_DIR: &str = ".versio/cache/urls";
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The responses already fetched by this run, so that each URL is requested at most once.
+static FETCHED: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+/// How to fetch a URL: whether to read only from the cache, and which proxy to use from the user preferences.
+#[derive(Clone, Debug, Default)]
+pub struct FetchOpts {
+  offline: bool,
+  proxy: Option<Proxy>
+}
+
+impl FetchOpts {
+  pub fn new(offline: bool, proxy: Option<Proxy>) -> FetchOpts { FetchOpts { offline, proxy } }
+}
+
+/// Fetch the content of a URL. Each response is also cached, so that an offline run can use the last response
+/// instead of making a request.
+pub fn fetch_url(url: &str, opts: &FetchOpts) -> Result<String> {
+  if let Some(body) = FETCHED.lock().unwrap().get(url) {
+    return Ok(body.clone());
+  }
+
+  let cache = cache_file(url);
+  let body = if opts.offline {
+    let cache = cache.ok_or_else(|| bad!("No cache for {} while offline.", url))?;
+    std::fs::read_to_string(&cache).with_context(|| format!("No cached response from {} while offline.", url))?
+  } else {
+    let body = block_on(get(url, opts.proxy.as_ref()))??;
+    if let Some(cache) = cache {
+      write_cache(&cache, &body);
+    }
+    body
+  };
+
+  FETCHED.lock().unwrap().insert(url.to_string(), body.clone());
+  Ok(body)
+}
+
+async fn get(url: &str, proxy: Option<&Proxy>) -> Result<String> {
+  let client = http_client(proxy)?.timeout(FETCH_TIMEOUT).build()?;
+  let resp = client.get(url).send().await.with_context(|| format!("Unable to fetch {}.", url))?;
+  if !resp.status().is_success() {
+    bail!("Unsuccessful request to {}: {}", url, resp.status().as_u16());
+  }
+  Ok(resp.text().await?)
+}
+
+/// Run a future from synchronous code, which might itself be running inside the (multi-threaded) runtime.
+fn block_on<F: Future>(fut: F) -> Result<F::Output> {
+  match tokio::runtime::Handle::try_current() {
+    Ok(handle) => Ok(tokio::task::block_in_place(|| handle.block_on(fut))),
+    Err(_) => Ok(tokio::runtime::Builder::new_current_thread().enable_all().build()?.block_on(fut))
+  }
+}
+
+/// The cache is named by a hash of the URL, so that distinct URLs never share a file, and long URLs still make a
+/// valid file name.
+fn cache_file(url: &str) -> Option<PathBuf> {
+  let name = blob_hash(url.as_bytes()).ok()?;
+  dirs::home_dir().map(|home| home.join(CACHE_DIR).join(name))
+}
+
+fn write_cache(path: &Path, body: &str) {
+  let written = path.parent().map(std::fs::create_dir_all).unwrap_or(Ok(())).and_then(|()| std::fs::write(path, body));
+  if let Err(e) = written {
+    warn!("Unable to write cache file {}: {}", path.display(), e);
+  }
+}
@@ -0,0 +1,138 @@
+//! Automatic repair of common problems in the config file, for `check --fix`.
+
+use crate::bail;
+use crate::config::{legal_tag, tag_sanitize, CONFIG_FILENAME};
+use crate::errors::Result;
+use crate::scan::parts::Part;
+use crate::scan::{Scanner, YamlScanner};
+use serde_yaml::Value;
+use std::path::Path;
+
+/// A single repair made to the config file, along with the line that was changed.
+pub struct Fix {
+  description: String,
+  line: usize,
+  removed: String,
+  added: String
+}
+
+impl Fix {
+  pub fn description(&self) -> &str { &self.description }
+
+  /// The one-based number of the changed line.
+  pub fn line(&self) -> usize { self.line }
+  pub fn removed(&self) -> &str { &self.removed }
+  pub fn added(&self) -> &str { &self.added }
+}
+
+/// Repair what can be repaired in the config file found in `root`, rewriting the file if anything was fixed.
+pub fn fix_config(root: &Path) -> Result<Vec<Fix>> {
+  let path = root.join(CONFIG_FILENAME);
+  if !path.exists() {
+    return Ok(Vec::new());
+  }
+
+  let mut data = std::fs::read_to_string(&path)?;
+  let config: Value = serde_yaml::from_str(&data)?;
+  let projects = config.get("projects").and_then(|p| p.as_sequence()).cloned().unwrap_or_default();
+
+  let mut fixes = Vec::new();
+  for (i, proj) in projects.iter().enumerate() {
+    let name = proj.get("name").and_then(|n| n.as_str()).unwrap_or("(unnamed)");
+
+    if let Some(prefix) = proj.get("tag_prefix").and_then(|p| p.as_str()) {
+      if !legal_tag(prefix) {
+        let sanitized = tag_sanitize(prefix);
+        let description = format!("Sanitized tag_prefix \"{}\" to \"{}\" in {}.", prefix, sanitized, name);
+        fixes.push(replace_prefix(&mut data, i, prefix, &sanitized, description)?);
+      }
+    }
+
+    let is_empty = |key: &str| proj.get(key).and_then(|v| v.as_sequence()).map(|s| s.is_empty());
+    if is_empty("includes") == Some(true) && is_empty("excludes") == Some(false) && proj.get("id").is_some() {
+      let description = format!("Added missing includes to {}.", name);
+      if let Some(fix) = include_all(&mut data, i, description)? {
+        fixes.push(fix);
+      }
+    }
+  }
+
+  if !fixes.is_empty() {
+    std::fs::write(&path, &data)?;
+  }
+  Ok(fixes)
+}
+
+fn replace_prefix(data: &mut String, index: usize, prefix: &str, sanitized: &str, description: String) -> Result<Fix> {
+  let parts = vec![Part::Map("projects".into()), Part::Seq(index), Part::Map("tag_prefix".into())];
+  let mark = YamlScanner::build(parts).find(data)?;
+  if !data[mark.start() ..].starts_with(prefix) {
+    bail!("Unable to fix escaped tag_prefix \"{}\".", prefix);
+  }
+
+  let line = line_of(data, mark.start());
+  let removed = line_text(data, line).to_string();
+  data.replace_range(mark.start() .. mark.start() + prefix.len(), sanitized);
+  let added = line_text(data, line).to_string();
+  Ok(Fix { description, line: line + 1, removed, added })
+}
+
+/// Replace an empty `includes` with one that includes all files in the project. Only block-style projects are
+/// fixed, where each key is on its own line.
+fn include_all(data: &mut String, index: usize, description: String) -> Result<Option<Fix>> {
+  let parts = vec![Part::Map("projects".into()), Part::Seq(index), Part::Map("id".into())];
+  let mark = YamlScanner::build(parts).find(data)?;
+  let id_line = line_of(data, mark.start());
+
+  let lines: Vec<&str> = data.split('\n').collect();
+  let key_col = match key_column(lines[id_line]) {
+    Some(key_col) => key_col,
+    None => return Ok(None)
+  };
+
+  // Search the lines of the project (which starts at its sequence marker) for its `includes` key.
+  let is_item = |l: &str| indent(l) + 2 == key_col && l.trim_start().starts_with("- ");
+  let belongs = |l: &str| l.trim().is_empty() || l.trim_start().starts_with('#') || indent(l) >= key_col;
+  let mut start = id_line;
+  while start > 0 && !is_item(lines[start]) && (belongs(lines[start - 1]) || is_item(lines[start - 1])) {
+    start -= 1;
+  }
+  let mut end = id_line + 1;
+  while end < lines.len() && belongs(lines[end]) {
+    end += 1;
+  }
+
+  let is_includes = |l: &str| key_column(l) == Some(key_col) && l[key_col ..].starts_with("includes:");
+  let line = match (start .. end).find(|i| is_includes(lines[*i])) {
+    Some(line) => line,
+    None => return Ok(None)
+  };
+  if lines[line][key_col + "includes:".len() ..].trim().trim_start_matches('[').trim_start() != "]" {
+    return Ok(None);
+  }
+
+  let removed = lines[line].to_string();
+  let added = format!("{}includes: [\"**/*\"]", &removed[.. key_col]);
+
+  let mut new_lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+  new_lines[line] = added.clone();
+  *data = new_lines.join("\n");
+  Ok(Some(Fix { description, line: line + 1, removed, added }))
+}
+
+/// The column of the first key on a line, skipping over any sequence marker.
+fn key_column(line: &str) -> Option<usize> {
+  let col = indent(line);
+  let rest = &line[col ..];
+  if rest.is_empty() || rest.starts_with('#') {
+    return None;
+  }
+  match rest.strip_prefix("- ") {
+    Some(item) => Some(col + 2 + indent(item)),
+    None => Some(col)
+  }
+}
+
+fn indent(line: &str) -> usize { line.len() - line.trim_start_matches(' ').len() }
+fn line_of(data: &str, byte: usize) -> usize { data[.. byte].matches('\n').count() }
+fn line_text(data: &str, line: usize) -> &str { data.split('\n').nth(line).unwrap_or("") }
@@ -1,55 +1,90 @@
 //! Interactions with git.
 
-use crate::config::{CommitConfig, CONFIG_FILENAME};
+use crate::config::{jobs_or_cpus, match_opts, CleanPolicy, CommitConfig, CommitHooks, ConfigFile, CONFIG_FILENAME};
 use crate::either::IterEither2 as E2;
 use crate::errors::{Context as _, Result};
-use crate::vcs::{VcsLevel, VcsState};
+use crate::fetch::FetchOpts;
+use crate::vcs::{VcsBackend, VcsLevel, VcsState};
 use crate::{bad, bail};
 use chrono::offset::Utc;
 use chrono::{DateTime, FixedOffset, TimeZone};
 use git2::build::CheckoutBuilder;
 use git2::string_array::StringArray;
-use git2::{AnnotatedCommit, AutotagOption, Blob, Commit, Cred, CredentialType, Diff, DiffOptions, FetchOptions, Index,
-           Object, ObjectType, Oid, PushOptions, Reference, ReferenceType, Remote, RemoteCallbacks, Repository,
-           RepositoryOpenFlags, RepositoryState, ResetType, Revwalk, Signature, Sort, Status, StatusOptions, Time};
+use git2::{AnnotatedCommit, AutotagOption, Blob, Commit, Cred, CredentialType, Diff, DiffOptions, Direction,
+           FetchOptions, Index, Object, ObjectType, Oid, ProxyOptions, PushOptions, Reference, ReferenceType, Remote,
+           RemoteCallbacks, Repository, RepositoryOpenFlags, RepositoryState, ResetType, Revwalk, Signature, Sort,
+           Status, StatusOptions, Time, TreeWalkMode, TreeWalkResult};
+use glob::Pattern;
+#[cfg(feature = "sequoia")]
 use openpgp::armor;
+#[cfg(feature = "sequoia")]
 use openpgp::serialize::stream::{Armorer, Message, Signer};
 use path_slash::PathBufExt as _;
 use regex::Regex;
+use reqwest::{Client, ClientBuilder, NoProxy};
+#[cfg(feature = "sequoia")]
 use sequoia_openpgp as openpgp;
+#[cfg(feature = "sequoia")]
 use sequoia_openpgp::crypto::KeyPair;
+#[cfg(feature = "sequoia")]
 use sequoia_openpgp::packet::key::SecretKeyMaterial;
+#[cfg(feature = "sequoia")]
 use sequoia_openpgp::parse::Parse;
+#[cfg(feature = "sequoia")]
 use sequoia_openpgp::policy::NullPolicy;
+#[cfg(feature = "sequoia")]
 use sequoia_openpgp::Cert;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use std::cmp::{min, Ord, Ordering, PartialOrd};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env::var;
 use std::ffi::OsStr;
 use std::fmt;
 use std::io::{stdout, Write};
 use std::iter::empty;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 use std::sync::{Arc, Mutex};
 use tracing::{error, info, trace, warn};
 
+/// The environment variable that names the remote to use, overriding the `remote` option of the config.
+pub const REMOTE_VAR: &str = "VERSIO_REMOTE";
+
+/// The file in the git directory where an offline release queues its pushes.
+const PUSH_QUEUE_FILE: &str = "versio-push.json";
+
 pub struct Repo {
   vcs: GitVcsLevel,
   ignore_current: bool,
   commit_config: CommitConfig,
+  auth: Option<Auth>,
+  proxy: Option<Proxy>,
+  github: GithubHost,
+  offline: bool,
+  no_cache: bool,
+  jobs: Option<NonZeroUsize>,
   cache: Arc<Mutex<RepoCache>>
 }
 
 pub struct RepoCache {
   pulled_tags: bool,
-  tags_to_push: Vec<String>
+  fetched_tags: HashSet<String>,
+  head_to_push: bool,
+  tags_to_push: Vec<String>,
+  tags_to_delete: Vec<String>
 }
 
 impl RepoCache {
   pub fn new() -> Arc<Mutex<RepoCache>> {
-    Arc::new(Mutex::new(RepoCache { pulled_tags: false, tags_to_push: Default::default() }))
+    Arc::new(Mutex::new(RepoCache {
+      pulled_tags: false,
+      fetched_tags: Default::default(),
+      head_to_push: false,
+      tags_to_push: Default::default(),
+      tags_to_delete: Default::default()
+    }))
   }
 }
 
@@ -73,7 +108,7 @@ impl Repo {
   pub fn cache(&self) -> Arc<Mutex<RepoCache>> { self.cache.clone() }
 
   /// Return the vcs level that this repository can support.
-  pub fn detect<P: AsRef<Path>>(path: P) -> Result<VcsLevel> {
+  pub fn detect<P: AsRef<Path>>(path: P, remote: Option<&str>) -> Result<VcsLevel> {
     let flags = RepositoryOpenFlags::empty();
     let repo = Repository::open_ext(path, flags, empty::<&OsStr>());
     let repo = match repo {
@@ -87,10 +122,12 @@ impl Repo {
     };
     trace!("Detected branch name: {:?}.", branch_name);
 
-    match find_remote_name(&repo, &branch_name) {
+    let file = repo.workdir().and_then(|w| ConfigFile::from_dir(w).ok());
+    match find_remote_name(&repo, &branch_name, preferred_remote(remote, file.as_ref()).as_deref()) {
       Ok(remote_name) => {
         trace!("Detected remote name: \"{}\".", remote_name);
-        if find_github_info(&repo, &remote_name, &Default::default()).is_ok() {
+        let github = file.map(|f| f.github_host());
+        if find_github_info(&repo, &remote_name, &Default::default(), &github.unwrap_or_default()).is_ok() {
           Ok(VcsLevel::Smart)
         } else {
           Ok(VcsLevel::Remote)
@@ -125,10 +162,15 @@ impl Repo {
   pub fn open<P: AsRef<Path>>(path: P, vcs: VcsState, commit_config: CommitConfig) -> Result<Repo> {
     let ignore_current = vcs.ignore_current();
     let cache = RepoCache::new();
+    let commit_config = if vcs.allow_dirty() { commit_config.with_clean(CleanPolicy::Warn) } else { commit_config };
+    let github = GithubHost::default();
+    let (offline, no_cache, jobs) = (vcs.offline(), vcs.no_cache(), vcs.jobs());
 
     if vcs.level().is_none() {
       let root = find_root_blind(path)?;
-      return Ok(Repo { ignore_current, vcs: GitVcsLevel::None { root }, commit_config, cache });
+      let vcs = GitVcsLevel::None { root };
+      let (auth, proxy) = (None, None);
+      return Ok(Repo { ignore_current, vcs, commit_config, auth, proxy, github, offline, no_cache, jobs, cache });
     }
 
     let flags = RepositoryOpenFlags::empty();
@@ -136,10 +178,16 @@ impl Repo {
     let branch_name = find_branch_name(&repo)?;
 
     if vcs.level().is_local() {
-      return Ok(Repo { ignore_current, vcs: GitVcsLevel::Local { repo, branch_name }, commit_config, cache });
+      let vcs = GitVcsLevel::Local { repo, branch_name };
+      let (auth, proxy) = (None, None);
+      return Ok(Repo { ignore_current, vcs, commit_config, auth, proxy, github, offline, no_cache, jobs, cache });
     }
 
-    let remote_name = find_remote_name(&repo, &branch_name)?;
+    let file = match (vcs.remote(), repo.workdir()) {
+      (None, Some(workdir)) => Some(ConfigFile::from_dir(workdir)?),
+      _ => None
+    };
+    let remote_name = find_remote_name(&repo, &branch_name, preferred_remote(vcs.remote(), file.as_ref()).as_deref())?;
     let fetches = RefCell::new(HashMap::new());
     let root = repo.workdir().ok_or_else(|| bad!("Repo has no working dir."))?.to_path_buf();
 
@@ -147,10 +195,52 @@ impl Repo {
       ignore_current,
       vcs: GitVcsLevel::from(vcs.level(), root, repo, branch_name, remote_name, fetches),
       commit_config,
+      auth: None,
+      proxy: None,
+      github,
+      offline,
+      no_cache,
+      jobs,
       cache
     })
   }
 
+  /// The files that are already dirty, but which the commit config lets us leave out of a commit. Only commands
+  /// that commit need these, and they have to find them before they write any files of their own.
+  pub fn tolerated_paths(&self) -> Result<HashSet<String>> {
+    match &self.vcs {
+      GitVcsLevel::None { .. } => Ok(HashSet::new()),
+      _ => tolerated_paths(self.repo()?, &self.commit_config)
+    }
+  }
+
+  /// Recognize remotes at the given GitHub host, which may be a GitHub Enterprise instance.
+  pub fn with_github(mut self, github: GithubHost) -> Repo {
+    self.github = github;
+    self
+  }
+
+  /// Use the given user credentials when talking to the remote.
+  pub fn with_auth(mut self, auth: Option<Auth>) -> Repo {
+    self.auth = auth;
+    self
+  }
+
+  /// Talk to the remote and its API through the given proxy of the user preferences, instead of the environment's.
+  pub fn with_proxy(mut self, proxy: Option<Proxy>) -> Repo {
+    self.proxy = proxy;
+    self
+  }
+
+  /// How to fetch URL content (such as the released version of an external project) alongside this repo.
+  pub fn fetch_opts(&self) -> FetchOpts { FetchOpts::new(self.offline, self.proxy.clone()) }
+
+  /// The number of things to do at once: the number that was given, or else the number of CPUs.
+  pub fn jobs(&self) -> usize { jobs_or_cpus(self.jobs) }
+
+  /// The number of things to do at once, only if it was given (by `--jobs` or `VERSIO_JOBS`).
+  pub fn given_jobs(&self) -> Option<NonZeroUsize> { self.jobs }
+
   pub fn working_dir(&self) -> Result<&Path> {
     match &self.vcs {
       GitVcsLevel::None { root } => Ok(root),
@@ -163,7 +253,7 @@ impl Repo {
   pub fn revparse_oid(&self, spec: FromTag) -> Result<String> {
     let repo = self.repo()?;
     if !self.ignore_current {
-      verify_current(repo).context("Can't complete revparse.")?;
+      verify_current(repo, &self.commit_config).context("Can't complete revparse.")?;
     }
     Ok(repo.revparse_single(spec.tag())?.id().to_string())
   }
@@ -183,7 +273,8 @@ impl Repo {
           let mut cache = cache.lock().unwrap();
           if !cache.pulled_tags {
             let specs: &[&str] = &["refs/tags/*:refs/tags/*"];
-            safe_fetch(repo, remote_name, specs, false).with_context(|| "Can't fetch all tags")?;
+            safe_fetch(repo, remote_name, specs, false, self.jobs(), self.auth.as_ref(), self.proxy.as_ref())
+              .with_context(|| "Can't fetch all tags")?;
             cache.pulled_tags = true;
           }
         }
@@ -193,9 +284,66 @@ impl Repo {
     }
   }
 
+  /// List the local tags that match the pattern with the oid of each, without fetching any tags from the remote.
+  pub fn local_tags(&self, pattern: &str) -> Result<Vec<(String, String)>> {
+    let repo = self.repo()?;
+    let mut tags = Vec::new();
+    for tag in repo.tag_names(Some(pattern))?.iter().flatten() {
+      let oid = repo.refname_to_id(&format!("refs/tags/{}", tag))?;
+      tags.push((tag.to_string(), oid.to_string()));
+    }
+    Ok(tags)
+  }
+
+  /// List all the tags on the remote with the oid of each, as `git ls-remote --tags` does.
+  pub fn remote_tags(&self) -> Result<HashMap<String, String>> {
+    match &self.vcs {
+      GitVcsLevel::None { .. } | GitVcsLevel::Local { .. } => err!("No remote at `none` or `local`."),
+      GitVcsLevel::Remote { repo, remote_name, .. } | GitVcsLevel::Smart { repo, remote_name, .. } => {
+        ls_remote_tags(repo, remote_name, self.auth.as_ref(), self.proxy.as_ref())
+      }
+    }
+  }
+
+  /// Push the given local tags to the remote, replacing any remote tags of the same names.
+  pub fn push_tags(&self, tags: &[String]) -> Result<()> {
+    for tag in tags {
+      self.push_tag(tag)?;
+    }
+    self.finish_tags()
+  }
+
+  /// Make sure the given tag is current with the remote, if the VCS level allows it.
+  pub fn fetch_tag(&self, tag: &str) -> Result<()> {
+    let (repo, remote_name) = match &self.vcs {
+      GitVcsLevel::None { .. } | GitVcsLevel::Local { .. } => return Ok(()),
+      GitVcsLevel::Remote { repo, remote_name, .. } | GitVcsLevel::Smart { repo, remote_name, .. } => {
+        (repo, remote_name)
+      }
+    };
+
+    let cache = self.cache();
+    let mut cache = cache.lock().unwrap();
+    if cache.pulled_tags || cache.fetched_tags.contains(tag) {
+      return Ok(());
+    }
+
+    let spec = format!("+refs/tags/{}:refs/tags/{}", tag, tag);
+    let (auth, proxy) = (self.auth.as_ref(), self.proxy.as_ref());
+    if let Err(e) = safe_fetch(repo, remote_name, &[spec.as_str()], false, self.jobs(), auth, proxy) {
+      // The tag might legitimately not exist yet on the remote: fall back to what we have locally.
+      warn!("Unable to fetch tag \"{}\", using the local tag (which may be stale) instead: {:#}", tag, e);
+    }
+    cache.fetched_tags.insert(tag.to_string());
+    Ok(())
+  }
+
   pub fn github_info(&self, auth: &Option<Auth>) -> Result<GithubInfo> {
     match &self.vcs {
-      GitVcsLevel::Smart { repo, .. } => find_github_info(repo, self.remote_name()?, auth),
+      GitVcsLevel::Smart { repo, .. } => {
+        let info = find_github_info(repo, self.remote_name()?, auth, &self.github)?;
+        Ok(info.with_proxy(self.proxy.clone()).with_no_cache(self.no_cache))
+      }
       GitVcsLevel::None { .. } | GitVcsLevel::Local { .. } | GitVcsLevel::Remote { .. } => {
         bail!("No github info at currnet level")
       }
@@ -245,6 +393,20 @@ impl Repo {
     Ok(revwalk.map(move |id| Ok(CommitInfo::new(repo, repo.find_commit(id?)?))))
   }
 
+  /// Return the IDs of the commit at `spec` and all of its ancestors, newest first, as in `git rev-list spec`.
+  pub fn ancestor_oids(&self, spec: &str) -> Result<impl Iterator<Item = Result<String>> + '_> {
+    let repo = self.repo()?;
+    let commit = repo
+      .revparse_single(spec)
+      .and_then(|obj| obj.peel_to_commit())
+      .with_context(|| format!("Can't find commit {}", spec))?;
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL)?;
+    revwalk.push(commit.id())?;
+
+    Ok(revwalk.map(|id| Ok(id?.to_string())))
+  }
+
   /// Return all commits as in `git rev-list from_sha..HEAD`.
   ///
   /// `from` may be any legal target of `rev-parse`.
@@ -272,7 +434,7 @@ impl Repo {
       GitVcsLevel::None { .. } => bail!("Can't get OID at `none`."),
       GitVcsLevel::Local { repo, .. } => {
         if !self.ignore_current {
-          verify_current(repo).context("Can't complete get.")?;
+          verify_current(repo, &self.commit_config).context("Can't complete get.")?;
         }
         get_oid_local(repo, spec)
       }
@@ -280,12 +442,13 @@ impl Repo {
       | GitVcsLevel::Smart { repo, branch_name, remote_name, fetches } => {
         if spec == "HEAD" {
           if !self.ignore_current {
-            verify_current(repo).context("Can't complete HEAD get.")?;
+            verify_current(repo, &self.commit_config).context("Can't complete HEAD get.")?;
           }
           get_oid_local(repo, spec)
         } else {
           // get_oid_remote() will verify current
-          get_oid_remote(repo, branch_name, spec, remote_name, fetches)
+          let auth = self.auth.as_ref();
+          get_oid_remote(repo, branch_name, spec, remote_name, fetches, auth, self.proxy.as_ref(), &self.commit_config)
         }
       }
     }
@@ -304,36 +467,95 @@ impl Repo {
       .and_then(|tag| tag.message().map(|m| m.to_string()))
   }
 
-  pub fn commit(&self) -> Result<bool> {
+  /// Find the commit that `spec` points to, if any.
+  pub fn commit_oid_of(&self, spec: &str) -> Option<String> {
+    let repo = self.repo().ok()?;
+    repo.revparse_single(spec).and_then(|obj| obj.peel_to_commit()).ok().map(|c| c.id().to_string())
+  }
+
+  /// Find the most recent commit reachable from HEAD that was committed at or before the given time.
+  pub fn last_commit_before(&self, time: &DateTime<FixedOffset>) -> Result<Option<String>> {
+    let repo = self.repo()?;
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TIME)?;
+    revwalk.push_head()?;
+
+    for oid in revwalk {
+      let commit = repo.find_commit(oid?)?;
+      if commit.time().seconds() <= time.timestamp() {
+        return Ok(Some(commit.id().to_string()));
+      }
+    }
+    Ok(None)
+  }
+
+  /// Commit all modified files, and push the branch.
+  pub fn commit(&self, tolerated: &HashSet<String>) -> Result<bool> {
+    let committed = self.commit_unpushed(tolerated)?;
+    if committed {
+      self.push_head(&[])?;
+    }
+    Ok(committed)
+  }
+
+  /// Like `commit`, but leave the branch to be pushed later by `push_branch`.
+  pub fn commit_unpushed(&self, tolerated: &HashSet<String>) -> Result<bool> {
     if let GitVcsLevel::None { .. } = self.vcs {
       return Ok(false);
     }
 
-    if let Some(mut index) = self.add_all_modified()? {
-      let tree_oid = index.write_tree()?;
-      self.commit_tree(tree_oid)?;
-      self.push_head(&[])?;
+    if let Some(mut index) = self.add_all_modified(tolerated)? {
+      match self.commit_config.hooks() {
+        Some(CommitHooks::Run) => {
+          index.write()?;
+          self.commit_with_hooks(self.commit_config.message())?;
+        }
+        hooks => {
+          if hooks.is_none() {
+            self.warn_bypassed_hooks()?;
+          }
+          let tree_oid = index.write_tree()?;
+          self.commit_tree(tree_oid, self.commit_config.message())?;
+        }
+      }
       Ok(true)
     } else {
       Ok(false)
     }
   }
 
-  fn add_all_modified(&self) -> Result<Option<Index>> {
+  /// The repo-relative paths of the files that are modified, deleted, or untracked in the working directory. There
+  /// are none without a repository.
+  pub fn modified_paths(&self) -> Result<Vec<String>> {
+    if let GitVcsLevel::None { .. } = self.vcs {
+      return Ok(Vec::new());
+    }
+
     let repo = self.repo()?;
     let mut status_opts = StatusOptions::new();
     status_opts.include_ignored(false);
     status_opts.include_untracked(true);
     status_opts.exclude_submodules(true);
 
-    let mut index = repo.index()?;
-    let mut found = false;
+    let mut paths = Vec::new();
     for s in repo.statuses(Some(&mut status_opts))?.iter().filter(|s| {
       let s = s.status();
       s.is_wt_modified() || s.is_wt_deleted() || s.is_wt_renamed() || s.is_wt_typechange() || s.is_wt_new()
     }) {
+      paths.push(s.path().ok_or_else(|| bad!("Bad path"))?.to_string());
+    }
+    Ok(paths)
+  }
+
+  fn add_all_modified(&self, tolerated: &HashSet<String>) -> Result<Option<Index>> {
+    let mut index = self.repo()?.index()?;
+    let mut found = false;
+    for path in self.modified_paths()? {
+      if tolerated.contains(&path) {
+        warn!("Not committing {}: it was already dirty.", path);
+        continue;
+      }
       found = true;
-      let path = s.path().ok_or_else(|| bad!("Bad path"))?;
       index.add_path(path.as_ref())?;
     }
 
@@ -344,7 +566,28 @@ impl Repo {
     }
   }
 
-  fn commit_tree(&self, tree_oid: Oid) -> Result<()> {
+  /// Revert the given commit with a new commit, and push it.
+  pub fn revert(&self, oid: &str) -> Result<()> {
+    if let GitVcsLevel::None { .. } = self.vcs {
+      return Ok(());
+    }
+
+    let repo = self.repo()?;
+    let commit = repo.find_commit(Oid::from_str(oid)?)?;
+    repo.revert(&commit, None)?;
+
+    let mut index = repo.index()?;
+    if index.has_conflicts() {
+      bail!("Unable to cleanly revert commit {}.", oid);
+    }
+    let tree_oid = index.write_tree()?;
+    let msg = format!("Revert \"{}\"\n\nThis reverts commit {}.", commit.summary().unwrap_or("-"), oid);
+    self.commit_tree(tree_oid, &msg)?;
+    repo.cleanup_state()?;
+    self.push_head(&[])
+  }
+
+  fn commit_tree(&self, tree_oid: Oid, msg: &str) -> Result<()> {
     // Based roughly on https://github.com/rust-lang/git2-rs/issues/507, but using sequoia/rust-crypto.
 
     let repo = self.repo()?;
@@ -354,18 +597,20 @@ impl Repo {
     trace!("Committing");
 
     let sig = Signature::now(self.commit_config.author(), self.commit_config.email())?;
-    let msg = self.commit_config.message();
 
-    let commit_oid = if repo.config()?.get_bool("commit.gpgSign").unwrap_or(false) {
-      let keypath = repo.config()?.get_path("versio.keypath").with_context(|| "No versio.keypath")?;
-      let keypair = find_keypair_for_id(&keypath)?;
+    let commit_oid = if !repo.config()?.get_bool("commit.gpgSign").unwrap_or(false) {
+      repo.commit(head, &sig, &sig, msg, &tree, &[&parent_commit])?
+    } else if find_backend(repo)?.is_git_cli() {
+      let (tree, parent) = (tree_oid.to_string(), parent_commit.id().to_string());
+      let args: [&str; 7] = ["commit-tree", &tree, "-p", &parent, "-S", "-m", msg];
+      let out = git_cli(repo, Some(self.commit_config()), None, &args)?;
+      Oid::from_str(&out)?
+    } else {
       let buf = repo.commit_create_buffer(&sig, &sig, msg, &tree, &[&parent_commit])?;
-      let out = sign_armored_detached(keypair, &buf)?;
+      let out = sign_buffer(repo, &buf)?;
 
       let contents = buf.as_str().ok_or_else(|| bad!("Commit buffer was not UTF-8"))?;
       repo.commit_signed(contents, std::str::from_utf8(&out)?, Some("gpgsig"))?
-    } else {
-      repo.commit(head, &sig, &sig, msg, &tree, &[&parent_commit])?
     };
 
     repo.reset(&repo.find_object(commit_oid, Some(ObjectType::Commit))?, ResetType::Mixed, None)?;
@@ -373,6 +618,23 @@ impl Repo {
     Ok(())
   }
 
+  /// Commit the index with the system `git`, which runs the repo's commit hooks (from `core.hooksPath`, if it's
+  /// set). A hook that rejects the commit fails the release.
+  fn commit_with_hooks(&self, msg: &str) -> Result<()> {
+    trace!("Committing with hooks");
+    git_cli(self.repo()?, Some(self.commit_config()), None, &["commit", "-m", msg])?;
+    Ok(())
+  }
+
+  fn warn_bypassed_hooks(&self) -> Result<()> {
+    let dir = hooks_dir(self.repo()?)?;
+    let found: Vec<_> = COMMIT_HOOKS.iter().copied().filter(|hook| dir.join(hook).is_file()).collect();
+    if !found.is_empty() {
+      warn!("Not running commit hooks {}: set commit.hooks to \"run\" or \"bypass\".", found.join(", "));
+    }
+    Ok(())
+  }
+
   fn find_last_commit(&self) -> Result<Commit> {
     let repo = self.repo()?;
     let obj = repo.head()?.resolve()?.peel(ObjectType::Commit)?;
@@ -408,7 +670,11 @@ impl Repo {
     let fsa = config.get_bool("tag.forceSignAnnotated").unwrap_or(false);
     let gsign = config.get_bool("tag.gpgSign").unwrap_or(false);
 
-    if fsa || gsign {
+    if find_backend(repo)?.is_git_cli() {
+      let oid = obj.id().to_string();
+      let kind = if fsa || gsign { "-s" } else { "-a" };
+      git_cli(repo, Some(self.commit_config()), None, &["tag", "-f", kind, "-m", msg, tag, &oid])?;
+    } else if fsa || gsign {
       // There's no tag_create_buffer() in libgit2, so we'll do this:
       //   - tag it
       //   - read the raw tag data
@@ -425,10 +691,7 @@ impl Repo {
       let odb = repo.odb()?;
       let tag_obj = odb.read(first_oid)?;
 
-      let keypath = repo.config()?.get_path("versio.keypath").with_context(|| "No versio.keypath")?;
-
-      let keypair = find_keypair_for_id(&keypath)?;
-      let outbuf = sign_armored_detached(keypair, tag_obj.data())?;
+      let outbuf = sign_buffer(repo, tag_obj.data())?;
       let detached_sig = std::str::from_utf8(&outbuf)?;
 
       repo.tag(tag, &obj, &tagger, &format!("{}{}", msg_string, detached_sig), true)?;
@@ -439,7 +702,32 @@ impl Repo {
     Ok(())
   }
 
+  /// Delete a tag locally; the remote tag is deleted by `finish_tags`.
+  pub fn delete_tag(&self, tag: &str) -> Result<()> {
+    if let GitVcsLevel::None { .. } = self.vcs {
+      return Ok(());
+    }
+
+    let repo = self.repo()?;
+    if repo.refname_to_id(&format!("refs/tags/{}", tag)).is_ok() {
+      repo.tag_delete(tag)?;
+    }
+
+    let cache = self.cache();
+    let mut cache = cache.lock().unwrap();
+    cache.tags_to_delete.push(tag.to_string());
+    Ok(())
+  }
+
   fn push_head(&self, tags: &[String]) -> Result<()> {
+    if self.offline {
+      let cache = self.cache();
+      let mut cache = cache.lock().unwrap();
+      cache.head_to_push = true;
+      cache.tags_to_push.extend(tags.iter().cloned());
+      return Ok(());
+    }
+
     let (repo, branch_name, remote_name) = match &self.vcs {
       GitVcsLevel::None { .. } | GitVcsLevel::Local { .. } => return Ok(()),
       GitVcsLevel::Remote { repo, branch_name, remote_name, .. }
@@ -452,18 +740,27 @@ impl Repo {
       refs.push(format!("+refs/tags/{}", tag));
     }
 
-    do_push(repo, remote_name, &refs)
+    do_push(repo, remote_name, &refs, self.auth.as_ref(), self.proxy.as_ref())
   }
 
+  /// Push the current branch, or queue it if offline.
+  pub fn push_branch(&self) -> Result<()> { self.push_head(&[]) }
+
   fn push_tag(&self, tag: &str) -> Result<()> {
     // don't actually push here: wait for `finish_tags` to push all tags
     let cache = self.cache();
     let mut cache = cache.lock().unwrap();
-    cache.tags_to_push.push(tag.to_string());
+    if !cache.tags_to_push.iter().any(|t| t == tag) {
+      cache.tags_to_push.push(tag.to_string());
+    }
     Ok(())
   }
 
   pub fn finish_tags(&self) -> Result<()> {
+    if self.offline {
+      return self.queue_pushes();
+    }
+
     let (repo, remote_name) = match &self.vcs {
       GitVcsLevel::None { .. } | GitVcsLevel::Local { .. } => return Ok(()),
       GitVcsLevel::Remote { repo, remote_name, .. } | GitVcsLevel::Smart { repo, remote_name, .. } => {
@@ -474,9 +771,66 @@ impl Repo {
     let specs: Vec<_> = {
       let cache = self.cache();
       let mut cache = cache.lock().unwrap();
-      cache.tags_to_push.drain(..).map(|t| format!("+refs/tags/{}", t)).collect()
+      let deletes: Vec<_> = cache.tags_to_delete.drain(..).map(|t| format!(":refs/tags/{}", t)).collect();
+      cache.tags_to_push.drain(..).map(|t| format!("+refs/tags/{}", t)).chain(deletes).collect()
     };
-    do_push(repo, remote_name, &specs)
+    do_push(repo, remote_name, &specs, self.auth.as_ref(), self.proxy.as_ref())
+  }
+
+  /// Add the branch and tags that would have been pushed to the queue file, for a later `versio push`.
+  fn queue_pushes(&self) -> Result<()> {
+    let repo = match &self.vcs {
+      GitVcsLevel::None { .. } => return Ok(()),
+      GitVcsLevel::Local { repo, .. } | GitVcsLevel::Remote { repo, .. } | GitVcsLevel::Smart { repo, .. } => repo
+    };
+
+    let path = repo.path().join(PUSH_QUEUE_FILE);
+    let mut queue = PushQueue::read(&path)?;
+    {
+      let cache = self.cache();
+      let mut cache = cache.lock().unwrap();
+      if std::mem::take(&mut cache.head_to_push) {
+        let branch_name = self.branch_name()?.as_ref().ok_or_else(|| bad!("No branch name for push."))?;
+        queue.branch = Some(branch_name.clone());
+      }
+      cache.tags_to_push.drain(..).for_each(|tag| queue.push_tag(tag));
+      cache.tags_to_delete.drain(..).for_each(|tag| queue.delete_tag(tag));
+    }
+
+    if !queue.is_empty() {
+      info!("Queued pushes in {}: run `versio push` to push them.", path.display());
+      queue.write(&path)?;
+    }
+    Ok(())
+  }
+
+  /// Push everything that offline releases have queued, and then clear the queue. Returns `None` if nothing was
+  /// queued.
+  pub fn push_queued(&self) -> Result<Option<PushQueue>> {
+    let (repo, remote_name) = match &self.vcs {
+      GitVcsLevel::None { .. } | GitVcsLevel::Local { .. } => bail!("Can't push at `none` or `local` level."),
+      GitVcsLevel::Remote { repo, remote_name, .. } | GitVcsLevel::Smart { repo, remote_name, .. } => {
+        (repo, remote_name)
+      }
+    };
+
+    let path = repo.path().join(PUSH_QUEUE_FILE);
+    let queue = PushQueue::read(&path)?;
+    if queue.is_empty() {
+      return Ok(None);
+    }
+
+    let pushed = do_push(repo, remote_name, &queue.specs(), self.auth.as_ref(), self.proxy.as_ref());
+    pushed.with_context(|| match queue.branch() {
+      Some(branch) => format!(
+        "Couldn't push the queue: if \"{}\" has moved on the remote since the offline release, pull (or rebase) it \
+         and run `versio push` again.",
+        branch
+      ),
+      None => "Couldn't push the queue.".to_string()
+    })?;
+    std::fs::remove_file(&path)?;
+    Ok(Some(queue))
   }
 
   pub fn branch_name(&self) -> Result<&Option<String>> {
@@ -503,6 +857,55 @@ impl Repo {
   }
 }
 
+/// The refs that offline releases would have pushed, in the order they were made.
+#[derive(Serialize, Deserialize, Default)]
+pub struct PushQueue {
+  branch: Option<String>,
+  tags: Vec<String>,
+  deleted_tags: Vec<String>
+}
+
+impl PushQueue {
+  fn read(path: &Path) -> Result<PushQueue> {
+    if !path.exists() {
+      return Ok(Default::default());
+    }
+    let data = std::fs::read_to_string(path)?;
+    serde_json::from_str(&data).with_context(|| format!("Malformed push queue {}.", path.display()))
+  }
+
+  fn write(&self, path: &Path) -> Result<()> { Ok(std::fs::write(path, serde_json::to_string_pretty(self)?)?) }
+
+  pub fn branch(&self) -> Option<&str> { self.branch.as_deref() }
+  pub fn tags(&self) -> &[String] { &self.tags }
+  pub fn deleted_tags(&self) -> &[String] { &self.deleted_tags }
+
+  fn is_empty(&self) -> bool { self.branch.is_none() && self.tags.is_empty() && self.deleted_tags.is_empty() }
+
+  fn push_tag(&mut self, tag: String) {
+    self.deleted_tags.retain(|t| t != &tag);
+    if !self.tags.contains(&tag) {
+      self.tags.push(tag);
+    }
+  }
+
+  fn delete_tag(&mut self, tag: String) {
+    self.tags.retain(|t| t != &tag);
+    if !self.deleted_tags.contains(&tag) {
+      self.deleted_tags.push(tag);
+    }
+  }
+
+  /// The refspecs to push. The branch isn't forced, since the remote may have moved on since the offline release:
+  /// the push fails instead of discarding those commits.
+  fn specs(&self) -> Vec<String> {
+    let branch = self.branch.iter().map(|b| format!("refs/heads/{}", b));
+    let tags = self.tags.iter().map(|t| format!("+refs/tags/{}", t));
+    let deletes = self.deleted_tags.iter().map(|t| format!(":refs/tags/{}", t));
+    branch.chain(tags).chain(deletes).collect()
+  }
+}
+
 #[derive(Clone)]
 pub struct Slice<'r> {
   repo: &'r Repo,
@@ -510,14 +913,91 @@ pub struct Slice<'r> {
 }
 
 impl<'r> Slice<'r> {
-  pub fn has_blob(&self, path: &str) -> Result<bool> { Ok(self.object(path).is_ok()) }
+  /// Whether the file exists in the slice, following the path into a submodule if it crosses one. Only a missing
+  /// file is `false`: an uninitialized submodule or a git error is returned as an error.
+  pub fn has_blob(&self, path: &str) -> Result<bool> {
+    if self.object(path).is_ok() {
+      return Ok(true);
+    }
+    Ok(self.in_submodule(path, |_| ())?.is_some())
+  }
+
   pub fn slice(&self, refspec: FromTagBuf) -> Slice<'r> { Slice { repo: self.repo, refspec } }
+  pub fn fetch_opts(&self) -> FetchOpts { self.repo.fetch_opts() }
 
   pub fn blob(&self, path: &str) -> Result<Blob> {
     let obj = self.object(path)?;
     obj.into_blob().map_err(|e| bad!("Not a blob: {} : {:?}", path, e))
   }
 
+  /// The object ID of the file's blob, following the path into a submodule if it crosses one.
+  pub fn blob_id(&self, path: &str) -> Result<String> {
+    match self.blob(path) {
+      Ok(blob) => Ok(blob.id().to_string()),
+      Err(e) => self.in_submodule(path, |blob| blob.id().to_string())?.ok_or(e)
+    }
+  }
+
+  /// The content of the file, following the path into a submodule if it crosses one.
+  pub fn blob_content(&self, path: &str) -> Result<Vec<u8>> {
+    match self.blob(path) {
+      Ok(blob) => Ok(blob.content().to_vec()),
+      Err(e) => self.in_submodule(path, |blob| blob.content().to_vec())?.ok_or(e)
+    }
+  }
+
+  /// Walk the path through the slice's tree, resolving any gitlink along the way to the commit in the submodule's
+  /// own repository. The submodule has to be initialized in the working directory, so that its objects exist. Returns
+  /// `None` if there is no file at the path, or no commit at all if the slice allows that (or there's no repo).
+  fn in_submodule<T, F: FnOnce(&Blob) -> T>(&self, path: &str, f: F) -> Result<Option<T>> {
+    if let GitVcsLevel::None { .. } = self.repo.vcs {
+      return Ok(None);
+    }
+    let repo = self.repo.repo()?;
+    let workdir = repo.workdir().ok_or_else(|| bad!("Repo has no working directory."))?;
+    let path = path.replace('\\', "/");
+    let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+
+    let mut sub: Option<Repository> = None;
+    let start = repo.revparse_single(&format!("{}^{{}}", self.refspec.tag())).and_then(|o| o.peel_to_commit());
+    let mut tree_id = match start {
+      Ok(commit) => commit.tree_id(),
+      Err(_) if self.refspec.else_none => return Ok(None),
+      Err(e) => return Err(e.into())
+    };
+
+    for (i, part) in parts.iter().enumerate() {
+      let (kind, id) = {
+        let current = sub.as_ref().unwrap_or(repo);
+        let tree = current.find_tree(tree_id)?;
+        let found = tree.get_name(part).map(|entry| (entry.kind(), entry.id()));
+        match found {
+          Some(found) => found,
+          None => return Ok(None)
+        }
+      };
+
+      match kind {
+        Some(ObjectType::Tree) => tree_id = id,
+        Some(ObjectType::Commit) => {
+          let sub_path = workdir.join(parts[..= i].join("/"));
+          trace!("Entering submodule at {}", sub_path.display());
+          let sub_repo =
+            Repository::open(&sub_path).map_err(|e| bad!("Can't open submodule at {}: {}", sub_path.display(), e))?;
+          tree_id = sub_repo.find_commit(id)?.tree_id();
+          sub = Some(sub_repo);
+        }
+        Some(ObjectType::Blob) if i == parts.len() - 1 => {
+          let current = sub.as_ref().unwrap_or(repo);
+          return Ok(Some(f(&current.find_blob(id)?)));
+        }
+        _ => return Ok(None)
+      }
+    }
+
+    Ok(None)
+  }
+
   pub fn subdirs(&self, path: Option<&String>, regex: &str) -> Result<Vec<String>> {
     trace!("Finding git subdirs at {:?}", path);
 
@@ -528,6 +1008,23 @@ impl<'r> Slice<'r> {
     Ok(tree.iter().filter_map(|entry| entry.name().map(|n| n.to_string())).filter(|n| filter.is_match(n)).collect())
   }
 
+  /// Find all files in the slice whose path matches the glob, in sorted order.
+  pub fn files_matching(&self, pattern: &Pattern) -> Result<Vec<String>> {
+    let tree = self.object("")?.into_tree().map_err(|_| bad!("No tree at {}", self.refspec.tag()))?;
+    let mut found = Vec::new();
+    tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+      if let (Some(ObjectType::Blob), Some(name)) = (entry.kind(), entry.name()) {
+        let path = format!("{}{}", dir, name);
+        if pattern.matches_with(&path, match_opts()) {
+          found.push(path);
+        }
+      }
+      TreeWalkResult::Ok
+    })?;
+    found.sort();
+    Ok(found)
+  }
+
   #[cfg(not(target_family = "windows"))]
   fn object(&self, path: &str) -> Result<Object> {
     Ok(self.repo.repo()?.revparse_single(&format!("{}:{}", self.refspec.tag(), path))?)
@@ -556,20 +1053,90 @@ impl<'r> Slice<'r> {
   }
 }
 
+#[derive(Clone)]
 pub struct GithubInfo {
   owner_name: String,
   repo_name: String,
-  token: Option<String>
+  token: Option<String>,
+  host: GithubHost,
+  proxy: Option<Proxy>,
+  no_cache: bool
 }
 
 impl GithubInfo {
-  pub fn new(owner_name: String, repo_name: String, token: Option<String>) -> GithubInfo {
-    GithubInfo { owner_name, repo_name, token }
+  pub fn new(owner_name: String, repo_name: String, token: Option<String>, host: GithubHost) -> GithubInfo {
+    GithubInfo { owner_name, repo_name, token, host, proxy: None, no_cache: false }
+  }
+
+  /// Talk to the API through the given proxy of the user preferences, instead of the environment's.
+  pub fn with_proxy(mut self, proxy: Option<Proxy>) -> GithubInfo {
+    self.proxy = proxy;
+    self
+  }
+
+  /// Always ask the API, instead of re-using (or saving) the responses cached by earlier runs.
+  pub fn with_no_cache(mut self, no_cache: bool) -> GithubInfo {
+    self.no_cache = no_cache;
+    self
   }
 
   pub fn owner_name(&self) -> &str { &self.owner_name }
   pub fn repo_name(&self) -> &str { &self.repo_name }
   pub fn token(&self) -> &Option<String> { &self.token }
+  pub fn host(&self) -> &GithubHost { &self.host }
+  pub fn proxy(&self) -> Option<&Proxy> { self.proxy.as_ref() }
+  pub fn no_cache(&self) -> bool { self.no_cache }
+  pub fn api_url(&self) -> &str { self.host.api_url() }
+
+  /// The web location of the repository, for building links to PRs and commits.
+  pub fn web_url(&self) -> String { format!("https://{}/{}/{}", self.host.host(), self.owner_name, self.repo_name) }
+
+  /// The web location of a PR, which Gitea calls a "pull" of the repository.
+  pub fn pr_url(&self, number: u32) -> String {
+    let pulls = if self.host.is_gitea() { "pulls" } else { "pull" };
+    format!("{}/{}/{}", self.web_url(), pulls, number)
+  }
+}
+
+/// The server that hosts remotes: either the public "github.com", a GitHub Enterprise instance, or a Gitea (or
+/// Forgejo) server.
+#[derive(Clone, Debug)]
+pub struct GithubHost {
+  host: String,
+  api_url: String,
+  gitea: bool
+}
+
+impl Default for GithubHost {
+  fn default() -> GithubHost {
+    GithubHost { host: "github.com".into(), api_url: "https://api.github.com/".into(), gitea: false }
+  }
+}
+
+impl GithubHost {
+  /// Create a host; the API URL of an enterprise host defaults to its `/api/` path.
+  pub fn new(host: Option<&str>, api_url: Option<&str>) -> GithubHost {
+    let default = GithubHost::default();
+    let host = host.unwrap_or(&default.host).to_string();
+    let api_url = match api_url {
+      Some(api_url) => api_url.to_string(),
+      None if host == default.host => default.api_url,
+      None => format!("https://{}/api/", host)
+    };
+    let api_url = if api_url.ends_with('/') { api_url } else { format!("{}/", api_url) };
+    GithubHost { host, api_url, gitea: false }
+  }
+
+  /// Create a Gitea or Forgejo host; the API URL defaults to its `/api/v1/` path.
+  pub fn gitea(host: &str, api_url: Option<&str>) -> GithubHost {
+    let api_url = api_url.map(|u| u.to_string()).unwrap_or_else(|| format!("https://{}/api/v1/", host));
+    let api_url = if api_url.ends_with('/') { api_url } else { format!("{}/", api_url) };
+    GithubHost { host: host.to_string(), api_url, gitea: true }
+  }
+
+  pub fn host(&self) -> &str { &self.host }
+  pub fn api_url(&self) -> &str { &self.api_url }
+  pub fn is_gitea(&self) -> bool { self.gitea }
 }
 
 #[derive(Clone, Eq)]
@@ -577,6 +1144,8 @@ pub struct CommitInfoBuf {
   id: String,
   summary: String,
   message: String,
+  author: String,
+  co_authors: Vec<Contributor>,
   kind: String,
   files: Vec<String>,
   time: DateTime<FixedOffset>
@@ -602,15 +1171,17 @@ impl Ord for CommitInfoBuf {
 
 impl CommitInfoBuf {
   pub fn new(
-    id: String, kind: String, summary: String, message: String, files: Vec<String>, time: DateTime<FixedOffset>
+    id: String, kind: String, summary: String, message: String, author: String, files: Vec<String>,
+    time: DateTime<FixedOffset>
   ) -> CommitInfoBuf {
-    CommitInfoBuf { id, summary, message, kind, files, time }
+    let co_authors = extract_co_authors(&message);
+    CommitInfoBuf { id, summary, message, author, co_authors, kind, files, time }
   }
 
   pub fn guess(id: String) -> CommitInfoBuf {
     let offset = FixedOffset::west_opt(0).expect("0 should be in bounds");
     let now = offset.timestamp_opt(Utc::now().timestamp(), 0).single().expect("utc/0 in bounds");
-    CommitInfoBuf::new(id, "-".into(), "-".into(), "".into(), Vec::new(), now)
+    CommitInfoBuf::new(id, "-".into(), "-".into(), "".into(), "-".into(), Vec::new(), now)
   }
 
   pub fn extract<'a>(repo: &'a Repository, commit: &Commit<'a>) -> Result<CommitInfoBuf> {
@@ -618,15 +1189,70 @@ impl CommitInfoBuf {
     let summary = commit.summary().unwrap_or("-").to_string();
     let message = commit.message().unwrap_or("-").to_string();
     let kind = extract_kind(&message);
+    let author = author_of(commit);
     let files = files_from_commit(repo, commit)?.collect();
-    Ok(CommitInfoBuf::new(id, kind, summary, message, files, time_to_datetime(&commit.time())))
+    Ok(CommitInfoBuf::new(id, kind, summary, message, author, files, time_to_datetime(&commit.time())))
   }
 
   pub fn id(&self) -> &str { &self.id }
   pub fn summary(&self) -> &str { &self.summary }
   pub fn message(&self) -> &str { &self.message }
+
+  /// The author of the commit, as `name <email>`.
+  pub fn author(&self) -> &str { &self.author }
+
+  /// The co-authors of the commit, from the `Co-authored-by:` trailers of its message.
+  pub fn co_authors(&self) -> &[Contributor] { &self.co_authors }
+
+  /// The author of the commit, followed by its co-authors.
+  pub fn contributors(&self) -> Vec<Contributor> {
+    Contributor::parse(&self.author).into_iter().chain(self.co_authors.iter().cloned()).collect()
+  }
+
   pub fn kind(&self) -> &str { &self.kind }
+
+  /// The scope of a conventional commit, such as the `api` of `feat(api): ...`.
+  pub fn scope(&self) -> Option<&str> { extract_scope(&self.summary) }
   pub fn files(&self) -> &[String] { &self.files }
+  pub fn time(&self) -> &DateTime<FixedOffset> { &self.time }
+}
+
+/// An author or co-author of a commit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Contributor {
+  name: String,
+  email: String
+}
+
+impl Contributor {
+  /// Parse a `name <email>` pair, as used by git and by `Co-authored-by:` trailers.
+  pub fn parse(text: &str) -> Option<Contributor> {
+    let text = text.trim();
+    let open = text.rfind('<')?;
+    let close = text[open ..].find('>')? + open;
+    let name = text[.. open].trim();
+    let email = text[open + 1 .. close].trim();
+    if name.is_empty() && email.is_empty() {
+      return None;
+    }
+    Some(Contributor { name: name.to_string(), email: email.to_string() })
+  }
+
+  pub fn name(&self) -> &str { &self.name }
+  pub fn email(&self) -> &str { &self.email }
+
+  /// True if this is the same person as the other contributor: by email if both have one, otherwise by name.
+  pub fn same_as(&self, other: &Contributor) -> bool {
+    if !self.email.is_empty() && !other.email.is_empty() {
+      self.email.eq_ignore_ascii_case(&other.email)
+    } else {
+      self.name == other.name
+    }
+  }
+}
+
+impl fmt::Display for Contributor {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{} <{}>", self.name, self.email) }
 }
 
 pub struct CommitInfo<'a> {
@@ -641,6 +1267,7 @@ impl<'a> CommitInfo<'a> {
   pub fn summary(&self) -> &str { self.commit.summary().unwrap_or("-") }
   pub fn message(&self) -> &str { self.commit.message().unwrap_or("-") }
   pub fn kind(&self) -> String { extract_kind(self.message()) }
+  pub fn author(&self) -> String { author_of(&self.commit) }
   pub fn files(&self) -> Result<impl Iterator<Item = String> + 'a> { files_from_commit(self.repo, &self.commit) }
   pub fn time(&self) -> DateTime<FixedOffset> { time_to_datetime(&self.commit.time()) }
 
@@ -650,6 +1277,7 @@ impl<'a> CommitInfo<'a> {
       self.kind(),
       self.summary().to_string(),
       self.message().to_string(),
+      self.author(),
       self.files()?.collect(),
       self.time()
     ))
@@ -719,6 +1347,8 @@ impl<'repo> DeltaIter<'repo> {
 pub struct FullPr {
   number: u32,
   title: String,
+  body: String,
+  labels: Vec<String>,
   head_ref: String,
   head_oid: Option<Oid>,
   base_oid: FromTagBuf,
@@ -730,9 +1360,10 @@ pub struct FullPr {
 }
 
 impl FullPr {
+  #[allow(clippy::too_many_arguments)]
   pub fn lookup(
-    repo: &Repo, base: FromTagBuf, headref: String, number: u32, title: String, closed_at: DateTime<FixedOffset>,
-    discovery_order: usize
+    repo: &Repo, base: FromTagBuf, headref: String, number: u32, title: String, body: String, labels: Vec<String>,
+    closed_at: DateTime<FixedOffset>, discovery_order: usize
   ) -> Result<FullPr> {
     let commit = repo.get_oid(&headref);
     match lookup_from_commit(repo, base.clone(), commit)? {
@@ -741,6 +1372,8 @@ impl FullPr {
         Ok(FullPr {
           number,
           title,
+          body,
+          labels,
           head_ref: headref,
           head_oid: None,
           base_oid: base,
@@ -754,6 +1387,8 @@ impl FullPr {
       Ok((commit, commits, base_time)) => Ok(FullPr {
         number,
         title,
+        body,
+        labels,
         head_ref: headref,
         head_oid: Some(commit.id()),
         base_oid: base,
@@ -768,8 +1403,11 @@ impl FullPr {
 
   pub fn number(&self) -> u32 { self.number }
   pub fn title(&self) -> &str { &self.title }
+  pub fn body(&self) -> &str { &self.body }
+  pub fn labels(&self) -> &[String] { &self.labels }
   pub fn head_ref(&self) -> &str { &self.head_ref }
   pub fn head_oid(&self) -> &Option<Oid> { &self.head_oid }
+  pub fn retain_commits<F: FnMut(&CommitInfoBuf) -> bool>(&mut self, f: F) { self.commits.retain(f); }
   pub fn base_oid(&self) -> FromTag { self.base_oid.as_from_tag() }
   pub fn commits(&self) -> &[CommitInfoBuf] { &self.commits }
   pub fn excludes(&self) -> &[String] { &self.excludes }
@@ -900,15 +1538,47 @@ impl fmt::Display for FromTagBuf {
   }
 }
 
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Debug, Default, Clone)]
 pub struct Auth {
-  github_token: Option<String>
+  github_token: Option<String>,
+  gitea_token: Option<String>,
+  ssh_key_path: Option<PathBuf>,
+  ssh_passphrase_env: Option<String>
 }
 
 impl Auth {
-  pub fn new(github_token: Option<String>) -> Auth { Auth { github_token } }
+  pub fn new(github_token: Option<String>) -> Auth { Auth { github_token, ..Default::default() } }
   pub fn github_token(&self) -> &Option<String> { &self.github_token }
+  pub fn gitea_token(&self) -> &Option<String> { &self.gitea_token }
+  pub fn ssh_key_path(&self) -> &Option<PathBuf> { &self.ssh_key_path }
+  pub fn ssh_passphrase_env(&self) -> &Option<String> { &self.ssh_passphrase_env }
   pub fn set_github_token(&mut self, token: Option<String>) { self.github_token = token; }
+  pub fn set_gitea_token(&mut self, token: Option<String>) { self.gitea_token = token; }
+}
+
+/// The proxy of the user preferences, which overrides the `HTTPS_PROXY` and `NO_PROXY` environment variables.
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct Proxy {
+  url: Option<String>,
+  no_proxy: Option<String>
+}
+
+impl Proxy {
+  pub fn url(&self) -> &Option<String> { &self.url }
+  pub fn no_proxy(&self) -> &Option<String> { &self.no_proxy }
+}
+
+/// Start building an HTTP client. Without a user `proxy`, the client uses the proxy of the environment.
+pub fn http_client(proxy: Option<&Proxy>) -> Result<ClientBuilder> {
+  let builder = Client::builder();
+  let url = proxy.and_then(|p| p.url.clone()).or_else(|| proxy_var("https_proxy"));
+  match (proxy, url) {
+    (Some(proxy), Some(url)) => {
+      let no_proxy = proxy.no_proxy.clone().or_else(|| proxy_var("no_proxy"));
+      Ok(builder.proxy(reqwest::Proxy::all(url)?.no_proxy(no_proxy.as_deref().and_then(NoProxy::from_string))))
+    }
+    _ => Ok(builder)
+  }
 }
 
 fn find_root_blind<P: AsRef<Path>>(path: P) -> Result<PathBuf> {
@@ -920,7 +1590,23 @@ fn find_root_blind<P: AsRef<Path>>(path: P) -> Result<PathBuf> {
   }
 }
 
-fn find_remote_name(repo: &Repository, branch_name: &Option<String>) -> Result<String> {
+/// The remote chosen by the user, if any: the `given` remote (from `--remote`), or else from the `VERSIO_REMOTE`
+/// environment variable, or else from the `remote` option of the config.
+fn preferred_remote(given: Option<&str>, file: Option<&ConfigFile>) -> Option<String> {
+  given
+    .map(|r| r.to_string())
+    .or_else(|| var(REMOTE_VAR).ok().filter(|r| !r.is_empty()))
+    .or_else(|| file.and_then(|f| f.remote()).map(|r| r.to_string()))
+}
+
+/// Find the remote to use: the `preferred` remote, if any; otherwise the remote configured for the branch, or
+/// the only remote in the repo.
+fn find_remote_name(repo: &Repository, branch_name: &Option<String>, preferred: Option<&str>) -> Result<String> {
+  if let Some(preferred) = preferred {
+    repo.find_remote(preferred).map_err(|e| bad!("Can't use remote \"{}\": {}", preferred, e.message()))?;
+    return Ok(preferred.to_string());
+  }
+
   let configured = branch_name
     .as_ref()
     .and_then(|branch_name| {
@@ -940,7 +1626,7 @@ fn find_remote_name(repo: &Repository, branch_name: &Option<String>) -> Result<S
     } else if remotes.len() == 1 {
       Ok(remotes.iter().next().unwrap().ok_or_else(|| bad!("Non-utf8 remote name."))?.to_string())
     } else {
-      err!("Too many remotes in this repo: {}.", e)
+      err!("Too many remotes in this repo (choose one with the `remote` option or `--remote`): {}.", e)
     }
   })
 }
@@ -963,13 +1649,18 @@ fn find_branch_name(repo: &Repository) -> Result<Option<String>> {
   }
 }
 
-fn find_github_info(repo: &Repository, remote_name: &str, auth: &Option<Auth>) -> Result<GithubInfo> {
+fn find_github_info(
+  repo: &Repository, remote_name: &str, auth: &Option<Auth>, github: &GithubHost
+) -> Result<GithubInfo> {
   let remote = repo.find_remote(remote_name)?;
 
   let url = remote.url().ok_or_else(|| bad!("Invalid utf8 remote url."))?;
-  let path = if let Some(url_suff) = url.strip_prefix("https://github.com/") {
-    url_suff
-  } else if let Some(url_suff) = url.strip_prefix("git@github.com:") {
+  let prefixes = [
+    format!("https://{}/", github.host()),
+    format!("git@{}:", github.host()),
+    format!("ssh://git@{}/", github.host())
+  ];
+  let path = if let Some(url_suff) = prefixes.iter().find_map(|p| url.strip_prefix(p.as_str())) {
     url_suff
   } else {
     return err!("Can't find github in remote url {}", url);
@@ -980,8 +1671,14 @@ fn find_github_info(repo: &Repository, remote_name: &str, auth: &Option<Auth>) -
   let slash = path.char_indices().find(|(_, c)| *c == '/').map(|(i, _)| i);
   let slash = slash.ok_or_else(|| bad!("No slash found in github path \"{}\".", path))?;
 
-  let token = auth.as_ref().and_then(|auth| auth.github_token().clone());
-  Ok(GithubInfo::new(path[0 .. slash].to_string(), path[slash + 1 ..].to_string(), token))
+  let token = auth.as_ref().and_then(|auth| {
+    if github.is_gitea() {
+      auth.gitea_token().clone()
+    } else {
+      auth.github_token().clone()
+    }
+  });
+  Ok(GithubInfo::new(path[0 .. slash].to_string(), path[slash + 1 ..].to_string(), token, github.clone()))
 }
 
 /// Hide ancestors of `from` from the revwalk, but don't hide anything if the commit-ish can't be found and
@@ -1066,6 +1763,30 @@ fn fast_forward(repo: &Repository, rfrnc: &mut Reference, rc: &AnnotatedCommit)
   Ok(())
 }
 
+/// The conventional commit scope of a commit summary (the `api` of `feat(api): ...`), if it has a non-empty one.
+fn extract_scope(summary: &str) -> Option<&str> {
+  let head = &summary[.. summary.find(':')?];
+  let open = head.find('(')?;
+  let close = head[open ..].find(')')? + open;
+  let scope = head[open + 1 .. close].trim();
+  if scope.is_empty() {
+    None
+  } else {
+    Some(scope)
+  }
+}
+
+/// The co-authors listed in the `Co-authored-by:` trailers of a commit message.
+fn extract_co_authors(message: &str) -> Vec<Contributor> {
+  const TRAILER: &str = "co-authored-by:";
+  message
+    .lines()
+    .map(|line| line.trim())
+    .filter(|line| line.get(.. TRAILER.len()).map(|head| head.eq_ignore_ascii_case(TRAILER)).unwrap_or(false))
+    .filter_map(|line| Contributor::parse(&line[TRAILER.len() ..]))
+    .collect()
+}
+
 /// Finds a conventional commit "type" from a commit message.
 ///
 /// The type can be one of the special characters "-" (no type found) or "!" ("BREAKING CHANGE:" or
@@ -1099,6 +1820,11 @@ fn extract_kind(message: &str) -> String {
   }
 }
 
+fn author_of(commit: &Commit) -> String {
+  let author = commit.author();
+  format!("{} <{}>", author.name().unwrap_or("-"), author.email().unwrap_or("-"))
+}
+
 fn files_from_commit<'a>(repo: &'a Repository, commit: &Commit<'a>) -> Result<impl Iterator<Item = String> + 'a> {
   if commit.parents().len() == 1 {
     let parent = commit.parent(0)?;
@@ -1151,9 +1877,9 @@ fn get_oid_local<'r>(repo: &'r Repository, spec: &str) -> Result<AnnotatedCommit
 
 fn get_oid_remote<'r>(
   repo: &'r Repository, branch_name: &Option<String>, spec: &str, remote_name: &str,
-  fetches: &RefCell<HashMap<String, Oid>>
+  fetches: &RefCell<HashMap<String, Oid>>, auth: Option<&Auth>, proxy: Option<&Proxy>, commit_config: &CommitConfig
 ) -> Result<AnnotatedCommit<'r>> {
-  let (commit, cached) = verified_fetch(repo, remote_name, fetches, spec)?;
+  let (commit, cached) = verified_fetch(repo, remote_name, fetches, spec, auth, proxy, commit_config)?;
 
   if let Some(branch_name) = branch_name {
     if !cached && spec == branch_name {
@@ -1165,9 +1891,10 @@ fn get_oid_remote<'r>(
 }
 
 fn verified_fetch<'r>(
-  repo: &'r Repository, remote_name: &str, fetches: &RefCell<HashMap<String, Oid>>, spec: &str
+  repo: &'r Repository, remote_name: &str, fetches: &RefCell<HashMap<String, Oid>>, spec: &str, auth: Option<&Auth>,
+  proxy: Option<&Proxy>, commit_config: &CommitConfig
 ) -> Result<(AnnotatedCommit<'r>, bool)> {
-  verify_current(repo).context("Can't start fetch.")?;
+  verify_current(repo, commit_config).context("Can't start fetch.")?;
 
   if let Some(oid) = fetches.borrow().get(spec).cloned() {
     info!("No fetch for \"{}\": already fetched.", spec);
@@ -1175,7 +1902,7 @@ fn verified_fetch<'r>(
     return Ok((fetch_commit, true));
   }
 
-  safe_fetch(repo, remote_name, &[spec], true)?;
+  safe_fetch(repo, remote_name, &[spec], true, 1, auth, proxy)?;
 
   // Assume a standard git config `remote.<remote_name>.fetch` layout; if not we can force the tracking
   // branch (change the refspec to "{refspec}:refs/remotes/{remote_name}/{refspec}"), or parse the config
@@ -1197,12 +1924,12 @@ fn verified_fetch<'r>(
   let fetch_commit = repo.find_annotated_commit(oid)?;
   assert!(fetch_commit.id() == oid);
 
-  verify_current(repo).context("Can't complete fetch.")?;
+  verify_current(repo, commit_config).context("Can't complete fetch.")?;
 
   Ok((fetch_commit, false))
 }
 
-fn verify_current(repo: &Repository) -> Result<()> {
+fn verify_current(repo: &Repository, commit_config: &CommitConfig) -> Result<()> {
   let state = repo.state();
   if state != RepositoryState::Clean {
     // Don't bother if we're in the middle of a merge, rebase, etc.
@@ -1215,28 +1942,70 @@ fn verify_current(repo: &Repository) -> Result<()> {
   status_opts.exclude_submodules(false);
 
   let statuses = repo.statuses(Some(&mut status_opts))?;
-  let bad_status = statuses.iter().find(|s| s.status() != Status::CURRENT);
-  if let Some(bad_status) = bad_status {
-    bail!("Repository is not current: {} = {:?}", bad_status.path().unwrap_or("<none>"), bad_status.status());
+  for bad_status in statuses.iter().filter(|s| s.status() != Status::CURRENT) {
+    let path = bad_status.path().unwrap_or("<none>");
+    if commit_config.tolerates(path, bad_status.status() == Status::WT_NEW)? {
+      continue;
+    }
+    if commit_config.clean() == CleanPolicy::Warn {
+      warn!("Repository is not current: {} = {:?}", path, bad_status.status());
+    } else {
+      bail!("Repository is not current: {} = {:?}", path, bad_status.status());
+    }
   }
   Ok(())
 }
 
-fn safe_fetch(repo: &Repository, remote_name: &str, specs: &[&str], all_tags: bool) -> Result<()> {
+/// Find the paths that are dirty, but which the cleanliness policy allows.
+fn tolerated_paths(repo: &Repository, commit_config: &CommitConfig) -> Result<HashSet<String>> {
+  let mut status_opts = StatusOptions::new();
+  status_opts.include_ignored(false);
+  status_opts.include_untracked(true);
+  status_opts.exclude_submodules(true);
+
+  let mut tolerated = HashSet::new();
+  for s in repo.statuses(Some(&mut status_opts))?.iter().filter(|s| s.status() != Status::CURRENT) {
+    let path = s.path().ok_or_else(|| bad!("Bad path"))?;
+    if commit_config.clean() == CleanPolicy::Warn || commit_config.tolerates(path, s.status() == Status::WT_NEW)? {
+      tolerated.insert(path.to_string());
+    }
+  }
+  Ok(tolerated)
+}
+
+/// Fetch the given refspecs (and maybe all tags). With the git CLI, up to `jobs` submodules are fetched at once.
+fn safe_fetch(
+  repo: &Repository, remote_name: &str, specs: &[&str], all_tags: bool, jobs: usize, auth: Option<&Auth>,
+  proxy: Option<&Proxy>
+) -> Result<()> {
   let state = repo.state();
   if state != RepositoryState::Clean {
     // Don't bother if we're in the middle of a merge, rebase, etc.
     bail!("Can't pull: repository {:?} isn't clean.", state);
   }
 
-  let mut remote = repo.find_remote(remote_name)?;
-
   // As of git server 2.6, you can fetch `refs/tags/xyz*`
-  do_fetch(&mut remote, specs, all_tags)
+  if find_backend(repo)?.is_git_cli() {
+    info!("Fetching {:?}{} with git", specs, if all_tags { " and all tags." } else { "." });
+    let jobs = format!("--jobs={}", jobs);
+    let mut args = vec!["fetch", jobs.as_str()];
+    if all_tags {
+      args.push("--tags");
+    }
+    args.push(remote_name);
+    args.extend(specs);
+    git_cli(repo, None, proxy, &args)?;
+    return Ok(());
+  }
+
+  let mut remote = repo.find_remote(remote_name)?;
+  do_fetch(&mut remote, specs, all_tags, auth, proxy)
 }
 
 /// Fetch the given refspecs (and maybe all tags) from the remote.
-fn do_fetch(remote: &mut Remote, refs: &[&str], all_tags: bool) -> Result<()> {
+fn do_fetch(
+  remote: &mut Remote, refs: &[&str], all_tags: bool, auth: Option<&Auth>, proxy: Option<&Proxy>
+) -> Result<()> {
   // WARNING: Currently not supporting fetching via sha:
   //
   // git has supported `git fetch <remote> <sha>` for a while, but it has to work a bit differently (since sha's
@@ -1246,7 +2015,7 @@ fn do_fetch(remote: &mut Remote, refs: &[&str], all_tags: bool) -> Result<()> {
 
   let mut cb = RemoteCallbacks::new();
 
-  cb.credentials(find_creds);
+  cb.credentials(|url, user, allowed| find_creds(auth, url, user, allowed));
   cb.transfer_progress(|stats| {
     if stats.received_objects() == stats.total_objects() {
       info!("Resolving deltas {}/{}", stats.indexed_deltas(), stats.total_deltas());
@@ -1273,6 +2042,7 @@ fn do_fetch(remote: &mut Remote, refs: &[&str], all_tags: bool) -> Result<()> {
 
   let mut fo = FetchOptions::new();
   fo.remote_callbacks(cb);
+  fo.proxy_options(proxy_options(remote.url(), proxy));
 
   if all_tags {
     fo.download_tags(AutotagOption::All);
@@ -1295,10 +2065,48 @@ fn do_fetch(remote: &mut Remote, refs: &[&str], all_tags: bool) -> Result<()> {
   Ok(())
 }
 
+/// List the tags of the remote with the oid that each refers to: for annotated tags, that's the oid of the tag
+/// object, not of its commit.
+fn ls_remote_tags(
+  repo: &Repository, remote_name: &str, auth: Option<&Auth>, proxy: Option<&Proxy>
+) -> Result<HashMap<String, String>> {
+  info!("Listing tags of remote {}", remote_name);
+  if find_backend(repo)?.is_git_cli() {
+    let listing = git_cli(repo, None, proxy, &["ls-remote", "--tags", remote_name])?;
+    let refs = listing.lines().filter_map(|line| line.split_once('\t')).map(|(oid, name)| (name, oid.to_string()));
+    return Ok(tag_refs(refs));
+  }
+
+  let mut remote = repo.find_remote(remote_name)?;
+  let proxy = proxy_options(remote.url(), proxy);
+  let mut cb = RemoteCallbacks::new();
+  cb.credentials(|url, user, allowed| find_creds(auth, url, user, allowed));
+
+  let conn = remote.connect_auth(Direction::Fetch, Some(cb), Some(proxy))?;
+  let tags = tag_refs(conn.list()?.iter().map(|head| (head.name(), head.oid().to_string())));
+  Ok(tags)
+}
+
+/// Collect the tags of a remote ref listing, skipping the peeled `^{}` entries.
+fn tag_refs<'a>(refs: impl Iterator<Item = (&'a str, String)>) -> HashMap<String, String> {
+  refs
+    .filter(|(name, _)| !name.ends_with("^{}"))
+    .filter_map(|(name, oid)| name.strip_prefix("refs/tags/").map(|tag| (tag.to_string(), oid)))
+    .collect()
+}
+
 fn find_creds(
-  _url: &str, username_from_url: Option<&str>, _allowed_types: CredentialType
+  auth: Option<&Auth>, _url: &str, username_from_url: Option<&str>, _allowed_types: CredentialType
 ) -> std::result::Result<Cred, git2::Error> {
   if let Some(username_from_url) = username_from_url {
+    // An explicitly configured key takes precedence over the agent, which might not be running at all.
+    if let Some(key_path) = auth.and_then(|a| a.ssh_key_path().as_ref()) {
+      let passphrase = auth.and_then(|a| a.ssh_passphrase_env().as_ref()).and_then(|env| var(env).ok());
+      if let Ok(v) = Cred::ssh_key(username_from_url, None, key_path, passphrase.as_deref()) {
+        return Ok(v);
+      }
+    }
+
     if let Ok(v) = Cred::ssh_key_from_agent(username_from_url) {
       return Ok(v);
     }
@@ -1313,11 +2121,19 @@ fn find_creds(
   Err(git2::Error::from_str("Unable to authenticate"))
 }
 
-pub fn do_push(repo: &Repository, remote_name: &str, specs: &[String]) -> Result<()> {
+pub fn do_push(
+  repo: &Repository, remote_name: &str, specs: &[String], auth: Option<&Auth>, proxy: Option<&Proxy>
+) -> Result<()> {
   info!("Pushing specs {:?} to remote {}", specs, remote_name);
+  if find_backend(repo)?.is_git_cli() {
+    let args: Vec<_> = ["push", remote_name].into_iter().chain(specs.iter().map(|s| s.as_str())).collect();
+    git_cli(repo, None, proxy, &args)?;
+    return Ok(());
+  }
+
   let mut cb = RemoteCallbacks::new();
 
-  cb.credentials(find_creds);
+  cb.credentials(|url, user, allowed| find_creds(auth, url, user, allowed));
   cb.push_update_reference(|rref, status| {
     if let Some(status) = status {
       error!("Couldn't push reference {}: {}", rref, status);
@@ -1326,14 +2142,212 @@ pub fn do_push(repo: &Repository, remote_name: &str, specs: &[String]) -> Result
     Ok(())
   });
 
+  let mut remote = repo.find_remote(remote_name)?;
   let mut push_opts = PushOptions::new();
   push_opts.remote_callbacks(cb);
-
-  let mut remote = repo.find_remote(remote_name)?;
+  push_opts.proxy_options(proxy_options(remote.url(), proxy));
   remote.push(specs, Some(&mut push_opts))?;
   Ok(())
 }
 
+/// Choose the proxy for a remote. libgit2 doesn't honor `NO_PROXY`, so an `https` (or `http`) remote uses the
+/// `HTTPS_PROXY` (or `HTTP_PROXY`) variable unless its host is excluded by `NO_PROXY`; other remotes, and remotes
+/// without such a variable, use the proxy of the git config, if any. The user's `proxy` overrides the variables.
+fn proxy_options(url: Option<&str>, proxy: Option<&Proxy>) -> ProxyOptions<'static> {
+  let mut opts = ProxyOptions::new();
+  let remote = url.and_then(|url| url.split_once("://")).filter(|(scheme, _)| *scheme == "https" || *scheme == "http");
+  match remote {
+    Some((scheme, rest)) => {
+      let no_proxy = proxy.and_then(|p| p.no_proxy.clone()).or_else(|| proxy_var("no_proxy"));
+      if no_proxy.map(|no_proxy| no_proxy_excludes(&no_proxy, url_host(rest))).unwrap_or(false) {
+        return opts;
+      }
+      match proxy.and_then(|p| p.url.clone()).or_else(|| proxy_var(&format!("{}_proxy", scheme))) {
+        Some(proxy) => opts.url(&proxy),
+        None => opts.auto()
+      };
+    }
+    None => {
+      opts.auto();
+    }
+  }
+  opts
+}
+
+/// Read a non-empty proxy variable, which may be in either upper or lower case.
+fn proxy_var(name: &str) -> Option<String> {
+  var(name.to_uppercase()).or_else(|_| var(name)).ok().filter(|v| !v.is_empty())
+}
+
+/// The host of a URL, after its "scheme://" prefix.
+fn url_host(url: &str) -> &str {
+  let authority = url.split('/').next().unwrap_or(url);
+  let host = authority.rsplit('@').next().unwrap_or(authority);
+  match host.strip_prefix('[') {
+    Some(ipv6) => ipv6.split(']').next().unwrap_or(ipv6),
+    None => host.split(':').next().unwrap_or(host)
+  }
+}
+
+/// True if the comma-separated `NO_PROXY` list has `*`, the host, or one of the host's parent domains.
+fn no_proxy_excludes(no_proxy: &str, host: &str) -> bool {
+  let host = host.to_lowercase();
+  no_proxy.split(',').map(|d| d.trim().to_lowercase()).any(|domain| {
+    if domain == "*" {
+      return true;
+    }
+    let domain = domain.trim_start_matches("*.").trim_start_matches('.');
+    !domain.is_empty() && (host == domain || host.ends_with(&format!(".{}", domain)))
+  })
+}
+
+/// The hooks that `git commit` runs.
+const COMMIT_HOOKS: &[&str] = &["pre-commit", "prepare-commit-msg", "commit-msg", "post-commit"];
+
+/// The directory of the repo's hooks: `core.hooksPath` (relative to the working directory) if it's set, or else
+/// the `hooks` directory of the git directory.
+fn hooks_dir(repo: &Repository) -> Result<PathBuf> {
+  match repo.config()?.get_path("core.hooksPath") {
+    Ok(path) if path.is_absolute() => Ok(path),
+    Ok(path) => Ok(repo.workdir().unwrap_or_else(|| repo.path()).join(path)),
+    Err(_) => Ok(repo.path().join("hooks"))
+  }
+}
+
+/// Find the backend configured by `versio.backend`: either `libgit2` (the default) or `git-cli`.
+fn find_backend(repo: &Repository) -> Result<VcsBackend> {
+  match repo.config()?.get_string("versio.backend") {
+    Ok(backend) => backend.parse(),
+    Err(_) => Ok(VcsBackend::Libgit2)
+  }
+}
+
+/// Run the system `git` in the repository, possibly as the configured author or through the user's proxy, and
+/// return its trimmed output.
+fn git_cli(
+  repo: &Repository, author: Option<&CommitConfig>, proxy: Option<&Proxy>, args: &[&str]
+) -> Result<String> {
+  let mut command = Command::new("git");
+  command.current_dir(repo.workdir().unwrap_or_else(|| repo.path()));
+  if let Some(author) = author {
+    command.arg("-c").arg(format!("user.name={}", author.author()));
+    command.arg("-c").arg(format!("user.email={}", author.email()));
+  }
+  if let Some(url) = proxy.and_then(|p| p.url.as_ref()) {
+    command.env("http_proxy", url).env("https_proxy", url);
+  }
+  if let Some(no_proxy) = proxy.and_then(|p| p.no_proxy.as_ref()) {
+    command.env("no_proxy", no_proxy);
+  }
+  let output = command.args(args).output().with_context(|| "Unable to run git.")?;
+
+  if !output.status.success() {
+    bail!("Command git {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim());
+  }
+  Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Sign the buffer the way that git would, according to `gpg.format`, returning an armored detached signature:
+/// `ssh` signs with `gpg.ssh.program` (`ssh-keygen`) and `user.signingKey`; `x509` (which is used by gitsign) runs
+/// `gpg.x509.program`; and `openpgp` (the default) signs with the key file at `versio.keypath`. If `versio.signer`
+/// is set, that program is run instead, in the same way that git runs `gpg.program`.
+fn sign_buffer(repo: &Repository, buf: &[u8]) -> Result<Vec<u8>> {
+  let config = repo.config()?;
+  let key = config.get_string("user.signingKey").ok();
+  if let Ok(signer) = config.get_string("versio.signer") {
+    return sign_with_program(&signer, key.as_deref(), buf);
+  }
+
+  match config.get_string("gpg.format").as_deref().unwrap_or("openpgp") {
+    "openpgp" => sign_with_keypath(repo, buf),
+    "ssh" => {
+      let program = config.get_string("gpg.ssh.program").unwrap_or_else(|_| "ssh-keygen".into());
+      let key = key.ok_or_else(|| bad!("No user.signingKey for ssh signing."))?;
+      sign_with_ssh(&program, &key, buf)
+    }
+    "x509" => {
+      let program = config.get_string("gpg.x509.program").unwrap_or_else(|_| "gpgsm".into());
+      sign_with_program(&program, key.as_deref(), buf)
+    }
+    other => err!("Unknown gpg.format \"{}\".", other)
+  }
+}
+
+/// Sign the buffer with an external program that behaves like `gpg`: it reads the buffer from stdin, and writes an
+/// armored detached signature to stdout.
+fn sign_with_program(program: &str, key: Option<&str>, buf: &[u8]) -> Result<Vec<u8>> {
+  let mut command = Command::new(program);
+  command.args(["--status-fd=2", "-bsa"]);
+  if let Some(key) = key {
+    command.args(["-u", key]);
+  }
+  run_signer(command, program, buf)
+}
+
+/// Sign the buffer with `ssh-keygen`. The key is either the path of a key file, or a literal public key (possibly
+/// prefixed by `key::`) whose private key is held by the ssh agent.
+fn sign_with_ssh(program: &str, key: &str, buf: &[u8]) -> Result<Vec<u8>> {
+  let literal = key.strip_prefix("key::").or_else(|| if key.starts_with("ssh-") { Some(key) } else { None });
+  let mut command = Command::new(program);
+  command.args(["-Y", "sign", "-n", "git"]);
+
+  let key_path = match literal {
+    Some(literal) => {
+      let path = std::env::temp_dir().join(format!("versio-signing-key-{}.pub", std::process::id()));
+      std::fs::write(&path, literal)?;
+      command.arg("-U");
+      path
+    }
+    None => expand_home(key)
+  };
+  command.arg("-f").arg(&key_path);
+
+  let result = run_signer(command, program, buf);
+  if literal.is_some() {
+    let _ = std::fs::remove_file(&key_path);
+  }
+  result
+}
+
+fn run_signer(mut command: Command, program: &str, buf: &[u8]) -> Result<Vec<u8>> {
+  let mut child = command
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .with_context(|| format!("Unable to run signer {}.", program))?;
+  child.stdin.take().ok_or_else(|| bad!("No stdin for signer {}.", program))?.write_all(buf)?;
+  let output = child.wait_with_output()?;
+
+  if !output.status.success() || output.stdout.is_empty() {
+    bail!("Signer {} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim());
+  }
+  Ok(output.stdout)
+}
+
+fn expand_home(path: &str) -> PathBuf {
+  match (path.strip_prefix("~/"), dirs::home_dir()) {
+    (Some(rest), Some(home)) => home.join(rest),
+    _ => PathBuf::from(path)
+  }
+}
+
+/// Sign the buffer with the key file at `versio.keypath`, returning an armored detached signature.
+#[cfg(feature = "sequoia")]
+fn sign_with_keypath(repo: &Repository, buf: &[u8]) -> Result<Vec<u8>> {
+  let keypath = repo.config()?.get_path("versio.keypath").with_context(|| "No versio.keypath")?;
+  let keypair = find_keypair_for_id(&keypath)?;
+  sign_armored_detached(keypair, buf)
+}
+
+#[cfg(not(feature = "sequoia"))]
+fn sign_with_keypath(_repo: &Repository, _buf: &[u8]) -> Result<Vec<u8>> {
+  err!("Versio was built without OpenPGP signing support: set `versio.signer`, or `versio.backend` to `git-cli`.")
+}
+
+/// Find the git blob hash of the given data, as in `git hash-object`.
+pub fn blob_hash(data: &[u8]) -> Result<String> { Ok(Oid::hash_object(ObjectType::Blob, data)?.to_string()) }
+
 pub fn time_to_datetime(time: &Time) -> DateTime<FixedOffset> {
   const MINUTES: i32 = 60;
   FixedOffset::east_opt(time.offset_minutes() * MINUTES)
@@ -1343,6 +2357,7 @@ pub fn time_to_datetime(time: &Time) -> DateTime<FixedOffset> {
     .expect("time/0 in bounds")
 }
 
+#[cfg(feature = "sequoia")]
 pub fn find_keypair_for_id(keypath: &Path) -> Result<KeyPair> {
   // This is based on sequoia docs here:
   // https://docs.rs/sequoia-openpgp/1.18.0/sequoia_openpgp/serialize/stream/struct.Armorer.html#method.kind and
@@ -1379,6 +2394,7 @@ pub fn find_keypair_for_id(keypath: &Path) -> Result<KeyPair> {
   }
 }
 
+#[cfg(feature = "sequoia")]
 pub fn sign_armored_detached(signing_keypair: KeyPair, buf: &[u8]) -> Result<Vec<u8>> {
   let mut out = vec![];
   {
@@ -1394,7 +2410,81 @@ pub fn sign_armored_detached(signing_keypair: KeyPair, buf: &[u8]) -> Result<Vec
 
 #[cfg(test)]
 mod test {
-  use super::extract_kind;
+  use super::{extract_co_authors, extract_kind, extract_scope, no_proxy_excludes, url_host, Contributor, FromTagBuf,
+              PushQueue, Repo};
+  use crate::config::CommitConfig;
+  use crate::vcs::{VcsLevel, VcsState};
+  use git2::{Repository, Signature};
+
+  #[test]
+  fn test_push_queue_round_trip() {
+    let path = std::env::temp_dir().join(format!("versio-push-queue-{}.json", std::process::id()));
+    assert!(PushQueue::read(&path).unwrap().is_empty());
+
+    let mut queue = PushQueue { branch: Some("main".into()), ..Default::default() };
+    queue.push_tag("proj-v1.0.0".into());
+    queue.push_tag("versio-prev".into());
+    queue.delete_tag("proj-v0.9.0".into());
+    queue.delete_tag("versio-prev".into());
+    queue.push_tag("versio-prev".into());
+    queue.write(&path).unwrap();
+
+    let read = PushQueue::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(read.branch(), Some("main"));
+    assert_eq!(read.tags(), ["proj-v1.0.0", "versio-prev"]);
+    assert_eq!(read.deleted_tags(), ["proj-v0.9.0"]);
+    assert_eq!(
+      read.specs(),
+      ["refs/heads/main", "+refs/tags/proj-v1.0.0", "+refs/tags/versio-prev", ":refs/tags/proj-v0.9.0"]
+    );
+  }
+
+  #[test]
+  fn test_submodule_blob() {
+    let dir = std::env::temp_dir().join(format!("versio-submodule-{}", std::process::id()));
+    let sig = Signature::now("Test", "test@example.com").unwrap();
+
+    let sub = Repository::init(dir.join("sub")).unwrap();
+    let mut tree = sub.treebuilder(None).unwrap();
+    tree.insert("package.json", sub.blob(b"{\"version\": \"1.2.3\"}").unwrap(), 0o100644).unwrap();
+    let tree = sub.find_tree(tree.write().unwrap()).unwrap();
+    let sub_commit = sub.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[]).unwrap();
+
+    let top = Repository::init(&dir).unwrap();
+    let mut tree = top.treebuilder(None).unwrap();
+    tree.insert("sub", sub_commit, 0o160000).unwrap();
+    let tree = top.find_tree(tree.write().unwrap()).unwrap();
+    top.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[]).unwrap();
+
+    let repo = Repo::open(&dir, VcsState::new(VcsLevel::Local, false), CommitConfig::default()).unwrap();
+    let slice = repo.slice(FromTagBuf::new("HEAD".into(), false));
+    let found = slice.has_blob("sub/package.json").unwrap();
+    let missing = slice.has_blob("sub/missing.json").unwrap();
+    let content = slice.blob_content("sub/package.json").unwrap();
+
+    // Without the submodule's repository, the file can't be read: that's an error, not a missing file.
+    std::fs::remove_dir_all(dir.join("sub")).unwrap();
+    let uninit = slice.has_blob("sub/package.json");
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(found);
+    assert!(!missing);
+    assert_eq!(content, b"{\"version\": \"1.2.3\"}");
+    assert!(uninit.is_err());
+  }
+
+  #[test]
+  fn test_co_authors() {
+    let message = "feat: pair on it\n\nSome body.\n\nCo-authored-by: Ann Smith <ann@example.com>\nco-authored-by: Bob \
+                   <bob@example.com>\nSigned-off-by: Carl <carl@example.com>\n";
+    let found = extract_co_authors(message);
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].name(), "Ann Smith");
+    assert_eq!(found[0].email(), "ann@example.com");
+    assert_eq!(found[1].name(), "Bob");
+    assert!(found[1].same_as(&Contributor::parse("Robert <BOB@example.com>").unwrap()));
+  }
 
   #[test]
   fn test_kind_simple() {
@@ -1451,6 +2541,13 @@ mod test {
     assert_eq!(&extract_kind(""), "-");
   }
 
+  #[test]
+  fn test_scope() {
+    assert_eq!(extract_scope("thing(api)!: this is (thing)"), Some("api"));
+    assert_eq!(extract_scope("thing: this is (thing)"), None);
+    assert_eq!(extract_scope("thing(): this is thing"), None);
+  }
+
   #[test]
   fn test_unconventional() {
     assert_eq!(&extract_kind("-"), "-");
@@ -1460,4 +2557,18 @@ mod test {
   fn test_uncertain() {
     assert_eq!(&extract_kind("ENG-123: I forgot to conventinal commit"), "eng-123");
   }
+
+  #[test]
+  fn test_url_host() {
+    assert_eq!(url_host("user:pass@github.com:443/chaaz/versio.git"), "github.com");
+    assert_eq!(url_host("[::1]:8080/repo"), "::1");
+  }
+
+  #[test]
+  fn test_no_proxy() {
+    assert!(no_proxy_excludes("localhost, .example.com", "git.example.com"));
+    assert!(no_proxy_excludes("*", "github.com"));
+    assert!(!no_proxy_excludes("example.com", "github.com"));
+    assert!(!no_proxy_excludes("hub.com", "github.com"));
+  }
 }
@@ -1,13 +1,26 @@
-//! Interactions with github API v4.
+//! Interactions with github API v4, and with the REST API of Gitea (or Forgejo) servers.
 
+use crate::bail;
 use crate::errors::Result;
-use crate::git::{time_to_datetime, Auth, CommitInfoBuf, FromTag, FromTagBuf, FullPr, GithubInfo, Repo, Span};
+use crate::git::{blob_hash, http_client, time_to_datetime, Auth, CommitInfoBuf, FromTag, FromTagBuf, FullPr,
+                 GithubInfo, Repo, Span};
 use chrono::{DateTime, FixedOffset, TimeZone, Utc};
-use octocrab::Octocrab;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::{Client, Response};
 use serde::de::{self, Deserializer, Visitor};
 use serde::Deserialize;
+use serde_json::{json, Value};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
+
+const CACHE_DIR: &str = ".versio/cache/github";
+
+/// How long a cached response is used: a merged PR can still have its title, body or labels edited.
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
 
 /// Find all changes in a repo more cleverly than `git rev-parse begin..end` using the GitHub v4 GraphQL API.
 ///
@@ -20,7 +33,6 @@ use std::fmt;
 /// rev-parse, we assume that this is the result of a "squash merge" from that PR (or some other type of PR
 /// rebase). The squash commit is excluded from all PRs: instead the PR's own commits are examined normally. In
 /// this way, the original type and size information from the PR is preserved.
-#[allow(clippy::map_entry)]
 pub async fn changes(auth: &Option<Auth>, repo: &Repo, baseref: FromTagBuf, headref: String) -> Result<Changes> {
   let mut all_commits = HashSet::new();
   let mut all_prs = HashMap::new();
@@ -34,6 +46,8 @@ pub async fn changes(auth: &Option<Auth>, repo: &Repo, baseref: FromTagBuf, head
     headref.clone(),
     0,
     "".into(),
+    "".into(),
+    Vec::new(),
     offset.timestamp_opt(Utc::now().timestamp(), 0).single().expect("utc/0 in bounds"),
     discover_order
   )?;
@@ -46,65 +60,92 @@ pub async fn changes(auth: &Option<Auth>, repo: &Repo, baseref: FromTagBuf, head
     Err(_) => return Ok(Changes { groups: all_prs, commits: all_commits })
   };
 
-  while let Some(span) = queue.pop_front() {
-    let commit_list = commits_from_v4_api(&github_info, &span).await?;
-    let commit_list: Vec<_> = commit_list
-      .into_iter()
-      .filter_map(|commit| {
-        if all_commits.contains(commit.oid()) {
-          return None;
+  while !queue.is_empty() {
+    // Query a few spans at once, but process the results in queue order, so that the outcome is the same as if
+    // they were queried one after the other.
+    let batch: Vec<_> = queue.drain(.. queue.len().min(repo.jobs())).collect();
+    let fetches: Vec<_> = batch
+      .iter()
+      .map(|span| {
+        if github_info.host().is_gitea() {
+          let oids = all_prs.get(&span.number()).map(|pr| pr.commits().iter().map(|c| c.id().to_string()));
+          tokio::spawn(commits_from_gitea_api(github_info.clone(), oids.into_iter().flatten().collect()))
+        } else {
+          tokio::spawn(commits_from_v4_api(github_info.clone(), SpanQuery::new(span)))
         }
+      })
+      .collect();
 
-        let mut retain = true;
-        let (oid, prs) = commit.extract();
-        for pr in prs.merged_only() {
-          let number = pr.number();
-          if !all_prs.contains_key(&number) {
-            let full_pr = match pr.lookup(repo, discover_order) {
-              Ok(pr) => pr,
-              Err(e) => return Some(Err(e))
-            };
-            discover_order += 1;
-            if let Some(span) = full_pr.span() {
-              queue.push_back(span);
-            }
-            all_prs.insert(number, full_pr);
-          }
-          let full_pr = all_prs.get_mut(&number).unwrap();
+    for (span, fetch) in batch.into_iter().zip(fetches) {
+      let commit_list = fetch.await??;
+      process_commits(repo, &span, commit_list, &mut all_commits, &mut all_prs, &mut queue, &mut discover_order)?;
+    }
+  }
 
-          if full_pr.best_guess() {
-            full_pr.add_commit(CommitInfoBuf::guess(oid.clone()));
-          } else if !full_pr.contains(&oid) {
-            retain = false;
+  Ok(Changes { commits: all_commits, groups: all_prs })
+}
+
+/// Sort the commits found by the API into their PRs, queueing up the span of any newly-discovered PR.
+#[allow(clippy::map_entry)]
+fn process_commits(
+  repo: &Repo, span: &Span, commit_list: Vec<(String, Vec<PrEdgeNode>)>, all_commits: &mut HashSet<String>,
+  all_prs: &mut HashMap<u32, FullPr>, queue: &mut VecDeque<Span>, discover_order: &mut usize
+) -> Result<()> {
+  let commit_list: Vec<_> = commit_list
+    .into_iter()
+    .filter_map(|(oid, prs)| {
+      if all_commits.contains(&oid) {
+        return None;
+      }
+
+      let mut retain = true;
+      for pr in prs {
+        let number = pr.number();
+        if !all_prs.contains_key(&number) {
+          let full_pr = match pr.lookup(repo, *discover_order) {
+            Ok(pr) => pr,
+            Err(e) => return Some(Err(e))
+          };
+          *discover_order += 1;
+          if let Some(span) = full_pr.span() {
+            queue.push_back(span);
           }
+          all_prs.insert(number, full_pr);
         }
+        let full_pr = all_prs.get_mut(&number).unwrap();
 
-        if retain {
-          Some(Ok(oid))
-        } else {
-          all_prs.get_mut(&span.number()).unwrap().add_exclude(&oid);
-          None
+        if full_pr.best_guess() {
+          full_pr.add_commit(CommitInfoBuf::guess(oid.clone()));
+        } else if !full_pr.contains(&oid) {
+          retain = false;
         }
-      })
-      .collect::<Result<_>>()?;
+      }
 
-    all_commits.extend(commit_list.into_iter());
-  }
+      if retain {
+        Some(Ok(oid))
+      } else {
+        all_prs.get_mut(&span.number()).unwrap().add_exclude(&oid);
+        None
+      }
+    })
+    .collect::<Result<_>>()?;
 
-  Ok(Changes { commits: all_commits, groups: all_prs })
+  all_commits.extend(commit_list);
+  Ok(())
 }
 
 pub fn line_commits_head(repo: &Repo, base: FromTag) -> Result<Vec<CommitInfoBuf>> {
   repo.commits_to_head(base, false)?.map(|i| i?.buffer()).collect::<Result<_>>()
 }
 
-async fn commits_from_v4_api(github_info: &GithubInfo, span: &Span) -> Result<Vec<ApiCommit>> {
-  let query = r#"query associatedPRs($since:GitTimestamp!, $sha:String!, $repo:String!, $owner:String!){
+const HISTORY_QUERY: &str = r#"query associatedPRs(
+    $since:GitTimestamp!, $sha:String!, $repo:String!, $owner:String!, $after:String
+){
   repository(name:$repo, owner:$owner){
     commit:object(expression: $sha){
       ... on Commit {
         oid
-        history(first:100, since:$since) {
+        history(first:100, since:$since, after:$after) {
           pageInfo {
             hasNextPage
             endCursor
@@ -123,10 +164,16 @@ fragment commitResult on Commit {
         node {
           number
           title
+          body
           state
           headRefName
           baseRefOid
           closedAt
+          labels(first:20) {
+            nodes {
+              name
+            }
+          }
         }
       }
     }
@@ -139,27 +186,44 @@ fragment commitResult on Commit {
     }
 }"#;
 
-  let variables = format!(
-    r#"{{ "sha": "{}", "since": "{}", "owner": "{}", "repo": "{}" }}"#,
-    span.end(),
-    time_to_datetime(span.since()).to_rfc3339(),
-    github_info.owner_name(),
-    github_info.repo_name()
-  );
+/// The parameters of a history query for a span, detached from the span so that the query can run on its own task.
+struct SpanQuery {
+  end: String,
+  since: String,
+  since_secs: i64,
+  begin: String
+}
 
-  let octo = Octocrab::builder();
-  let token = github_info.token().clone();
-  let octo = if let Some(token) = token { octo.personal_token(token) } else { octo };
-  let octo = octo.build()?;
-  let full_query = serde_json::json!({"query": &query, "variables": &variables});
-  let changes: ChangesResponse = octo.post("/graphql", Some(&full_query)).await?;
+impl SpanQuery {
+  fn new(span: &Span) -> SpanQuery {
+    SpanQuery {
+      end: span.end().to_string(),
+      since: time_to_datetime(span.since()).to_rfc3339(),
+      since_secs: span.since().seconds(),
+      begin: span.begin().tag().to_string()
+    }
+  }
+}
+
+async fn commits_from_v4_api(github_info: GithubInfo, span: SpanQuery) -> Result<Vec<(String, Vec<PrEdgeNode>)>> {
+  let cache = cache_file(&github_info, &span);
+  let nodes = match cache.as_deref().and_then(read_cache) {
+    Some(nodes) => nodes,
+    None => {
+      let nodes = history_from_v4_api(&github_info, &span).await?;
+      if let Some(cache) = cache.as_ref().filter(|_| all_merged(&nodes)) {
+        write_cache(cache, &nodes);
+      }
+      nodes
+    }
+  };
 
-  let changes = changes.data.repository.commit.history.nodes;
+  let changes = nodes.into_iter().map(serde_json::from_value::<ApiCommit>).collect::<serde_json::Result<Vec<_>>>()?;
   let mut changes: HashMap<String, ApiCommit> = changes.into_iter().map(|c| (c.oid().to_string(), c)).collect();
 
-  // Remove anything reachable by span.begin()
+  // Remove anything reachable by span.begin
   let mut remqueue = VecDeque::new();
-  remqueue.push_back(span.begin().tag().to_string());
+  remqueue.push_back(span.begin);
   while let Some(rem) = remqueue.pop_front() {
     if let Some(commit) = changes.remove(&rem) {
       for edge in commit.parents.edges {
@@ -168,7 +232,272 @@ fragment commitResult on Commit {
     }
   }
 
-  Ok(changes.into_values().collect())
+  Ok(changes.into_values().map(|c| c.extract()).map(|(oid, prs)| (oid, prs.merged_only().collect())).collect())
+}
+
+/// Find the PR of each commit with the Gitea REST API. Gitea only knows the single PR that merged a commit, so
+/// commits are queried one at a time; they're taken from the local history of the span, instead of from the API.
+async fn commits_from_gitea_api(github_info: GithubInfo, oids: Vec<String>) -> Result<Vec<(String, Vec<PrEdgeNode>)>> {
+  let client = api_client(&github_info)?;
+
+  let mut commits = Vec::new();
+  for oid in oids {
+    let pr = match pr_from_gitea_api(&client, &github_info, &oid).await? {
+      Some(pr) => vec![serde_json::from_value::<GiteaPr>(pr)?.into_node()],
+      None => Vec::new()
+    };
+    commits.push((oid, pr));
+  }
+  Ok(commits)
+}
+
+/// Fetch the PR that merged a commit, if any. Only found PRs are cached: a commit that hasn't been merged yet
+/// might still be.
+async fn pr_from_gitea_api(client: &Client, github_info: &GithubInfo, oid: &str) -> Result<Option<Value>> {
+  let cache = commit_cache_file(github_info, oid);
+  if let Some(mut pr) = cache.as_deref().and_then(read_cache) {
+    return Ok(pr.pop());
+  }
+
+  let url = format!(
+    "{}repos/{}/{}/commits/{}/pull",
+    github_info.api_url(),
+    github_info.owner_name(),
+    github_info.repo_name(),
+    oid
+  );
+  let resp = send_with_retry("Gitea query", || client.get(url.as_str()).send()).await?;
+  if resp.status().as_u16() == 404 {
+    return Ok(None);
+  }
+  if !resp.status().is_success() {
+    let quota = RateQuota::from_headers(resp.headers());
+    bail!("Unsuccessful request for the PR of {}: {}{}", oid, resp.status().as_u16(), quota);
+  }
+
+  let pr: Value = resp.json().await?;
+  if let Some(cache) = &cache {
+    write_cache(cache, std::slice::from_ref(&pr));
+  }
+  Ok(Some(pr))
+}
+
+/// A client for the API of the host, which authenticates with the token (if any) and goes through the user's proxy.
+fn api_client(github_info: &GithubInfo) -> Result<Client> {
+  let mut headers = HeaderMap::new();
+  if let Some(token) = github_info.token() {
+    headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", token))?);
+  }
+  let builder = http_client(github_info.proxy())?;
+  Ok(builder.user_agent(concat!("versio/", env!("CARGO_PKG_VERSION"))).default_headers(headers).build()?)
+}
+
+/// Page through the entire history of the span end, returning the raw commit nodes.
+async fn history_from_v4_api(github_info: &GithubInfo, span: &SpanQuery) -> Result<Vec<Value>> {
+  let client = api_client(github_info)?;
+  let url = format!("{}graphql", github_info.api_url());
+
+  let mut nodes = Vec::new();
+  let mut after: Option<String> = None;
+  loop {
+    let variables = json!({
+      "sha": &span.end,
+      "since": &span.since,
+      "owner": github_info.owner_name(),
+      "repo": github_info.repo_name(),
+      "after": &after
+    });
+    let full_query = json!({"query": HISTORY_QUERY, "variables": variables});
+    let query = &full_query;
+    // The route is under the API URL, so that it is found under an enterprise API path (e.g.
+    // "https://ghe.host/api/").
+    let resp = send_with_retry("GitHub query", || client.post(url.as_str()).json(query).send()).await?;
+    let quota = RateQuota::from_headers(resp.headers());
+    if !resp.status().is_success() {
+      bail!("GitHub query failed with status {}{}: {}", resp.status().as_u16(), quota, resp.text().await?);
+    }
+    let page: Value = resp.json().await?;
+    if let Some(errors) = page.get("errors").and_then(|e| e.as_array()).filter(|e| !e.is_empty()) {
+      let messages: Vec<_> = errors.iter().filter_map(|e| e.get("message").and_then(|m| m.as_str())).collect();
+      bail!("GitHub query failed{}: {}", quota, messages.join("; "));
+    }
+    let page: ChangesResponse = serde_json::from_value(page)?;
+    let history = page.data.repository.commit.history;
+    nodes.extend(history.nodes);
+
+    match history.page_info.end_cursor {
+      Some(cursor) if history.page_info.has_next_page => after = Some(cursor),
+      _ => break
+    }
+  }
+
+  Ok(nodes)
+}
+
+/// The most times that a throttled or failing API request is retried.
+const MAX_RETRIES: u32 = 5;
+
+/// The longest that a retry will wait: an exhausted quota that won't reset for longer than this is an error.
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(120);
+
+/// Send an API request, retrying with exponential backoff while the API is rate-limiting the requests (including
+/// secondary or "abuse" limits) or is temporarily unavailable. The wait honors any `Retry-After` or rate limit
+/// reset header of the response. Other responses, successful or not, are returned as-is.
+async fn send_with_retry<F, Fut>(desc: &str, send: F) -> Result<Response>
+where
+  F: Fn() -> Fut,
+  Fut: Future<Output = reqwest::Result<Response>>
+{
+  let mut attempt = 0;
+  loop {
+    let resp = send().await?;
+    let status = resp.status().as_u16();
+    let quota = RateQuota::from_headers(resp.headers());
+
+    match status {
+      429 | 502 | 503 | 504 => (),
+      403 if quota.exhausted() || quota.retry_after.is_some() => (),
+      403 => {
+        // Secondary rate limits are only described by the body.
+        let body = resp.text().await?;
+        let lower = body.to_lowercase();
+        if !lower.contains("rate limit") && !lower.contains("abuse") {
+          bail!("{} was forbidden{}: {}", desc, quota, body);
+        }
+      }
+      _ => return Ok(resp)
+    }
+
+    let wait = quota.retry_wait(attempt);
+    if attempt >= MAX_RETRIES || wait > MAX_RETRY_WAIT {
+      bail!("{} failed with status {} after {} retries{}.", desc, status, attempt, quota);
+    }
+    warn!("{} returned status {}{}: retrying in {}s.", desc, status, quota, wait.as_secs());
+    tokio::time::sleep(wait).await;
+    attempt += 1;
+  }
+}
+
+/// The rate limit information of an API response, which is displayed as a parenthetical.
+struct RateQuota {
+  limit: Option<u64>,
+  remaining: Option<u64>,
+  reset: Option<i64>,
+  retry_after: Option<u64>
+}
+
+impl RateQuota {
+  fn from_headers(headers: &HeaderMap) -> RateQuota {
+    let get = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(|v| v.trim().to_string());
+    RateQuota {
+      limit: get("x-ratelimit-limit").and_then(|v| v.parse().ok()),
+      remaining: get("x-ratelimit-remaining").and_then(|v| v.parse().ok()),
+      reset: get("x-ratelimit-reset").and_then(|v| v.parse().ok()),
+      retry_after: get("retry-after").and_then(|v| v.parse().ok())
+    }
+  }
+
+  fn exhausted(&self) -> bool { self.remaining == Some(0) }
+
+  /// How long to wait before retrying, if the response says.
+  fn wait(&self) -> Option<Duration> {
+    if let Some(secs) = self.retry_after {
+      Some(Duration::from_secs(secs))
+    } else if self.exhausted() {
+      self.reset.map(|reset| Duration::from_secs((reset - Utc::now().timestamp()).max(1) as u64))
+    } else {
+      None
+    }
+  }
+
+  /// How long to wait before the retry that follows `attempt` earlier retries: as long as the response says, or else
+  /// an exponential backoff.
+  fn retry_wait(&self, attempt: u32) -> Duration { self.wait().unwrap_or_else(|| Duration::from_secs(1 << attempt)) }
+}
+
+impl fmt::Display for RateQuota {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match (self.remaining, self.limit) {
+      (Some(remaining), Some(limit)) => write!(f, " ({} of {} requests remaining", remaining, limit)?,
+      (Some(remaining), None) => write!(f, " ({} requests remaining", remaining)?,
+      _ => return Ok(())
+    }
+    match self.reset.and_then(|reset| Utc.timestamp_opt(reset, 0).single()) {
+      Some(reset) => write!(f, ", resets at {})", reset.to_rfc3339()),
+      None => write!(f, ")")
+    }
+  }
+}
+
+/// The location of the cached history of a span: the history of a commit since a given time never changes, so it
+/// can be re-used across runs. The history is only good for the query that fetched it, so a changed query (that
+/// asks for more fields) starts a new cache.
+fn cache_file(github_info: &GithubInfo, span: &SpanQuery) -> Option<PathBuf> {
+  if github_info.no_cache() {
+    return None;
+  }
+  let query = blob_hash(HISTORY_QUERY.as_bytes()).ok()?;
+  dirs::home_dir().map(|home| {
+    home
+      .join(CACHE_DIR)
+      .join(github_info.host().host())
+      .join(github_info.owner_name())
+      .join(github_info.repo_name())
+      .join(&query[.. 12])
+      .join(format!("{}-{}.json", span.end, span.since_secs))
+  })
+}
+
+/// True if every PR of the history nodes is merged, so that the history can be cached: an open PR can still be
+/// merged (or its title edited), and a closed PR can be reopened.
+fn all_merged(nodes: &[Value]) -> bool {
+  nodes
+    .iter()
+    .flat_map(|node| node["associatedPullRequests"]["edges"].as_array().into_iter().flatten())
+    .all(|edge| edge["node"]["state"].as_str() == Some("MERGED"))
+}
+
+/// The location of the cached PR that merged a commit.
+fn commit_cache_file(github_info: &GithubInfo, oid: &str) -> Option<PathBuf> {
+  if github_info.no_cache() {
+    return None;
+  }
+  dirs::home_dir().map(|home| {
+    home
+      .join(CACHE_DIR)
+      .join(github_info.host().host())
+      .join(github_info.owner_name())
+      .join(github_info.repo_name())
+      .join("pulls")
+      .join(format!("{}.json", oid))
+  })
+}
+
+/// Read a cached response, unless it's missing or older than `CACHE_TTL`.
+fn read_cache(path: &Path) -> Option<Vec<Value>> {
+  let age = std::fs::metadata(path).and_then(|m| m.modified()).ok()?.elapsed().unwrap_or_default();
+  if age > CACHE_TTL {
+    return None;
+  }
+  let data = std::fs::read_to_string(path).ok()?;
+  match serde_json::from_str(&data) {
+    Ok(nodes) => Some(nodes),
+    Err(e) => {
+      warn!("Ignoring bad cache file {}: {}", path.display(), e);
+      None
+    }
+  }
+}
+
+fn write_cache(path: &Path, nodes: &[Value]) {
+  let written = path
+    .parent()
+    .map(std::fs::create_dir_all)
+    .unwrap_or(Ok(()))
+    .and_then(|()| std::fs::write(path, serde_json::to_string(nodes)?));
+  if let Err(e) = written {
+    warn!("Unable to write cache file {}: {}", path.display(), e);
+  }
 }
 
 pub struct Changes {
@@ -180,6 +509,17 @@ impl Changes {
   pub fn commits(&self) -> &HashSet<String> { &self.commits }
   pub fn groups(&self) -> &HashMap<u32, FullPr> { &self.groups }
   pub fn into_groups(self) -> HashMap<u32, FullPr> { self.groups }
+
+  /// Keep only the commits that pass the filter, dropping any PR that is left without commits.
+  pub fn retain_commits<F: FnMut(&CommitInfoBuf) -> bool>(&mut self, mut f: F) {
+    for pr in self.groups.values_mut() {
+      pr.retain_commits(&mut f);
+    }
+    self.groups.retain(|number, pr| *number == 0 || !pr.commits().is_empty());
+
+    let kept: HashSet<&str> = self.groups.values().flat_map(|pr| pr.commits().iter().map(|c| c.id())).collect();
+    self.commits.retain(|oid| kept.contains(oid.as_str()));
+  }
 }
 
 #[derive(Deserialize)]
@@ -204,7 +544,17 @@ struct TopCommit {
 
 #[derive(Deserialize)]
 struct History {
-  nodes: Vec<ApiCommit>
+  #[serde(rename = "pageInfo")]
+  page_info: PageInfo,
+  nodes: Vec<Value>
+}
+
+#[derive(Deserialize)]
+struct PageInfo {
+  #[serde(rename = "hasNextPage")]
+  has_next_page: bool,
+  #[serde(rename = "endCursor")]
+  end_cursor: Option<String>
 }
 
 #[derive(Deserialize)]
@@ -256,12 +606,27 @@ struct PrEdgeNode {
   number: u32,
   state: String,
   title: String,
+  #[serde(default)]
+  body: Option<String>,
   #[serde(rename = "headRefName")]
   head_ref_name: String,
   #[serde(rename = "baseRefOid")]
   base_ref_oid: String,
   #[serde(rename = "closedAt", deserialize_with = "deserialize_datetime")]
-  closed_at: DateTime<FixedOffset>
+  closed_at: DateTime<FixedOffset>,
+  #[serde(default)]
+  labels: LabelList
+}
+
+/// The labels of a PR. Histories cached before labels were queried don't have any (nor a body).
+#[derive(Deserialize, Default)]
+struct LabelList {
+  nodes: Vec<LabelNode>
+}
+
+#[derive(Deserialize)]
+struct LabelNode {
+  name: String
 }
 
 impl PrEdgeNode {
@@ -275,12 +640,53 @@ impl PrEdgeNode {
       self.head_ref_name,
       self.number,
       self.title,
+      self.body.unwrap_or_default(),
+      self.labels.nodes.into_iter().map(|l| l.name).collect(),
       self.closed_at,
       discover_order
     )
   }
 }
 
+/// A PR as returned by the Gitea REST API.
+#[derive(Deserialize)]
+struct GiteaPr {
+  number: u32,
+  title: String,
+  #[serde(default)]
+  body: Option<String>,
+  head: GiteaBranch,
+  base: GiteaBranch,
+  merge_base: Option<String>,
+  #[serde(deserialize_with = "deserialize_datetime")]
+  merged_at: DateTime<FixedOffset>,
+  #[serde(default)]
+  labels: Vec<LabelNode>
+}
+
+#[derive(Deserialize)]
+struct GiteaBranch {
+  #[serde(rename = "ref")]
+  ref_name: String,
+  sha: String
+}
+
+impl GiteaPr {
+  /// Convert to the same PR structure that is found by the GitHub API.
+  fn into_node(self) -> PrEdgeNode {
+    PrEdgeNode {
+      number: self.number,
+      state: "MERGED".into(),
+      title: self.title,
+      body: self.body,
+      head_ref_name: self.head.ref_name,
+      base_ref_oid: self.merge_base.unwrap_or(self.base.sha),
+      closed_at: self.merged_at,
+      labels: LabelList { nodes: self.labels }
+    }
+  }
+}
+
 fn deserialize_datetime<'de, D: Deserializer<'de>>(desr: D) -> std::result::Result<DateTime<FixedOffset>, D::Error> {
   struct DateTimeVisitor;
 
@@ -306,3 +712,144 @@ fn deserialize_datetime<'de, D: Deserializer<'de>>(desr: D) -> std::result::Resu
 
   desr.deserialize_any(DateTimeVisitor)
 }
+
+#[cfg(test)]
+mod test {
+  use super::{all_merged, api_client, commit_cache_file, read_cache, write_cache, RateQuota, CACHE_TTL};
+  use crate::git::{GithubHost, GithubInfo, Proxy};
+  use chrono::Utc;
+  use reqwest::header::HeaderMap;
+  use serde_json::json;
+  use std::io::{Read, Write};
+  use std::net::TcpListener;
+  use std::sync::mpsc::{channel, Receiver};
+  use std::time::{Duration, SystemTime};
+
+  /// Answer a single HTTP request on a local port, and send back the (lowercased) head of that request.
+  fn serve_once() -> (u16, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = channel();
+    std::thread::spawn(move || {
+      let (mut stream, _) = listener.accept().unwrap();
+      let (mut head, mut buf) = (Vec::new(), [0; 1024]);
+      while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        match stream.read(&mut buf).unwrap() {
+          0 => break,
+          n => head.extend_from_slice(&buf[.. n])
+        }
+      }
+      stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").unwrap();
+      tx.send(String::from_utf8_lossy(&head).to_lowercase()).unwrap();
+    });
+    (port, rx)
+  }
+
+  fn get(info: &GithubInfo, url: &str) -> u16 {
+    let client = api_client(info).unwrap();
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(client.get(url).send()).unwrap().status().as_u16()
+  }
+
+  #[test]
+  fn test_api_client_proxy() {
+    let (port, head) = serve_once();
+    let proxy: Proxy = serde_json::from_value(json!({ "url": format!("http://127.0.0.1:{}", port) })).unwrap();
+    let info = GithubInfo::new("o".into(), "r".into(), Some("tkn".into()), GithubHost::default());
+    let info = info.with_proxy(Some(proxy));
+
+    // The request goes to the proxy, with the token and the user agent of the client.
+    assert_eq!(get(&info, "http://api.example.invalid/graphql"), 200);
+    let head = head.recv().unwrap();
+    assert!(head.starts_with("get http://api.example.invalid/graphql http/1.1\r\n"), "{}", head);
+    assert!(head.contains("\r\nauthorization: bearer tkn\r\n"), "{}", head);
+    assert!(head.contains("\r\nuser-agent: versio/"), "{}", head);
+  }
+
+  #[test]
+  fn test_api_client_no_proxy() {
+    // The proxy isn't listening, so the request only succeeds if it goes straight to the server.
+    let (port, head) = serve_once();
+    let proxy = json!({ "url": "http://127.0.0.1:9", "no_proxy": "127.0.0.1" });
+    let proxy: Proxy = serde_json::from_value(proxy).unwrap();
+    let info = GithubInfo::new("o".into(), "r".into(), None, GithubHost::default()).with_proxy(Some(proxy));
+
+    assert_eq!(get(&info, &format!("http://127.0.0.1:{}/repos", port)), 200);
+    let head = head.recv().unwrap();
+    assert!(head.starts_with("get /repos http/1.1\r\n"), "{}", head);
+    assert!(!head.contains("authorization:"), "{}", head);
+  }
+
+  fn quota(headers: &[(&'static str, &str)]) -> RateQuota {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+      map.insert(*name, value.parse().unwrap());
+    }
+    RateQuota::from_headers(&map)
+  }
+
+  #[test]
+  fn test_rate_quota_headers() {
+    let q = quota(&[("x-ratelimit-limit", "5000"), ("x-ratelimit-remaining", " 12 "), ("x-ratelimit-reset", "0")]);
+    assert_eq!((q.limit, q.remaining, q.reset, q.retry_after), (Some(5000), Some(12), Some(0), None));
+    assert!(!q.exhausted());
+    assert_eq!(q.to_string(), " (12 of 5000 requests remaining, resets at 1970-01-01T00:00:00+00:00)");
+
+    let q = quota(&[("retry-after", "30"), ("x-ratelimit-remaining", "bogus")]);
+    assert_eq!((q.limit, q.remaining, q.reset, q.retry_after), (None, None, None, Some(30)));
+    assert_eq!(q.to_string(), "");
+  }
+
+  #[test]
+  fn test_rate_quota_wait() {
+    // Without any rate limit headers, the wait doubles with every attempt.
+    let none = quota(&[]);
+    let backoff: Vec<_> = (0 .. 5).map(|a| none.retry_wait(a).as_secs()).collect();
+    assert_eq!(backoff, vec![1, 2, 4, 8, 16]);
+
+    assert_eq!(quota(&[("retry-after", "30")]).retry_wait(3), Duration::from_secs(30));
+
+    let reset = (Utc::now().timestamp() + 60).to_string();
+    let wait = quota(&[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", &reset)]).retry_wait(0);
+    assert!(wait > Duration::from_secs(55) && wait <= Duration::from_secs(60));
+
+    // A reset that already passed is retried right away; a quota that isn't exhausted falls back to the backoff.
+    let passed = quota(&[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", "0")]);
+    assert_eq!(passed.retry_wait(4), Duration::from_secs(1));
+    let remaining = quota(&[("x-ratelimit-remaining", "3"), ("x-ratelimit-reset", &reset)]);
+    assert_eq!(remaining.retry_wait(2), Duration::from_secs(4));
+  }
+
+  #[test]
+  fn test_all_merged() {
+    let node = |states: &[&str]| {
+      let edges: Vec<_> = states.iter().map(|s| json!({ "node": { "state": s } })).collect();
+      json!({ "oid": "abc", "associatedPullRequests": { "edges": edges } })
+    };
+
+    assert!(all_merged(&[]));
+    assert!(all_merged(&[node(&["MERGED"]), node(&[])]));
+    assert!(!all_merged(&[node(&["MERGED"]), node(&["MERGED", "OPEN"])]));
+    assert!(!all_merged(&[node(&["CLOSED"])]));
+  }
+
+  #[test]
+  fn test_read_cache_expires() {
+    let path = std::env::temp_dir().join(format!("versio-gh-cache-{}.json", std::process::id()));
+    write_cache(&path, &[json!({ "oid": "abc" })]);
+    let fresh = read_cache(&path);
+    let old = SystemTime::now() - CACHE_TTL - Duration::from_secs(60);
+    std::fs::File::options().write(true).open(&path).unwrap().set_modified(old).unwrap();
+    let stale = read_cache(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(fresh, Some(vec![json!({ "oid": "abc" })]));
+    assert_eq!(stale, None);
+  }
+
+  #[test]
+  fn test_no_cache() {
+    let info = GithubInfo::new("o".into(), "r".into(), None, GithubHost::default()).with_no_cache(true);
+    assert_eq!(commit_cache_file(&info, "abc"), None);
+  }
+}
@@ -1,30 +1,57 @@
 //! Simple implementation of the `init` command.
 
 use crate::bail;
-use crate::config::CONFIG_FILENAME;
+use crate::config::{legal_tag, BAZEL_MODULE_PATTERN, CONFIG_FILENAME};
 use crate::errors::{Error, Result};
 use crate::mark::Mark;
-use crate::scan::{find_reg_data, JsonScanner, Scanner, TomlScanner, XmlScanner};
+use crate::scan::{find_reg_data, JsonScanner, Scanner, TomlScanner, XmlScanner, YamlScanner};
+use glob::{glob, Pattern};
 use ignore::WalkBuilder;
 use path_slash::PathExt;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::ffi::OsStr;
 use std::fmt::Write as _;
 use std::fs::OpenOptions;
-use std::io::Write;
+use std::io::{BufRead, Write};
 use std::path::Path;
 use tracing::warn;
 
-pub fn init(max_depth: u16) -> Result<()> {
+pub fn init(max_depth: u16, interactive: bool) -> Result<()> {
   if Path::new(CONFIG_FILENAME).exists() {
     bail!("Versio is already initialized.");
   }
 
+  // Workspace packages are listed by the root project, so they replace any `package.json` found by walking.
+  let workspaces = find_workspaces()?;
+  let is_walked = |name: &OsStr| workspaces.is_none() || name != "package.json";
+
   let walk = WalkBuilder::new("./").max_depth(Some(max_depth as usize)).build();
-  let projs: Vec<_> = walk
-    .filter_map(|r| r.map_err(Error::from).and_then(|e| find_project(e.file_name(), e.path())).transpose())
+  let walked: Vec<_> = walk
+    .filter_map(|r| {
+      r.map_err(Error::from)
+        .and_then(|e| if is_walked(e.file_name()) { find_project(e.file_name(), e.path()) } else { Ok(None) })
+        .transpose()
+    })
     .collect::<Result<_>>()?;
 
+  let mut projs = workspaces.unwrap_or_default();
+  projs.extend(walked);
+
+  if interactive {
+    let stdin = std::io::stdin();
+    let mut prompt = Prompt::new(stdin.lock(), std::io::stdout());
+    let yaml = match prompt.wizard(projs)? {
+      Some(yaml) => yaml,
+      None => {
+        println!("Nothing written.");
+        return Ok(());
+      }
+    };
+    std::fs::write(CONFIG_FILENAME, yaml)?;
+    append_ignore()?;
+    return Ok(());
+  }
+
   if projs.is_empty() {
     println!("No projects found.");
   }
@@ -33,6 +60,133 @@ pub fn init(max_depth: u16) -> Result<()> {
   Ok(())
 }
 
+/// The questions and answers of `init --interactive`.
+struct Prompt<R: BufRead, W: Write> {
+  input: R,
+  output: W
+}
+
+impl<R: BufRead, W: Write> Prompt<R, W> {
+  pub fn new(input: R, output: W) -> Prompt<R, W> { Prompt { input, output } }
+
+  /// Walk through the found projects and the sizes, and return the resulting config; or `None` if the user
+  /// declines to write it.
+  pub fn wizard(&mut self, projs: Vec<ProjSummary>) -> Result<Option<String>> {
+    if projs.is_empty() {
+      writeln!(self.output, "No projects found.")?;
+    }
+
+    let mut kept = Vec::new();
+    for mut proj in projs {
+      writeln!(self.output)?;
+      let (kind, root) = (proj.labels().join("/"), proj.root().unwrap_or("."));
+      writeln!(self.output, "Found {} project \"{}\" at {}.", kind, proj.name(), root)?;
+      match self.ask("Keep, rename, or skip it? (k/r/s)", "k")?.to_lowercase().as_str() {
+        "k" | "keep" => kept.push(proj),
+        "r" | "rename" => {
+          proj.name = self.ask("New name", proj.name())?;
+          kept.push(proj);
+        }
+        "s" | "skip" => (),
+        other => bail!("Unknown choice \"{}\".", other)
+      }
+    }
+
+    if !kept.is_empty() {
+      writeln!(self.output)?;
+    }
+    let count = kept.len();
+    let mut prefixes = HashSet::new();
+    for proj in &mut kept {
+      let suggested = proj.tag_prefix(count, &mut prefixes);
+      prefixes.remove(&suggested);
+      let prefix = self.ask(&format!("Tag prefix for \"{}\"", proj.name()), &suggested)?;
+      if !legal_tag(&prefix) {
+        bail!("Illegal tag prefix \"{}\".", prefix);
+      }
+      if !prefixes.insert(prefix.clone()) {
+        bail!("Tag prefix \"{}\" is already taken.", prefix);
+      }
+      proj.prefix = Some(prefix);
+    }
+
+    writeln!(self.output)?;
+    let sizes = if self.confirm("Use the angular conventions for commit sizes?", true)? {
+      SizesSummary::Angular
+    } else {
+      let mut sizes = Vec::new();
+      for (size, types) in ANGULAR_SIZES {
+        let answer = self.ask(&format!("Commit types for {} (comma-separated)", size), types)?;
+        let types = answer.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect();
+        sizes.push((size.to_string(), types));
+      }
+      SizesSummary::Custom(sizes)
+    };
+
+    let yaml = generate_yaml_sized(&kept, &sizes);
+    writeln!(self.output)?;
+    writeln!(self.output, "{}", yaml)?;
+    if self.confirm(&format!("Write this to {}?", CONFIG_FILENAME), true)? {
+      Ok(Some(yaml))
+    } else {
+      Ok(None)
+    }
+  }
+
+  fn ask(&mut self, question: &str, default: &str) -> Result<String> {
+    write!(self.output, "{} [{}]: ", question, default)?;
+    self.output.flush()?;
+
+    let mut answer = String::new();
+    if self.input.read_line(&mut answer)? == 0 {
+      bail!("Unexpected end of input.");
+    }
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default.to_string() } else { answer.to_string() })
+  }
+
+  fn confirm(&mut self, question: &str, default: bool) -> Result<bool> {
+    let answer = self.ask(&format!("{} (y/n)", question), if default { "y" } else { "n" })?;
+    match answer.to_lowercase().as_str() {
+      "y" | "yes" => Ok(true),
+      "n" | "no" => Ok(false),
+      other => bail!("Unknown answer \"{}\".", other)
+    }
+  }
+}
+
+/// The sizes and commit types that `use_angular` adds, which are the defaults for a custom map.
+const ANGULAR_SIZES: [(&str, &str); 5] = [
+  ("major", "!"),
+  ("minor", "feat"),
+  ("patch", "fix"),
+  ("none", "build, chore, ci, docs, perf, refactor, style, test"),
+  ("fail", "*")
+];
+
+enum SizesSummary {
+  Angular,
+  Custom(Vec<(String, Vec<String>)>)
+}
+
+impl SizesSummary {
+  pub fn append(&self, yaml: &mut String) {
+    yaml.push_str("sizes:\n");
+    match self {
+      SizesSummary::Angular => {
+        yaml.push_str("  use_angular: true\n");
+        yaml.push_str("  fail: [\"*\"]\n");
+      }
+      SizesSummary::Custom(sizes) => {
+        for (size, types) in sizes.iter().filter(|(_, types)| !types.is_empty()) {
+          let types: Vec<_> = types.iter().map(|t| format!("\"{}\"", t.replace('"', "\\\""))).collect();
+          writeln!(yaml, "  {}: [{}]", size, types.join(", ")).unwrap();
+        }
+      }
+    }
+  }
+}
+
 fn find_project(name: &OsStr, file: &Path) -> Result<Option<ProjSummary>> {
   let fname = match name.to_str() {
     Some(n) => n,
@@ -40,11 +194,7 @@ fn find_project(name: &OsStr, file: &Path) -> Result<Option<ProjSummary>> {
   };
 
   if fname == "package.json" {
-    let name = extract_name(file, |d| JsonScanner::new("name").find(&d))?;
-    let dir = file.parent().unwrap();
-    let mut proj = ProjSummary::new_file(name, dir.to_slash_lossy(), "package.json", "json", "version", &["npm"]);
-    proj.hook("post_write", "npm install --package-lock-only");
-    return Ok(Some(proj));
+    return Ok(Some(npm_project(file, false)?));
   }
 
   if fname == "Cargo.toml" {
@@ -71,6 +221,22 @@ fn find_project(name: &OsStr, file: &Path) -> Result<Option<ProjSummary>> {
     return Ok(Some(ProjSummary::new_file(name, dir, "pom.xml", "xml", "project.version", &["mvn"])));
   }
 
+  if fname.ends_with(".csproj") || fname.ends_with(".fsproj") || fname == "Directory.Build.props" {
+    return Ok(msbuild_project(fname, file));
+  }
+
+  if fname == "composer.json" {
+    return Ok(Some(composer_project(file)?));
+  }
+
+  if fname.ends_with(".podspec") {
+    return Ok(Some(podspec_project(fname, file)?));
+  }
+
+  if fname == "pyproject.toml" {
+    return Ok(pyproject_project(file));
+  }
+
   if fname == "setup.py" {
     let name_reg = r#"name *= *['"]([^'"]*)['"]"#;
     let version_reg = r#"version *= *['"](\d+\.\d+\.\d+)['"]"#;
@@ -79,13 +245,26 @@ fn find_project(name: &OsStr, file: &Path) -> Result<Option<ProjSummary>> {
     return Ok(Some(ProjSummary::new_file(name, dir, "setup.py", "pattern", version_reg, &["pip"])));
   }
 
-  if file.is_dir()
-    && file
-      .read_dir()?
-      .filter_map(|e| e.ok().and_then(|e| e.file_name().into_string().ok()))
-      .any(|n| n.ends_with("*.tf"))
-  {
-    return Ok(Some(ProjSummary::new_tags("terraform", file.to_slash_lossy(), false, &["terraform"])));
+  if file.is_dir() && has_terraform(file) {
+    return Ok(Some(terraform_project(file)));
+  }
+
+  if fname == "Chart.yaml" {
+    let name = extract_name(file, |d| YamlScanner::new("name").find(&d))?;
+    let dir = file.parent().unwrap();
+    return Ok(Some(ProjSummary::new_helm(name, dir.to_slash_lossy(), &["helm"])));
+  }
+
+  if fname == "MODULE.bazel" {
+    return Ok(Some(bazel_project(file)?));
+  }
+
+  if fname == "WORKSPACE" || fname == "WORKSPACE.bazel" {
+    let dir = file.parent().unwrap();
+    if !dir.join("MODULE.bazel").exists() {
+      let name = dir.file_name().and_then(|n| n.to_str()).unwrap_or("bazel");
+      return Ok(Some(ProjSummary::new_tags(name, dir.to_slash_lossy(), false, &["bazel"])));
+    }
   }
 
   if fname == "Dockerfile" {
@@ -100,6 +279,165 @@ fn find_project(name: &OsStr, file: &Path) -> Result<Option<ProjSummary>> {
   Ok(None)
 }
 
+/// An MSBuild project (or a `Directory.Build.props` shared by several) can keep its version as an attribute or in a
+/// property group. Files without a version are skipped.
+fn msbuild_project(fname: &str, file: &Path) -> Option<ProjSummary> {
+  let data = std::fs::read_to_string(file).ok()?;
+  let parts = ["Project@Version", "Project.PropertyGroup.Version"]
+    .into_iter()
+    .find(|parts| XmlScanner::new(parts).find_version(&data).is_ok())?;
+
+  let dir = file.parent().unwrap();
+  let name = match fname.rsplit_once('.') {
+    Some((_, "props")) => dir.file_name().and_then(|n| n.to_str()).unwrap_or("project"),
+    Some((stem, _)) => stem,
+    None => fname
+  };
+  Some(ProjSummary::new_file(name, dir.to_slash_lossy(), fname, "xml", parts, &["nuget"]))
+}
+
+/// A `pyproject.toml` keeps its version in `[project]` (PEP 621) or in `[tool.poetry]`. Files without a static
+/// version (such as a `dynamic` one) are skipped.
+fn pyproject_project(file: &Path) -> Option<ProjSummary> {
+  let data = std::fs::read_to_string(file).ok()?;
+  let table = ["project", "tool.poetry"]
+    .into_iter()
+    .find(|table| TomlScanner::new(&format!("{}.version", table)).find_version(&data).is_ok())?;
+
+  let name = TomlScanner::new(&format!("{}.name", table)).find(&data).ok()?.value().to_string();
+  let dir = file.parent().unwrap().to_slash_lossy();
+  let parts = format!("{}.version", table);
+  Some(ProjSummary::new_file(name, dir, "pyproject.toml", "toml", parts, &["pip"]))
+}
+
+fn has_terraform(dir: &Path) -> bool {
+  let entries = match dir.read_dir() {
+    Ok(entries) => entries,
+    Err(_) => return false
+  };
+  entries.filter_map(|e| e.ok()).any(|e| e.path().extension() == Some(OsStr::new("tf")))
+}
+
+/// Terraform modules use only VCS tags. Each module under the `modules` directory of another module is its own
+/// project, named for its directory.
+fn terraform_project(dir: &Path) -> ProjSummary {
+  let parent = dir.parent().filter(|p| p.file_name() == Some(OsStr::new("modules")));
+  let is_sub = parent.and_then(|p| p.parent()).map(has_terraform) == Some(true);
+  let name = if is_sub { dir.file_name().and_then(|n| n.to_str()).unwrap_or("module") } else { "terraform" };
+  ProjSummary::new_tags(name, dir.to_slash_lossy(), false, &["terraform"])
+}
+
+/// Composer packages often leave the version out of `composer.json`, and use only VCS tags.
+fn composer_project(file: &Path) -> Result<ProjSummary> {
+  let name = extract_name(file, |d| JsonScanner::new("name").find(&d))?;
+  let dir = file.parent().unwrap().to_slash_lossy();
+  let data = std::fs::read_to_string(file)?;
+  if JsonScanner::new("version").find_version(&data).is_ok() {
+    Ok(ProjSummary::new_file(name, dir, "composer.json", "json", "version", &["composer"]))
+  } else {
+    Ok(ProjSummary::new_tags(name, dir, false, &["composer"]))
+  }
+}
+
+/// A Bazel module names itself and (usually) its version in the `module(...)` call of `MODULE.bazel`. Modules
+/// without a version there use only VCS tags.
+fn bazel_project(file: &Path) -> Result<ProjSummary> {
+  let data = std::fs::read_to_string(file)?;
+  let dir = file.parent().unwrap();
+  let name_reg = r#"\bmodule\s*\([^)]*?\bname\s*=\s*"([^"]*)""#;
+  let name = match find_reg_data(&data, name_reg) {
+    Ok(mark) => mark.value().to_string(),
+    Err(_) => dir.file_name().and_then(|n| n.to_str()).unwrap_or("bazel").to_string()
+  };
+
+  if find_reg_data(&data, BAZEL_MODULE_PATTERN).is_ok() {
+    Ok(ProjSummary::new_bazel(name, dir.to_slash_lossy(), &["bazel"]))
+  } else {
+    Ok(ProjSummary::new_tags(name, dir.to_slash_lossy(), false, &["bazel"]))
+  }
+}
+
+fn podspec_project(fname: &str, file: &Path) -> Result<ProjSummary> {
+  let name_reg = r#"\b\w+\.name *= *['"]([^'"]*)['"]"#;
+  let version_reg = r#"\b\w+\.version *= *['"](\d+\.\d+\.\d+)['"]"#;
+  let name = extract_name(file, |d| find_reg_data(&d, name_reg))?;
+  let dir = file.parent().unwrap().to_slash_lossy();
+  if extract_name(file, |d| find_reg_data(&d, version_reg)).is_ok() {
+    Ok(ProjSummary::new_file(name, dir, fname, "pattern", version_reg, &["cocoapods"]))
+  } else {
+    warn!("Couldn't find version in \"{}\". Please edit the .versio.yaml file.", fname);
+    Ok(ProjSummary::new_file(name, dir, "EDIT_ME", "pattern", "EDIT_ME", &["cocoapods"]))
+  }
+}
+
+fn npm_project(file: &Path, pnpm: bool) -> Result<ProjSummary> {
+  let name = extract_name(file, |d| JsonScanner::new("name").find(&d))?;
+  let dir = file.parent().unwrap();
+  let mut proj = ProjSummary::new_file(name, dir.to_slash_lossy(), "package.json", "json", "version", &["npm"]);
+  if pnpm {
+    proj.hook("post_write", "pnpm install --lockfile-only");
+  } else {
+    proj.hook("post_write", "npm install --package-lock-only");
+  }
+  Ok(proj)
+}
+
+/// Find the packages of an npm / yarn (`workspaces` in the root `package.json`) or pnpm (`pnpm-workspace.yaml`)
+/// workspace, if this is one. The root package is included only if it isn't private.
+fn find_workspaces() -> Result<Option<Vec<ProjSummary>>> {
+  let pnpm = Path::new("pnpm-workspace.yaml").exists();
+  let globs = match workspace_globs(pnpm)? {
+    Some(globs) => globs,
+    None => return Ok(None)
+  };
+
+  let mut projs = Vec::new();
+  let root = Path::new("./package.json");
+  if root.exists() {
+    let root_json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(root)?)?;
+    if root_json.get("private").and_then(|p| p.as_bool()) != Some(true) {
+      projs.push(npm_project(root, pnpm)?);
+    }
+  }
+
+  let (excludes, includes): (Vec<_>, Vec<_>) = globs.iter().partition(|g| g.starts_with('!'));
+  let excludes = excludes.iter().map(|g| Pattern::new(&g[1 ..])).collect::<std::result::Result<Vec<_>, _>>()?;
+  let mut files = BTreeSet::new();
+  for include in includes {
+    for file in glob(&format!("{}/package.json", include.trim_end_matches('/')))? {
+      let file = file?;
+      if !excludes.iter().any(|p| p.matches_path(&file) || file.parent().map(|d| p.matches_path(d)) == Some(true)) {
+        files.insert(file);
+      }
+    }
+  }
+
+  for file in files {
+    projs.push(npm_project(&Path::new(".").join(file), pnpm)?);
+  }
+  Ok(Some(projs))
+}
+
+fn workspace_globs(pnpm: bool) -> Result<Option<Vec<String>>> {
+  let strings = |v: &[serde_json::Value]| v.iter().filter_map(|g| g.as_str().map(|g| g.to_string())).collect();
+
+  if pnpm {
+    let yaml: serde_yaml::Value = serde_yaml::from_str(&std::fs::read_to_string("pnpm-workspace.yaml")?)?;
+    let packages = yaml.get("packages").and_then(|p| p.as_sequence());
+    Ok(packages.map(|p| p.iter().filter_map(|g| g.as_str().map(|g| g.to_string())).collect()))
+  } else if Path::new("package.json").exists() {
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string("package.json")?)?;
+    let workspaces = match json.get("workspaces") {
+      Some(serde_json::Value::Array(globs)) => Some(strings(globs)),
+      Some(workspaces) => workspaces.get("packages").and_then(|p| p.as_array()).map(|globs| strings(globs)),
+      None => None
+    };
+    Ok(workspaces)
+  } else {
+    Ok(None)
+  }
+}
+
 fn add_gemspec(fname: &str, file: &Path) -> Result<Option<ProjSummary>> {
   let spec_suffix = ".gemspec";
   if let Some(fname_pref) = fname.strip_suffix(spec_suffix) {
@@ -148,7 +486,9 @@ fn write_yaml(projs: &[ProjSummary]) -> Result<()> {
   Ok(std::fs::write(CONFIG_FILENAME, yaml)?)
 }
 
-fn generate_yaml(projs: &[ProjSummary]) -> String {
+fn generate_yaml(projs: &[ProjSummary]) -> String { generate_yaml_sized(projs, &SizesSummary::Angular) }
+
+fn generate_yaml_sized(projs: &[ProjSummary], sizes: &SizesSummary) -> String {
   let mut yaml = String::new();
   yaml.push_str("options:\n");
   yaml.push_str("  prev_tag: \"versio-prev\"\n");
@@ -197,10 +537,7 @@ fn generate_yaml(projs: &[ProjSummary]) -> String {
     yaml.push('\n');
   }
 
-  yaml.push_str("sizes:\n");
-  yaml.push_str("  use_angular: true\n");
-  yaml.push_str("  fail: [\"*\"]\n");
-
+  sizes.append(&mut yaml);
   yaml
 }
 
@@ -215,6 +552,7 @@ struct ProjSummary {
   tag_prefix_separator: String,
   root: String,
   subs: bool,
+  prefix: Option<String>,
   version: VersionSummary,
   hooks: HashMap<String, String>
 }
@@ -228,6 +566,7 @@ impl ProjSummary {
       name: name.to_string(),
       root: root.to_string(),
       subs: false,
+      prefix: None,
       labels: labels.iter().map(|s| s.to_string()).collect(),
       tag_prefix_separator: "-".into(),
       version: VersionSummary::File(FileVersionSummary::new(
@@ -249,6 +588,7 @@ impl ProjSummary {
       name: name.to_string(),
       root: root.to_string(),
       subs,
+      prefix: None,
       labels: labels.iter().map(|s| s.to_string()).collect(),
       tag_prefix_separator: "-".into(),
       version: VersionSummary::Tag(TagVersionSummary::new()),
@@ -256,6 +596,32 @@ impl ProjSummary {
     }
   }
 
+  pub fn new_helm(name: impl ToString, root: impl ToString, labels: &[impl ToString]) -> ProjSummary {
+    ProjSummary {
+      name: name.to_string(),
+      root: root.to_string(),
+      subs: false,
+      prefix: None,
+      labels: labels.iter().map(|s| s.to_string()).collect(),
+      tag_prefix_separator: "-".into(),
+      version: VersionSummary::Helm,
+      hooks: HashMap::new()
+    }
+  }
+
+  pub fn new_bazel(name: impl ToString, root: impl ToString, labels: &[impl ToString]) -> ProjSummary {
+    ProjSummary {
+      name: name.to_string(),
+      root: root.to_string(),
+      subs: false,
+      prefix: None,
+      labels: labels.iter().map(|s| s.to_string()).collect(),
+      tag_prefix_separator: "-".into(),
+      version: VersionSummary::Bazel,
+      hooks: HashMap::new()
+    }
+  }
+
   fn name(&self) -> &str { &self.name }
   fn labels(&self) -> &[String] { &self.labels }
   fn hooks(&self) -> &HashMap<String, String> { &self.hooks }
@@ -273,6 +639,10 @@ impl ProjSummary {
   }
 
   fn tag_prefix(&self, projs_len: usize, prefixes: &mut HashSet<String>) -> String {
+    if let Some(prefix) = &self.prefix {
+      return prefix.clone();
+    }
+
     let prefix = if projs_len == 1 { "".into() } else { tag_sanitize(&self.name) };
 
     let prefix = if prefixes.contains(&prefix) {
@@ -289,14 +659,18 @@ impl ProjSummary {
   fn append_version(&self, yaml: &mut String) {
     match &self.version {
       VersionSummary::File(f) => f.append(yaml),
-      VersionSummary::Tag(t) => t.append(yaml)
+      VersionSummary::Tag(t) => t.append(yaml),
+      VersionSummary::Helm => yaml.push_str("      helm: true\n"),
+      VersionSummary::Bazel => yaml.push_str("      bazel: true\n")
     }
   }
 }
 
 enum VersionSummary {
   File(FileVersionSummary),
-  Tag(TagVersionSummary)
+  Tag(TagVersionSummary),
+  Helm,
+  Bazel
 }
 
 struct FileVersionSummary {
@@ -334,7 +708,7 @@ impl TagVersionSummary {
 }
 
 fn tag_sanitize(name: &str) -> String {
-  // match the logic of `config::legal_tag`
+  // a stricter version of `config::legal_tag`
   let mut prefix: String =
     name.chars().filter(|c| c.is_ascii() && (*c == '/' || *c == '_' || *c == '-' || c.is_alphanumeric())).collect();
 
@@ -349,3 +723,73 @@ fn tag_sanitize(name: &str) -> String {
 
   prefix
 }
+
+#[cfg(test)]
+mod test {
+  use super::find_project;
+  use std::ffi::OsStr;
+
+  /// Detect a project from a single file, returning its name, labels, and the version part of its config.
+  fn detect(fname: &str, content: &str) -> Option<(String, Vec<String>, String)> {
+    let dir = std::env::temp_dir().join(format!("versio-init-{}-{}", fname, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join(fname), content).unwrap();
+    let found = find_project(OsStr::new(fname), &dir.join(fname));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    found.unwrap().map(|proj| {
+      let mut version = String::new();
+      proj.append_version(&mut version);
+      (proj.name().to_string(), proj.labels().to_vec(), version)
+    })
+  }
+
+  fn file_version(file: &str, file_type: &str, parts: &str) -> String {
+    format!("      file: \"{}\"\n      {}: \"{}\"\n", file, file_type, parts)
+  }
+
+  #[test]
+  fn test_detect_pyproject() {
+    let pep621 = "[project]\nname = \"pkg\"\nversion = \"1.2.3\"\n";
+    assert_eq!(
+      detect("pyproject.toml", pep621),
+      Some(("pkg".into(), vec!["pip".into()], file_version("pyproject.toml", "toml", "project.version")))
+    );
+
+    let poetry = "[tool.poetry]\nname = \"poem\"\nversion = \"0.1.0\"\n";
+    assert_eq!(
+      detect("pyproject.toml", poetry),
+      Some(("poem".into(), vec!["pip".into()], file_version("pyproject.toml", "toml", "tool.poetry.version")))
+    );
+
+    let dynamic = "[project]\nname = \"pkg\"\ndynamic = [\"version\"]\n";
+    assert_eq!(detect("pyproject.toml", dynamic), None);
+    assert_eq!(detect("pyproject.toml", "[build-system]\nrequires = [\"setuptools\"]\n"), None);
+  }
+
+  #[test]
+  fn test_detect_composer() {
+    let versioned = r#"{ "name": "vendor/pkg", "version": "1.2.3" }"#;
+    assert_eq!(
+      detect("composer.json", versioned),
+      Some(("vendor/pkg".into(), vec!["composer".into()], file_version("composer.json", "json", "version")))
+    );
+
+    // Without a version, a composer package is versioned by its tags.
+    let tagged = r#"{ "name": "vendor/pkg" }"#;
+    let tags = "      tags:\n        default: \"0.0.0\"\n".to_string();
+    assert_eq!(detect("composer.json", tagged), Some(("vendor/pkg".into(), vec!["composer".into()], tags)));
+  }
+
+  #[test]
+  fn test_detect_podspec() {
+    let versioned = "Pod::Spec.new do |s|\n  s.name = 'Kit'\n  s.version = \"1.2.3\"\nend\n";
+    let pattern = r#"      pattern: '\b\w+\.version *= *[''"](\d+\.\d+\.\d+)[''"]'"#;
+    let version = format!("      file: \"Kit.podspec\"\n{}\n", pattern);
+    assert_eq!(detect("Kit.podspec", versioned), Some(("Kit".into(), vec!["cocoapods".into()], version)));
+
+    let unversioned = "Pod::Spec.new do |spec|\n  spec.name = \"Kit\"\n  spec.version = ENV['VERSION']\nend\n";
+    let edit_me = "      file: \"EDIT_ME\"\n      pattern: 'EDIT_ME'\n".to_string();
+    assert_eq!(detect("Kit.podspec", unversioned), Some(("Kit".into(), vec!["cocoapods".into()], edit_me)));
+  }
+}
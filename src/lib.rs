@@ -4,18 +4,25 @@
 
 #[macro_use]
 pub mod errors;
+pub mod api;
 pub mod commands;
 pub mod init;
 pub(crate) mod scan;
 pub mod vcs;
 
 mod analyze;
+mod ci;
 mod config;
 mod either;
+mod events;
+mod fetch;
+mod fix;
 mod git;
 mod github;
 mod mark;
+mod migrate;
 mod mono;
 mod output;
 mod state;
 mod template;
+mod validate;
@@ -3,7 +3,8 @@
 use crate::bail;
 use crate::errors::Result;
 use crate::scan::parts::{deserialize_parts, Part};
-use crate::scan::{find_reg_data, scan_reg_data, JsonScanner, Scanner, TomlScanner, XmlScanner, YamlScanner};
+use crate::scan::{find_all_reg_data, find_reg_data, scan_all_reg_data, scan_reg_data, HclScanner, JsonScanner, Scanner,
+                  TomlScanner, XmlScanner, YamlScanner};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::fmt;
@@ -13,10 +14,13 @@ use std::path::{Path, PathBuf};
 #[derive(Clone, Deserialize, Serialize, Debug)]
 #[serde(untagged)]
 pub enum Picker {
+  // Listed first: as an untagged enum, its `prefix` distinguishes it from the other scanning pickers.
+  ImageTag(ImageTagPicker),
   Json(ScanningPicker<JsonScanner>),
   Yaml(ScanningPicker<YamlScanner>),
   Toml(ScanningPicker<TomlScanner>),
   Xml(ScanningPicker<XmlScanner>),
+  Hcl(ScanningPicker<HclScanner>),
   Line(LinePicker),
   File(FilePicker)
 }
@@ -25,10 +29,12 @@ impl Picker {
   #[cfg(test)]
   pub fn picker_type(&self) -> &'static str {
     match self {
+      Picker::ImageTag(_) => "image_tag",
       Picker::Json(_) => "json",
       Picker::Yaml(_) => "yaml",
       Picker::Toml(_) => "toml",
       Picker::Xml(_) => "xml",
+      Picker::Hcl(_) => "hcl",
       Picker::Line(_) => "line",
       Picker::File(_) => "file"
     }
@@ -36,10 +42,12 @@ impl Picker {
 
   pub fn scan(&self, data: NamedData) -> Result<MarkedData> {
     match self {
+      Picker::ImageTag(p) => p.scan(data),
       Picker::Json(p) => p.scan(data),
       Picker::Yaml(p) => p.scan(data),
       Picker::Toml(p) => p.scan(data),
       Picker::Xml(p) => p.scan(data),
+      Picker::Hcl(p) => p.scan(data),
       Picker::Line(p) => p.scan(data),
       Picker::File(p) => p.scan(data)
     }
@@ -47,10 +55,12 @@ impl Picker {
 
   pub fn find(&self, data: &str) -> Result<Mark> {
     match self {
+      Picker::ImageTag(p) => p.find_version(data),
       Picker::Json(p) => p.find_version(data),
       Picker::Yaml(p) => p.find_version(data),
       Picker::Toml(p) => p.find_version(data),
       Picker::Xml(p) => p.find_version(data),
+      Picker::Hcl(p) => p.find_version(data),
       Picker::Line(p) => p.find_version(data),
       Picker::File(p) => p.find_version(data)
     }
@@ -80,12 +90,22 @@ impl<T: Scanner> ScanningPicker<T> {
 
 #[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct LinePicker {
-  pattern: String
+  pattern: String,
+  #[serde(default)]
+  pattern_all: bool
 }
 
 impl LinePicker {
-  pub fn new(pattern: String) -> LinePicker { LinePicker { pattern } }
-  pub fn find(&self, data: &str) -> Result<Mark> { find_reg_data(data, &self.pattern) }
+  pub fn new(pattern: String, pattern_all: bool) -> LinePicker { LinePicker { pattern, pattern_all } }
+
+  /// Find the (first) match; if `pattern_all` is set, every match must have the same value.
+  pub fn find(&self, data: &str) -> Result<Mark> {
+    if self.pattern_all {
+      Ok(find_all_reg_data(data, &self.pattern)?.swap_remove(0))
+    } else {
+      find_reg_data(data, &self.pattern)
+    }
+  }
 
   pub fn find_version(&self, data: &str) -> Result<Mark> {
     let mark = self.find(data)?;
@@ -93,16 +113,67 @@ impl LinePicker {
     Ok(mark)
   }
 
-  pub fn scan(&self, data: NamedData) -> Result<MarkedData> { scan_reg_data(data, &self.pattern) }
+  pub fn scan(&self, data: NamedData) -> Result<MarkedData> {
+    if self.pattern_all {
+      scan_all_reg_data(data, &self.pattern)
+    } else {
+      scan_reg_data(data, &self.pattern)
+    }
+  }
+}
+
+/// Picks the tag of a container image (`myrepo/app:1.2.3`) in a YAML file, leaving the rest of the image alone.
+/// The tag follows the `prefix` if one is given, or else the last `:` of the image.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct ImageTagPicker {
+  #[serde(deserialize_with = "deserialize_parts")]
+  image: Vec<Part>,
+  prefix: Option<String>
+}
+
+impl ImageTagPicker {
+  pub fn new(image: Vec<Part>, prefix: Option<String>) -> ImageTagPicker { ImageTagPicker { image, prefix } }
+
+  pub fn find(&self, data: &str) -> Result<Mark> {
+    let image = YamlScanner::build(self.image.clone()).find(data)?;
+    if !data[image.start() ..].starts_with(image.value()) {
+      bail!("Unable to pick the tag of escaped image \"{}\".", image.value());
+    }
+
+    let prefix_len = match &self.prefix {
+      Some(prefix) if image.value().starts_with(prefix.as_str()) => prefix.len(),
+      Some(prefix) => bail!("Image \"{}\" doesn't start with \"{}\".", image.value(), prefix),
+      None => match image.value().rfind(':') {
+        Some(colon) if !image.value()[colon ..].contains('/') => colon + 1,
+        _ => bail!("Image \"{}\" doesn't have a tag.", image.value())
+      }
+    };
+
+    Ok(Mark::new(image.value()[prefix_len ..].to_string(), image.start() + prefix_len))
+  }
+
+  pub fn find_version(&self, data: &str) -> Result<Mark> {
+    let mark = self.find(data)?;
+    mark.validate_version()?;
+    Ok(mark)
+  }
+
+  pub fn scan(&self, data: NamedData) -> Result<MarkedData> {
+    let mark = self.find(data.data())?;
+    Ok(data.mark(mark))
+  }
 }
 
 #[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct FilePicker {}
 
 impl FilePicker {
+  /// The value is the whole file, except for any byte-order mark and surrounding whitespace: those are left in
+  /// place, so that writing a new value only changes the value itself.
   pub fn find(&self, data: &str) -> Result<Mark> {
-    let value = data.trim_end().to_string();
-    Ok(Mark::new(value, 0))
+    let body = data.strip_prefix('\u{feff}').unwrap_or(data).trim_start();
+    let start = data.len() - body.len();
+    Ok(Mark::new(body.trim_end().to_string(), start))
   }
 
   pub fn find_version(&self, data: &str) -> Result<Mark> {
@@ -131,21 +202,28 @@ impl NamedData {
   pub fn writeable_path(&self) -> &Path { &self.writeable_path }
   pub fn data(&self) -> &str { &self.data }
   pub fn mark(self, mark: Mark) -> MarkedData { MarkedData::new(self.writeable_path, self.data, mark) }
+  pub fn mark_all(self, marks: Vec<Mark>) -> MarkedData { MarkedData::new_all(self.writeable_path, self.data, marks) }
 }
 
+/// Data with one or more marks, which all have the same value. The first mark is the primary mark.
 pub struct MarkedData {
   writeable_path: PathBuf,
   data: String,
-  mark: Mark
+  marks: Vec<Mark>
 }
 
 impl MarkedData {
   pub fn new(writeable_path: PathBuf, data: String, mark: Mark) -> MarkedData {
-    MarkedData { writeable_path, data, mark }
+    MarkedData { writeable_path, data, marks: vec![mark] }
   }
 
-  pub fn value(&self) -> &str { self.mark.value() }
-  pub fn start(&self) -> usize { self.mark.start() }
+  pub fn new_all(writeable_path: PathBuf, data: String, marks: Vec<Mark>) -> MarkedData {
+    assert!(!marks.is_empty(), "No marks for {}.", writeable_path.to_string_lossy());
+    MarkedData { writeable_path, data, marks }
+  }
+
+  pub fn value(&self) -> &str { self.marks[0].value() }
+  pub fn start(&self) -> usize { self.marks[0].start() }
 
   pub fn write_new_value(&mut self, new_val: &str) -> Result<()> {
     self.set_value(new_val);
@@ -154,10 +232,17 @@ impl MarkedData {
   }
 
   fn set_value(&mut self, new_val: &str) {
-    let st = self.start();
-    let ed = st + self.value().len();
-    self.data.replace_range(st .. ed, new_val);
-    self.mark.set_value(new_val.to_string());
+    // The marks are in order and all have the same value, so each is shifted by the length change of the marks
+    // before it.
+    let mut shift: isize = 0;
+    for mark in &mut self.marks {
+      let st = (mark.start() as isize + shift) as usize;
+      let ed = st + mark.value().len();
+      self.data.replace_range(st .. ed, new_val);
+      shift += new_val.len() as isize - mark.value().len() as isize;
+      mark.byte_start = st;
+      mark.set_value(new_val.to_string());
+    }
   }
 
   fn write(&self) -> Result<()> { Ok(std::fs::write(&self.writeable_path, &self.data)?) }
@@ -210,7 +295,10 @@ impl CharMark {
 
 #[cfg(test)]
 mod test {
-  use super::find_reg_data;
+  use super::{find_reg_data, FilePicker, ImageTagPicker, MarkedData};
+  use crate::scan::find_all_reg_data;
+  use crate::scan::parts::IntoPartVec as _;
+  use std::path::PathBuf;
 
   #[test]
   fn test_find_reg() {
@@ -222,4 +310,53 @@ Current rev is "v1.2.3" because it is."#;
     assert_eq!("1.2.3", mark.value());
     assert_eq!(32, mark.start());
   }
+
+  #[test]
+  fn test_set_all() {
+    let data = "badge: v1.2.3\ninstall: v1.2.3 now\n";
+    let marks = find_all_reg_data(data, "v(\\d+\\.\\d+\\.\\d+)").unwrap();
+    assert_eq!(2, marks.len());
+
+    let mut marked = MarkedData::new_all(PathBuf::from("test"), data.to_string(), marks);
+    marked.set_value("10.20.30");
+    assert_eq!("badge: v10.20.30\ninstall: v10.20.30 now\n", marked.data);
+  }
+
+  #[test]
+  fn test_file_whitespace() {
+    let data = "\u{feff}  1.2.3\r\n\n";
+    let mark = FilePicker {}.find_version(data).unwrap();
+    assert_eq!("1.2.3", mark.value());
+
+    let mut marked = MarkedData::new(PathBuf::from("test"), data.to_string(), mark);
+    marked.set_value("1.3.0");
+    assert_eq!("\u{feff}  1.3.0\r\n\n", marked.data);
+  }
+
+  #[test]
+  fn test_find_all_mismatch() {
+    let data = "badge: v1.2.3\ninstall: v1.2.4 now\n";
+    assert!(find_all_reg_data(data, "v(\\d+\\.\\d+\\.\\d+)").is_err());
+  }
+
+  #[test]
+  fn test_image_tag() {
+    let data = r#"
+spec:
+  template:
+    spec:
+      containers:
+        - name: app
+          image: "registry:5000/myrepo/app:1.2.3"
+"#;
+
+    let parts = "spec.template.spec.containers.0.image".into_part_vec();
+    let mark = ImageTagPicker::new(parts.clone(), Some("registry:5000/myrepo/app:".into())).find(data).unwrap();
+    assert_eq!("1.2.3", mark.value());
+    assert_eq!(Some("1.2.3\""), data.get(mark.start() .. mark.start() + 6));
+
+    let mark = ImageTagPicker::new(parts.clone(), None).find(data).unwrap();
+    assert_eq!("1.2.3", mark.value());
+    assert!(ImageTagPicker::new(parts, Some("myrepo/other:".into())).find(data).is_err());
+  }
 }
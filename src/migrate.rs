@@ -0,0 +1,211 @@
+//! Migration of older config files to the current `config_version`, for `config migrate`.
+
+use crate::bail;
+use crate::config::CONFIG_FILENAME;
+use crate::errors::Result;
+use serde_yaml::{Mapping, Value};
+use std::path::Path;
+
+/// The version of the config file shape that this build of Versio writes.
+pub const CONFIG_VERSION: u64 = 3;
+
+/// Each migration takes the config from the version at its index (plus one) to the next version, and returns
+/// `false` if nothing needed to be changed.
+const MIGRATIONS: &[fn(&mut Mapping) -> Result<bool>] = &[migrate_1_to_2, migrate_2_to_3];
+
+/// The version of a parsed config file; a file without a `config_version` is version 1.
+pub fn config_version(value: &Value) -> Result<u64> {
+  match value.get("config_version") {
+    None | Some(Value::Null) => Ok(1),
+    Some(Value::Number(n)) => match n.as_u64() {
+      Some(v) if v >= 1 => Ok(v),
+      _ => bail!("Illegal config_version {}.", n)
+    },
+    Some(_) => bail!("config_version must be a number.")
+  }
+}
+
+/// Bring a parsed config file up to the current version in place, returning the version it started at and whether
+/// anything besides its version was changed, or `None` if it was already current.
+pub fn migrate_value(value: &mut Value) -> Result<Option<(u64, bool)>> {
+  let version = config_version(value)?;
+  if version > CONFIG_VERSION {
+    bail!("Config version {} is newer than this Versio supports ({}): upgrade Versio.", version, CONFIG_VERSION);
+  }
+  if version == CONFIG_VERSION {
+    return Ok(None);
+  }
+
+  let config = match value.as_mapping_mut() {
+    Some(config) => config,
+    None => return Ok(None)
+  };
+  let mut changed = false;
+  for migration in &MIGRATIONS[version as usize - 1 ..] {
+    changed |= migration(config)?;
+  }
+  config.insert("config_version".into(), CONFIG_VERSION.into());
+  Ok(Some((version, changed)))
+}
+
+/// Rewrite the config file found in `root` in the current version, returning the version it was in, or `None` if
+/// it didn't need to be migrated. The file is re-serialized, so its comments and formatting are not preserved.
+pub fn migrate_config(root: &Path) -> Result<Option<u64>> {
+  let path = root.join(CONFIG_FILENAME);
+  if !path.exists() {
+    bail!("No {} found in {}.", CONFIG_FILENAME, root.display());
+  }
+
+  let mut value: Value = serde_yaml::from_str(&std::fs::read_to_string(&path)?)?;
+  let from = migrate_value(&mut value)?.map(|(from, _)| from);
+  if from.is_some() {
+    // Put the version first, where it's easy to find.
+    if let Some(config) = value.as_mapping_mut() {
+      let mut ordered = Mapping::new();
+      ordered.insert("config_version".into(), CONFIG_VERSION.into());
+      ordered.extend(std::mem::take(config).into_iter().filter(|(k, _)| k.as_str() != Some("config_version")));
+      *config = ordered;
+    }
+    std::fs::write(&path, serde_yaml::to_string(&value)?)?;
+  }
+  Ok(from)
+}
+
+/// Version 2 names a location's command `get` everywhere: version 1 also allowed `cmd`.
+fn migrate_1_to_2(config: &mut Mapping) -> Result<bool> {
+  fn rename_cmd(location: &mut Value) -> Result<bool> {
+    let location = match location.as_mapping_mut() {
+      Some(location) => location,
+      None => return Ok(false)
+    };
+    if location.contains_key("get") && location.contains_key("cmd") {
+      bail!("Can't have both 'get' and 'cmd' for location.");
+    }
+    match location.remove("cmd") {
+      Some(cmd) => {
+        location.insert("get".into(), cmd);
+        Ok(true)
+      }
+      None => Ok(false)
+    }
+  }
+
+  fn fix_project(project: &mut Mapping) -> Result<bool> {
+    let mut changed = false;
+    if let Some(version) = project.get_mut("version") {
+      changed |= rename_cmd(version)?;
+    }
+    if let Some(also) = project.get_mut("also").and_then(|a| a.as_sequence_mut()) {
+      for location in also {
+        changed |= rename_cmd(location)?;
+      }
+    }
+    Ok(changed)
+  }
+
+  let mut changed = false;
+  for key in ["projects", "projects_from"] {
+    if let Some(projects) = config.get_mut(key).and_then(|p| p.as_sequence_mut()) {
+      for project in projects.iter_mut().filter_map(|p| p.as_mapping_mut()) {
+        changed |= fix_project(project)?;
+      }
+    }
+  }
+  if let Some(defaults) = config.get_mut("project_defaults").and_then(|d| d.as_mapping_mut()) {
+    changed |= fix_project(defaults)?;
+  }
+  Ok(changed)
+}
+
+/// Version 3 replaces each `${VAR}` in a string with the environment variable `VAR`: in version 2, it was left for
+/// the shell (as in a hook), so it's escaped as `$${VAR}`.
+fn migrate_2_to_3(config: &mut Mapping) -> Result<bool> {
+  fn escape(value: &mut Value) -> bool {
+    match value {
+      Value::String(text) if text.contains("${") => {
+        *text = text.replace("${", "$${");
+        true
+      }
+      Value::Sequence(seq) => escape_all(seq.iter_mut()),
+      Value::Mapping(map) => escape_all(map.iter_mut().map(|(_, v)| v)),
+      Value::Tagged(tagged) => escape(&mut tagged.value),
+      _ => false
+    }
+  }
+
+  fn escape_all<'v>(values: impl Iterator<Item = &'v mut Value>) -> bool {
+    let mut changed = false;
+    for value in values {
+      changed |= escape(value);
+    }
+    changed
+  }
+
+  Ok(escape_all(config.iter_mut().map(|(_, v)| v)))
+}
+
+#[cfg(test)]
+mod test {
+  use super::{config_version, migrate_value, CONFIG_VERSION};
+  use serde_yaml::Value;
+
+  #[test]
+  fn test_migrate_cmd() {
+    let mut value: Value = serde_yaml::from_str(
+      r#"
+projects:
+  - name: proj
+    id: 1
+    version:
+      cmd: "poetry version -s"
+      set: "poetry version {version}"
+"#
+    )
+    .unwrap();
+
+    assert_eq!(migrate_value(&mut value).unwrap(), Some((1, true)));
+    assert_eq!(config_version(&value).unwrap(), CONFIG_VERSION);
+    let version = &value["projects"][0]["version"];
+    assert_eq!(version["get"].as_str(), Some("poetry version -s"));
+    assert!(version.get("cmd").is_none());
+    assert_eq!(migrate_value(&mut value).unwrap(), None);
+  }
+
+  #[test]
+  fn test_migrate_env_vars() {
+    let mut value: Value = serde_yaml::from_str(
+      r#"
+config_version: 2
+projects:
+  - name: proj
+    id: 1
+    hooks:
+      post_write: echo ${HOME} $${USER}
+"#
+    )
+    .unwrap();
+
+    assert_eq!(migrate_value(&mut value).unwrap(), Some((2, true)));
+    assert_eq!(value["projects"][0]["hooks"]["post_write"].as_str(), Some("echo $${HOME} $$${USER}"));
+  }
+
+  #[test]
+  fn test_migrate_unchanged() {
+    let mut value: Value = serde_yaml::from_str("projects:\n  - name: proj\n    id: 1\n    version: {get: x}").unwrap();
+    assert_eq!(migrate_value(&mut value).unwrap(), Some((1, false)));
+    assert_eq!(config_version(&value).unwrap(), CONFIG_VERSION);
+  }
+
+  #[test]
+  fn test_migrate_cmd_and_get() {
+    let mut value: Value = serde_yaml::from_str("projects:\n  - version: {get: x, cmd: y}").unwrap();
+    let err = migrate_value(&mut value).unwrap_err();
+    assert!(err.to_string().contains("both 'get' and 'cmd'"));
+  }
+
+  #[test]
+  fn test_migrate_too_new() {
+    let mut value: Value = serde_yaml::from_str("config_version: 99\nprojects: []").unwrap();
+    assert!(migrate_value(&mut value).is_err());
+  }
+}
@@ -1,81 +1,236 @@
 //! A monorepo can read and alter the current state of all projects.
 
 use crate::analyze::{analyze, Analysis};
-use crate::bail;
-use crate::config::{Config, ConfigFile, Depends, FsConfig, Project, ProjectId, Size};
+use crate::commands::failed_hashes;
+use crate::config::{branch_matches, legal_tag, Config, ConfigCache, ConfigFile, Depends, FsConfig, Overlap, Project,
+                    ProjectId, Size};
 use crate::either::{IterEither2 as E2, IterEither3 as E3};
-use crate::errors::Result;
-use crate::git::{Auth, CommitInfoBuf, FromTag, FromTagBuf, FullPr, GithubInfo, Repo};
+use crate::errors::{Context as _, Error, Result, VersioError};
+use crate::events::{Events, PROGRESS};
+use crate::git::{Auth, CommitInfoBuf, Contributor, FromTag, FromTagBuf, FullPr, GithubInfo, Proxy, Repo};
 use crate::github::{changes, line_commits_head, Changes};
-use crate::state::{CommitArgs, CurrentState, OldTags, PrevFiles, PrevTagMessage, StateRead, StateWrite};
+use crate::output::ProjLine;
+use crate::state::{
+  CommitArgs, CurrentState, OldTags, Pause, PrevState, PrevTagMessage, ReleaseRecord, StateRead, StateWrite
+};
+use crate::template::{construct_release_notes, read_template, CrossLink, SharedPr, SharedPrs};
 use crate::vcs::VcsState;
-use chrono::{DateTime, FixedOffset};
+use crate::{bad, bail};
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone as _};
+use path_slash::PathBufExt as _;
 use serde::Deserialize;
+use serde_json::Value;
 use std::cmp::{max, Ordering};
-use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::iter::{empty, once};
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
-use tracing::trace;
+use tracing::{debug, info, info_span, trace, warn};
 
 const USER_PREFS_DIR: &str = ".versio";
 const USER_PREFS_FILE: &str = "prefs.toml";
+const RELEASE_NOTES_FILE: &str = "RELEASE_NOTES.md";
+
+/// The environment variable that, if set, fails on a malformed prev tag annotation instead of ignoring it.
+pub const STRICT_PREV_VAR: &str = "VERSIO_STRICT_PREV";
 
 pub struct Mono {
   current: Config<CurrentState>,
   next: StateWrite,
   last_commits: HashMap<ProjectId, String>,
   repo: Repo,
-  user_prefs: UserPrefs
+  user_prefs: UserPrefs,
+  sparse: bool,
+  held_back: bool
 }
 
 impl Mono {
   pub fn here(vcs: VcsState) -> Result<Mono> { Mono::open(".", vcs) }
+  pub fn here_only(vcs: VcsState, only: &[String]) -> Result<Mono> { Mono::open_only(".", vcs, only) }
+  pub fn open<P: AsRef<Path>>(dir: P, vcs: VcsState) -> Result<Mono> { Mono::open_only(dir, vcs, &[]) }
 
-  pub fn open<P: AsRef<Path>>(dir: P, vcs: VcsState) -> Result<Mono> {
+  /// Open the monorepo in sparse mode, restricted to the projects selected by ID, name, or label (and their
+  /// dependents): no other project is analyzed or has its tags fetched. An empty selection opens all projects.
+  pub fn open_only<P: AsRef<Path>>(dir: P, vcs: VcsState, only: &[String]) -> Result<Mono> {
     let root = Repo::find_working_dir(dir.as_ref(), *vcs.level(), false)?;
 
     // A little dance to construct a state and config.
     let file = ConfigFile::from_dir(&root)?;
+    let sparse = !only.is_empty();
+    let file = if sparse { file.restrict(only)? } else { file };
     trace!("Using commit message: {}", file.commit_config().message());
 
-    let repo = Repo::open(dir.as_ref(), vcs, file.commit_config().clone())?;
+    let user_prefs = read_env_prefs()?;
+    let strict_prev = user_prefs.strict_prev() || vcs.strict_prev();
+    let repo = Repo::open(dir.as_ref(), vcs, file.commit_config().clone())?
+      .with_auth(user_prefs.auth().clone())
+      .with_proxy(user_prefs.proxy().clone())
+      .with_github(file.github_host());
+
+    // Without a repo there's no branch, but there's also no prev tag to find.
+    let file = match repo.branch_name() {
+      Ok(branch) => file.for_branch(branch.as_deref())?,
+      Err(_) => file
+    };
     let projects = file.projects().iter();
-    let old_tags = find_old_tags(projects, file.prev_tag(), &repo)?;
-    let state = CurrentState::new(root, old_tags);
+    let old_tags = find_old_tags(projects, file.prev_tag(), &repo, strict_prev)?;
+    let state = CurrentState::new(root, old_tags).with_fetch_opts(repo.fetch_opts());
     let current = Config::new(state, file);
 
     let last_commits = find_last_commits(&current, &repo)?;
     let next = StateWrite::new();
-    let user_prefs = read_env_prefs()?;
 
-    Ok(Mono { current, next, last_commits, repo, user_prefs })
+    Ok(Mono { current, next, last_commits, repo, user_prefs, sparse, held_back: false })
   }
 
-  pub fn check_branch(&self) -> std::result::Result<(), (String, String)> {
-    if let Ok(branch_name) = self.repo.branch_name() {
-      if let Some(branch_name) = branch_name {
-        if let Some(cfg_name) = self.current.branch() {
-          if branch_name != cfg_name {
-            Err((cfg_name.clone(), branch_name.clone()))
-          } else {
-            Ok(())
+  /// Verify that the current branch matches the configured `branch` pattern, if any.
+  pub fn check_branch(&self) -> Result<()> {
+    if let (Ok(branch_name), Some(cfg_name)) = (self.repo.branch_name(), self.current.branch()) {
+      match branch_name {
+        Some(branch_name) => {
+          if !branch_matches(cfg_name, branch_name)? {
+            bail!("Branch name \"{}\" doesn't match \"{}\".", branch_name, cfg_name);
           }
-        } else {
-          Ok(())
         }
-      } else if let Some(cfg_name) = self.current.branch() {
-        Err((cfg_name.clone(), "((No branch))".to_string()))
-      } else {
-        Ok(())
+        None => bail!("Branch name \"((No branch))\" doesn't match \"{}\".", cfg_name)
       }
-    } else {
-      Ok(())
+    }
+    Ok(())
+  }
+
+  /// True if the project may be released from the current branch, according to its own `branch` pattern.
+  fn on_project_branch(&self, proj: &Project) -> Result<bool> {
+    match (self.repo.branch_name(), proj.branch()) {
+      (Ok(Some(branch_name)), Some(pattern)) => branch_matches(pattern, branch_name),
+      (Ok(None), Some(_)) => Ok(false),
+      _ => Ok(true)
     }
   }
 
+  /// True if the prev tag should be advanced on commit: not if only some projects were released.
+  pub fn advances_prev(&self) -> bool { !self.sparse && !self.held_back }
+
   pub fn write_changelogs(&mut self) -> Result<()> { self.next.write_changelogs() }
 
-  pub fn commit(&mut self, advance_prev: bool, pause: bool) -> Result<()> {
+  /// Render the files that `commit` would write into `dir`, without touching the working tree.
+  pub fn preview(&self, dir: &Path) -> Result<Vec<PathBuf>> { self.next.preview(dir) }
+
+  /// Describe what `commit` would do, without doing it.
+  pub fn planned_actions(&self) -> Value {
+    let prev_tag = if self.advances_prev() { Some(self.current.prev_tag()) } else { None };
+    self.next.planned_actions(&self.last_commits, prev_tag)
+  }
+
+  /// Undo the release recorded in the prev tag: revert its commit, delete the tags it created, and move the
+  /// prev tag back to where it was.
+  pub fn rollback(&self) -> Result<ReleaseRecord> {
+    let prev_tag = self.current.prev_tag();
+    let record = match read_annotation(&self.repo, prev_tag)?.into_release() {
+      Some(record) => record,
+      None => bail!("No release recorded at tag \"{}\" to roll back.", prev_tag)
+    };
+
+    if let Some(commit) = record.commit() {
+      self.repo.revert(commit)?;
+    }
+
+    for tag in record.tags() {
+      self.repo.delete_tag(tag)?;
+    }
+
+    if let Some(prior) = record.prior_commit() {
+      let msg = serde_json::to_string(&PrevTagMessage::new(record.prior_versions().clone()))?;
+      self.repo.update_tag_anno(prev_tag, prior, &msg)?;
+    } else {
+      self.repo.delete_tag(prev_tag)?;
+    }
+
+    self.repo.finish_tags()?;
+    Ok(record)
+  }
+
+  /// Copy each of a project's version tags under the `from` prefix to the same commit under the `to` prefix,
+  /// keeping any annotation. If `delete_old`, the old tags are deleted, both locally and on the remote.
+  pub fn migrate_tags(&self, id: &ProjectId, from: &str, to: &str, delete_old: bool) -> Result<Vec<(String, String)>> {
+    let proj = self.get_project(id)?;
+    if from == to {
+      bail!("Old and new tag prefixes are both \"{}\".", to);
+    }
+    if !legal_tag(to) {
+      return Err(VersioError::IllegalPrefix(to.to_string(), false).into());
+    }
+
+    let mut moved = Vec::new();
+    for tag in self.repo.tag_names(Some(&proj.tag_pattern(from, "*")))?.iter().flatten() {
+      let vers = match proj.parse_tag(from, tag) {
+        Some(vers) => vers,
+        None => continue
+      };
+
+      let new_tag = proj.format_tag(to, &vers);
+      let oid = self.repo.revparse_oid(FromTag::new(&format!("{}^{{}}", tag), false))?;
+      if let Some(existing) = self.repo.commit_oid_of(&format!("refs/tags/{}", new_tag)) {
+        if existing != oid {
+          bail!("Tag {} already exists at {}, not at {} like {}.", new_tag, existing, oid, tag);
+        }
+      }
+
+      // exclude the trailing signature, which won't match the new tag
+      match self.repo.annotation_of(tag) {
+        Some(anno) => self.repo.update_tag_anno(&new_tag, &oid, clip_signature(&anno))?,
+        None => self.repo.update_tag(&new_tag, &oid)?
+      }
+      if delete_old {
+        self.repo.delete_tag(tag)?;
+      }
+      moved.push((tag.to_string(), new_tag));
+    }
+
+    self.repo.finish_tags()?;
+    Ok(moved)
+  }
+
+  /// Delete old version tags of the project (or of every project), locally and on the remote. The tags of each
+  /// project's `keep` newest releases are kept, as are the tags of any prereleases newer than those; if
+  /// `prereleases`, only the older prerelease tags are deleted. Tags whose versions can't be parsed are never
+  /// deleted. If `dry`, nothing is deleted. Returns the deleted (or would-be deleted) tags.
+  pub fn prune_tags(
+    &self, id: Option<&ProjectId>, keep: NonZeroUsize, prereleases: bool, dry: bool
+  ) -> Result<Vec<String>> {
+    if let Some(id) = id {
+      self.get_project(id)?;
+    }
+
+    let mut pruned = Vec::new();
+    for proj in self.current.projects().iter().filter(|p| id.map(|id| p.id() == id).unwrap_or(true)) {
+      // The patterns of different majors might overlap, so a tag is only considered once.
+      let mut tags = BTreeSet::new();
+      for fnmatch in tag_fnmatches(proj) {
+        tags.extend(self.repo.tag_names(Some(&fnmatch))?.iter().flatten().map(|t| t.to_string()));
+      }
+      let versions = tags
+        .into_iter()
+        .filter_map(|tag| proj.tag_version(&tag).and_then(|v| PruneVersion::parse(&v)).map(|v| (v, tag)))
+        .collect();
+
+      for tag in prunable_tags(versions, keep, prereleases) {
+        if !dry {
+          self.repo.delete_tag(&tag)?;
+        }
+        pruned.push(tag);
+      }
+    }
+
+    if !dry {
+      self.repo.finish_tags()?;
+    }
+    Ok(pruned)
+  }
+
+  /// Write, commit, tag, and push the release; or if `pause` is given, run the release up to its stage and save the
+  /// rest of the release in its file.
+  pub fn commit(&mut self, advance_prev: bool, pause: Option<Pause>, events: &Events) -> Result<()> {
     self.next.commit(
       &self.repo,
       CommitArgs::new(
@@ -84,13 +239,30 @@ impl Mono {
         self.current.old_tags().current(),
         advance_prev,
         &self.current.hooks(),
-        pause
+        pause,
+        events
+      )
+    )
+  }
+
+  /// Like `commit`, but skip all file writes, commands, and hooks: only create and push the tags.
+  pub fn tag_only(&mut self, advance_prev: bool, events: &Events) -> Result<()> {
+    self.next.tag_only(
+      &self.repo,
+      CommitArgs::new(
+        self.current.prev_tag(),
+        &self.last_commits,
+        self.current.old_tags().current(),
+        advance_prev,
+        &self.current.hooks(),
+        None,
+        events
       )
     )
   }
 
   pub fn get_project(&self, id: &ProjectId) -> Result<&Project> {
-    self.current.get_project(id).ok_or_else(|| bad!("No such project {}", id))
+    self.current.get_project(id).ok_or_else(|| VersioError::UncoveredProject(id.clone()).into())
   }
 
   pub fn write_chains(&mut self, ids: &[(ProjectId, ProjectId)], vers: &HashMap<ProjectId, String>) -> Result<()> {
@@ -107,7 +279,7 @@ impl Mono {
   pub fn diff(&self) -> Result<Analysis> {
     let prev_config = self.current.slice_to_prev(&self.repo)?;
 
-    let curt_annotate = self.current.annotate()?;
+    let curt_annotate = self.current.annotate_par(self.repo.jobs())?;
     let prev_annotate = prev_config.annotate()?;
 
     Ok(analyze(prev_annotate, curt_annotate))
@@ -116,26 +288,36 @@ impl Mono {
   pub fn config(&self) -> &Config<CurrentState> { &self.current }
   pub fn repo(&self) -> &Repo { &self.repo }
 
-  pub fn set_by_id(&mut self, id: &ProjectId, val: &str) -> Result<()> {
-    self.do_project_write(id, move |p, n| p.set_value(n, val))
+  /// The last commit since the prev tag of each project that has one, which is where its new tag is placed.
+  pub fn last_commits(&self) -> &HashMap<ProjectId, String> { &self.last_commits }
+
+  pub fn set_by_id(&mut self, id: &ProjectId, to: &SetTo) -> Result<()> {
+    let val = match to {
+      SetTo::Value(val) => val.clone(),
+      SetTo::Bump(size) => {
+        let curt = self.current.get_value(id)?.ok_or_else(|| VersioError::UncoveredProject(id.clone()))?;
+        size.apply(&curt)?
+      }
+    };
+    self.do_project_write(id, move |p, n| p.set_value(n, &val))
   }
 
-  pub fn set_by_name(&mut self, name: &str, val: &str) -> Result<()> {
+  pub fn set_by_name(&mut self, name: &str, to: &SetTo) -> Result<()> {
     let id = self.current.find_unique(name)?.clone();
-    self.set_by_id(&id, val)
+    self.set_by_id(&id, to)
   }
 
-  pub fn set_by_exact_name(&mut self, name: &str, val: &str) -> Result<()> {
+  pub fn set_by_exact_name(&mut self, name: &str, to: &SetTo) -> Result<()> {
     let id = self.current.find_exact(name)?.clone();
-    self.set_by_id(&id, val)
+    self.set_by_id(&id, to)
   }
 
-  pub fn set_by_only(&mut self, val: &str) -> Result<()> {
+  pub fn set_by_only(&mut self, to: &SetTo) -> Result<()> {
     if self.current.projects().len() != 1 {
       bail!("No solo project.");
     }
     let id = self.current.projects().first().unwrap().id().clone();
-    self.set_by_id(&id, val)
+    self.set_by_id(&id, to)
   }
 
   pub fn forward_by_id(&mut self, id: &ProjectId, val: &str) -> Result<()> {
@@ -143,29 +325,243 @@ impl Mono {
   }
 
   pub async fn write_changelog(
-    &mut self, id: &ProjectId, changelog: &Changelog, new_vers: &str
+    &mut self, id: &ProjectId, changelog: &Changelog, new_vers: &str, compare: Option<String>,
+    cross: HashMap<u32, SharedPr>
   ) -> Result<Option<PathBuf>> {
-    let proj = self.current.get_project(id).ok_or_else(|| bad!("No such project {}", id))?;
-    proj.write_changelog(&mut self.next, changelog, new_vers).await
+    let proj = self.current.get_project(id).ok_or_else(|| VersioError::UncoveredProject(id.clone()))?;
+    let links = proj.release_links(compare).with_cross(cross);
+    proj.write_changelog(&mut self.next, changelog, new_vers, &links).await
+  }
+
+  /// Apply the planned increment of a single project: set its new version (or move its tag forward), and write its
+  /// changelog. The project's target in `targets` (see `release_targets`), if any, replaces the incremented
+  /// version. Returns what was done to the project, and the changelog path if one was written.
+  pub async fn release_project(
+    &mut self, plan: &Plan, id: &ProjectId, targets: &HashMap<ProjectId, String>, locktags: bool
+  ) -> Result<(Released, Option<PathBuf>)> {
+    let (size, changelog) = plan.incrs().get(id).ok_or_else(|| bad!("No plan for project {}.", id))?;
+    let prev_config = self.current.slice_to_prev(&self.repo)?;
+
+    let curt_vers = self
+      .current
+      .get_value(id)
+      .with_context(|| VersioError::MissingVersion(id.clone()))?
+      .ok_or_else(|| VersioError::UncoveredProject(id.clone()))?;
+    let prev_vers = prev_config.get_value(id).with_context(|| format!("Unable to find prev {} value.", id))?;
+    let proj = self.get_project(id)?;
+
+    if size != &Size::Empty && !self.on_project_branch(proj)? {
+      // Leave the project for a release from its own branch, which will need the prev tag where it is.
+      self.held_back = true;
+      return Ok((Released::NoChange { locked: false, prev: prev_vers, curt: curt_vers }, None));
+    }
+
+    let released = if size == &Size::Empty {
+      Released::NoChange { locked: false, prev: prev_vers, curt: curt_vers }
+    } else if let Some(prev_vers) = prev_vers {
+      if size.is_failure() {
+        bail!("Couldn't parse conventional commit(s): {}", failed_hashes(plan));
+      }
+      let target = match targets.get(id) {
+        Some(target) => target.clone(),
+        None => proj.floor_version(size.apply(&prev_vers)?)?
+      };
+
+      if Size::less_than(&curt_vers, &target)? {
+        proj.verify_restrictions(&target)?;
+        Released::Changed { prev: prev_vers, curt: curt_vers, target }
+      } else {
+        proj.verify_restrictions(&curt_vers)?;
+        if locktags {
+          Released::NoChange { locked: true, prev: Some(prev_vers), curt: curt_vers }
+        } else {
+          Released::Forward { prev: prev_vers, curt: curt_vers, target }
+        }
+      }
+    } else {
+      proj.verify_restrictions(&curt_vers)?;
+      if locktags {
+        Released::NoChange { locked: true, prev: None, curt: curt_vers }
+      } else {
+        Released::New { curt: curt_vers }
+      }
+    };
+
+    // The changelog annotates the project's tag, so it's written first: moving the tag then keeps that message.
+    let (compare, cross) = self.release_link_parts(plan, id, changelog, &released, targets)?;
+    let wrote = self.write_changelog(id, changelog, released.version(), compare, cross).await?;
+    match &released {
+      Released::Changed { target, .. } => self.set_by_id(id, &SetTo::Value(target.clone()))?,
+      Released::Forward { curt, .. } | Released::New { curt } => self.forward_by_id(id, curt)?,
+      Released::NoChange { .. } => ()
+    }
+    Ok((released, wrote))
+  }
+
+  /// Write the release notes of all the released projects (in config order) to `RELEASE_NOTES.md` in `dir`, using
+  /// the `release_notes_template`. Nothing is written if no project was released.
+  pub async fn write_release_notes(
+    &mut self, plan: &Plan, released: &[(ProjectId, Released)], targets: &HashMap<ProjectId, String>, dir: &str
+  ) -> Result<Option<PathBuf>> {
+    let mut released: Vec<_> = released.iter().filter(|(_, r)| !matches!(r, Released::NoChange { .. })).collect();
+    released.sort_by_key(|(id, _)| self.current.projects().iter().position(|p| p.id() == id));
+
+    let mut parts = Vec::new();
+    for (id, released) in released {
+      let (_, changelog) = plan.incrs().get(id).ok_or_else(|| bad!("No plan for project {}.", id))?;
+      let (compare, cross) = self.release_link_parts(plan, id, changelog, released, targets)?;
+      parts.push((self.get_project(id)?, changelog, released.version(), compare, cross));
+    }
+    if parts.is_empty() {
+      return Ok(None);
+    }
+
+    let mut releases = Vec::new();
+    for (proj, changelog, version, compare, cross) in parts {
+      let links = proj.release_links(compare).with_cross(cross);
+      releases.push((changelog, ProjLine::from_version(proj, version.to_string())?, version, links));
+    }
+    let tmpl = read_template(self.current.release_notes_template(), None, true).await?;
+    let notes = construct_release_notes(releases, &tmpl)?;
+
+    let path = PathBuf::from_slash(dir).join(RELEASE_NOTES_FILE);
+    self.next.write_shared_file(path.clone(), notes)?;
+    Ok(Some(path))
+  }
+
+  /// The comparison URL and the shared PR links of a project's release.
+  fn release_link_parts(
+    &self, plan: &Plan, id: &ProjectId, changelog: &Changelog, released: &Released,
+    targets: &HashMap<ProjectId, String>
+  ) -> Result<(Option<String>, HashMap<u32, SharedPr>)> {
+    let proj = self.get_project(id)?;
+    let compare = released.prev_version().and_then(|prev| plan.compare_url(proj, prev, released.version()));
+    let cross = match proj.shared_prs() {
+      SharedPrs::Full => HashMap::new(),
+      SharedPrs::Link | SharedPrs::Collapse => self.cross_links(plan, id, changelog, targets)?
+    };
+    Ok((compare, cross))
+  }
+
+  /// Find the other projects that release each of the shared PRs in a project's changelog, along with their new
+  /// versions. The PR is collapsed unless this is the first project (by ID) that releases it.
+  fn cross_links(
+    &self, plan: &Plan, id: &ProjectId, changelog: &Changelog, targets: &HashMap<ProjectId, String>
+  ) -> Result<HashMap<u32, SharedPr>> {
+    let prev_config = self.current.slice_to_prev(&self.repo)?;
+
+    let mut cross = HashMap::new();
+    for entry in changelog.entries() {
+      let pr = match entry {
+        ChangelogEntry::Pr(pr, _) if pr.number() > 0 => pr,
+        _ => continue
+      };
+
+      let projects = plan.pr_projects(pr.number());
+      let mut also = Vec::new();
+      for other in projects.iter().filter(|other| *other != id) {
+        let version = match targets.get(other) {
+          Some(target) => Some(target.clone()),
+          None => self.planned_version(plan, other, &prev_config)?
+        };
+        if let Some(version) = version {
+          also.push(CrossLink::new(other.clone(), self.get_project(other)?.name(), version));
+        }
+      }
+      if !also.is_empty() {
+        cross.insert(pr.number(), SharedPr::new(also, projects.first() != Some(id)));
+      }
+    }
+    Ok(cross)
   }
 
   fn do_project_write<F, T>(&mut self, id: &ProjectId, f: F) -> Result<T>
   where
     F: FnOnce(&Project, &mut StateWrite) -> Result<T>
   {
-    let proj = self.current.get_project(id).ok_or_else(|| bad!("No such project {}", id))?;
+    let proj = self.current.get_project(id).ok_or_else(|| VersioError::UncoveredProject(id.clone()))?;
     f(proj, &mut self.next)
   }
 
-  pub fn check(&self) -> Result<()> {
-    for project in self.current.projects() {
-      project.check(self.current.state_read())?;
+  /// Compare each project's version tags with the version found in the project's files at each tag, and
+  /// report any drift between the two. Projects whose version isn't read from files are skipped.
+  pub fn audit(&self) -> Result<Vec<Drift>> {
+    let mut drifts = Vec::new();
+    for proj in self.current.projects().iter().filter(|p| p.reads_files()) {
+      let mut tags = HashSet::new();
+      for fnmatch in tag_fnmatches(proj) {
+        for tag in self.repo.tag_names(Some(&fnmatch))?.iter().flatten() {
+          tags.insert(tag.to_string());
+        }
+      }
+
+      if let Some(current) = self.current.get_value(proj.id())? {
+        if let Some(tag) = proj.full_version(&current) {
+          if !tags.contains(&tag) {
+            drifts.push(Drift::MissingTag { name: proj.name().to_string(), tag });
+          }
+        }
+      }
+
+      let mut tags: Vec<_> = tags.into_iter().collect();
+      tags.sort();
+      for tag in tags {
+        let tag_version = match proj.tag_version(&tag) {
+          Some(v) => v,
+          None => continue
+        };
+
+        let slice = self.repo.slice(FromTagBuf::new(tag.clone(), false));
+        let state = PrevState::new(slice, OldTags::new(HashMap::new(), HashMap::new()));
+        let name = proj.name().to_string();
+        match proj.get_value(&state) {
+          Ok(file_version) if file_version == tag_version => (),
+          Ok(file_version) => drifts.push(Drift::Mismatch { name, tag, tag_version, file_version }),
+          Err(e) => drifts.push(Drift::Unreadable { name, tag, error: e.to_string() })
+        }
+      }
     }
-    Ok(())
+    Ok(drifts)
   }
 
+  /// Compare the local version tags of each project, and the prev tag, with the tags of the same names on the
+  /// remote, and report each one that's missing from the remote or points elsewhere. If `push`, those tags are
+  /// pushed, replacing the remote tags.
+  pub fn verify_tags(&self, push: bool) -> Result<Vec<TagSync>> {
+    let mut local = BTreeMap::new();
+    for proj in self.current.projects() {
+      for fnmatch in tag_fnmatches(proj) {
+        for (tag, oid) in self.repo.local_tags(&fnmatch)? {
+          if proj.tag_version(&tag).is_some() {
+            local.insert(tag, oid);
+          }
+        }
+      }
+    }
+    local.extend(self.repo.local_tags(self.current.prev_tag())?);
+
+    let remote = self.repo.remote_tags()?;
+    let unsynced: Vec<_> = local
+      .into_iter()
+      .filter_map(|(tag, oid)| match remote.get(&tag) {
+        None => Some(TagSync::Unpushed { tag, oid }),
+        Some(remote) if remote != &oid => Some(TagSync::Different { tag, oid, remote: remote.clone() }),
+        Some(_) => None
+      })
+      .collect();
+
+    if push && !unsynced.is_empty() {
+      let tags: Vec<_> = unsynced.iter().map(|sync| sync.tag().to_string()).collect();
+      self.repo.push_tags(&tags)?;
+    }
+    Ok(unsynced)
+  }
+
+  pub fn check(&self) -> Result<()> { self.current.check(self.repo.jobs()) }
+  pub fn overlaps(&self) -> Result<Vec<Overlap>> { self.current.overlaps() }
+
   pub async fn keyed_files(&self) -> Result<impl Iterator<Item = Result<(String, String)>> + '_> {
-    let changes = self.changes().await?;
+    let changes = self.changes(None).await?;
     let prs = changes.into_groups().into_values().filter(|pr| !pr.best_guess());
 
     let mut vec = Vec::new();
@@ -176,11 +572,162 @@ impl Mono {
     Ok(vec.into_iter().flatten())
   }
 
-  pub async fn build_plan(&self) -> Result<Plan> {
+  /// The files changed since the prev tag, in sorted order, of each project that covers any of them.
+  pub async fn changed_files(&self) -> Result<HashMap<ProjectId, BTreeSet<String>>> {
+    let mut changed: HashMap<ProjectId, BTreeSet<String>> = HashMap::new();
+    for keyed in self.keyed_files().await? {
+      let (_, file) = keyed?;
+      for id in self.covering_projects(&file)? {
+        changed.entry(id).or_default().insert(file.clone());
+      }
+    }
+    Ok(changed)
+  }
+
+  /// Every file changed since the prev tag, in sorted order, with the IDs of the projects that cover it.
+  pub async fn coverage(&self) -> Result<BTreeMap<String, Vec<ProjectId>>> {
+    let mut coverage = BTreeMap::new();
+    for keyed in self.keyed_files().await? {
+      let (_, file) = keyed?;
+      if let Entry::Vacant(entry) = coverage.entry(file) {
+        let ids = self.covering_projects(entry.key())?;
+        entry.insert(ids);
+      }
+    }
+    Ok(coverage)
+  }
+
+  /// The IDs of the projects that cover the given repo-relative path.
+  pub fn covering_projects(&self, path: &str) -> Result<Vec<ProjectId>> {
+    let mut ids = Vec::new();
+    for proj in self.current.projects() {
+      if proj.does_cover(path)? {
+        ids.push(proj.id().clone());
+      }
+    }
+    Ok(ids)
+  }
+
+  /// Build a plan from the changes since the `prev_tag`, or since the given base.
+  pub async fn build_plan(&self, since: Option<&FromTagBuf>) -> Result<Plan> {
+    let changes = self.changes(since).await?;
+    self.plan_changes(changes)
+  }
+
+  /// Record the previous and planned versions of each dependency in the changelogs of the plan, so that they can
+  /// show how the dependency was bumped. This reads the previous config, so only commands that show changelogs
+  /// should call it.
+  pub fn bump_deps(&self, plan: &mut Plan) -> Result<()> {
+    let prev_config = self.current.slice_to_prev(&self.repo)?;
+    let targets = self.release_targets(plan)?;
+
+    let mut bumps: HashMap<ProjectId, DepBump> = HashMap::new();
+    for (_, changelog) in plan.incrs.values() {
+      for entry in changelog.entries() {
+        if let ChangelogEntry::Dep(id, ..) = entry {
+          if bumps.contains_key(id) {
+            continue;
+          }
+          let next = match targets.get(id) {
+            Some(target) => Some(target.clone()),
+            None => self.planned_version(plan, id, &prev_config)?
+          };
+          bumps.insert(id.clone(), DepBump { prev: prev_config.get_value(id)?, next });
+        }
+      }
+    }
+
+    for (_, changelog) in plan.incrs.values_mut() {
+      for entry in &mut changelog.entries {
+        if let ChangelogEntry::Dep(id, _, bump) = entry {
+          if let Some(found) = bumps.get(id) {
+            *bump = found.clone();
+          }
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Build the plan of a release that was already made: the changes between the project's tag for `version` and
+  /// its previous version tag, or the start of history if there is none. Returns the plan and the previous
+  /// version, or `None` if the project has no tag for `version`.
+  pub async fn release_plan_of(&self, id: &ProjectId, version: &str) -> Result<Option<(Plan, Option<String>)>> {
+    let proj = self.get_project(id)?;
+    let tag = match proj.full_version(version) {
+      Some(tag) => tag,
+      None => return Ok(None)
+    };
+
+    let mut tags = Vec::new();
+    for fnmatch in tag_fnmatches(proj) {
+      tags.extend(self.repo.tag_names(Some(&fnmatch))?.iter().flatten().map(|t| t.to_string()));
+    }
+    if !tags.contains(&tag) {
+      return Ok(None);
+    }
+
+    let mut earlier = Vec::new();
+    for vers in tags_to_versions(proj, &tags) {
+      if Size::less_than(&vers, version)? {
+        earlier.push(vers);
+      }
+    }
+    earlier.sort_by(version_sort);
+    let prev = earlier.into_iter().next();
+
+    // Without an earlier tag, the empty base can't be found, so the whole history up to the tag is used.
+    let base = match prev.as_ref().and_then(|prev| proj.full_version(prev)) {
+      Some(prev_tag) => FromTagBuf::new(prev_tag, false),
+      None => FromTagBuf::new(String::new(), true)
+    };
+    let changes = changes(&self.user_prefs.auth, &self.repo, base, tag).await?;
+    Ok(Some((self.plan_changes(changes)?, prev)))
+  }
+
+  /// Render the release notes of every project (in config order) that has a tag for `version`, using the
+  /// `release_notes_template` and the changes that were released in that version. This is useful to regenerate
+  /// the notes of a past release after its template is fixed: nothing is bumped, written, or tagged. Returns
+  /// `None` if no project has a tag for the version.
+  pub async fn notes_of(&self, version: &str) -> Result<Option<String>> {
+    let mut parts = Vec::new();
+    for proj in self.current.projects() {
+      if let Some((plan, prev)) = self.release_plan_of(proj.id(), version).await? {
+        let compare = prev.and_then(|prev| plan.compare_url(proj, &prev, version));
+        parts.push((proj, plan, compare));
+      }
+    }
+
+    let mut releases = Vec::new();
+    for (proj, plan, compare) in &parts {
+      if let Some((_, changelog)) = plan.incrs().get(proj.id()) {
+        let links = proj.release_links(compare.clone());
+        releases.push((changelog, ProjLine::from_version(proj, version.to_string())?, version, links));
+      }
+    }
+    if releases.is_empty() {
+      return Ok(None);
+    }
+
+    let tmpl = read_template(self.current.release_notes_template(), None, true).await?;
+    Ok(Some(construct_release_notes(releases, &tmpl)?))
+  }
+
+  /// Write release notes to `RELEASE_NOTES.md` in `dir` directly, outside of any release.
+  pub fn write_notes_file(&self, dir: &str, notes: &str) -> Result<PathBuf> {
+    let dir = PathBuf::from_slash(dir);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(RELEASE_NOTES_FILE);
+    std::fs::write(&path, notes)?;
+    Ok(path)
+  }
+
+  fn plan_changes(&self, changes: Changes) -> Result<Plan> {
     let mut plan = PlanBuilder::create(&self.repo, self.current.file(), self.user_prefs.auth());
+    let _span = info_span!(target: PROGRESS, "plan").entered();
 
     // Consider the grouped, unsquashed commits to determine project sizing and changelogs.
-    for pr in self.changes().await?.groups().values() {
+    for pr in changes.groups().values() {
       plan.start_pr(pr)?;
       for commit in pr.included_commits() {
         plan.start_commit(commit.clone())?;
@@ -193,8 +740,15 @@ impl Mono {
       plan.finish_pr()?;
     }
 
-    // Some projects might depend on other projects.
+    for (id, size) in self.external_sizes()? {
+      plan.external(id, size);
+    }
+
+    // Some projects are versioned in lockstep with others; some might depend on other projects. Groups are
+    // leveled both before and after deps, so that dependents see group increments, and vice versa.
+    plan.handle_groups()?;
     plan.handle_deps()?;
+    plan.handle_groups()?;
 
     // Sort projects by earliest closed date, mark duplicate commits.
     plan.sort_and_dedup()?;
@@ -202,12 +756,237 @@ impl Mono {
     Ok(plan.build())
   }
 
-  pub async fn changes(&self) -> Result<Changes> {
-    let base = FromTagBuf::new(self.current.prev_tag().to_string(), true);
-    changes(&self.user_prefs.auth, &self.repo, base, "HEAD".into()).await
+  /// The size of the change of each external project (whose version is fetched from a URL) since its last release,
+  /// whose tag records the version that it saw. A project that hasn't been released has a change of size `none`,
+  /// so that its dependents take up its current version.
+  fn external_sizes(&self) -> Result<Vec<(ProjectId, Size)>> {
+    let prev_config = self.current.slice_to_prev(&self.repo)?;
+    let mut sizes = Vec::new();
+    for proj in self.current.projects().iter().filter(|p| p.is_external()) {
+      // An unreachable URL shouldn't hold up the other projects: the external one just isn't released this time.
+      let curt = match self.current.get_value(proj.id()) {
+        Ok(curt) => curt.ok_or_else(|| VersioError::UncoveredProject(proj.id().clone()))?,
+        Err(e) => {
+          warn!("Skipping external project {}: {:#}", proj.id(), e);
+          continue;
+        }
+      };
+      if let Some(size) = Size::since(prev_config.latest_tag(proj.id()).map(|t| t.as_str()), &curt)? {
+        sizes.push((proj.id().clone(), size));
+      }
+    }
+    Ok(sizes)
+  }
+
+  /// Find the shared target version of every project in a version group: the largest target of any member.
+  pub fn group_targets(&self, plan: &Plan) -> Result<HashMap<ProjectId, String>> {
+    let prev_config = self.current.slice_to_prev(&self.repo)?;
+    let mut targets = HashMap::new();
+
+    for group in self.current.file().version_groups() {
+      let mut best: Option<String> = None;
+      for id in group {
+        let cand = match self.planned_version(plan, id, &prev_config)? {
+          Some(cand) => cand,
+          None => continue
+        };
+        best = match best {
+          Some(best) if !Size::less_than(&best, &cand)? => Some(best),
+          _ => Some(cand)
+        };
+      }
+
+      if let Some(best) = best {
+        targets.extend(group.iter().map(|id| (id.clone(), best.clone())));
+      }
+    }
+
+    Ok(targets)
+  }
+
+  /// Find the target version of every project whose version is fixed by other projects: the members of a version
+  /// group (see `group_targets`), and projects with a `derived` version, which is computed from the planned
+  /// versions of their dependencies. A derived project that depends on another derived project must be listed
+  /// after it.
+  pub fn release_targets(&self, plan: &Plan) -> Result<HashMap<ProjectId, String>> {
+    let prev_config = self.current.slice_to_prev(&self.repo)?;
+    let mut targets = self.group_targets(plan)?;
+
+    // An external project is released with the version that it fetched.
+    for proj in self.current.projects().iter().filter(|p| p.is_external() && plan.incrs().contains_key(p.id())) {
+      if let Some(curt) = self.current.get_value(proj.id())? {
+        targets.insert(proj.id().clone(), curt);
+      }
+    }
+
+    for proj in self.current.projects() {
+      let derived = match proj.derived() {
+        Some(derived) => derived,
+        None => continue
+      };
+
+      let mut dep_ids: Vec<_> = proj.depends().keys().collect();
+      dep_ids.sort();
+      let mut children = Vec::new();
+      for dep_id in dep_ids {
+        let version = match targets.get(dep_id) {
+          Some(target) => Some(target.clone()),
+          None => self.planned_version(plan, dep_id, &prev_config)?
+        };
+        if let Some(version) = version {
+          children.push((self.get_project(dep_id)?, version));
+        }
+      }
+
+      let current = self.current.get_value(proj.id())?;
+      let target = derived
+        .derive(&children, current.as_deref())
+        .with_context(|| format!("Can't derive version of {}.", proj.id()))?;
+      targets.insert(proj.id().clone(), target);
+    }
+
+    Ok(targets)
+  }
+
+  /// The projects (in config order) whose planned increment is at least `size_min`.
+  pub fn changing_projects(&self, plan: &Plan, size_min: Size) -> Result<Vec<ChangingProject<'_>>> {
+    let prev_config = self.current.slice_to_prev(&self.repo)?;
+    let targets = self.release_targets(plan)?;
+
+    let mut changing = Vec::new();
+    for proj in self.current.projects() {
+      let size = match plan.incrs().get(proj.id()) {
+        Some((size, _)) if size.is_failure() => bail!("Couldn't parse conventional commit(s): {}", failed_hashes(plan)),
+        Some((size, _)) if *size >= size_min => *size,
+        _ => continue
+      };
+      let target = match targets.get(proj.id()) {
+        Some(target) => target.clone(),
+        None => match self.planned_version(plan, proj.id(), &prev_config)? {
+          Some(target) => target,
+          None => continue
+        }
+      };
+      changing.push((proj, size, prev_config.get_value(proj.id())?, target));
+    }
+    Ok(changing)
+  }
+
+  /// The version of a project once its planned increment is released, ignoring any version group; or `None` if
+  /// there is no such project.
+  pub fn planned_version<S: StateRead>(
+    &self, plan: &Plan, id: &ProjectId, prev_config: &Config<S>
+  ) -> Result<Option<String>> {
+    let curt = match self.current.get_value(id)? {
+      Some(curt) => curt,
+      None => return Ok(None)
+    };
+    let target = match (plan.incrs().get(id), prev_config.get_value(id)?) {
+      (Some((size, _)), Some(prev)) if *size != Size::Empty && !size.is_failure() => {
+        self.get_project(id)?.floor_version(size.apply(&prev)?)?
+      }
+      _ => curt.clone()
+    };
+    Ok(Some(if Size::less_than(&curt, &target)? { target } else { curt }))
   }
+
+  /// Find the changes since the `prev_tag`, or since the given base.
+  pub async fn changes(&self, since: Option<&FromTagBuf>) -> Result<Changes> {
+    let base = match since {
+      Some(since) => since.clone(),
+      None => FromTagBuf::new(self.current.prev_tag().to_string(), true)
+    };
+    let mut changes = changes(&self.user_prefs.auth, &self.repo, base, "HEAD".into()).await?;
+
+    if self.sparse {
+      let projects = self.current.projects();
+      changes.retain_commits(|commit| {
+        commit.files().iter().any(|file| projects.iter().any(|p| p.does_cover(file).unwrap_or(true)))
+      });
+    }
+    Ok(changes)
+  }
+
+  /// Resolve a `--since` argument to a base for changes: either an ISO date (`2021-03-04` or a full RFC 3339
+  /// timestamp), which resolves to the last commit at or before that time, or any ref that git can rev-parse.
+  pub fn since_base(&self, since: &str) -> Result<FromTagBuf> {
+    match parse_date(since, false) {
+      Some(date) => {
+        let oid = self.repo.last_commit_before(&date)?.ok_or_else(|| bad!("No commits found before {}.", since))?;
+        Ok(FromTagBuf::new(oid, false))
+      }
+      None => {
+        if self.repo.commit_oid_of(since).is_none() {
+          bail!("Can't find commit or date \"{}\".", since);
+        }
+        Ok(FromTagBuf::new(since.to_string(), false))
+      }
+    }
+  }
+}
+
+/// The prev tag of a monorepo, found without reading its annotation (as `Mono::open` does), so that a malformed
+/// annotation can still be examined and repaired.
+pub struct PrevTag {
+  repo: Repo,
+  tag: String
+}
+
+impl PrevTag {
+  pub fn here(vcs: VcsState) -> Result<PrevTag> {
+    let root = Repo::find_working_dir(".", *vcs.level(), false)?;
+    let file = ConfigFile::from_dir(&root)?;
+    let prefs = read_env_prefs()?;
+    let repo = Repo::open(".", vcs, file.commit_config().clone())?;
+    let repo = repo.with_auth(prefs.auth().clone()).with_proxy(prefs.proxy().clone());
+    let file = match repo.branch_name() {
+      Ok(branch) => file.for_branch(branch.as_deref())?,
+      Err(_) => file
+    };
+
+    let tag = file.prev_tag().to_string();
+    repo.fetch_tag(&tag)?;
+    Ok(PrevTag { repo, tag })
+  }
+
+  pub fn tag(&self) -> &str { &self.tag }
+
+  /// The annotation of the prev tag, without any trailing signature.
+  pub fn annotation(&self) -> Result<String> {
+    match self.repo.annotation_of(&self.tag) {
+      Some(anno) => Ok(clip_signature(&anno).to_string()),
+      None => bail!("No annotated tag \"{}\" found.", self.tag)
+    }
+  }
+
+  /// Replace the annotation of the prev tag, leaving the tag on the same commit.
+  pub fn set_annotation(&self, msg: &PrevTagMessage) -> Result<()> {
+    let oid = self.repo.commit_oid_of(&format!("refs/tags/{}", self.tag));
+    let oid = oid.ok_or_else(|| bad!("No tag \"{}\" found.", self.tag))?;
+    self.repo.update_tag_anno(&self.tag, &oid, &serde_json::to_string(msg)?)?;
+    self.repo.finish_tags()
+  }
+}
+
+/// Parse an ISO date (`2021-03-04`, at the start of the day, or at its end if `end_of_day`) or a full RFC 3339
+/// timestamp.
+pub fn parse_date(date: &str, end_of_day: bool) -> Option<DateTime<FixedOffset>> {
+  DateTime::parse_from_rfc3339(date).ok().or_else(|| {
+    let (h, m, s) = if end_of_day { (23, 59, 59) } else { (0, 0, 0) };
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+      .ok()
+      .and_then(|d| d.and_hms_opt(h, m, s))
+      .map(|d| FixedOffset::east_opt(0).unwrap().from_utc_datetime(&d))
+  })
 }
 
+/// Read the user's authorization preferences, as overridden by environment variables.
+pub fn read_user_auth() -> Result<Option<Auth>> { read_env_prefs().map(|prefs| prefs.auth) }
+
+/// Read the proxy of the user preferences, which overrides the environment for git remotes, the `git` command, and
+/// the GitHub API alike.
+pub fn read_user_proxy() -> Result<Option<Proxy>> { read_user_prefs().map(|prefs| prefs.proxy) }
+
 /// Read the user preferences file, with some values override with environment variables.
 fn read_env_prefs() -> Result<UserPrefs> {
   read_user_prefs().map(|mut prefs| {
@@ -218,6 +997,12 @@ fn read_env_prefs() -> Result<UserPrefs> {
         prefs.auth = Some(Auth::new(Some(token)));
       }
     }
+    if let Ok(token) = std::env::var("GITEA_TOKEN") {
+      prefs.auth.get_or_insert_with(Default::default).set_gitea_token(Some(token));
+    }
+    if std::env::var(STRICT_PREV_VAR).map(|v| !v.is_empty() && v != "0" && v != "false").unwrap_or(false) {
+      prefs.strict_prev = true;
+    }
     prefs
   })
 }
@@ -232,17 +1017,25 @@ fn read_user_prefs() -> Result<UserPrefs> {
     return Ok(Default::default());
   }
 
-  let user_prefs: UserPrefs = toml::from_str(&std::fs::read_to_string(homefile)?)?;
-  Ok(user_prefs)
+  let user_prefs = toml::from_str(&std::fs::read_to_string(&homefile)?);
+  Ok(user_prefs.unwrap_or_else(|e| {
+    warn!("Ignoring the malformed preferences at \"{}\": {}", homefile.display(), e);
+    Default::default()
+  }))
 }
 
 #[derive(Deserialize, Debug, Default)]
 struct UserPrefs {
-  auth: Option<Auth>
+  auth: Option<Auth>,
+  #[serde(default)]
+  strict_prev: bool,
+  proxy: Option<Proxy>
 }
 
 impl UserPrefs {
   fn auth(&self) -> &Option<Auth> { &self.auth }
+  fn proxy(&self) -> &Option<Proxy> { &self.proxy }
+  fn strict_prev(&self) -> bool { self.strict_prev }
   fn auth_mut(&mut self) -> &mut Option<Auth> { &mut self.auth }
 }
 
@@ -315,18 +1108,87 @@ impl PlanInfo {
   pub fn add_failed_commit(&mut self, failure: CommitInfoBuf) { self.failed_commits.insert(failure); }
 }
 
+/// What a release did to a single project.
+pub enum Released {
+  /// The version was changed to the target version.
+  Changed {
+    /// The version at the previous release.
+    prev: String,
+    /// The version before this release, which may already be ahead of `prev` if it was changed by hand.
+    curt: String,
+    /// The version that the project was changed to.
+    target: String
+  },
+  /// The version was already at or beyond the target version, so the project's tag was moved forward.
+  Forward {
+    /// The version at the previous release.
+    prev: String,
+    /// The version of the project, which was left as is.
+    curt: String,
+    /// The version that the release planned, which `curt` already meets.
+    target: String
+  },
+  /// The project had no previous version, so the project's tag was moved forward.
+  New {
+    /// The version of the project, which was left as is.
+    curt: String
+  },
+  /// Nothing was done: either there was no change, or the tag wasn't moved because tags were locked.
+  NoChange {
+    /// True if the project's tag would have moved, but tags were locked.
+    locked: bool,
+    /// The version at the previous release, if the project had one.
+    prev: Option<String>,
+    /// The version of the project, which was left as is.
+    curt: String
+  }
+}
+
+impl Released {
+  /// The version of the project after the release.
+  pub fn version(&self) -> &str {
+    match self {
+      Released::Changed { target, .. } => target,
+      Released::Forward { curt, .. } | Released::New { curt } | Released::NoChange { curt, .. } => curt
+    }
+  }
+
+  /// The version of the project before the release, if it had one.
+  pub fn prev_version(&self) -> Option<&str> {
+    match self {
+      Released::Changed { prev, .. } | Released::Forward { prev, .. } => Some(prev),
+      Released::NoChange { prev, .. } => prev.as_deref(),
+      Released::New { .. } => None
+    }
+  }
+}
+
 pub struct Plan {
   incrs: HashMap<ProjectId, (Size, Changelog)>, // proj ID, incr size, changelog
   ineffective: Vec<LoggedPr>,                   // PRs that didn't apply to any project
   chain_writes: Vec<(ProjectId, ProjectId)>,
-  info: PlanInfo
+  info: PlanInfo,
+  web_url: Option<String>,
+  shared: HashMap<u32, Vec<ProjectId>> // PR number, the projects that release it
 }
 
 impl Plan {
   pub fn incrs(&self) -> &HashMap<ProjectId, (Size, Changelog)> { &self.incrs }
+
+  /// The projects (in order of ID) whose changelogs include a PR.
+  pub fn pr_projects(&self, number: u32) -> &[ProjectId] {
+    self.shared.get(&number).map(|p| p.as_slice()).unwrap_or(&[])
+  }
   pub fn ineffective(&self) -> &[LoggedPr] { &self.ineffective }
   pub fn chain_writes(&self) -> &[(ProjectId, ProjectId)] { &self.chain_writes }
   pub fn info(&self) -> &PlanInfo { &self.info }
+
+  /// The GitHub URL that compares the tags of two versions of a project, if the project is tagged and the repo is
+  /// on GitHub.
+  pub fn compare_url(&self, proj: &Project, prev: &str, next: &str) -> Option<String> {
+    let web_url = self.web_url.as_ref()?;
+    Some(format!("{}/compare/{}...{}", web_url, proj.full_version(prev)?, proj.full_version(next)?))
+  }
 }
 
 pub struct Changelog {
@@ -335,7 +1197,30 @@ pub struct Changelog {
 
 pub enum ChangelogEntry {
   Pr(LoggedPr, Size),
-  Dep(ProjectId, String)
+  Dep(ProjectId, String, DepBump)
+}
+
+/// The versions of a depended-on project before and after the release, where they're known.
+#[derive(Default, Clone)]
+pub struct DepBump {
+  prev: Option<String>,
+  next: Option<String>
+}
+
+impl DepBump {
+  pub fn prev(&self) -> Option<&str> { self.prev.as_deref() }
+  pub fn next(&self) -> Option<&str> { self.next.as_deref() }
+
+  /// How the dependency `name` was bumped, such as "Bumped core 1.4.2 → 1.5.0."; or empty if its new version
+  /// isn't known, or is unchanged.
+  pub fn text(&self, name: &str) -> String {
+    match (self.prev(), self.next()) {
+      (_, None) => String::new(),
+      (Some(prev), Some(next)) if prev == next => String::new(),
+      (Some(prev), Some(next)) => format!("Bumped {} {} → {}.", name, prev, next),
+      (None, Some(next)) => format!("Bumped {} {}.", name, next)
+    }
+  }
 }
 
 impl Changelog {
@@ -344,7 +1229,7 @@ impl Changelog {
   pub fn add_entry(&mut self, pr: LoggedPr, size: Size) { self.entries.push(ChangelogEntry::Pr(pr, size)); }
 
   pub fn add_dep(&mut self, id: ProjectId, name: impl ToString) {
-    self.entries.push(ChangelogEntry::Dep(id, name.to_string()));
+    self.entries.push(ChangelogEntry::Dep(id, name.to_string(), DepBump::default()));
   }
 
   pub fn is_empty(&self) -> bool { self.entries.is_empty() }
@@ -353,27 +1238,32 @@ impl Changelog {
 pub struct LoggedPr {
   number: u32,
   title: String,
-  _closed_at: DateTime<FixedOffset>,
+  body: String,
+  closed_at: DateTime<FixedOffset>,
   discovery_order: usize,
   commits: Vec<LoggedCommit>,
   url: Option<String>
 }
 
 impl LoggedPr {
+  pub fn new(
+    number: u32, title: String, body: String, closed_at: DateTime<FixedOffset>, discovery_order: usize,
+    url: Option<String>
+  ) -> LoggedPr {
+    LoggedPr { number, title, body, closed_at, discovery_order, commits: Vec::new(), url }
+  }
+
   pub fn capture(pr: &FullPr, url: Option<String>) -> LoggedPr {
-    LoggedPr {
-      number: pr.number(),
-      title: pr.title().to_string(),
-      _closed_at: *pr.closed_at(),
-      discovery_order: pr.discovery_order(),
-      commits: Vec::new(),
-      url
-    }
+    let (title, body) = (pr.title().to_string(), pr.body().to_string());
+    LoggedPr::new(pr.number(), title, body, *pr.closed_at(), pr.discovery_order(), url)
   }
 
+  pub fn add_commit(&mut self, commit: LoggedCommit) { self.commits.push(commit); }
+
   pub fn number(&self) -> u32 { self.number }
   pub fn title(&self) -> &str { &self.title }
-  pub fn _closed_at(&self) -> &DateTime<FixedOffset> { &self._closed_at }
+  pub fn body(&self) -> &str { &self.body }
+  pub fn closed_at(&self) -> &DateTime<FixedOffset> { &self.closed_at }
   pub fn discovery_order(&self) -> usize { self.discovery_order }
   pub fn commits(&self) -> &[LoggedCommit] { &self.commits }
   pub fn url(&self) -> &Option<String> { &self.url }
@@ -386,12 +1276,46 @@ pub struct LoggedCommit {
   size: Size,
   applies: bool,
   duplicate: bool,
-  url: Option<String>
+  url: Option<String>,
+  contributors: Vec<Contributor>,
+  kind: String,
+  size_entry: String,
+  scoped: bool,
+  files: Vec<String>
 }
 
 impl LoggedCommit {
-  pub fn new(oid: String, summary: String, message: String, size: Size, url: Option<String>) -> LoggedCommit {
-    LoggedCommit { oid, summary, message, size, applies: false, duplicate: false, url }
+  pub fn new(
+    oid: String, summary: String, message: String, size: Size, url: Option<String>, contributors: Vec<Contributor>
+  ) -> LoggedCommit {
+    LoggedCommit {
+      oid,
+      summary,
+      message,
+      size,
+      applies: false,
+      duplicate: false,
+      url,
+      contributors,
+      kind: String::new(),
+      size_entry: String::new(),
+      scoped: false,
+      files: Vec::new()
+    }
+  }
+
+  /// Mark whether the commit applies because its scope names the project.
+  pub fn with_scoped(mut self, scoped: bool) -> LoggedCommit {
+    self.applies = scoped;
+    self.scoped = scoped;
+    self
+  }
+
+  /// Record why the commit has its size: the kind of the commit, and the entry of the sizes map that matched it.
+  pub fn with_kind(mut self, kind: String, size_entry: String) -> LoggedCommit {
+    self.kind = kind;
+    self.size_entry = size_entry;
+    self
   }
 
   pub fn applies(&self) -> bool { self.applies }
@@ -402,12 +1326,25 @@ impl LoggedCommit {
   pub fn message(&self) -> &str { &self.message }
   pub fn size(&self) -> Size { self.size }
   pub fn url(&self) -> &Option<String> { &self.url }
+  pub fn kind(&self) -> &str { &self.kind }
+  pub fn size_entry(&self) -> &str { &self.size_entry }
+
+  /// The author of the commit, followed by its co-authors.
+  pub fn contributors(&self) -> &[Contributor] { &self.contributors }
+
+  /// If the commit applies because its scope names the project, instead of because of the files it changed.
+  pub fn scoped(&self) -> bool { self.scoped }
+
+  /// The files of the commit that the project covers.
+  pub fn files(&self) -> &[String] { &self.files }
 }
 
 struct PlanBuilder<'s> {
   on_pr_sizes: HashMap<ProjectId, LoggedPr>,
   on_ineffective: Option<LoggedPr>,
   on_commit: Option<CommitInfoBuf>,
+  on_scoped: Option<Vec<ProjectId>>,
+  // projects declaring the scope of the current commit, if any
   prev: Slicer<'s>,
   current: &'s ConfigFile,
   incrs: HashMap<ProjectId, (Size, Changelog)>,
@@ -421,12 +1358,13 @@ struct PlanBuilder<'s> {
 
 impl<'s> PlanBuilder<'s> {
   fn create(repo: &'s Repo, current: &'s ConfigFile, auth: &Option<Auth>) -> PlanBuilder<'s> {
-    let prev = Slicer::init(repo);
+    let prev = Slicer::init(repo, current);
     let github_info = repo.github_info(auth).ok();
     PlanBuilder {
       on_pr_sizes: HashMap::new(),
       on_ineffective: None,
       on_commit: None,
+      on_scoped: None,
       prev,
       current,
       incrs: HashMap::new(),
@@ -443,10 +1381,8 @@ impl<'s> PlanBuilder<'s> {
       pr.number(),
       self.github_info.as_ref().map(|gh| gh.repo_name()).unwrap_or("<no gh>")
     );
-    let url = self
-      .github_info
-      .as_ref()
-      .map(|gh| format!("https://github.com/{}/{}/pull/{}", gh.owner_name(), gh.repo_name(), pr.number()));
+    debug!(target: PROGRESS, pr = pr.number(), "planning PR");
+    let url = self.github_info.as_ref().map(|gh| gh.pr_url(pr.number()));
     self.on_pr_sizes =
       self.current.projects().iter().map(|p| (p.id().clone(), LoggedPr::capture(pr, url.clone()))).collect();
     self.on_ineffective = Some(LoggedPr::capture(pr, url));
@@ -457,9 +1393,10 @@ impl<'s> PlanBuilder<'s> {
     trace!("planning PR done.");
     let mut found = false;
     for (proj_id, logged_pr) in self.on_pr_sizes.drain() {
-      let (size, changelog) = self.incrs.entry(proj_id).or_insert((Size::Empty, Changelog::empty()));
+      let (size, changelog) = self.incrs.entry(proj_id.clone()).or_insert((Size::Empty, Changelog::empty()));
       let pr_size = logged_pr.commits.iter().filter(|c| c.applies).map(|c| c.size).max();
       if let Some(pr_size) = pr_size {
+        info!(target: PROGRESS, project_id = %proj_id, pr = logged_pr.number(), size = %pr_size, "sized PR");
         found = true;
         *size = max(*size, pr_size);
         changelog.add_entry(logged_pr, pr_size);
@@ -479,21 +1416,29 @@ impl<'s> PlanBuilder<'s> {
     let kind = commit.kind().to_string();
     let summary = commit.summary().to_string();
     let msg = commit.message().to_string();
+    let contributors = commit.contributors();
     self.prev.slice_to(FromTagBuf::new(id.clone(), false))?;
 
     let url = self
       .github_info
       .as_ref()
-      .map(|gh| format!("https://github.com/{}/{}/commit/{}", gh.owner_name(), gh.repo_name(), id));
+      .map(|gh| format!("{}/commit/{}", gh.web_url(), id));
     trace!("  planning commit {} at {}.", id, url.as_deref().unwrap_or("<no url>"));
+    debug!(target: PROGRESS, commit = %id, "planning commit");
 
+    self.on_scoped = scoped_projects(self.current.projects(), &commit);
     for (proj_id, logged_pr) in &mut self.on_pr_sizes {
       if let Some(cur_project) = self.current.get_project(proj_id) {
-        let size = cur_project.size(self.current.sizes(), &kind)?;
+        let (size_entry, size) = cur_project.size_entry(self.current.sizes(), &kind)?;
         if size.is_failure() {
           self.info.add_failed_commit(commit.clone());
         }
-        logged_pr.commits.push(LoggedCommit::new(id.clone(), summary.clone(), msg.clone(), size, url.clone()));
+        let scoped = self.on_scoped.as_ref().map(|ids| ids.contains(proj_id)).unwrap_or(false);
+        let logged =
+          LoggedCommit::new(id.clone(), summary.clone(), msg.clone(), size, url.clone(), contributors.clone())
+            .with_kind(kind.clone(), size_entry.to_string())
+            .with_scoped(scoped);
+        logged_pr.add_commit(logged);
       }
     }
 
@@ -510,14 +1455,24 @@ impl<'s> PlanBuilder<'s> {
     trace!("    planning file {}.", path);
     let commit = self.on_commit.as_ref().ok_or_else(|| bad!("Not on a commit"))?;
     let commit_id = commit.id();
+    debug!(target: PROGRESS, commit = commit_id, file = path, "planning file");
+
+    if self.on_scoped.is_some() {
+      trace!("      scoped commit: files ignored.");
+      return Ok(());
+    }
 
     for prev_project in self.prev.file()?.projects() {
-      if let Some(logged_pr) = self.on_pr_sizes.get_mut(prev_project.id()) {
-        trace!("      vs current project {}.", prev_project.id());
+      let cur_id = self.current.current_project(prev_project).map(|p| p.id()).unwrap_or(prev_project.id());
+      if let Some(logged_pr) = self.on_pr_sizes.get_mut(cur_id) {
+        trace!("      vs current project {}.", cur_id);
         if prev_project.does_cover(path)? {
-          let LoggedCommit { applies, .. } = logged_pr.commits.iter_mut().find(|c| c.oid == commit_id).unwrap();
+          let LoggedCommit { applies, files, .. } =
+            logged_pr.commits.iter_mut().find(|c| c.oid == commit_id).unwrap();
           *applies = true;
+          files.push(path.to_string());
           trace!("        covered.");
+          debug!(target: PROGRESS, project_id = %cur_id, commit = commit_id, file = path, "covered file");
         } else {
           trace!("        not covered.");
         }
@@ -530,6 +1485,12 @@ impl<'s> PlanBuilder<'s> {
 
   pub fn finish_file(&mut self) -> Result<()> { Ok(()) }
 
+  /// Record the change of an external project, which has no commits of its own.
+  pub fn external(&mut self, id: ProjectId, size: Size) {
+    let (val, _) = self.incrs.entry(id).or_insert((Size::Empty, Changelog::empty()));
+    *val = max(*val, size);
+  }
+
   pub fn handle_deps(&mut self) -> Result<()> {
     // Use a modified Kahn's algorithm to traverse deps in order.
     let mut queue: VecDeque<ProjectId> = VecDeque::new();
@@ -555,6 +1516,7 @@ impl<'s> PlanBuilder<'s> {
           if converted_size > Size::Empty {
             let (val, ch_log) = &mut self.incrs.entry(depd_id.clone()).or_insert((Size::Empty, Changelog::empty()));
             *val = max(*val, converted_size);
+            info!(target: PROGRESS, project_id = %depd_id, dep_id = %id, size = %converted_size, "sized dependency");
             let project = self.current.projects().iter().find(|p| p.id() == &id).unwrap();
             ch_log.add_dep(id.clone(), project.name());
           }
@@ -571,6 +1533,19 @@ impl<'s> PlanBuilder<'s> {
     Ok(())
   }
 
+  pub fn handle_groups(&mut self) -> Result<()> {
+    for group in self.current.version_groups() {
+      let size = group.iter().filter_map(|id| self.incrs.get(id)).map(|(size, _)| *size).max();
+      if let Some(size) = size.filter(|size| *size > Size::Empty) {
+        for id in group.iter().filter(|id| self.current.get_project(id).is_some()) {
+          let (val, _) = self.incrs.entry(id.clone()).or_insert((Size::Empty, Changelog::empty()));
+          *val = max(*val, size);
+        }
+      }
+    }
+    Ok(())
+  }
+
   pub fn sort_and_dedup(&mut self) -> Result<()> {
     for (.., changelog) in self.incrs.values_mut() {
       changelog.entries.sort_by(|entry1, entry2| match entry1 {
@@ -578,8 +1553,8 @@ impl<'s> PlanBuilder<'s> {
           ChangelogEntry::Pr(pr2, _) => pr2.discovery_order().cmp(&pr1.discovery_order()),
           _ => Ordering::Greater
         },
-        ChangelogEntry::Dep(pr_id1, _) => match entry2 {
-          ChangelogEntry::Dep(pr_id2, _) => pr_id1.to_string().cmp(&pr_id2.to_string()),
+        ChangelogEntry::Dep(pr_id1, ..) => match entry2 {
+          ChangelogEntry::Dep(pr_id2, ..) => pr_id1.to_string().cmp(&pr_id2.to_string()),
           _ => Ordering::Less
         }
       });
@@ -601,12 +1576,46 @@ impl<'s> PlanBuilder<'s> {
   }
 
   pub fn build(self) -> Plan {
-    Plan { incrs: self.incrs, ineffective: self.ineffective, chain_writes: self.chain_writes, info: self.info }
+    for (id, (size, _)) in &self.incrs {
+      info!(target: PROGRESS, project_id = %id, size = %size, "planned");
+    }
+
+    let web_url = self.github_info.as_ref().map(|gh| gh.web_url());
+
+    let mut shared: HashMap<u32, Vec<ProjectId>> = HashMap::new();
+    for (id, (_, changelog)) in &self.incrs {
+      for entry in changelog.entries() {
+        if let ChangelogEntry::Pr(pr, _) = entry {
+          if pr.number() > 0 && pr.commits().iter().any(|c| c.included()) {
+            shared.entry(pr.number()).or_default().push(id.clone());
+          }
+        }
+      }
+    }
+    for projects in shared.values_mut() {
+      projects.sort();
+    }
+
+    let (incrs, ineffective, chain_writes, info) = (self.incrs, self.ineffective, self.chain_writes, self.info);
+    Plan { incrs, ineffective, chain_writes, info, web_url, shared }
+  }
+}
+
+/// The projects that declare the conventional-commit scope of `commit`, or `None` if the commit has no scope or
+/// no project declares it (in which case the commit is attributed by the files it touches).
+fn scoped_projects(projects: &[Project], commit: &CommitInfoBuf) -> Option<Vec<ProjectId>> {
+  let scope = commit.scope()?;
+  let ids: Vec<_> = projects.iter().filter(|p| p.has_scope(scope)).map(|p| p.id().clone()).collect();
+  if ids.is_empty() {
+    None
+  } else {
+    Some(ids)
   }
 }
 
 struct LastCommitBuilder<'s, C: StateRead> {
   on_line_commit: Option<String>,
+  on_line_scoped: bool,
   last_commits: HashMap<ProjectId, String>,
   prev: Slicer<'s>,
   current: &'s Config<C>
@@ -614,13 +1623,18 @@ struct LastCommitBuilder<'s, C: StateRead> {
 
 impl<'s, C: StateRead> LastCommitBuilder<'s, C> {
   fn create(repo: &'s Repo, current: &'s Config<C>) -> LastCommitBuilder<'s, C> {
-    let prev = Slicer::init(repo);
-    LastCommitBuilder { on_line_commit: None, last_commits: HashMap::new(), prev, current }
+    let prev = Slicer::init(repo, current.file());
+    LastCommitBuilder { on_line_commit: None, on_line_scoped: false, last_commits: HashMap::new(), prev, current }
   }
 
   pub fn start_line_commit(&mut self, commit: &CommitInfoBuf) -> Result<()> {
     let id = commit.id().to_string();
     self.on_line_commit = Some(id.clone());
+    let scoped = scoped_projects(self.current.projects(), commit);
+    self.on_line_scoped = scoped.is_some();
+    for proj_id in scoped.unwrap_or_default() {
+      self.last_commits.entry(proj_id).or_insert_with(|| id.clone());
+    }
     self.prev.slice_to(FromTagBuf::new(id, false))?;
     Ok(())
   }
@@ -629,14 +1643,16 @@ impl<'s, C: StateRead> LastCommitBuilder<'s, C> {
 
   pub fn start_line_file(&mut self, path: &str) -> Result<()> {
     let commit_id = self.on_line_commit.as_ref().ok_or_else(|| bad!("Not on a line commit"))?;
+    if self.on_line_scoped {
+      return Ok(());
+    }
 
     for prev_project in self.prev.file()?.projects() {
-      let proj_id = prev_project.id();
-      if self.current.get_project(proj_id).is_some()
-        && prev_project.does_cover(path)?
-        && !self.last_commits.contains_key(proj_id)
-      {
-        self.last_commits.insert(proj_id.clone(), commit_id.clone());
+      if let Some(cur_project) = self.current.file().current_project(prev_project) {
+        let proj_id = cur_project.id();
+        if !self.last_commits.contains_key(proj_id) && prev_project.does_cover(path)? {
+          self.last_commits.insert(proj_id.clone(), commit_id.clone());
+        }
       }
     }
     Ok(())
@@ -647,33 +1663,106 @@ impl<'s, C: StateRead> LastCommitBuilder<'s, C> {
   pub fn build(self) -> Result<HashMap<ProjectId, String>> { Ok(self.last_commits) }
 }
 
-#[allow(clippy::large_enum_variant)]
-enum Slicer<'r> {
-  Orig(&'r Repo),
-  Slice(FsConfig<PrevFiles<'r>>)
+/// The config at a previous commit, which is re-sliced as commits are walked.
+struct Slicer<'r> {
+  repo: &'r Repo,
+  current: &'r ConfigFile,
+  config: Option<ConfigFile>,
+  cache: ConfigCache
 }
 
 impl<'r> Slicer<'r> {
-  pub fn init(repo: &'r Repo) -> Slicer<'r> { Slicer::Orig(repo) }
+  pub fn init(repo: &'r Repo, current: &'r ConfigFile) -> Slicer<'r> {
+    Slicer { repo, current, config: None, cache: ConfigCache::new() }
+  }
 
   pub fn file(&self) -> Result<&ConfigFile> {
-    match self {
-      Slicer::Slice(fsc) => Ok(fsc.file()),
-      _ => err!("Slicer not sliced")
+    match &self.config {
+      Some(file) => Ok(file),
+      None => err!("Slicer not sliced")
     }
   }
 
+  /// Slice to the config at the given commit. If that config can't be parsed (say, a broken `.versio.yaml` was
+  /// committed and later fixed), keep the config of the commit walked just before this one, or the current
+  /// config if this is the first: otherwise the broken commit would fail every plan that walks over it. Any other
+  /// failure, such as being unable to read the commit, is returned.
   pub fn slice_to(&mut self, id: FromTagBuf) -> Result<()> {
-    *self = Slicer::Slice(match self {
-      Slicer::Orig(repo) => FsConfig::from_slice(repo.slice(id))?,
-      Slicer::Slice(fsc) => fsc.slice_to(id)?
-    });
+    let commit = id.tag().to_string();
+    match FsConfig::from_slice(self.repo.slice(id), &mut self.cache) {
+      Ok(fsc) => self.config = Some(fsc.into_file()),
+      Err(e) if !is_malformed_config(&e) => return Err(e),
+      Err(e) if self.config.is_some() => {
+        warn!("Can't read the config at {}, using the last readable config instead: {:#}", commit, e);
+      }
+      Err(e) => {
+        warn!("Can't read the config at {}, using the current config instead: {:#}", commit, e);
+        self.config = Some(self.current.clone());
+      }
+    }
     Ok(())
   }
 }
 
-fn find_old_tags<'s, I: Iterator<Item = &'s Project>>(projects: I, prev_tag: &str, repo: &Repo) -> Result<OldTags> {
-  let mut by_proj_oid = HashMap::new(); // Map<proj_id, Map<oid, Vec<tag>>>
+fn is_malformed_config(e: &Error) -> bool {
+  matches!(e.downcast_ref::<VersioError>(), Some(VersioError::MalformedConfig))
+}
+
+/// A project that will be released, with its size, its previous version (if any), and the version it will be
+/// released with.
+pub type ChangingProject<'p> = (&'p Project, Size, Option<String>, String);
+
+/// The version tags of each project, by the commit that they point to.
+type TagsByOid<'s> = HashMap<ProjectId, (&'s Project, HashMap<String, Vec<String>>)>;
+
+fn find_old_tags<'s, I: Iterator<Item = &'s Project>>(
+  projects: I, prev_tag: &str, repo: &Repo, strict_prev: bool
+) -> Result<OldTags> {
+  let (mut by_proj_oid, proj_ids) = find_tags_by_oid(projects, repo)?;
+
+  let mut current = HashMap::new();
+  for commit_oid in repo.commits_to_head(FromTag::new(prev_tag, true), false)?.map(|c| c.map(|c| c.id())) {
+    take_tags_at(&mut by_proj_oid, &commit_oid?, &mut current);
+  }
+
+  // A malformed annotation shouldn't stop every command: fall back to the versions found from tags.
+  let prev = match pull_from_annotation(repo, prev_tag) {
+    Ok(prev) => prev,
+    Err(e) if !strict_prev => {
+      warn!("Ignoring the annotation of tag \"{}\", using only versions from tags: {:#}", prev_tag, e);
+      HashMap::new()
+    }
+    Err(e) => return Err(e)
+  };
+  fill_from_prev(&prev, &proj_ids, &mut current);
+
+  let old_tags = OldTags::new(current, prev);
+  trace!("Found old tags: {:?}", old_tags);
+  Ok(old_tags)
+}
+
+/// Find the old tags as they were at a past commit: the latest version tag of each project at or before `at`.
+/// Projects that have no version tag there have no old version, since the prev tag annotation doesn't apply.
+pub fn find_old_tags_at<'s, I: Iterator<Item = &'s Project>>(projects: I, repo: &Repo, at: &str) -> Result<OldTags> {
+  let (mut by_proj_oid, _) = find_tags_by_oid(projects, repo)?;
+
+  let mut current = HashMap::new();
+  for commit_oid in repo.ancestor_oids(at)? {
+    if by_proj_oid.is_empty() {
+      break;
+    }
+    take_tags_at(&mut by_proj_oid, &commit_oid?, &mut current);
+  }
+
+  let old_tags = OldTags::new(current, HashMap::new());
+  trace!("Found old tags at {}: {:?}", at, old_tags);
+  Ok(old_tags)
+}
+
+fn find_tags_by_oid<'s, I: Iterator<Item = &'s Project>>(
+  projects: I, repo: &Repo
+) -> Result<(TagsByOid<'s>, HashSet<ProjectId>)> {
+  let mut by_proj_oid = HashMap::new();
   let mut proj_ids = HashSet::new();
 
   // TODO(performance): tags requests on the server can take a while. We should combine all tag patterns for all
@@ -687,48 +1776,79 @@ fn find_old_tags<'s, I: Iterator<Item = &'s Project>>(projects: I, prev_tag: &st
       for tag in repo.tag_names(Some(fnmatch.as_str()))?.iter().flatten() {
         let oid = repo.revparse_oid(FromTag::new(&format!("{}^{{}}", tag), false))?;
         trace!("Found proj {} tag {} at {}.", proj.id(), tag, oid);
-        let by_id = by_proj_oid
-          .entry(proj.id().clone())
-          .or_insert_with(|| (proj.tag_prefix_separator().to_string(), HashMap::new()));
+        let by_id = by_proj_oid.entry(proj.id().clone()).or_insert_with(|| (proj, HashMap::new()));
         by_id.1.entry(oid).or_insert_with(Vec::new).push(tag.to_string());
       }
     }
   }
+  Ok((by_proj_oid, proj_ids))
+}
 
-  let mut current = HashMap::new();
-  for commit_oid in repo.commits_to_head(FromTag::new(prev_tag, true), false)?.map(|c| c.map(|c| c.id())) {
-    let commit_oid = commit_oid?;
-    by_proj_oid.retain(|proj_id, (sep, by_id)| {
-      if let Some(tags) = by_id.remove(&commit_oid) {
-        let mut versions = tags_to_versions(sep, &tags);
-        versions.sort_unstable_by(version_sort);
-        current.insert(proj_id.clone(), versions[0].clone());
-        false
-      } else {
-        true
-      }
-    });
+/// Take the highest version tag of every project that has one at `commit_oid` as its current version, and stop
+/// looking for that project's tags.
+fn take_tags_at(by_proj_oid: &mut TagsByOid, commit_oid: &str, current: &mut HashMap<ProjectId, String>) {
+  by_proj_oid.retain(|proj_id, (proj, by_id)| {
+    if let Some(tags) = by_id.remove(commit_oid) {
+      let mut versions = tags_to_versions(proj, &tags);
+      versions.sort_unstable_by(version_sort);
+      current.insert(proj_id.clone(), versions[0].clone());
+      false
+    } else {
+      true
+    }
+  });
+}
+
+fn pull_from_annotation(repo: &Repo, prev_tag: &str) -> Result<HashMap<ProjectId, String>> {
+  read_annotation(repo, prev_tag).map(|m| m.into_versions())
+}
+
+fn read_annotation(repo: &Repo, prev_tag: &str) -> Result<PrevTagMessage> {
+  match repo.annotation_of(prev_tag) {
+    Some(anno) => PrevTagMessage::parse(clip_signature(&anno))
+      .with_context(|| format!("Can't read tag \"{}\": see `versio prev get`.", prev_tag)),
+    None => Ok(Default::default())
   }
+}
 
-  let prev = pull_from_annotation(repo, prev_tag)?;
-  fill_from_prev(&prev, &proj_ids, &mut current);
+/// Exclude the trailing signature (if any) of a tag annotation.
+fn clip_signature(anno: &str) -> &str {
+  match anno.find("\n-----BEGIN PGP SIGNATURE-----") {
+    Some(p) => &anno[.. p],
+    None => anno
+  }
+}
 
-  let old_tags = OldTags::new(current, prev);
-  trace!("Found old tags: {:?}", old_tags);
-  Ok(old_tags)
+/// The new version of a project: either an exact value, or an increment from its current version.
+pub enum SetTo {
+  Value(String),
+  Bump(Size)
 }
 
-fn pull_from_annotation(repo: &Repo, prev_tag: &str) -> Result<HashMap<ProjectId, String>> {
-  repo
-    .annotation_of(prev_tag)
-    .map(|anno| {
-      // search for and exclude trailing signature
-      let clip = if let Some(p) = anno.find("\n-----BEGIN PGP SIGNATURE-----") { &anno[.. p] } else { anno.as_str() };
-      serde_json::from_str::<PrevTagMessage>(clip)
-    })
-    .transpose()
-    .map_err(|e| e.into())
-    .map(|o| o.unwrap_or_default().into_versions())
+/// A disagreement between a project's tags and the version recorded in its files.
+pub enum Drift {
+  /// The project's current version has no matching tag.
+  MissingTag { name: String, tag: String },
+  /// The tag points at a commit where the project's files record a different version.
+  Mismatch { name: String, tag: String, tag_version: String, file_version: String },
+  /// The version couldn't be read from the project's files at the tag.
+  Unreadable { name: String, tag: String, error: String }
+}
+
+/// A local tag that doesn't match the remote.
+pub enum TagSync {
+  /// The tag isn't on the remote at all, usually because a push failed.
+  Unpushed { tag: String, oid: String },
+  /// The remote tag of the same name points at a different oid.
+  Different { tag: String, oid: String, remote: String }
+}
+
+impl TagSync {
+  pub fn tag(&self) -> &str {
+    match self {
+      TagSync::Unpushed { tag, .. } | TagSync::Different { tag, .. } => tag
+    }
+  }
 }
 
 fn fill_from_prev(
@@ -743,37 +1863,92 @@ fn fill_from_prev(
   }
 }
 
-/// Construct a fnmatch pattern for a project that can be used to retrieve the project's tags.
+/// Construct the fnmatch patterns for a project that can be used to retrieve the project's tags, according to
+/// its `tag_format`.
 ///
-/// This will return an empty iterator if the project doesn't have a tag_prefix. The resulting patterns are
-/// usable by both `Repository::tag_names` and as a git fetch refspec `refs/tags/{pattern}`.
-fn tag_fnmatches(proj: &Project) -> impl Iterator<Item = String> + '_ {
-  let majors = proj.tag_majors();
-
-  let majors_v = if let Some(majors) = majors {
-    E2::A(majors.iter().map(|major| format!("v{}.*", major)))
-  } else {
-    E2::B(once("v*".to_string()))
+/// This will return no patterns if the project doesn't have a tag_prefix. The resulting patterns are usable by
+/// both `Repository::tag_names` and as a git fetch refspec `refs/tags/{pattern}`.
+fn tag_fnmatches(proj: &Project) -> Vec<String> {
+  let tag_prefix = match proj.tag_prefix() {
+    Some(tag_prefix) => tag_prefix,
+    None => return Vec::new()
   };
 
-  let sep = proj.tag_prefix_separator();
-  let tag_prefix = proj.tag_prefix().as_deref();
-  match tag_prefix {
-    None => E3::A(empty()),
-    Some("") => E3::B(majors_v),
-    Some(pref) => E3::C(majors_v.map(move |major_v| format!("{}{}{}", pref, sep, major_v)))
+  match proj.tag_majors() {
+    Some(majors) => majors.iter().map(|major| proj.tag_pattern(tag_prefix, &format!("{}.*", major))).collect(),
+    None => vec![proj.tag_pattern(tag_prefix, "*")]
+  }
+}
+
+/// A version that may have a prerelease (`1.2.3-rc.1`), ordered by semver precedence. Build metadata is ignored.
+#[derive(PartialEq, Eq)]
+struct PruneVersion {
+  core: [u32; 3],
+  pre: Option<Vec<String>>
+}
+
+impl PruneVersion {
+  fn parse(vers: &str) -> Option<PruneVersion> {
+    let vers = vers.split('+').next().unwrap_or(vers);
+    let (core, pre) = match vers.split_once('-') {
+      Some((core, pre)) => (core, Some(pre.split('.').map(|p| p.to_string()).collect())),
+      None => (vers, None)
+    };
+    Size::parts(core).ok().map(|core| PruneVersion { core, pre })
   }
 }
 
-fn tags_to_versions(prefix_sep: &str, tags: &[String]) -> Vec<String> {
-  tags
-    .iter()
-    .map(|tag| {
-      let v = tag.rfind(prefix_sep).map(|d| d + 1).unwrap_or(0);
-      tag[v + 1 ..].to_string()
+impl Ord for PruneVersion {
+  fn cmp(&self, other: &PruneVersion) -> Ordering {
+    self.core.cmp(&other.core).then_with(|| match (&self.pre, &other.pre) {
+      (None, None) => Ordering::Equal,
+      (None, Some(_)) => Ordering::Greater,
+      (Some(_), None) => Ordering::Less,
+      (Some(p1), Some(p2)) => {
+        for (i1, i2) in p1.iter().zip(p2.iter()) {
+          let ord = match (i1.parse::<u64>(), i2.parse::<u64>()) {
+            (Ok(n1), Ok(n2)) => n1.cmp(&n2),
+            (Ok(_), Err(_)) => Ordering::Less,
+            (Err(_), Ok(_)) => Ordering::Greater,
+            (Err(_), Err(_)) => i1.cmp(i2)
+          };
+          if ord != Ordering::Equal {
+            return ord;
+          }
+        }
+        p1.len().cmp(&p2.len())
+      }
     })
-    .filter(|v| Size::parts(v).is_ok())
-    .collect()
+  }
+}
+
+impl PartialOrd for PruneVersion {
+  fn partial_cmp(&self, other: &PruneVersion) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+/// The tags to prune out of the tags of a project's versions: all but those of the `keep` newest releases, and of
+/// any prereleases newer than those. If `prereleases`, only prerelease tags are pruned.
+fn prunable_tags(mut versions: Vec<(PruneVersion, String)>, keep: NonZeroUsize, prereleases: bool) -> Vec<String> {
+  versions.sort_by(|(v1, _), (v2, _)| v2.cmp(v1));
+
+  let mut releases = 0;
+  let mut prunable = Vec::new();
+  for (vers, tag) in versions {
+    if releases < keep.get() {
+      if vers.pre.is_none() {
+        releases += 1;
+      }
+      continue;
+    }
+    if !prereleases || vers.pre.is_some() {
+      prunable.push(tag);
+    }
+  }
+  prunable
+}
+
+fn tags_to_versions(proj: &Project, tags: &[String]) -> Vec<String> {
+  tags.iter().filter_map(|tag| proj.tag_version(tag)).collect()
 }
 
 #[allow(clippy::ptr_arg)]
@@ -807,3 +1982,214 @@ fn version_sort(a: &String, b: &String) -> Ordering {
     Ordering::Equal
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::{parse_date, prunable_tags, ConfigFile, DepBump, Events, Mono, PlanBuilder, ProjectId, PruneVersion, Repo,
+              SetTo, Size, VcsState};
+  use crate::config::{CommitConfig, CONFIG_FILENAME};
+  use crate::errors::Result;
+  use crate::vcs::VcsLevel;
+  use git2::{Repository, Signature, Time};
+  use std::num::NonZeroUsize;
+  use std::path::Path;
+
+  fn vers(v: &str) -> PruneVersion { PruneVersion::parse(v).unwrap() }
+
+  #[test]
+  fn test_prune_version_cmp() {
+    assert!(vers("1.2.3") < vers("1.2.4"));
+    assert!(vers("1.10.0") > vers("1.9.9"));
+    assert!(vers("1.2.3-rc.1") < vers("1.2.3"));
+    assert!(vers("1.2.3-rc.2") < vers("1.2.3-rc.10"));
+    assert!(vers("1.2.3-1") < vers("1.2.3-alpha"));
+    assert!(vers("1.2.3-alpha") < vers("1.2.3-alpha.1"));
+    assert!(vers("1.2.3+build.5") == vers("1.2.3"));
+    assert!(PruneVersion::parse("1.2").is_none());
+  }
+
+  #[test]
+  fn test_dep_bump_text() {
+    let bump =
+      |prev: Option<&str>, next: Option<&str>| DepBump { prev: prev.map(Into::into), next: next.map(Into::into) };
+    assert_eq!(bump(Some("1.4.2"), Some("1.5.0")).text("core"), "Bumped core 1.4.2 → 1.5.0.");
+    assert_eq!(bump(None, Some("1.0.0")).text("core"), "Bumped core 1.0.0.");
+    assert_eq!(bump(Some("1.4.2"), Some("1.4.2")).text("core"), "");
+    assert_eq!(bump(Some("1.4.2"), None).text("core"), "");
+    assert_eq!(DepBump::default().text("core"), "");
+  }
+
+  #[test]
+  fn test_prunable_tags() {
+    let tags = |vs: &[&str]| vs.iter().map(|v| (vers(v), format!("v{}", v))).collect::<Vec<_>>();
+    let all = ["1.0.0", "1.1.0-rc.1", "1.1.0", "2.0.0-rc.1", "1.2.0", "2.0.0-rc.2"];
+    let keep = |n| NonZeroUsize::new(n).unwrap();
+
+    // The newest release is kept, along with the prereleases newer than it.
+    assert_eq!(prunable_tags(tags(&all), keep(1), false), ["v1.1.0", "v1.1.0-rc.1", "v1.0.0"]);
+    assert_eq!(prunable_tags(tags(&all), keep(2), false), ["v1.1.0-rc.1", "v1.0.0"]);
+    assert_eq!(prunable_tags(tags(&all), keep(1), true), ["v1.1.0-rc.1"]);
+    assert!(prunable_tags(tags(&all), keep(5), false).is_empty());
+  }
+
+  #[test]
+  fn test_plan_version_groups() {
+    let data = r#"
+options:
+  version_groups: [[1, 2], [3, 4]]
+projects:
+  - { name: a, id: 1, version: { file: "a" } }
+  - { name: b, id: 2, version: { file: "b" } }
+  - { name: c, id: 3, version: { file: "c" }, depends: { 2: { size: match } } }
+  - { name: d, id: 4, version: { file: "d" } }
+  - { name: e, id: 5, version: { file: "e" } }
+  - { name: f, id: 6, version: { file: "f" } }
+"#;
+
+    let dir = std::env::temp_dir().join(format!("versio-plan-groups-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join(CONFIG_FILENAME), data).unwrap();
+    let repo = Repo::open(&dir, VcsState::new(VcsLevel::None, false), CommitConfig::default());
+    let file = ConfigFile::from_dir(&dir);
+    std::fs::remove_dir_all(&dir).unwrap();
+    let (repo, file) = (repo.unwrap(), file.unwrap());
+
+    let mut plan = PlanBuilder::create(&repo, &file, &None);
+    plan.external(ProjectId::from_id(1), Size::Minor);
+    plan.external(ProjectId::from_id(5), Size::Patch);
+    plan.handle_groups().unwrap();
+    plan.handle_deps().unwrap();
+    plan.handle_groups().unwrap();
+    let plan = plan.build();
+
+    // The group of `a` levels `b`, whose dependent `c` levels the rest of its own group.
+    let size = |id| plan.incrs().get(&ProjectId::from_id(id)).map(|(size, _)| *size);
+    assert_eq!(size(1), Some(Size::Minor));
+    assert_eq!(size(2), Some(Size::Minor));
+    assert_eq!(size(3), Some(Size::Minor));
+    assert_eq!(size(4), Some(Size::Minor));
+    assert_eq!(size(5), Some(Size::Patch));
+    assert_eq!(size(6), None);
+  }
+
+  #[test]
+  fn test_rollback() {
+    let dir = std::env::temp_dir().join(format!("versio-rollback-{}", std::process::id()));
+
+    // Project files are written relative to the current directory, so root the project at the repo instead.
+    let data = format!(
+      r#"
+projects:
+  - name: proj
+    id: 1
+    root: "{}"
+    tag_prefix: "proj"
+    version:
+      file: "VERSION"
+"#,
+      dir.display()
+    );
+
+    let result = (|| -> Result<_> {
+      std::fs::create_dir_all(&dir)?;
+      std::fs::write(dir.join(CONFIG_FILENAME), data)?;
+      std::fs::write(dir.join("VERSION"), "1.0.0\n")?;
+      let git = Repository::init(&dir)?;
+      let mut index = git.index()?;
+      index.add_path(Path::new(CONFIG_FILENAME))?;
+      index.add_path(Path::new("VERSION"))?;
+      index.write()?;
+      let tree = index.write_tree()?;
+      let sig = Signature::now("Test", "test@example.com")?;
+      git.commit(Some("HEAD"), &sig, &sig, "init", &git.find_tree(tree)?, &[])?;
+
+      let vcs = || VcsState::new(VcsLevel::Local, false);
+      let mut mono = Mono::open(&dir, vcs())?;
+      mono.set_by_id(&ProjectId::from_id(1), &SetTo::Value("1.1.0".into()))?;
+      mono.commit(true, None, &Events::none())?;
+      let released = ["proj-v1.1.0", "versio-prev"].map(|t| git.refname_to_id(&format!("refs/tags/{}", t)).is_ok());
+      let released_vers = std::fs::read_to_string(dir.join("VERSION"))?;
+
+      let record = Mono::open(&dir, vcs())?.rollback()?;
+      let tags = git.tag_names(None)?.iter().flatten().map(|t| t.to_string()).collect::<Vec<_>>();
+      let head = git.head()?.peel_to_commit()?;
+      let vers = std::fs::read_to_string(dir.join("VERSION"))?;
+      let reverted = record.commit().map(|c| c == head.parent_id(0).unwrap().to_string());
+      Ok((released, released_vers, record.tags().to_vec(), tags, head.tree_id() == tree, reverted, vers))
+    })();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let (released, released_vers, recorded, tags, same_tree, reverted, vers) = result.unwrap();
+    assert_eq!(released, [true, true]);
+    assert_eq!(released_vers, "1.1.0\n");
+    assert_eq!(recorded, ["proj-v1.1.0"]);
+    assert!(tags.is_empty(), "{:?}", tags);
+    assert!(same_tree);
+    assert_eq!(reverted, Some(true));
+    assert_eq!(vers, "1.0.0\n");
+  }
+
+  #[test]
+  fn test_since_base() {
+    let data = r#"
+projects:
+  - name: proj
+    id: 1
+    tag_prefix: "proj"
+    version:
+      tags:
+        default: "0.0.0"
+"#;
+
+    let dir = std::env::temp_dir().join(format!("versio-since-base-{}", std::process::id()));
+    let result = (|| -> Result<_> {
+      std::fs::create_dir_all(&dir)?;
+      std::fs::write(dir.join(CONFIG_FILENAME), data)?;
+      let git = Repository::init(&dir)?;
+      let mut index = git.index()?;
+      index.add_path(Path::new(CONFIG_FILENAME))?;
+      index.write()?;
+      let tree = git.find_tree(index.write_tree()?)?;
+
+      // Commits on 2021-03-02 and 2021-03-06, at noon UTC.
+      let sig = |secs| Signature::new("Test", "test@example.com", &Time::new(secs, 0));
+      let first = git.commit(Some("HEAD"), &sig(1614686400)?, &sig(1614686400)?, "first", &tree, &[])?;
+      let parent = git.find_commit(first)?;
+      let second = git.commit(Some("HEAD"), &sig(1615032000)?, &sig(1615032000)?, "second", &tree, &[&parent])?;
+      git.tag_lightweight("start", &parent.into_object(), false)?;
+
+      let mono = Mono::open(&dir, VcsState::new(VcsLevel::Local, false))?;
+      let base = |since: &str| mono.since_base(since).map(|b| b.tag().to_string()).map_err(|e| e.to_string());
+      let sinces = ["2021-03-04", "2021-03-06T12:00:00Z", "2021-03-06", "start", "HEAD~1", "2021-03-01", "nope"];
+      let bases = sinces.map(base);
+      Ok((first.to_string(), second.to_string(), bases))
+    })();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let (first, second, bases) = result.unwrap();
+    let [before, exact, day, tag, rev, too_early, missing] = bases;
+    assert_eq!(before, Ok(first.clone()));
+    assert_eq!(exact, Ok(second));
+
+    // A date alone is the start of that day.
+    assert_eq!(day, Ok(first));
+    assert_eq!(tag, Ok("start".to_string()));
+    assert_eq!(rev, Ok("HEAD~1".to_string()));
+    assert!(too_early.unwrap_err().contains("No commits found before 2021-03-01"));
+    assert!(missing.unwrap_err().contains("Can't find commit or date \"nope\""));
+  }
+
+  #[test]
+  fn test_parse_date() {
+    let rfc = |d: &str| chrono::DateTime::parse_from_rfc3339(d).unwrap();
+
+    assert_eq!(parse_date("2021-03-04", false), Some(rfc("2021-03-04T00:00:00Z")));
+    assert_eq!(parse_date("2021-03-04", true), Some(rfc("2021-03-04T23:59:59Z")));
+    assert_eq!(parse_date("2021-03-04T10:20:30+02:00", false), Some(rfc("2021-03-04T08:20:30Z")));
+    assert_eq!(parse_date("2021-03-04T10:20:30+02:00", true), Some(rfc("2021-03-04T10:20:30+02:00")));
+
+    assert_eq!(parse_date("2021-13-04", false), None);
+    assert_eq!(parse_date("03/04/2021", false), None);
+    assert_eq!(parse_date("yesterday", true), None);
+  }
+}
@@ -1,64 +1,470 @@
 //! The way we output things to the user.
 
-use crate::analyze::Analysis;
-use crate::commands::{failed_hashes, InfoShow};
-use crate::config::{Project, ProjectId, Size};
-use crate::errors::{Context as _, Result};
+use crate::analyze::{Analysis, AnnotatedMark};
+use crate::ci::Ci;
+use crate::commands::{failed_hashes, DiffFormat, FilesFormat, InfoFormat, InfoShow, ReleaseFormat};
+use crate::config::{Config, Overlap, Project, ProjectId, Size, CONFIG_FILENAME};
+use crate::errors::{Context as _, Error, Result, VersioError};
+use crate::events::Events;
+use crate::fix::Fix;
+use crate::git::PushQueue;
 use crate::github::Changes;
-use crate::mono::ChangelogEntry;
-use crate::mono::{Mono, Plan};
-use crate::state::StateRead;
+use crate::migrate::CONFIG_VERSION;
+use crate::mono::{Changelog, ChangelogEntry, LoggedCommit};
+use crate::mono::{Drift, Mono, Plan, TagSync};
+use crate::state::{PauseStage, StateRead};
 use crate::template::{construct_changelog_html, read_template};
-use serde_json::json;
+use crate::validate::SchemaError;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
 
-pub struct Output {}
+pub struct Output {
+  events: Events,
+  ci: Option<String>
+}
 
 impl Default for Output {
   fn default() -> Output { Output::new() }
 }
 
 impl Output {
-  pub fn new() -> Output { Output {} }
-  pub fn check(&self) -> CheckOutput { CheckOutput::new() }
+  pub fn new() -> Output { Output { events: Events::none(), ci: None } }
+  pub fn with_events(events: &Events) -> Output { Output { events: events.clone(), ci: None } }
+
+  /// Report to the CI system chosen with `--ci`, instead of the one from the environment.
+  pub fn with_ci(mut self, ci: Option<&str>) -> Output {
+    self.ci = ci.map(|ci| ci.to_string());
+    self
+  }
+
+  pub fn check(&self) -> CheckOutput { CheckOutput::new().with_ci(self.detect_ci()) }
   pub fn projects(&self, wide: bool, vers_only: bool) -> ProjOutput { ProjOutput::new(wide, vers_only) }
   pub fn info(&self, show: InfoShow) -> ProjOutput { ProjOutput::info(show) }
-  pub fn diff(&self) -> DiffOutput { DiffOutput::new() }
-  pub fn files(&self) -> FilesOutput { FilesOutput::new() }
+  pub fn show(&self) -> ShowOutput { ShowOutput::new() }
+  pub fn diff(&self, format: DiffFormat) -> DiffOutput { DiffOutput::new(format) }
+  pub fn migrate(&self, delete_old: bool) -> MigrateOutput { MigrateOutput::new(delete_old) }
+  pub fn prune(&self, dry: bool) -> PruneOutput { PruneOutput::new(dry) }
+  pub fn prev(&self) -> PrevOutput { PrevOutput::new() }
+  pub fn config_migrate(&self) -> ConfigMigrateOutput { ConfigMigrateOutput::new() }
+  pub fn schema_check(&self, path: &Path, at: Option<&str>) -> SchemaCheckOutput { SchemaCheckOutput::new(path, at) }
+  pub fn files(&self, format: FilesFormat) -> FilesOutput { FilesOutput::new(format) }
+  pub fn coverage(&self, format: FilesFormat, problems: bool) -> CoverageOutput {
+    CoverageOutput::new(format, problems)
+  }
   pub fn changes(&self) -> ChangesOutput { ChangesOutput::new() }
-  pub fn plan(&self) -> PlanOutput { PlanOutput::new() }
-  pub fn release(&self) -> ReleaseOutput { ReleaseOutput::new() }
-  pub fn resume(&self) -> ResumeOutput { ResumeOutput::new() }
+  pub fn plan(&self) -> PlanOutput { PlanOutput::new(self.events.clone()).with_ci(self.detect_ci()) }
+  pub fn release(&self, format: ReleaseFormat) -> ReleaseOutput {
+    ReleaseOutput::new(self.events.clone(), format).with_ci(self.detect_ci())
+  }
+  pub fn resume(&self) -> ResumeOutput { ResumeOutput::new(self.events.clone()) }
+  pub fn rollback(&self) -> RollbackOutput { RollbackOutput::new() }
+  pub fn audit(&self) -> AuditOutput { AuditOutput::new() }
+  pub fn verify_tags(&self, push: bool) -> VerifyTagsOutput { VerifyTagsOutput::new(push) }
+  pub fn push(&self) -> PushOutput { PushOutput::new() }
+  pub fn notes(&self, version: &str) -> NotesOutput { NotesOutput::new(version) }
+  pub fn exec(&self) -> ExecOutput { ExecOutput::new() }
+
+  fn detect_ci(&self) -> Option<Ci> { Ci::detect(self.ci.as_deref()) }
 }
 
-pub struct CheckOutput {}
+pub struct CheckOutput {
+  ci: Option<Ci>
+}
 
 impl Default for CheckOutput {
   fn default() -> CheckOutput { CheckOutput::new() }
 }
 
 impl CheckOutput {
-  pub fn new() -> CheckOutput { CheckOutput {} }
+  pub fn new() -> CheckOutput { CheckOutput { ci: None } }
+
+  pub fn with_ci(mut self, ci: Option<Ci>) -> CheckOutput {
+    self.ci = ci;
+    self
+  }
+
   pub fn write_done(&mut self) -> Result<()> { Ok(()) }
 
+  pub fn write_fixes(&mut self, fixes: &[Fix]) -> Result<()> {
+    if fixes.is_empty() {
+      println!("Nothing to fix.");
+      return Ok(());
+    }
+
+    for fix in fixes {
+      println!("{}", fix.description());
+      println!("@@ line {} @@", fix.line());
+      println!("-{}", fix.removed());
+      println!("+{}", fix.added());
+      if let Some(ci) = &self.ci {
+        ci.notice_at(CONFIG_FILENAME, fix.line(), fix.description());
+      }
+    }
+    Ok(())
+  }
+
+  pub fn write_overlaps(&mut self, overlaps: &[Overlap]) {
+    for overlap in overlaps {
+      let ((a_id, a_pat), (b_id, b_pat)) = (overlap.first(), overlap.second());
+      println!("Projects {} (\"{}\") and {} (\"{}\") both cover {}.", a_id, a_pat, b_id, b_pat, overlap.file());
+    }
+  }
+
+  pub fn write_failed(&mut self, error: &Error) {
+    if let Some(ci) = &self.ci {
+      ci.error(&format!("Versio check failed: {:#}", error));
+    }
+  }
+
   pub fn commit(&mut self) -> Result<()> {
     println!("Check complete.");
     Ok(())
   }
 }
 
-pub struct ResumeOutput {}
+pub struct ResumeOutput {
+  events: Events,
+  paused: Option<PauseStage>,
+  done: bool
+}
 
 impl Default for ResumeOutput {
-  fn default() -> ResumeOutput { ResumeOutput::new() }
+  fn default() -> ResumeOutput { ResumeOutput::new(Events::none()) }
 }
 
 impl ResumeOutput {
-  pub fn new() -> ResumeOutput { ResumeOutput {} }
-  pub fn write_done(&mut self) -> Result<()> { Ok(()) }
+  pub fn new(events: Events) -> ResumeOutput { ResumeOutput { events, paused: None, done: false } }
+  pub fn write_started(&mut self) -> Result<()> { self.events.phase_started("resume") }
+  pub fn write_done(&mut self) -> Result<()> {
+    self.done = true;
+    self.events.phase_finished("resume")
+  }
+
+  pub fn write_pause(&mut self, stage: PauseStage) -> Result<()> {
+    self.paused = Some(stage);
+    self.events.emit("paused", json!({ "stage": stage.as_str() }))
+  }
+
+  pub fn commit(&mut self) -> Result<()> {
+    if self.events.owns_stdout() {
+      return Ok(());
+    }
+    if let Some(stage) = self.paused {
+      println!("Paused for {}: use --resume to continue.", stage.as_str());
+    } else if self.done {
+      println!("Release complete.");
+    }
+    Ok(())
+  }
+}
+
+pub struct RollbackOutput {
+  reverted: Option<String>,
+  deleted: Vec<String>
+}
+
+impl Default for RollbackOutput {
+  fn default() -> RollbackOutput { RollbackOutput::new() }
+}
+
+impl RollbackOutput {
+  pub fn new() -> RollbackOutput { RollbackOutput { reverted: None, deleted: Vec::new() } }
+  pub fn write_reverted(&mut self, commit: &str) { self.reverted = Some(commit.to_string()); }
+  pub fn write_deleted(&mut self, tag: &str) { self.deleted.push(tag.to_string()); }
 
   pub fn commit(&mut self) -> Result<()> {
-    println!("Release complete.");
+    if let Some(commit) = &self.reverted {
+      println!("Reverted commit {}.", commit);
+    }
+    for tag in &self.deleted {
+      println!("Deleted tag {}.", tag);
+    }
+    println!("Rollback complete.");
+    Ok(())
+  }
+}
+
+pub struct MigrateOutput {
+  moved: Vec<(String, String)>,
+  deleted: bool
+}
+
+impl Default for MigrateOutput {
+  fn default() -> MigrateOutput { MigrateOutput::new(false) }
+}
+
+impl MigrateOutput {
+  pub fn new(deleted: bool) -> MigrateOutput { MigrateOutput { moved: Vec::new(), deleted } }
+  pub fn write_moved(&mut self, moved: Vec<(String, String)>) { self.moved.extend(moved); }
+
+  pub fn commit(&mut self) -> Result<()> {
+    if self.moved.is_empty() {
+      println!("No tags to migrate.");
+      return Ok(());
+    }
+
+    let verb = if self.deleted { "Moved" } else { "Copied" };
+    for (old, new) in &self.moved {
+      println!("{} tag {} to {}.", verb, old, new);
+    }
+    Ok(())
+  }
+}
+
+pub struct PruneOutput {
+  pruned: Vec<String>,
+  dry: bool
+}
+
+impl Default for PruneOutput {
+  fn default() -> PruneOutput { PruneOutput::new(false) }
+}
+
+impl PruneOutput {
+  pub fn new(dry: bool) -> PruneOutput { PruneOutput { pruned: Vec::new(), dry } }
+  pub fn write_pruned(&mut self, pruned: Vec<String>) { self.pruned.extend(pruned); }
+
+  pub fn commit(&mut self) -> Result<()> {
+    if self.pruned.is_empty() {
+      println!("No tags to prune.");
+      return Ok(());
+    }
+
+    let verb = if self.dry { "Would delete" } else { "Deleted" };
+    for tag in &self.pruned {
+      println!("{} tag {}.", verb, tag);
+    }
+    Ok(())
+  }
+}
+
+pub struct ConfigMigrateOutput {
+  from: Option<u64>
+}
+
+impl Default for ConfigMigrateOutput {
+  fn default() -> ConfigMigrateOutput { ConfigMigrateOutput::new() }
+}
+
+impl ConfigMigrateOutput {
+  pub fn new() -> ConfigMigrateOutput { ConfigMigrateOutput { from: None } }
+  pub fn write_migrated(&mut self, from: Option<u64>) { self.from = from; }
+
+  pub fn commit(&mut self) -> Result<()> {
+    match self.from {
+      Some(from) => println!("Migrated config from version {} to {}.", from, CONFIG_VERSION),
+      None => println!("Config is already at version {}.", CONFIG_VERSION)
+    }
+    Ok(())
+  }
+}
+
+pub struct SchemaCheckOutput {
+  name: String,
+  errors: Vec<String>
+}
+
+impl SchemaCheckOutput {
+  pub fn new(path: &Path, at: Option<&str>) -> SchemaCheckOutput {
+    let name = match at {
+      Some(at) => format!("{}:{}", at, path.display()),
+      None => path.display().to_string()
+    };
+    SchemaCheckOutput { name, errors: Vec::new() }
+  }
+
+  pub fn write_errors(&mut self, errors: &[SchemaError]) {
+    self.errors.extend(
+      errors.iter().map(|e| format!("{}:{}:{}: {} (at {})", self.name, e.line(), e.column(), e.message(), e.path()))
+    );
+  }
+
+  pub fn commit(&mut self) -> Result<()> {
+    if self.errors.is_empty() {
+      println!("{} matches the schema.", self.name);
+    }
+    for error in &self.errors {
+      println!("{}", error);
+    }
+    Ok(())
+  }
+}
+
+pub struct PrevOutput {
+  annotation: Option<String>,
+  set: Option<String>
+}
+
+impl Default for PrevOutput {
+  fn default() -> PrevOutput { PrevOutput::new() }
+}
+
+impl PrevOutput {
+  pub fn new() -> PrevOutput { PrevOutput { annotation: None, set: None } }
+  pub fn write_annotation(&mut self, anno: &str) { self.annotation = Some(anno.to_string()); }
+  pub fn write_set(&mut self, tag: &str) { self.set = Some(tag.to_string()); }
+
+  pub fn commit(&mut self) -> Result<()> {
+    if let Some(anno) = &self.annotation {
+      // Pretty-print a JSON annotation, but show anything else as-is so that it can be repaired.
+      match serde_json::from_str::<Value>(anno) {
+        Ok(value) => println!("{}", serde_json::to_string_pretty(&value)?),
+        Err(_) => println!("{}", anno)
+      }
+    }
+    if let Some(tag) = &self.set {
+      println!("Updated annotation of tag {}.", tag);
+    }
+    Ok(())
+  }
+}
+
+pub struct AuditOutput {
+  drifts: Vec<Drift>
+}
+
+impl Default for AuditOutput {
+  fn default() -> AuditOutput { AuditOutput::new() }
+}
+
+impl AuditOutput {
+  pub fn new() -> AuditOutput { AuditOutput { drifts: Vec::new() } }
+  pub fn write_drifts(&mut self, drifts: Vec<Drift>) { self.drifts.extend(drifts); }
+
+  pub fn commit(&mut self) -> Result<()> {
+    if self.drifts.is_empty() {
+      println!("No drift found.");
+      return Ok(());
+    }
+
+    for drift in &self.drifts {
+      match drift {
+        Drift::MissingTag { name, tag } => println!("{} : missing tag {} for current version", name, tag),
+        Drift::Mismatch { name, tag, tag_version, file_version } => {
+          println!("{} : tag {} is {}, but files at that tag are {}", name, tag, tag_version, file_version)
+        }
+        Drift::Unreadable { name, tag, error } => println!("{} : can't read version at tag {}: {}", name, tag, error)
+      }
+    }
+    println!("Found {} drift(s).", self.drifts.len());
+    Ok(())
+  }
+}
+
+pub struct VerifyTagsOutput {
+  push: bool,
+  unsynced: Vec<TagSync>
+}
+
+impl VerifyTagsOutput {
+  pub fn new(push: bool) -> VerifyTagsOutput { VerifyTagsOutput { push, unsynced: Vec::new() } }
+  pub fn write_unsynced(&mut self, unsynced: Vec<TagSync>) { self.unsynced.extend(unsynced); }
+
+  pub fn commit(&mut self) -> Result<()> {
+    if self.unsynced.is_empty() {
+      println!("All tags match the remote.");
+      return Ok(());
+    }
+
+    for sync in &self.unsynced {
+      match sync {
+        TagSync::Unpushed { tag, oid } => println!("{} : at {} locally, but not on the remote", tag, &oid[.. 7]),
+        TagSync::Different { tag, oid, remote } => {
+          println!("{} : at {} locally, but at {} on the remote", tag, &oid[.. 7], &remote[.. 7])
+        }
+      }
+    }
+    if self.push {
+      println!("Pushed {} tag(s).", self.unsynced.len());
+    } else {
+      println!("Found {} tag(s) that don't match the remote.", self.unsynced.len());
+    }
+    Ok(())
+  }
+}
+
+pub struct PushOutput {
+  pushed: Option<PushQueue>
+}
+
+impl Default for PushOutput {
+  fn default() -> PushOutput { PushOutput::new() }
+}
+
+impl PushOutput {
+  pub fn new() -> PushOutput { PushOutput { pushed: None } }
+  pub fn write_pushed(&mut self, pushed: Option<PushQueue>) { self.pushed = pushed; }
+
+  pub fn commit(&mut self) -> Result<()> {
+    let pushed = match &self.pushed {
+      Some(pushed) => pushed,
+      None => {
+        println!("Nothing to push.");
+        return Ok(());
+      }
+    };
+
+    if let Some(branch) = pushed.branch() {
+      println!("Pushed branch {}.", branch);
+    }
+    for tag in pushed.tags() {
+      println!("Pushed tag {}.", tag);
+    }
+    for tag in pushed.deleted_tags() {
+      println!("Deleted tag {}.", tag);
+    }
+    Ok(())
+  }
+}
+
+pub struct ExecOutput {
+  ran: usize
+}
+
+impl Default for ExecOutput {
+  fn default() -> ExecOutput { ExecOutput::new() }
+}
+
+impl ExecOutput {
+  pub fn new() -> ExecOutput { ExecOutput { ran: 0 } }
+
+  /// Announce each project before its command runs, since the command's own output follows immediately.
+  pub fn write_running(&mut self, name: &str, prev: Option<&str>, target: &str) {
+    match prev {
+      Some(prev) => println!("==> {} : {} -> {}", name, prev, target),
+      None => println!("==> {} : new {}", name, target)
+    }
+    self.ran += 1;
+  }
+
+  pub fn commit(&mut self) -> Result<()> {
+    if self.ran == 0 {
+      println!("No projects to run in.");
+    } else {
+      println!("Ran in {} project(s).", self.ran);
+    }
+    Ok(())
+  }
+}
+
+pub struct NotesOutput {
+  version: String,
+  notes: Option<String>,
+  wrote: Option<PathBuf>
+}
+
+impl NotesOutput {
+  pub fn new(version: &str) -> NotesOutput { NotesOutput { version: version.to_string(), notes: None, wrote: None } }
+  pub fn write_notes(&mut self, notes: String) { self.notes = Some(notes); }
+  pub fn write_wrote(&mut self, wrote: PathBuf) { self.wrote = Some(wrote); }
+
+  pub fn commit(&mut self) -> Result<()> {
+    match (&self.wrote, &self.notes) {
+      (Some(wrote), _) => println!("Wrote release notes of {} to {}.", self.version, wrote.to_string_lossy()),
+      (None, Some(notes)) => print!("{}", notes),
+      (None, None) => println!("No project has a tag for version {}.", self.version)
+    }
     Ok(())
   }
 }
@@ -68,16 +474,40 @@ pub struct ProjOutput {
   vers_only: bool,
   proj_lines: Vec<ProjLine>,
   info_only: bool,
-  show: InfoShow
+  show: InfoShow,
+  last_commits: HashMap<ProjectId, String>,
+  changed_files: HashMap<ProjectId, BTreeSet<String>>
 }
 
 impl ProjOutput {
   pub fn new(wide: bool, vers_only: bool) -> ProjOutput {
-    ProjOutput { show: InfoShow::new(), info_only: false, wide, vers_only, proj_lines: Vec::new() }
+    ProjOutput {
+      show: InfoShow::new(),
+      info_only: false,
+      wide,
+      vers_only,
+      proj_lines: Vec::new(),
+      last_commits: HashMap::new(),
+      changed_files: HashMap::new()
+    }
   }
 
   pub fn info(show: InfoShow) -> ProjOutput {
-    ProjOutput { info_only: true, show, wide: false, vers_only: false, proj_lines: Vec::new() }
+    ProjOutput {
+      info_only: true,
+      show,
+      wide: false,
+      vers_only: false,
+      proj_lines: Vec::new(),
+      last_commits: HashMap::new(),
+      changed_files: HashMap::new()
+    }
+  }
+
+  pub fn write_last_commits(&mut self, last_commits: HashMap<ProjectId, String>) { self.last_commits = last_commits; }
+
+  pub fn write_changed_files(&mut self, changed_files: HashMap<ProjectId, BTreeSet<String>>) {
+    self.changed_files = changed_files;
   }
 
   pub fn write_projects<I: Iterator<Item = Result<ProjLine>>>(&mut self, lines: I) -> Result<()> {
@@ -94,34 +524,21 @@ impl ProjOutput {
     let name_width = self.proj_lines.iter().map(|l| l.name.len()).max().unwrap_or(0);
     let id_width = self.proj_lines.iter().map(|l| l.id.to_string().len()).max().unwrap_or(0);
     if self.info_only {
-      let val = json!(self
-        .proj_lines
-        .iter()
-        .map(|line| {
-          let root = line.root.as_deref().unwrap_or(".");
-          let mut val = json!({});
-          if self.show.name() {
-            val["name"] = json!(line.name);
-          }
-          if self.show.root() {
-            val["root"] = json!(root);
-          }
-          if self.show.id() {
-            val["id"] = json!(line.id);
-          }
-          if self.show.full_version() {
-            val["full_version"] = json!(line.full_version);
-          }
-          if self.show.tag_prefix() {
-            val["tag_prefix"] = json!(line.tag_prefix);
-          }
-          if self.show.version() {
-            val["version"] = json!(line.version);
-          }
-          val
-        })
-        .collect::<Vec<_>>());
-      println!("{}", serde_json::to_string(&val)?);
+      let rows: Vec<_> = self.proj_lines.iter().map(|line| self.info_fields(line)).collect();
+      let objects = || rows.iter().map(|row| row.iter().cloned().collect::<Map<_, _>>()).collect::<Vec<_>>();
+      match self.show.format() {
+        InfoFormat::Json => println!("{}", serde_json::to_string(&objects())?),
+        InfoFormat::Yaml => print!("{}", serde_yaml::to_string(&objects())?),
+        InfoFormat::Toml => {
+          // TOML has no null, so absent values are left out.
+          let projects: Vec<_> = rows
+            .iter()
+            .map(|row| row.iter().filter(|(_, v)| !v.is_null()).cloned().collect::<Map<_, _>>())
+            .collect();
+          print!("{}", toml::to_string(&json!({ "projects": projects }))?)
+        }
+        InfoFormat::Table => println_table(&rows)
+      }
     } else {
       for line in &self.proj_lines {
         if self.vers_only {
@@ -137,6 +554,67 @@ impl ProjOutput {
   }
 }
 
+impl ProjOutput {
+  /// The fields of a line shown by `info`, in display order.
+  fn info_fields(&self, line: &ProjLine) -> Vec<(String, Value)> {
+    let mut fields = Vec::new();
+    if self.show.name() {
+      fields.push(("name".to_string(), json!(line.name)));
+    }
+    if self.show.root() {
+      fields.push(("root".to_string(), json!(line.root.as_deref().unwrap_or("."))));
+    }
+    if self.show.id() {
+      fields.push(("id".to_string(), json!(line.id)));
+    }
+    if self.show.full_version() {
+      fields.push(("full_version".to_string(), json!(line.full_version)));
+    }
+    if self.show.tag_prefix() {
+      fields.push(("tag_prefix".to_string(), json!(line.tag_prefix)));
+    }
+    if self.show.version() {
+      fields.push(("version".to_string(), json!(line.version)));
+    }
+    if self.show.last_commit() {
+      fields.push(("last_commit".to_string(), json!(self.last_commits.get(&line.id))));
+    }
+    if self.show.changed_files() {
+      let files: Vec<_> = self.changed_files.get(&line.id).into_iter().flatten().collect();
+      fields.push(("changed_files".to_string(), json!(files)));
+    }
+    fields
+  }
+}
+
+fn println_table(rows: &[Vec<(String, Value)>]) {
+  let cell = |v: &Value| match v {
+    Value::Null => "-".to_string(),
+    Value::String(s) => s.clone(),
+    Value::Array(a) if a.is_empty() => "-".to_string(),
+    Value::Array(a) => a.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>().join(","),
+    other => other.to_string()
+  };
+
+  let headers: Vec<String> =
+    rows.first().map(|r| r.iter().map(|(k, _)| k.to_uppercase()).collect()).unwrap_or_default();
+  let cells: Vec<Vec<String>> = rows.iter().map(|r| r.iter().map(|(_, v)| cell(v)).collect()).collect();
+  let widths: Vec<usize> = headers
+    .iter()
+    .enumerate()
+    .map(|(i, h)| cells.iter().map(|c| c[i].len()).chain(Some(h.len())).max().unwrap_or(0))
+    .collect();
+
+  let print_row = |row: &[String]| {
+    let line: Vec<_> = row.iter().zip(&widths).map(|(c, w)| format!("{:w$}", c, w = w)).collect();
+    println!("{}", line.join("  ").trim_end());
+  };
+  print_row(&headers);
+  for row in &cells {
+    print_row(row);
+  }
+}
+
 pub struct ProjLine {
   pub id: ProjectId,
   pub name: String,
@@ -171,28 +649,113 @@ impl ProjLine {
   }
 }
 
+pub struct ShowOutput {
+  projects: Vec<Value>
+}
+
+impl Default for ShowOutput {
+  fn default() -> ShowOutput { ShowOutput::new() }
+}
+
+impl ShowOutput {
+  pub fn new() -> ShowOutput { ShowOutput { projects: Vec::new() } }
+
+  /// Record each current project, with its previous version (if it was in the previous release) and the size of
+  /// its planned increment.
+  pub fn write_show<C: StateRead, P: StateRead>(
+    &mut self, current: &Config<C>, prev: &Config<P>, plan: &Plan
+  ) -> Result<()> {
+    for proj in current.projects() {
+      let id = proj.id();
+      let size = plan.incrs().get(id).map(|(size, _)| *size).unwrap_or(Size::Empty);
+      self.projects.push(json!({
+        "id": id,
+        "name": proj.name(),
+        "version": current.get_value(id)?,
+        "prev_version": prev.get_value(id)?,
+        "size": size.to_string()
+      }));
+    }
+    Ok(())
+  }
+
+  pub fn commit(&mut self) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&self.projects)?);
+    Ok(())
+  }
+}
+
 pub struct DiffOutput {
-  analysis: Option<Analysis>
+  format: DiffFormat,
+  analysis: Option<Analysis>,
+  files: Option<HashMap<ProjectId, BTreeSet<String>>>
 }
 
 impl Default for DiffOutput {
-  fn default() -> DiffOutput { DiffOutput::new() }
+  fn default() -> DiffOutput { DiffOutput::new(DiffFormat::Text) }
 }
 
 impl DiffOutput {
-  pub fn new() -> DiffOutput { DiffOutput { analysis: None } }
+  pub fn new(format: DiffFormat) -> DiffOutput { DiffOutput { format, analysis: None, files: None } }
 
   pub fn write_analysis(&mut self, analysis: Analysis) -> Result<()> {
     self.analysis = Some(analysis);
     Ok(())
   }
 
+  /// Record the changed files of each project that covers them.
+  pub fn write_files(&mut self, files: impl Iterator<Item = Result<(String, String)>>, mono: &Mono) -> Result<()> {
+    let by_proj = self.files.get_or_insert_with(HashMap::new);
+    for file in files {
+      let (_, path) = file?;
+      for id in mono.covering_projects(&path)? {
+        by_proj.entry(id).or_default().insert(path.clone());
+      }
+    }
+    Ok(())
+  }
+
   pub fn commit(&mut self) -> Result<()> {
     if let Some(analysis) = &self.analysis {
-      println_analysis(analysis);
+      match self.format {
+        DiffFormat::Text => println_analysis(analysis),
+        DiffFormat::Json => println!("{}", serde_json::to_string_pretty(&self.analysis_json(analysis))?)
+      }
     }
     Ok(())
   }
+
+  fn analysis_json(&self, analysis: &Analysis) -> Value {
+    let with_files = |mut val: Value, id: &ProjectId| {
+      if let Some(files) = &self.files {
+        val["files"] = json!(files.get(id).map(|f| f.iter().collect::<Vec<_>>()).unwrap_or_default());
+      }
+      val
+    };
+    let mark = |mark: &AnnotatedMark| json!({ "id": mark.id(), "name": mark.name(), "version": mark.mark() });
+
+    let removed: Vec<_> = analysis.older().iter().map(mark).collect();
+    let added: Vec<_> = analysis.newer().iter().map(|m| with_files(mark(m), m.id())).collect();
+
+    let mut changed = Vec::new();
+    let mut unchanged = Vec::new();
+    for change in analysis.changes() {
+      let new_mark = change.new_mark();
+      let mut val = mark(new_mark);
+      if let Some((o, _)) = change.name() {
+        val["prev_name"] = json!(o);
+      }
+      match change.value() {
+        Some((o, _)) => {
+          val["prev_version"] = json!(o);
+          changed.push(with_files(val, new_mark.id()));
+        }
+        None => unchanged.push(with_files(val, new_mark.id()))
+      }
+    }
+
+    json!({ "added": added, "removed": removed, "changed": changed, "unchanged": unchanged })
+  }
 }
 
 fn println_analysis(analysis: &Analysis) {
@@ -242,24 +805,118 @@ fn println_analysis(analysis: &Analysis) {
 }
 
 pub struct FilesOutput {
-  files: Vec<(String, String)>
+  format: FilesFormat,
+  files: Vec<(String, String, Vec<ProjectId>)>
 }
 
 impl Default for FilesOutput {
-  fn default() -> FilesOutput { FilesOutput::new() }
+  fn default() -> FilesOutput { FilesOutput::new(FilesFormat::Text) }
 }
 
 impl FilesOutput {
-  pub fn new() -> FilesOutput { FilesOutput { files: Vec::new() } }
+  pub fn new(format: FilesFormat) -> FilesOutput { FilesOutput { format, files: Vec::new() } }
+
+  /// Record the changed files, along with (for JSON output) the projects that cover each.
+  pub fn write_files(&mut self, files: impl Iterator<Item = Result<(String, String)>>, mono: &Mono) -> Result<()> {
+    for file in files {
+      let (key, path) = file?;
+      let covering = match self.format {
+        FilesFormat::Json => mono.covering_projects(&path)?,
+        FilesFormat::Text => Vec::new()
+      };
+      self.files.push((key, path, covering));
+    }
+    Ok(())
+  }
 
-  pub fn write_files(&mut self, files: impl Iterator<Item = Result<(String, String)>>) -> Result<()> {
-    self.files = files.collect::<std::result::Result<_, _>>()?;
+  pub fn commit(&mut self) -> Result<()> {
+    match self.format {
+      FilesFormat::Text => {
+        for (key, path, _) in &self.files {
+          println!("{} : {}", key, path);
+        }
+      }
+      FilesFormat::Json => {
+        let files: Vec<_> = self
+          .files
+          .iter()
+          .map(|(key, path, projects)| json!({ "kind": key, "path": path, "projects": projects }))
+          .collect();
+        println!("{}", serde_json::to_string_pretty(&files)?);
+      }
+    }
     Ok(())
   }
+}
+
+pub struct CoverageOutput {
+  format: FilesFormat,
+  problems: bool,
+  files: Vec<(String, Vec<(ProjectId, String)>)>
+}
+
+impl Default for CoverageOutput {
+  fn default() -> CoverageOutput { CoverageOutput::new(FilesFormat::Text, false) }
+}
+
+impl CoverageOutput {
+  pub fn new(format: FilesFormat, problems: bool) -> CoverageOutput {
+    CoverageOutput { format, problems, files: Vec::new() }
+  }
+
+  pub fn write_coverage(&mut self, coverage: BTreeMap<String, Vec<ProjectId>>, mono: &Mono) {
+    for (path, ids) in coverage {
+      let projects: Vec<_> = ids
+        .into_iter()
+        .map(|id| {
+          let name = mono.get_project(&id).map(|p| p.name().to_string()).unwrap_or_default();
+          (id, name)
+        })
+        .collect();
+      self.files.push((path, projects));
+    }
+  }
 
   pub fn commit(&mut self) -> Result<()> {
-    for (key, path) in &self.files {
-      println!("{} : {}", key, path);
+    let status = |projects: &[(ProjectId, String)]| match projects.len() {
+      0 => "uncovered",
+      1 => "covered",
+      _ => "multiple"
+    };
+    let shown: Vec<_> = self.files.iter().filter(|(_, projects)| !self.problems || projects.len() != 1).collect();
+
+    match self.format {
+      FilesFormat::Text => {
+        for (path, projects) in &shown {
+          let names: Vec<_> = projects.iter().map(|(_, name)| name.as_str()).collect();
+          match names.len() {
+            0 => println!("! {} : (no project)", path),
+            1 => println!("  {} : {}", path, names[0]),
+            _ => println!("* {} : {}", path, names.join(", "))
+          }
+        }
+        let uncovered = self.files.iter().filter(|(_, p)| p.is_empty()).count();
+        let multiple = self.files.iter().filter(|(_, p)| p.len() > 1).count();
+        if shown.is_empty() && self.problems {
+          println!("Every changed file is covered by exactly one project.");
+        }
+        println!(
+          "{} changed files: {} covered by no project, {} covered by multiple projects.",
+          self.files.len(),
+          uncovered,
+          multiple
+        );
+      }
+      FilesFormat::Json => {
+        let files: Vec<_> = shown
+          .iter()
+          .map(|(path, projects)| {
+            let projs: Vec<_> = projects.iter().map(|(id, name)| json!({ "id": id, "name": name })).collect();
+            json!({ "path": path, "status": status(projects), "projects": projs })
+          })
+          .collect();
+        println!("{}", serde_json::to_string_pretty(&files)?);
+      }
     }
     Ok(())
   }
@@ -295,9 +952,12 @@ fn println_changes(changes: &Changes) {
   for g in changes.groups().values() {
     let head_oid = g.head_oid().as_ref().map(|o| o.to_string()).unwrap_or_else(|| "<not found>".to_string());
     println!("  {}: {} ({} -> {})", g.number(), g.head_ref(), g.base_oid(), head_oid);
+    if !g.labels().is_empty() {
+      println!("    labels: {}", g.labels().join(", "));
+    }
     println!("    commits:");
     for cmt in g.commits() {
-      println!("      {}", cmt.id());
+      println!("      {} {} {}", cmt.id(), cmt.time().format("%Y-%m-%d"), cmt.author());
     }
     println!("    excludes:");
     for cmt in g.excludes() {
@@ -315,15 +975,30 @@ pub struct PlanOutput {
   plan: Option<Plan>,
   id: Option<ProjectId>,
   template: Option<String>,
-  orig_dir: Option<PathBuf>
+  orig_dir: Option<PathBuf>,
+  explain: bool,
+  events: Events,
+  ci: Option<Ci>
 }
 
 impl Default for PlanOutput {
-  fn default() -> PlanOutput { PlanOutput::new() }
+  fn default() -> PlanOutput { PlanOutput::new(Events::none()) }
 }
 
 impl PlanOutput {
-  pub fn new() -> PlanOutput { PlanOutput { plan: None, id: None, template: None, orig_dir: None } }
+  pub fn new(events: Events) -> PlanOutput {
+    PlanOutput { plan: None, id: None, template: None, orig_dir: None, explain: false, events, ci: None }
+  }
+
+  pub fn with_ci(mut self, ci: Option<Ci>) -> PlanOutput {
+    self.ci = ci;
+    self
+  }
+
+  /// Also show why each commit has its size, and which files of the commit the project covers.
+  pub fn write_explain(&mut self, explain: bool) { self.explain = explain; }
+
+  pub fn write_started(&mut self) -> Result<()> { self.events.phase_started("plan") }
 
   pub fn write_plan(
     &mut self, plan: Plan, id: Option<ProjectId>, template: Option<&str>, orig_dir: &Path
@@ -337,6 +1012,18 @@ impl PlanOutput {
   }
 
   pub async fn commit(&mut self, mono: &Mono) -> Result<()> {
+    if let Some(plan) = &self.plan {
+      self.emit_plan(plan, mono)?;
+      // CI reports are written to stdout, so they're skipped when it holds the event stream.
+      if let Some(ci) = self.ci.as_ref().filter(|_| !self.events.owns_stdout()) {
+        self.report_plan(plan, mono, ci)?;
+      }
+    }
+    self.events.phase_finished("plan")?;
+    if self.events.owns_stdout() {
+      return Ok(());
+    }
+
     if let Some(plan) = &self.plan {
       self.println_plan(plan, mono).await
     } else {
@@ -345,6 +1032,67 @@ impl PlanOutput {
     }
   }
 
+  fn emit_plan(&self, plan: &Plan, mono: &Mono) -> Result<()> {
+    for (id, (size, changelog)) in plan.incrs() {
+      if self.id.as_ref().map(|self_id| id != self_id).unwrap_or(false) {
+        continue;
+      }
+      let name = mono.get_project(id).map(|p| p.name().to_string()).unwrap_or_default();
+      self.events.emit("project_planned", json!({ "id": id.to_string(), "name": name, "size": size.to_string() }))?;
+      if self.explain {
+        self.emit_explained(id, changelog)?;
+      }
+    }
+    Ok(())
+  }
+
+  fn emit_explained(&self, id: &ProjectId, changelog: &Changelog) -> Result<()> {
+    for entry in changelog.entries() {
+      if let ChangelogEntry::Pr(pr, _) = entry {
+        for c in pr.commits().iter().filter(|c| c.included()) {
+          self.events.emit(
+            "commit_explained",
+            json!({
+              "id": id.to_string(),
+              "pr": pr.number(),
+              "commit": c.oid(),
+              "kind": c.kind(),
+              "size_entry": c.size_entry(),
+              "size": c.size().to_string(),
+              "scoped": c.scoped(),
+              "files": c.files()
+            })
+          )?;
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Summarize the plan for the CI system, and report the projects whose commits can't be parsed as problems.
+  fn report_plan(&self, plan: &Plan, mono: &Mono, ci: &Ci) -> Result<()> {
+    let prev_config = mono.config().slice_to_prev(mono.repo())?;
+    let targets = mono.release_targets(plan)?;
+    let mut rows = Vec::new();
+    for proj in mono.config().projects() {
+      let id = proj.id();
+      let size = match plan.incrs().get(id) {
+        Some((size, _)) if self.id.as_ref().map(|self_id| id == self_id).unwrap_or(true) => size,
+        _ => continue
+      };
+      let name = proj.name().to_string();
+      if size.is_failure() {
+        ci.error(&format!("Non-parseable conventional commits in {}: {}", name, failed_hashes(plan)));
+      }
+      let next = match targets.get(id) {
+        Some(target) => Some(target.clone()),
+        None => mono.planned_version(plan, id, &prev_config)?
+      };
+      rows.push(vec![name, size.to_string(), prev_config.get_value(id)?.unwrap_or_default(), next.unwrap_or_default()]);
+    }
+    ci.summarize("Versio plan", &["Project", "Size", "Previous", "Planned"], &rows)
+  }
+
   async fn println_plan(&self, plan: &Plan, mono: &Mono) -> Result<()> {
     self.println_plan_incrs(plan, mono).await?;
     self.println_plan_ineff(plan);
@@ -368,7 +1116,7 @@ impl PlanOutput {
         }
       }
 
-      let curt_proj = mono.get_project(id).unwrap();
+      let curt_proj = mono.get_project(id)?;
       println!("{} : {}", curt_proj.name(), size);
 
       let curt_config = mono.config();
@@ -376,14 +1124,18 @@ impl PlanOutput {
       let prev_vers = prev_config.get_value(id).with_context(|| format!("Unable to find prev {} value.", id))?;
       let curt_vers = curt_config
         .get_value(id)
-        .with_context(|| format!("Unable to find project {} value.", id))?
-        .unwrap_or_else(|| panic!("No such project {}.", id));
+        .with_context(|| VersioError::MissingVersion(id.clone()))?
+        .ok_or_else(|| VersioError::UncoveredProject(id.clone()))?;
 
       if let Some(prev_vers) = prev_vers {
         if size.is_failure() {
           println!("  ! Non-parseable conventional commits: {}", failed_hashes(plan));
         } else if size != &Size::Empty {
-          let target = size.apply(&prev_vers)?;
+          let incremented = size.apply(&prev_vers)?;
+          let target = curt_proj.floor_version(incremented.clone())?;
+          if target != incremented {
+            println!("  ^ Raised from {} to min_version {}.", incremented, target);
+          }
           if Size::less_than(&curt_vers, &target)? {
             if curt_proj.verify_restrictions(&target).is_err() {
               println!("  ! Illegal size change for restricted project {}.", curt_proj.id());
@@ -415,11 +1167,16 @@ impl PlanOutput {
                 " "
               };
               println!("    {} commit {} ({}) : {}", symbol, &c.oid()[.. 7], c.size(), c.message().trim());
+              if self.explain {
+                println_explained(c);
+              }
             }
           }
-          ChangelogEntry::Dep(proj_id, proj_name) => {
-            println!("  Depends on: {} ({})", proj_name, proj_id);
-          }
+          ChangelogEntry::Dep(proj_id, proj_name, bump) => match (bump.prev(), bump.next()) {
+            (Some(prev), Some(next)) => println!("  Depends on: {} ({}) {} -> {}", proj_name, proj_id, prev, next),
+            (None, Some(next)) => println!("  Depends on: {} ({}) -> {}", proj_name, proj_id, next),
+            _ => println!("  Depends on: {} ({})", proj_name, proj_id)
+          },
         }
       }
     }
@@ -466,13 +1223,14 @@ impl PlanOutput {
       let curt_config = mono.config();
       let curt_vers = curt_config
         .get_value(id)
-        .with_context(|| format!("Unable to find project {} value.", id))?
-        .unwrap_or_else(|| panic!("No such project {}.", id));
+        .with_context(|| VersioError::MissingVersion(id.clone()))?
+        .ok_or_else(|| VersioError::UncoveredProject(id.clone()))?;
 
-      let proj = curt_config.get_project(id).ok_or_else(|| bad!("No such project ID {}", id))?;
+      let proj = curt_config.get_project(id).ok_or_else(|| VersioError::UncoveredProject(id.clone()))?;
+      let links = proj.release_links(None);
       let proj = ProjLine::from_version(proj, curt_vers.clone())?;
 
-      let html = construct_changelog_html(changelog, proj, &curt_vers, "".to_string(), template)?;
+      let html = construct_changelog_html(changelog, proj, &curt_vers, &links, "".to_string(), template)?;
       println!("{}", html);
       break;
     }
@@ -481,16 +1239,44 @@ impl PlanOutput {
   }
 }
 
+/// Print why the commit has its size, and why it applies to the project.
+fn println_explained(c: &LoggedCommit) {
+  if c.size_entry() == "*" {
+    println!("        type \"{}\" is not in sizes, so it matched \"*\" : {}", c.kind(), c.size());
+  } else {
+    println!("        type \"{}\" matched sizes entry \"{}\" : {}", c.kind(), c.size_entry(), c.size());
+  }
+  if c.scoped() {
+    println!("        scope names this project");
+  }
+  for file in c.files() {
+    println!("        changed {}", file);
+  }
+}
+
 pub struct ReleaseOutput {
-  result: ReleaseResult
+  result: ReleaseResult,
+  events: Events,
+  format: ReleaseFormat,
+  actions: Option<Value>,
+  ci: Option<Ci>
 }
 
 impl Default for ReleaseOutput {
-  fn default() -> ReleaseOutput { ReleaseOutput::new() }
+  fn default() -> ReleaseOutput { ReleaseOutput::new(Events::none(), ReleaseFormat::Text) }
 }
 
 impl ReleaseOutput {
-  pub fn new() -> ReleaseOutput { ReleaseOutput { result: ReleaseResult::Empty } }
+  pub fn new(events: Events, format: ReleaseFormat) -> ReleaseOutput {
+    ReleaseOutput { result: ReleaseResult::Empty, events, format, actions: None, ci: None }
+  }
+
+  pub fn with_ci(mut self, ci: Option<Ci>) -> ReleaseOutput {
+    self.ci = ci;
+    self
+  }
+
+  pub fn write_started(&mut self) -> Result<()> { self.events.phase_started("release") }
 
   pub fn write_empty(&mut self) -> Result<()> {
     self.result = ReleaseResult::Empty;
@@ -498,27 +1284,104 @@ impl ReleaseOutput {
   }
 
   pub fn write_logged(&mut self, path: PathBuf) { self.result.append_logged(path); }
+
   pub fn write_done(&mut self) { self.result.append_done(); }
+
   pub fn write_commit(&mut self) { self.result.append_commit(); }
-  pub fn write_pause(&mut self) { self.result.append_pause(); }
+
+  pub fn write_pause(&mut self, stage: PauseStage) -> Result<()> {
+    self.events.emit("paused", json!({ "stage": stage.as_str() }))?;
+    self.result.append_pause(stage);
+    Ok(())
+  }
+
   pub fn write_dry(&mut self) { self.result.append_dry(); }
+
+  pub fn write_previewed(&mut self, paths: Vec<PathBuf>) {
+    for path in paths {
+      self.result.append_previewed(path);
+    }
+  }
+
+  pub fn write_actions(&mut self, actions: Value) { self.actions = Some(actions); }
   pub fn write_wrote_changelogs(&mut self) { self.result.append_wrote_channgelogs(); }
+  pub fn write_tags_only(&mut self) { self.result.append_tags_only(); }
 
-  pub fn write_changed(&mut self, name: String, prev: String, curt: String, targ: String) {
+  pub fn write_changed(
+    &mut self, id: &ProjectId, name: String, prev: String, curt: String, targ: String
+  ) -> Result<()> {
+    self.emit_project("changed", id, &name, Some(&prev), &curt, Some(&targ))?;
     self.result.append_changed(name, prev, curt, targ);
+    Ok(())
   }
 
-  pub fn write_forward(&mut self, all: bool, name: String, prev: String, curt: String, targ: String) {
+  pub fn write_forward(
+    &mut self, all: bool, id: &ProjectId, name: String, prev: String, curt: String, targ: String
+  ) -> Result<()> {
+    self.emit_project("forward", id, &name, Some(&prev), &curt, Some(&targ))?;
     self.result.append_forward(all, name, prev, curt, targ);
+    Ok(())
   }
 
-  pub fn write_no_change(&mut self, all: bool, locked: bool, name: String, prev: Option<String>, curt: String) {
+  pub fn write_no_change(
+    &mut self, all: bool, locked: bool, id: &ProjectId, name: String, prev: Option<String>, curt: String
+  ) -> Result<()> {
+    self.emit_project("no_change", id, &name, prev.as_deref(), &curt, None)?;
     self.result.append_no_change(all, locked, name, prev, curt);
+    Ok(())
+  }
+
+  pub fn write_new(&mut self, all: bool, id: &ProjectId, name: String, curt: String) -> Result<()> {
+    self.emit_project("new", id, &name, None, &curt, None)?;
+    self.result.append_new(all, name, curt);
+    Ok(())
+  }
+
+  fn emit_project(
+    &self, action: &str, id: &ProjectId, name: &str, prev: Option<&str>, curt: &str, targ: Option<&str>
+  ) -> Result<()> {
+    self.events.emit(
+      "project_released",
+      json!({ "action": action, "id": id.to_string(), "name": name, "prev": prev, "current": curt, "target": targ })
+    )
   }
 
-  pub fn write_new(&mut self, all: bool, name: String, curt: String) { self.result.append_new(all, name, curt); }
+  /// Summarize every project's release for the CI system. If a single project was released, its new version
+  /// becomes the build number.
+  fn report(&self, ci: &Ci) -> Result<()> {
+    let projects = self.result.projects();
+    let changed: Vec<_> = projects.iter().filter(|p| p["action"] == "changed").collect();
+    if let ([project], false) = (changed.as_slice(), self.result.is_dry()) {
+      ci.build_number(project["target"].as_str().unwrap_or(""));
+    }
 
-  pub fn commit(&mut self) { self.result.commit(); }
+    let text = |v: &Value| v.as_str().unwrap_or("").to_string();
+    let rows: Vec<_> = projects
+      .iter()
+      .map(|p| {
+        let new = if p["target"].is_null() { text(&p["current"]) } else { text(&p["target"]) };
+        vec![text(&p["name"]), text(&p["action"]).replace('_', " "), text(&p["prev"]), new]
+      })
+      .collect();
+    ci.summarize("Versio release", &["Project", "Action", "Previous", "New"], &rows)
+  }
+
+  pub fn commit(&mut self) -> Result<()> {
+    self.events.phase_finished("release")?;
+    if let Some(ci) = self.ci.as_ref().filter(|_| !self.events.owns_stdout()) {
+      self.report(ci)?;
+    }
+    if !self.events.owns_stdout() {
+      match self.format {
+        ReleaseFormat::Text => self.result.commit(),
+        ReleaseFormat::Json => {
+          let output = json!({ "projects": self.result.projects(), "actions": self.actions });
+          println!("{}", serde_json::to_string_pretty(&output)?);
+        }
+      }
+    }
+    Ok(())
+  }
 }
 
 enum ReleaseResult {
@@ -530,9 +1393,11 @@ impl ReleaseResult {
   fn append_logged(&mut self, path: PathBuf) { self.append(ReleaseEvent::Logged(path)); }
   fn append_done(&mut self) { self.append(ReleaseEvent::Done); }
   fn append_commit(&mut self) { self.append(ReleaseEvent::Commit); }
-  fn append_pause(&mut self) { self.append(ReleaseEvent::Pause); }
+  fn append_pause(&mut self, stage: PauseStage) { self.append(ReleaseEvent::Pause(stage)); }
   fn append_dry(&mut self) { self.append(ReleaseEvent::Dry); }
+  fn append_previewed(&mut self, path: PathBuf) { self.append(ReleaseEvent::Previewed(path)); }
   fn append_wrote_channgelogs(&mut self) { self.append(ReleaseEvent::WroteChangelogs); }
+  fn append_tags_only(&mut self) { self.append(ReleaseEvent::TagsOnly); }
 
   fn append_changed(&mut self, name: String, prev: String, curt: String, targ: String) {
     self.append(ReleaseEvent::Changed(name, prev, curt, targ));
@@ -567,6 +1432,21 @@ impl ReleaseResult {
       ReleaseResult::Wrote(w) => w.commit()
     }
   }
+
+  fn is_dry(&self) -> bool {
+    match self {
+      ReleaseResult::Empty => false,
+      ReleaseResult::Wrote(w) => w.events.iter().any(|ev| matches!(ev, ReleaseEvent::Dry))
+    }
+  }
+
+  /// The per-project results, for machine-readable output: this includes unchanged projects.
+  fn projects(&self) -> Vec<Value> {
+    match self {
+      ReleaseResult::Empty => Vec::new(),
+      ReleaseResult::Wrote(w) => w.events.iter().filter_map(|ev| ev.project()).collect()
+    }
+  }
 }
 
 struct WroteReleases {
@@ -586,26 +1466,49 @@ impl WroteReleases {
 
 enum ReleaseEvent {
   Logged(PathBuf),
+  Previewed(PathBuf),
   Changed(String, String, String, String),
   Forward(bool, String, String, String, String),
   NoChange(bool, bool, String, Option<String>, String),
   New(bool, String, String),
   Commit,
-  Pause,
+  Pause(PauseStage),
   Dry,
   WroteChangelogs,
+  TagsOnly,
   Done
 }
 
 impl ReleaseEvent {
+  fn project(&self) -> Option<Value> {
+    let project = |action: &str, name: &str, prev: Option<&str>, curt: &str, targ: Option<&str>| {
+      json!({ "action": action, "name": name, "prev": prev, "current": curt, "target": targ })
+    };
+
+    match self {
+      ReleaseEvent::Changed(name, prev, curt, targ) => Some(project("changed", name, Some(prev), curt, Some(targ))),
+      ReleaseEvent::Forward(_, name, prev, curt, targ) => {
+        Some(project("forward", name, Some(prev), curt, Some(targ)))
+      }
+      ReleaseEvent::NoChange(_, locked, name, prev, curt) => {
+        let action = if *locked { "locked" } else { "no_change" };
+        Some(project(action, name, prev.as_deref(), curt, None))
+      }
+      ReleaseEvent::New(_, name, curt) => Some(project("new", name, None, curt, None)),
+      _ => None
+    }
+  }
+
   fn commit(&mut self) {
     match self {
       ReleaseEvent::Logged(p) => println!("Wrote changelog at {}.", p.to_string_lossy()),
+      ReleaseEvent::Previewed(p) => println!("Previewed {}.", p.to_string_lossy()),
       ReleaseEvent::Done => println!("Release complete."),
       ReleaseEvent::Commit => println!("Changes committed."),
-      ReleaseEvent::Pause => println!("Paused for commit: use --resume to continue."),
+      ReleaseEvent::Pause(stage) => println!("Paused for {}: use --resume to continue.", stage.as_str()),
       ReleaseEvent::Dry => println!("Dry run: no actual changes."),
       ReleaseEvent::WroteChangelogs => println!("Changelogs only: only changelogs written."),
+      ReleaseEvent::TagsOnly => println!("Tags only: no files written."),
       ReleaseEvent::Changed(name, prev, curt, targ) => {
         if prev == curt {
           println!("  {} : {} -> {}", name, prev, targ);
@@ -646,3 +1549,35 @@ impl ReleaseEvent {
     }
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::ReleaseOutput;
+  use crate::commands::ReleaseFormat;
+  use crate::config::ProjectId;
+  use crate::events::Events;
+  use serde_json::{json, Value};
+
+  #[test]
+  fn test_project_released_event() {
+    let file = std::env::temp_dir().join(format!("versio-events-{}.ndjson", std::process::id()));
+    let result = (|| -> crate::errors::Result<_> {
+      let mut output = ReleaseOutput::new(Events::ndjson(Some(&file))?, ReleaseFormat::Text);
+      output.write_changed(&ProjectId::from_id(3), "proj".into(), "1.0.0".into(), "1.0.0".into(), "1.1.0".into())?;
+      output.write_new(true, &ProjectId::from_id(4), "other".into(), "0.1.0".into())?;
+      Ok(std::fs::read_to_string(&file)?)
+    })();
+    std::fs::remove_file(&file).unwrap();
+
+    let events: Vec<Value> = result.unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    let changed = json!({
+      "event": "project_released", "action": "changed", "id": "3", "name": "proj", "prev": "1.0.0",
+      "current": "1.0.0", "target": "1.1.0"
+    });
+    let new = json!({
+      "event": "project_released", "action": "new", "id": "4", "name": "other", "prev": null, "current": "0.1.0",
+      "target": null
+    });
+    assert_eq!(events, vec![changed, new]);
+  }
+}
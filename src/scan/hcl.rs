@@ -0,0 +1,430 @@
+//! Utilities to find a mark in an HCL (Terraform) file.
+
+use crate::bail;
+use crate::errors::{Error, Result};
+use crate::mark::Mark;
+#[cfg(test)]
+use crate::scan::parts::ToPart;
+use crate::scan::parts::{IntoPartVec, Part};
+use crate::scan::Scanner;
+
+pub struct HclScanner {
+  target: Vec<Part>
+}
+
+impl HclScanner {
+  pub fn new(target: &str) -> HclScanner { HclScanner { target: target.into_part_vec() } }
+
+  #[cfg(test)]
+  pub fn from_parts(target: &[&dyn ToPart]) -> HclScanner { HclScanner { target: target.into_part_vec() } }
+}
+
+impl Scanner for HclScanner {
+  fn build(parts: Vec<Part>) -> HclScanner { HclScanner { target: parts } }
+  fn find(&self, data: &str) -> Result<Mark> { scan_hcl(data, self.target.clone()) }
+}
+
+/// Find the string at the target. Each part names an attribute, an object key, a tuple index, or a block type or
+/// label: so `module.vpc.version` is the `version` attribute of a `module "vpc" { ... }` block. If the string is a
+/// simple version constraint like `~> 1.2.0`, only its version is marked, so that its operator is kept.
+fn scan_hcl<P: IntoPartVec>(data: &str, loc: P) -> Result<Mark> {
+  let parts = loc.into_part_vec();
+  if parts.is_empty() {
+    bail!("No parts found for HCL spec");
+  }
+
+  match Cursor::new(data).body(&parts, false)? {
+    Some(mark) => constraint_version(mark),
+    None => bail!("Couldn't find version in HCL: no value at {:?}", parts)
+  }
+}
+
+fn constraint_version(mark: Mark) -> Result<Mark> {
+  let value = mark.value();
+  if value.contains(',') {
+    bail!("Can't pick a single version from constraint \"{}\".", value);
+  }
+
+  let ops = ["~>", ">=", "<=", "!=", "=", ">", "<"];
+  let op = ops.iter().find(|op| value.starts_with(**op)).map(|op| op.len()).unwrap_or(0);
+  let version = value[op ..].trim_start();
+  Ok(Mark::new(version.to_string(), mark.start() + value.len() - version.len()))
+}
+
+fn is_key(part: &Part, key: &str) -> bool {
+  match part {
+    Part::Map(k) => k == key,
+    Part::Seq(n) => n.to_string() == key
+  }
+}
+
+struct Cursor<'a> {
+  data: &'a str,
+  pos: usize
+}
+
+impl<'a> Cursor<'a> {
+  fn new(data: &'a str) -> Cursor<'a> { Cursor { data, pos: 0 } }
+  fn rest(&self) -> &'a str { &self.data[self.pos ..] }
+  fn peek(&self) -> Option<char> { self.rest().chars().next() }
+
+  fn bump(&mut self) {
+    if let Some(c) = self.peek() {
+      self.pos += c.len_utf8();
+    }
+  }
+
+  fn unexpected(&self) -> Error {
+    match self.peek() {
+      Some(c) => bad!("Unexpected \"{}\" at byte {} of HCL.", c, self.pos),
+      None => bad!("Unexpected end of HCL.")
+    }
+  }
+
+  /// Skip spaces and comments; and also newlines, if `lines` is set.
+  fn skip(&mut self, lines: bool) {
+    loop {
+      let rest = self.rest();
+      if rest.starts_with('#') || rest.starts_with("//") {
+        self.pos += rest.find('\n').unwrap_or(rest.len());
+      } else if rest.starts_with("/*") {
+        self.pos += rest.find("*/").map(|e| e + 2).unwrap_or(rest.len());
+      } else {
+        match self.peek() {
+          Some('\n') if lines => self.bump(),
+          Some(c) if c != '\n' && c.is_whitespace() => self.bump(),
+          _ => return
+        }
+      }
+    }
+  }
+
+  fn ident(&mut self) -> Option<&'a str> {
+    let rest = self.rest();
+    if !rest.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+      return None;
+    }
+    let len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-')).unwrap_or(rest.len());
+    self.pos += len;
+    Some(&rest[.. len])
+  }
+
+  /// Read a quoted string, marking its (unescaped) contents.
+  fn string(&mut self) -> Result<Mark> {
+    self.bump();
+    let start = self.pos;
+    loop {
+      match self.peek() {
+        Some('"') => {
+          let mark = Mark::new(self.data[start .. self.pos].to_string(), start);
+          self.bump();
+          return Ok(mark);
+        }
+        Some('\\') => {
+          self.bump();
+          self.bump();
+        }
+        Some('$') | Some('%') if self.rest()[1 ..].starts_with('{') => {
+          self.pos += 2;
+          self.skip_group('}')?;
+        }
+        Some('\n') | None => bail!("Unterminated string at byte {} of HCL.", start),
+        Some(_) => self.bump()
+      }
+    }
+  }
+
+  /// Skip a heredoc like `<<EOT ... EOT` or `<<-EOT ... EOT`.
+  fn heredoc(&mut self) -> Result<()> {
+    self.pos += 2;
+    if self.rest().starts_with('-') {
+      self.bump();
+    }
+    let tag = self.ident().ok_or_else(|| self.unexpected())?;
+    while let Some(eol) = self.rest().find('\n') {
+      self.pos += eol + 1;
+      if self.rest().lines().next().map(|l| l.trim() == tag) == Some(true) {
+        self.pos += tag.len() + self.rest().len() - self.rest().trim_start().len();
+        return Ok(());
+      }
+    }
+    bail!("Unterminated heredoc \"{}\" in HCL.", tag)
+  }
+
+  /// Skip past the matching `close`, over any nested brackets and strings.
+  fn skip_group(&mut self, close: char) -> Result<()> {
+    loop {
+      self.skip(true);
+      match self.peek() {
+        Some(c) if c == close => {
+          self.bump();
+          return Ok(());
+        }
+        Some('"') => {
+          self.string()?;
+        }
+        Some('{') | Some('[') | Some('(') => self.skip_nested()?,
+        Some('<') if self.rest().starts_with("<<") => self.heredoc()?,
+        Some(_) => self.bump(),
+        None => bail!("Missing \"{}\" in HCL.", close)
+      }
+    }
+  }
+
+  fn skip_nested(&mut self) -> Result<()> {
+    let close = match self.peek() {
+      Some('{') => '}',
+      Some('[') => ']',
+      _ => ')'
+    };
+    self.bump();
+    self.skip_group(close)
+  }
+
+  /// Skip an expression, up to the end of its line or its enclosing object or tuple.
+  fn skip_expr(&mut self) -> Result<()> {
+    loop {
+      self.skip(false);
+      match self.peek() {
+        None | Some('\n') | Some(',') | Some('}') | Some(']') | Some(')') => return Ok(()),
+        Some('"') => {
+          self.string()?;
+        }
+        Some('{') | Some('[') | Some('(') => self.skip_nested()?,
+        Some('<') if self.rest().starts_with("<<") => self.heredoc()?,
+        Some(_) => self.bump()
+      }
+    }
+  }
+
+  /// Search the attributes and blocks of a body, which is the whole file unless `nested` is set.
+  fn body(&mut self, parts: &[Part], nested: bool) -> Result<Option<Mark>> {
+    loop {
+      self.skip(true);
+      match self.peek() {
+        None if nested => bail!("Missing \"}}\" in HCL."),
+        None => return Ok(None),
+        Some('}') if nested => {
+          self.bump();
+          return Ok(None);
+        }
+        _ => ()
+      }
+
+      let name = self.ident().ok_or_else(|| self.unexpected())?;
+      self.skip(false);
+      if self.rest().starts_with('=') && !self.rest().starts_with("==") {
+        self.bump();
+        if is_key(&parts[0], name) {
+          if let Some(mark) = self.value(&parts[1 ..])? {
+            return Ok(Some(mark));
+          }
+        } else {
+          self.skip_expr()?;
+        }
+        continue;
+      }
+
+      // A block: its labels must match the parts that follow its type.
+      let mut rest = if is_key(&parts[0], name) { Some(&parts[1 ..]) } else { None };
+      loop {
+        self.skip(false);
+        let label = match self.peek() {
+          Some('{') => break,
+          Some('"') => self.string()?.into_value(),
+          _ => self.ident().ok_or_else(|| self.unexpected())?.to_string()
+        };
+        rest = rest.filter(|r| r.first().map(|p| is_key(p, &label)) == Some(true)).map(|r| &r[1 ..]);
+      }
+      self.bump();
+
+      match rest {
+        Some(rest) if !rest.is_empty() => {
+          if let Some(mark) = self.body(rest, true)? {
+            return Ok(Some(mark));
+          }
+        }
+        _ => self.skip_group('}')?
+      }
+    }
+  }
+
+  /// Search an attribute value: the target itself is a string if there are no parts left.
+  fn value(&mut self, parts: &[Part]) -> Result<Option<Mark>> {
+    self.skip(false);
+    if parts.is_empty() {
+      return match self.peek() {
+        Some('"') => Ok(Some(self.string()?)),
+        _ => bail!("Value at byte {} of HCL is not a string.", self.pos)
+      };
+    }
+
+    match self.peek() {
+      Some('{') => self.object(parts),
+      Some('[') => self.tuple(parts),
+      _ => {
+        self.skip_expr()?;
+        Ok(None)
+      }
+    }
+  }
+
+  fn object(&mut self, parts: &[Part]) -> Result<Option<Mark>> {
+    self.bump();
+    loop {
+      self.skip(true);
+      let key = match self.peek() {
+        Some('}') => {
+          self.bump();
+          return Ok(None);
+        }
+        Some(',') => {
+          self.bump();
+          continue;
+        }
+        Some('"') => self.string()?.into_value(),
+        _ => self.ident().ok_or_else(|| self.unexpected())?.to_string()
+      };
+
+      self.skip(false);
+      match self.peek() {
+        Some('=') | Some(':') => self.bump(),
+        _ => return Err(self.unexpected())
+      }
+
+      if is_key(&parts[0], &key) {
+        if let Some(mark) = self.value(&parts[1 ..])? {
+          return Ok(Some(mark));
+        }
+      } else {
+        self.skip_expr()?;
+      }
+    }
+  }
+
+  fn tuple(&mut self, parts: &[Part]) -> Result<Option<Mark>> {
+    self.bump();
+    let mut i = 0;
+    loop {
+      self.skip(true);
+      match self.peek() {
+        Some(']') => {
+          self.bump();
+          return Ok(None);
+        }
+        None => bail!("Missing \"]\" in HCL."),
+        _ => ()
+      }
+
+      if is_key(&parts[0], &i.to_string()) {
+        if let Some(mark) = self.value(&parts[1 ..])? {
+          return Ok(Some(mark));
+        }
+      } else {
+        self.skip_expr()?;
+      }
+
+      i += 1;
+      self.skip(true);
+      if self.peek() == Some(',') {
+        self.bump();
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::HclScanner;
+  use crate::scan::Scanner;
+
+  #[test]
+  fn test_hcl_attribute() {
+    let doc = r#"
+version = "1.2.3""#;
+
+    let mark = HclScanner::new("version").find(doc).unwrap();
+    assert_eq!("1.2.3", mark.value());
+    assert_eq!(12, mark.start());
+  }
+
+  #[test]
+  fn test_hcl_module() {
+    let doc = r#"
+# The network.
+module "vpc" {
+  source  = "app.terraform.io/example/vpc/aws"
+  version = "~> 1.2.0"
+}
+
+module "db" {
+  source  = "app.terraform.io/example/db/aws"
+  version = "2.0.1"
+}"#;
+
+    let mark = HclScanner::new("module.vpc.version").find(doc).unwrap();
+    assert_eq!("1.2.0", mark.value());
+    assert_eq!(&doc[mark.start() .. mark.start() + 5], "1.2.0");
+
+    let mark = HclScanner::new("module.db.version").find(doc).unwrap();
+    assert_eq!("2.0.1", mark.value());
+    assert_eq!(&doc[mark.start() .. mark.start() + 5], "2.0.1");
+  }
+
+  #[test]
+  fn test_hcl_required_providers() {
+    let doc = r#"
+terraform {
+  required_version = ">= 1.3.0"
+
+  required_providers {
+    aws = {
+      source  = "hashicorp/aws"
+      version = ">= 4.2.0"
+    }
+    random = { source = "hashicorp/random", version = "3.1.0" }
+  }
+}"#;
+
+    let mark = HclScanner::new("terraform.required_providers.aws.version").find(doc).unwrap();
+    assert_eq!("4.2.0", mark.value());
+    assert_eq!(&doc[mark.start() .. mark.start() + 5], "4.2.0");
+
+    let mark = HclScanner::new("terraform.required_providers.random.version").find(doc).unwrap();
+    assert_eq!("3.1.0", mark.value());
+
+    let mark = HclScanner::new("terraform.required_version").find(doc).unwrap();
+    assert_eq!("1.3.0", mark.value());
+  }
+
+  #[test]
+  fn test_hcl_skips() {
+    let doc = r#"
+locals {
+  tags = ["a", "b}"]
+  policy = <<-EOT
+    version = "0.0.1"
+  EOT
+  name = "${var.prefix}-${lookup(var.names, "x", "y")}"
+}
+
+/* module "vpc" { version = "0.0.2" } */
+module "vpc" {
+  version = "1.2.3"
+}"#;
+
+    let mark = HclScanner::from_parts(&[&"module", &"vpc", &"version"]).find(doc).unwrap();
+    assert_eq!("1.2.3", mark.value());
+    assert_eq!(&doc[mark.start() .. mark.start() + 5], "1.2.3");
+  }
+
+  #[test]
+  fn test_hcl_compound() {
+    let doc = r#"
+module "vpc" {
+  version = ">= 1.2.0, < 2.0.0"
+}"#;
+
+    assert!(HclScanner::new("module.vpc.version").find(doc).is_err());
+  }
+}
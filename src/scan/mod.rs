@@ -1,13 +1,16 @@
+mod hcl;
 mod json;
 pub mod parts;
 mod toml;
 mod xml;
 mod yaml;
 
+pub use self::hcl::HclScanner;
 pub use self::json::JsonScanner;
 pub use self::toml::TomlScanner;
 pub use self::xml::XmlScanner;
 pub use self::yaml::YamlScanner;
+use crate::bail;
 use crate::errors::Result;
 use crate::mark::{Mark, MarkedData, NamedData};
 use crate::scan::parts::Part;
@@ -43,3 +46,28 @@ pub fn scan_reg_data(data: NamedData, pattern: &str) -> Result<MarkedData> {
   let mark = find_reg_data(data.data(), pattern)?;
   Ok(data.mark(mark))
 }
+
+/// Find every match of the pattern, ensuring that they all have the same value.
+pub fn find_all_reg_data(data: &str, pattern: &str) -> Result<Vec<Mark>> {
+  let pattern = Regex::new(pattern)?;
+  let mut marks = Vec::new();
+  for found in pattern.captures_iter(data) {
+    let item = found.get(1).ok_or_else(|| bad!("No capture group in {}.", pattern))?;
+    if let Some(first) = marks.first().map(|m: &Mark| m.value()) {
+      if first != item.as_str() {
+        bail!("Mismatched values for {}: \"{}\" vs \"{}\".", pattern, first, item.as_str());
+      }
+    }
+    marks.push(Mark::new(item.as_str().to_string(), item.start()));
+  }
+
+  if marks.is_empty() {
+    bail!("No match for {}", pattern);
+  }
+  Ok(marks)
+}
+
+pub fn scan_all_reg_data(data: NamedData, pattern: &str) -> Result<MarkedData> {
+  let marks = find_all_reg_data(data.data(), pattern)?;
+  Ok(data.mark_all(marks))
+}
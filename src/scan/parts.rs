@@ -13,7 +13,33 @@ impl IntoPartVec for Vec<Part> {
 }
 
 impl IntoPartVec for &str {
-  fn into_part_vec(self) -> Vec<Part> { self.split('.').map(parse_part).collect() }
+  fn into_part_vec(self) -> Vec<Part> { split_parts(self) }
+}
+
+/// Split a dotted target into its parts. A part in double quotes (as in `package.metadata."my.tool".version`) is
+/// always a map key, even if it contains dots or looks like a number.
+fn split_parts(target: &str) -> Vec<Part> {
+  let to_part = |part: &str, quoted: bool| if quoted { Part::Map(part.to_string()) } else { parse_part(part) };
+
+  let mut parts = Vec::new();
+  let mut current = String::new();
+  let (mut in_quotes, mut quoted) = (false, false);
+  for c in target.chars() {
+    match c {
+      '"' => {
+        in_quotes = !in_quotes;
+        quoted = true;
+      }
+      '.' if !in_quotes => {
+        parts.push(to_part(&current, quoted));
+        current.clear();
+        quoted = false;
+      }
+      c => current.push(c)
+    }
+  }
+  parts.push(to_part(&current, quoted));
+  parts
 }
 
 impl IntoPartVec for &[&dyn ToPart] {
@@ -60,9 +60,10 @@ impl<'de> Visitor<'de> for NthElement {
   where
     V: MapAccess<'de>
   {
+    // A numeric part can also name a table, such as `[2024]`.
     let expected_key: String = match &self.part {
       Part::Map(key) => key.clone(),
-      _ => return Err(de::Error::invalid_type(Unexpected::Map, &self))
+      Part::Seq(n) => n.to_string()
     };
 
     let mut got_val: Option<Spanned<String>> = None;
@@ -175,6 +176,40 @@ thing = [ "thing2", "1.2.3" ]"#;
     assert_eq!(24, mark.start());
   }
 
+  #[test]
+  fn test_toml_quoted() {
+    let doc = r#"
+[[0]]
+"the.version" = "1.2.3""#;
+
+    let mark = TomlScanner::new("0.0.\"the.version\"").find(doc).unwrap();
+    assert_eq!("1.2.3", mark.value());
+    assert_eq!(24, mark.start());
+  }
+
+  #[test]
+  fn test_toml_tables() {
+    let doc = r#"
+[package]
+metadata.tool.version = "2.0.0"
+
+[[bin]]
+name = "first"
+version = "1.0.0"
+
+[[bin]]
+name = "second"
+version = "1.2.3""#;
+
+    let mark = TomlScanner::new("package.metadata.tool.version").find(doc).unwrap();
+    assert_eq!("2.0.0", mark.value());
+    assert_eq!(36, mark.start());
+
+    let mark = TomlScanner::new("bin.1.version").find(doc).unwrap();
+    assert_eq!("1.2.3", mark.value());
+    assert_eq!(&doc[mark.start() .. mark.start() + 5], "1.2.3");
+  }
+
   #[test]
   fn test_toml_utf8() {
     let doc = r#"
@@ -27,6 +27,7 @@ impl Scanner for XmlScanner {
 
 fn scan_xml<P: IntoPartVec>(data: &str, loc: P) -> Result<Mark> {
   let mut parts = loc.into_part_vec();
+  let attr = split_attr(&mut parts);
   parts.reverse();
 
   if parts.is_empty() {
@@ -35,22 +36,36 @@ fn scan_xml<P: IntoPartVec>(data: &str, loc: P) -> Result<Mark> {
 
   let mut extra_depth = 0;
   let mut on_target = false;
+  // The parts matched by the open elements, which are restored as each element closes: an element (such as one of
+  // several `PropertyGroup`s) might not hold the target, which can then be in a later sibling.
+  let mut matched = Vec::new();
 
   for token in Tokenizer::from(data) {
     match token? {
       Token::ElementStart { local, .. } => {
         if extra_depth == 0 && is_match_str(local.as_str(), parts.last()) {
-          parts.pop();
-          if parts.is_empty() {
-            on_target = true;
-          }
+          matched.extend(parts.pop());
+          on_target = parts.is_empty();
         } else {
           extra_depth += 1;
         }
       }
+      Token::Attribute { local, value, .. } if on_target && attr.as_deref() == Some(local.as_str()) => {
+        return Ok(Mark::new(value.as_str().into(), value.start()));
+      }
+      Token::ElementEnd { end, .. } if on_target && attr.is_some() => {
+        // The target doesn't have the attribute, but a later one might.
+        on_target = false;
+        if is_ending(&end) {
+          parts.extend(matched.pop());
+        }
+      }
       Token::ElementEnd { end, .. } if is_ending(&end) => {
         if extra_depth > 0 {
           extra_depth -= 1;
+        } else if let Some(part) = matched.pop() {
+          parts.push(part);
+          on_target = false;
         } else {
           bail!("Couldn't find version in XML: still expecting {:?}", parts);
         }
@@ -67,6 +82,18 @@ fn scan_xml<P: IntoPartVec>(data: &str, loc: P) -> Result<Mark> {
   bail!("Couldn't find version at end of XML: still expecting {:?}", parts)
 }
 
+/// Remove the attribute (as in `Project@Version`) from the last part of the target, if there is one.
+fn split_attr(parts: &mut [Part]) -> Option<String> {
+  match parts.last_mut() {
+    Some(Part::Map(last)) => last.find('@').map(|i| {
+      let attr = last[i + 1 ..].to_string();
+      last.truncate(i);
+      attr
+    }),
+    _ => None
+  }
+}
+
 fn is_ending(end: &ElementEnd) -> bool { matches!(end, ElementEnd::Close(..) | ElementEnd::Empty) }
 
 #[cfg(test)]
@@ -110,6 +137,60 @@ mod test {
     assert_eq!(21, mark.start());
   }
 
+  #[test]
+  fn test_xml_attr() {
+    let doc = r#"
+<Project Sdk="Microsoft.NET.Sdk" Version="1.2.3">
+  <Version>0.0.1</Version>
+</Project>"#;
+
+    let mark = XmlScanner::new("Project@Version").find(doc).unwrap();
+    assert_eq!("1.2.3", mark.value());
+    assert_eq!(43, mark.start());
+  }
+
+  #[test]
+  fn test_xml_attr_nested() {
+    let doc = r#"
+<project>
+  <thing version="1.2.3" />
+</project>"#;
+
+    let mark = XmlScanner::new("project.thing@version").find(doc).unwrap();
+    assert_eq!("1.2.3", mark.value());
+    assert_eq!(29, mark.start());
+    assert!(XmlScanner::new("project.thing@other").find(doc).is_err());
+  }
+
+  #[test]
+  fn test_xml_later_sibling() {
+    let doc = r#"
+<Project Sdk="Microsoft.NET.Sdk">
+  <PropertyGroup>
+    <TargetFramework>net8.0</TargetFramework>
+  </PropertyGroup>
+  <PropertyGroup Condition="'$(Configuration)' == 'Release'" />
+  <PropertyGroup>
+    <Version>1.2.3</Version>
+  </PropertyGroup>
+</Project>"#;
+
+    let mark = XmlScanner::new("Project.PropertyGroup.Version").find(doc).unwrap();
+    assert_eq!("1.2.3", mark.value());
+    assert_eq!(doc.find("1.2.3").unwrap(), mark.start());
+    assert!(XmlScanner::new("Project.PropertyGroup.Other").find(doc).is_err());
+
+    let doc = r#"
+<project>
+  <thing />
+  <thing version="1.2.3" />
+</project>"#;
+
+    let mark = XmlScanner::new("project.thing@version").find(doc).unwrap();
+    assert_eq!("1.2.3", mark.value());
+    assert_eq!(doc.find("1.2.3").unwrap(), mark.start());
+  }
+
   #[test]
   fn test_xml_utf8() {
     let doc = r#"
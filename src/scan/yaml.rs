@@ -14,7 +14,6 @@ pub struct YamlScanner {
 }
 
 impl YamlScanner {
-  #[cfg(test)]
   pub fn new(target: &str) -> YamlScanner { YamlScanner { target: target.into_part_vec() } }
 
   #[cfg(test)]
@@ -1,30 +1,88 @@
 //! The mechanisms used to read and write state, both current and historical.
 
-use crate::config::{CommitConfig, HookSet, ProjectId};
+use crate::config::{match_opts, par_map, CommitConfig, HookSet, ProjectId, CONFIG_FILENAME};
 use crate::errors::{Context as _, Result};
-use crate::git::{FromTagBuf, Repo, Slice};
+use crate::events::{Events, PROGRESS};
+use crate::fetch::FetchOpts;
+use crate::git::{blob_hash, Repo, Slice};
 use crate::mark::{NamedData, Picker};
+use crate::{bad, bail};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use glob::Pattern;
+use ignore::{Walk, WalkBuilder};
 use path_slash::{PathBufExt as _, PathExt as _};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::collections::{HashMap, HashSet};
 use std::fs::OpenOptions;
 use std::mem::take;
-use std::path::{Path, PathBuf};
-use tracing::{trace, warn};
+use std::path::{Component, Path, PathBuf};
+use tracing::{info, info_span, trace, warn};
+
+/// The file at the base of the repo that holds the paused state of a release, unless another file is given.
+pub const PAUSED_FILENAME: &str = ".versio-paused";
+
+/// A stage of a release that can be paused before: each stage runs after the ones before it.
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum PauseStage {
+  /// Commit the written files.
+  #[default]
+  Commit,
+  /// Create the tags.
+  Tag,
+  /// Push the branch and tags.
+  Push
+}
+
+impl PauseStage {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      PauseStage::Commit => "commit",
+      PauseStage::Tag => "tag",
+      PauseStage::Push => "push"
+    }
+  }
+}
+
+/// Where to pause a release: before which stage, and the file to save the paused state in.
+#[derive(Copy, Clone, Debug)]
+pub struct Pause<'a> {
+  stage: PauseStage,
+  file: &'a Path
+}
+
+impl<'a> Pause<'a> {
+  pub fn new(stage: PauseStage, file: &'a Path) -> Pause<'a> { Pause { stage, file } }
+  pub fn stage(&self) -> PauseStage { self.stage }
+  pub fn file(&self) -> &'a Path { self.file }
+}
 
 pub trait StateRead: FilesRead {
   fn latest_tag(&self, proj: &ProjectId) -> Option<&String>;
+
+  /// True if this is the state of the working tree, rather than of an earlier commit.
+  fn is_current(&self) -> bool { false }
+
+  /// How to fetch any URLs that the state refers to.
+  fn fetch_opts(&self) -> FetchOpts;
 }
 
 impl<S: StateRead> StateRead for &S {
   fn latest_tag(&self, proj: &ProjectId) -> Option<&String> { <S as StateRead>::latest_tag(*self, proj) }
+  fn is_current(&self) -> bool { <S as StateRead>::is_current(*self) }
+  fn fetch_opts(&self) -> FetchOpts { <S as StateRead>::fetch_opts(*self) }
 }
 
 pub trait FilesRead {
   fn has_file(&self, path: &Path) -> Result<bool>;
   fn read_file(&self, path: &Path) -> Result<String>;
   fn subdirs(&self, root: Option<&String>, regex: &str) -> Result<Vec<String>>;
+
+  /// Find all files whose path (relative to the repo root) matches the glob, in sorted order.
+  fn files_matching(&self, pattern: &Pattern) -> Result<Vec<String>>;
 }
 
 impl<F: FilesRead> FilesRead for &F {
@@ -33,25 +91,40 @@ impl<F: FilesRead> FilesRead for &F {
   fn subdirs(&self, root: Option<&String>, regex: &str) -> Result<Vec<String>> {
     <F as FilesRead>::subdirs(*self, root, regex)
   }
+  fn files_matching(&self, pattern: &Pattern) -> Result<Vec<String>> {
+    <F as FilesRead>::files_matching(*self, pattern)
+  }
 }
 
 pub struct CurrentState {
   files: CurrentFiles,
-  tags: OldTags
+  tags: OldTags,
+  fetch: FetchOpts
 }
 
 impl FilesRead for CurrentState {
   fn has_file(&self, path: &Path) -> Result<bool> { self.files.has_file(path) }
   fn read_file(&self, path: &Path) -> Result<String> { self.files.read_file(path) }
   fn subdirs(&self, root: Option<&String>, regex: &str) -> Result<Vec<String>> { self.files.subdirs(root, regex) }
+  fn files_matching(&self, pattern: &Pattern) -> Result<Vec<String>> { self.files.files_matching(pattern) }
 }
 
 impl StateRead for CurrentState {
   fn latest_tag(&self, proj: &ProjectId) -> Option<&String> { self.tags.latest(proj) }
+  fn is_current(&self) -> bool { true }
+  fn fetch_opts(&self) -> FetchOpts { self.fetch.clone() }
 }
 
 impl CurrentState {
-  pub fn new(root: PathBuf, tags: OldTags) -> CurrentState { CurrentState { files: CurrentFiles::new(root), tags } }
+  pub fn new(root: PathBuf, tags: OldTags) -> CurrentState {
+    CurrentState { files: CurrentFiles::new(root), tags, fetch: FetchOpts::default() }
+  }
+
+  pub fn with_fetch_opts(mut self, fetch: FetchOpts) -> CurrentState {
+    self.fetch = fetch;
+    self
+  }
+
   pub fn old_tags(&self) -> &OldTags { &self.tags }
 }
 
@@ -66,13 +139,33 @@ impl FilesRead for CurrentFiles {
   fn subdirs(&self, root: Option<&String>, regex: &str) -> Result<Vec<String>> {
     let filter = Regex::new(regex)?;
     let root = root.map(|s| s.as_str()).unwrap_or(".");
-    PathBuf::from_slash(root)
-      .read_dir()?
-      .filter_map(|e| e.map(|e| e.file_name().into_string().ok()).transpose())
+    unignored_walk(&PathBuf::from_slash(root), Some(1))
+      .filter(|e| e.as_ref().map(|e| e.depth() == 1).unwrap_or(true))
+      .filter_map(|e| e.map(|e| e.file_name().to_str().map(|n| n.to_string())).transpose())
       .filter(|n| n.as_ref().map(|n| filter.is_match(n)).unwrap_or(true))
       .map(|r| r.map_err(|e| e.into()))
       .collect()
   }
+
+  fn files_matching(&self, pattern: &Pattern) -> Result<Vec<String>> {
+    let mut found = Vec::new();
+    for entry in unignored_walk(&self.root, None) {
+      let entry = entry?;
+      let path = entry.path().strip_prefix(&self.root).unwrap_or_else(|_| entry.path()).to_slash_lossy();
+      if entry.file_type().map(|t| t.is_file()).unwrap_or(false) && pattern.matches_with(&path, match_opts()) {
+        found.push(path.into_owned());
+      }
+    }
+    found.sort();
+    Ok(found)
+  }
+}
+
+/// Walk the files and directories under `root`, skipping anything excluded by `.gitignore` (or similar) files.
+///
+/// This keeps build output and other untracked paths (e.g. `target/v2`) from being mistaken for project content.
+pub fn unignored_walk(root: &Path, max_depth: Option<usize>) -> Walk {
+  WalkBuilder::new(root).max_depth(max_depth).hidden(false).filter_entry(|e| e.file_name() != ".git").build()
 }
 
 impl CurrentFiles {
@@ -88,10 +181,12 @@ impl<'r> FilesRead for PrevState<'r> {
   fn has_file(&self, path: &Path) -> Result<bool> { self.files.has_file(path) }
   fn read_file(&self, path: &Path) -> Result<String> { self.files.read_file(path) }
   fn subdirs(&self, root: Option<&String>, regex: &str) -> Result<Vec<String>> { self.files.subdirs(root, regex) }
+  fn files_matching(&self, pattern: &Pattern) -> Result<Vec<String>> { self.files.files_matching(pattern) }
 }
 
 impl<'r> StateRead for PrevState<'r> {
   fn latest_tag(&self, proj: &ProjectId) -> Option<&String> { self.tags.latest(proj) }
+  fn fetch_opts(&self) -> FetchOpts { self.files.slice.fetch_opts() }
 }
 
 impl<'r> PrevState<'r> {
@@ -107,13 +202,21 @@ impl<'r> FilesRead for PrevFiles<'r> {
   fn read_file(&self, path: &Path) -> Result<String> { read_from_slice(&self.slice, path) }
 
   fn subdirs(&self, root: Option<&String>, regex: &str) -> Result<Vec<String>> { self.slice.subdirs(root, regex) }
+  fn files_matching(&self, pattern: &Pattern) -> Result<Vec<String>> { self.slice.files_matching(pattern) }
 }
 
 impl<'r> PrevFiles<'r> {
   pub fn from_slice(slice: Slice<'r>) -> Result<PrevFiles> { Ok(PrevFiles::new(slice)) }
 
   pub fn new(slice: Slice<'r>) -> PrevFiles { PrevFiles { slice } }
-  pub fn slice_to(&self, spec: FromTagBuf) -> Result<PrevFiles<'r>> { PrevFiles::from_slice(self.slice.slice(spec)) }
+
+  /// The object ID of the file's blob, if the file exists in the slice.
+  pub fn blob_id(&self, path: &str) -> Result<Option<String>> {
+    if !self.slice.has_blob(path)? {
+      return Ok(None);
+    }
+    Ok(Some(self.slice.blob_id(path)?))
+  }
 }
 
 #[derive(Debug)]
@@ -142,6 +245,8 @@ pub struct StateWrite {
   tag_head: Vec<String>,
   tag_commit: HashMap<String, String>,
   tag_head_or_last: Vec<(String, ProjectId)>,
+  #[serde(default)]
+  tag_messages: HashMap<String, String>,
   new_tags: HashMap<ProjectId, String>
 }
 
@@ -159,6 +264,7 @@ impl StateWrite {
       tag_head: Vec::new(),
       tag_commit: HashMap::new(),
       tag_head_or_last: Vec::new(),
+      tag_messages: HashMap::new(),
       new_tags: HashMap::new()
     }
   }
@@ -171,12 +277,22 @@ impl StateWrite {
     Ok(())
   }
 
+  /// Write a file that doesn't belong to any one project, such as aggregated release notes.
+  pub fn write_shared_file<C: ToString>(&mut self, file: PathBuf, content: C) -> Result<()> {
+    self.writes.push(FileWrite::Write { path: file, val: content.to_string(), changelog: true });
+    Ok(())
+  }
+
   pub fn update_mark<C: ToString>(&mut self, pick: PickPath, content: C, proj_id: &ProjectId) -> Result<()> {
     self.writes.push(FileWrite::Update { pick, val: content.to_string() });
     self.proj_writes.insert(proj_id.clone());
     Ok(())
   }
 
+  /// Treat the project as written, even though the write was made on behalf of another project: this is used when
+  /// one project writes its version into another project's files.
+  pub fn attribute_to(&mut self, proj_id: &ProjectId) { self.proj_writes.insert(proj_id.clone()); }
+
   pub fn send_cmd(&mut self, cmd: String, val: String, root: Option<String>, proj_id: &ProjectId) -> Result<()> {
     self.commands.push(SetCommand::new(cmd, val, root));
     self.proj_commands.insert(proj_id.clone());
@@ -191,6 +307,62 @@ impl StateWrite {
     Ok(())
   }
 
+  /// Annotate the given tag with a message: the tag is created as an annotated tag instead of a lightweight one.
+  pub fn annotate_tag<T: ToString, M: ToString>(&mut self, tag: T, msg: M) -> Result<()> {
+    self.tag_messages.insert(tag.to_string(), msg.to_string());
+    Ok(())
+  }
+
+  pub fn has_tag_message(&self, tag: &str) -> bool { self.tag_messages.contains_key(tag) }
+
+  /// Describe what `commit` would do, without doing it. A tag `target` of "HEAD" is the release commit, if one is
+  /// made.
+  pub fn planned_actions(&self, last_commits: &HashMap<ProjectId, String>, prev_tag: Option<&str>) -> Value {
+    let file = |w: &FileWrite| json!({ "path": w.path().to_string_lossy(), "changelog": w.is_changelog() });
+    let files: Vec<_> = self.writes.iter().map(file).collect();
+    let commands: Vec<_> = self.commands.iter().map(|c| json!({ "command": c.cmd, "value": c.val })).collect();
+
+    let mut tags: Vec<_> = self.tag_head.iter().map(|tag| json!({ "tag": tag, "target": "HEAD" })).collect();
+    for (tag, proj_id) in &self.tag_head_or_last {
+      let target = self.tag_target(proj_id, last_commits).unwrap_or("HEAD");
+      tags.push(json!({ "tag": tag, "target": target, "message": self.tag_messages.get(tag) }));
+    }
+    tags.extend(self.tag_commit.iter().map(|(tag, oid)| json!({ "tag": tag, "target": oid })));
+    if let Some(prev_tag) = prev_tag {
+      tags.push(json!({ "tag": prev_tag, "target": "HEAD" }));
+    }
+
+    json!({ "files": files, "commands": commands, "commit": self.will_commit(), "tags": tags })
+  }
+
+  /// True if a release commit is attempted: a file was written, or a command was run that may have changed some.
+  fn will_commit(&self) -> bool { !self.writes.is_empty() || !self.commands.is_empty() }
+
+  /// Where a project's tag goes: the release commit ("HEAD") if the project was written, or else the latest commit
+  /// of the project, if known.
+  fn tag_target<'a>(
+    &self, proj_id: &ProjectId, last_commits: &'a HashMap<ProjectId, String>
+  ) -> Option<&'a str> {
+    if self.proj_writes.contains(proj_id) {
+      Some("HEAD")
+    } else {
+      last_commits.get(proj_id).map(|oid| oid.as_str())
+    }
+  }
+
+  /// Render every file write into `dir` instead of the working tree, at the same paths relative to `dir`, and return
+  /// the rendered files. Files that are only updated are copied into `dir` first. Commands aren't run.
+  pub fn preview(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut previewed = Vec::new();
+    for write in &self.writes {
+      let dest = write.write_at(dir)?;
+      if !previewed.contains(&dest) {
+        previewed.push(dest);
+      }
+    }
+    Ok(previewed)
+  }
+
   pub fn write_changelogs(&mut self) -> Result<()> {
     // TODO(later): we're probably not going to do anything else after this, but should we remove the changelogs
     // from `self.writes`, just in case?
@@ -200,23 +372,46 @@ impl StateWrite {
     Ok(())
   }
 
+  /// Discard all file writes and commands, and commit only the tags. Since no project is written, each project's
+  /// tag goes to its latest commit.
+  pub fn tag_only(&mut self, repo: &Repo, data: CommitArgs) -> Result<()> {
+    self.writes.clear();
+    self.commands.clear();
+    self.proj_writes.clear();
+    self.proj_commands.clear();
+    self.commit(repo, data)
+  }
+
   pub fn commit(&mut self, repo: &Repo, data: CommitArgs) -> Result<()> {
+    // Remember which files were already dirty, so that we don't commit them along with our own changes. A file
+    // that we write can't be one of them, or our changes to it would be left out of the commit.
+    let tolerated = repo.tolerated_paths()?;
+    for write in &self.writes {
+      let path = write.path().to_slash_lossy();
+      if tolerated.contains(path.trim_start_matches("./")) {
+        bail!("Can't write {}: it already has uncommitted changes, which would be left out of the release.", path);
+      }
+    }
+
     for write in &self.writes {
       write.write()?;
+      let event = json!({ "path": write.path().to_string_lossy(), "changelog": write.is_changelog() });
+      data.events.emit("file_written", event)?;
     }
-    let did_write = !self.writes.is_empty();
+    let did_write = self.will_commit();
     self.writes.clear();
 
     for cmd in &self.commands {
       cmd.exec()?;
+      data.events.emit("command_run", json!({ "command": cmd.cmd }))?;
     }
     self.commands.clear();
 
-    for proj_id in &self.proj_writes {
-      if let Some((root, hooks)) = data.hooks.get(proj_id) {
-        hooks.execute_post_write(root)?;
-      }
-    }
+    // The hooks of different projects might not be safe to run at the same time (or their output would
+    // interleave), so they only do so if the user asks for it.
+    let hooks: Vec<_> = self.proj_writes.iter().filter_map(|proj_id| data.hooks.get(proj_id)).collect();
+    let jobs = repo.given_jobs().map(|j| j.get()).unwrap_or(1);
+    par_map(&hooks, jobs, |(root, hooks)| hooks.execute_post_write(root))?;
 
     let me = take(self);
     let prev_tag = data.prev_tag.to_string();
@@ -230,13 +425,15 @@ impl StateWrite {
       old_tags,
       data.advance_prev,
       repo.commit_config().clone()
-    );
+    )
+    .with_tolerated(tolerated);
 
-    if data.pause {
-      let file = OpenOptions::new().create(true).write(true).truncate(true).open(".versio-paused")?;
-      Ok(serde_json::to_writer(file, &commit_state)?)
-    } else {
-      commit_state.resume(repo)
+    match data.pause {
+      Some(pause) => {
+        commit_state.resume_until(repo, Some(pause.stage()), data.events)?;
+        commit_state.pause(repo, pause.file())
+      }
+      None => commit_state.resume(repo, data.events)
     }
   }
 }
@@ -247,15 +444,17 @@ pub struct CommitArgs<'a> {
   old_tags: &'a HashMap<ProjectId, String>,
   advance_prev: bool,
   hooks: &'a HashMap<ProjectId, (Option<&'a String>, &'a HookSet)>,
-  pause: bool
+  pause: Option<Pause<'a>>,
+  events: &'a Events
 }
 
 impl<'a> CommitArgs<'a> {
   pub fn new(
     prev_tag: &'a str, last_commits: &'a HashMap<ProjectId, String>, old_tags: &'a HashMap<ProjectId, String>,
-    advance_prev: bool, hooks: &'a HashMap<ProjectId, (Option<&'a String>, &'a HookSet)>, pause: bool
+    advance_prev: bool, hooks: &'a HashMap<ProjectId, (Option<&'a String>, &'a HookSet)>, pause: Option<Pause<'a>>,
+    events: &'a Events
   ) -> CommitArgs<'a> {
-    CommitArgs { prev_tag, last_commits, old_tags, advance_prev, hooks, pause }
+    CommitArgs { prev_tag, last_commits, old_tags, advance_prev, hooks, pause, events }
   }
 }
 
@@ -276,7 +475,19 @@ pub struct CommitState {
   last_commits: HashMap<ProjectId, String>,
   old_tags: HashMap<ProjectId, String>,
   advance_prev: bool,
-  commit_config: CommitConfig
+  commit_config: CommitConfig,
+  #[serde(default)]
+  tolerated: HashSet<String>,
+  #[serde(default)]
+  paused_at: Option<PausedAt>,
+  #[serde(default)]
+  stage: PauseStage,
+  #[serde(default)]
+  commit: Option<String>,
+  #[serde(default)]
+  unpushed: bool,
+  #[serde(default)]
+  written: Vec<String>
 }
 
 impl CommitState {
@@ -284,70 +495,391 @@ impl CommitState {
     write: StateWrite, did_write: bool, prev_tag: String, last_commits: HashMap<ProjectId, String>,
     old_tags: HashMap<ProjectId, String>, advance_prev: bool, commit_config: CommitConfig
   ) -> CommitState {
-    CommitState { write, did_write, prev_tag, last_commits, old_tags, advance_prev, commit_config }
+    CommitState {
+      write,
+      did_write,
+      prev_tag,
+      last_commits,
+      old_tags,
+      advance_prev,
+      commit_config,
+      tolerated: HashSet::new(),
+      paused_at: None,
+      stage: PauseStage::Commit,
+      commit: None,
+      unpushed: false,
+      written: Vec::new()
+    }
+  }
+
+  /// Leave these already-dirty files out of the commit, even when it's resumed later.
+  pub fn with_tolerated(mut self, tolerated: HashSet<String>) -> CommitState {
+    self.tolerated = tolerated;
+    self
   }
 
   pub fn commit_config(&self) -> &CommitConfig { &self.commit_config }
 
-  pub fn resume(&mut self, repo: &Repo) -> Result<()> {
-    // TODO(later): executing a setter command may have changed the local filesystem: should we check the repo
-    // state for _MODIFIED instead of relying on did_write ?
-    //
-    //  repo.statuses(Some(&mut status_opts))?.iter().filter(|s| {
-    //    let s = s.status();
-    //    s.is_wt_modified() || s.is_wt_deleted() || s.is_wt_renamed() || s.is_wt_typechange() || s.is_wt_new()
-    //  }).any()
+  /// The next stage to run when the release is resumed.
+  pub fn stage(&self) -> PauseStage { self.stage }
+
+  /// Save the state of the release in `paused_file`, to be resumed later.
+  pub fn pause(&mut self, repo: &Repo, paused_file: &Path) -> Result<()> {
+    self.paused_at = Some(PausedAt::capture(repo)?.with_files(repo, &self.tolerated)?);
+    let file = OpenOptions::new()
+      .create(true)
+      .write(true)
+      .truncate(true)
+      .open(paused_file)
+      .with_context(|| format!("Can't write {}.", paused_file.display()))?;
+    Ok(serde_json::to_writer(file, self)?)
+  }
 
-    if self.did_write {
-      trace!("Wrote files, so committing.");
-      repo.commit()?;
+  /// Ensure that the repo is still in the state it was when the release was paused.
+  pub fn verify_paused(&self, repo: &Repo, force: bool) -> Result<()> {
+    let paused_at = match &self.paused_at {
+      Some(paused_at) => paused_at,
+      None => {
+        warn!("No paused state recorded: unable to verify the repository.");
+        return Ok(());
+      }
+    };
+
+    let problems = paused_at.compare(&PausedAt::capture(repo)?);
+    if problems.is_empty() {
+      return Ok(());
+    }
+
+    if force {
+      for problem in &problems {
+        warn!("Resuming anyway: {}", problem);
+      }
+      Ok(())
     } else {
+      bail!("Repository has changed since the release was paused ({}): use `--force` to resume.", problems.join("; "))
+    }
+  }
+
+  /// Write the files of the paused release again, if the working directory doesn't have them: for example, if the
+  /// release is resumed in a fresh clone, from a pause file that was saved elsewhere.
+  pub fn restore_paused(&self, repo: &Repo, paused_file: &Path) -> Result<()> {
+    let paused_at = match &self.paused_at {
+      Some(paused_at) if !paused_at.files.is_empty() => paused_at,
+      _ => return Ok(())
+    };
+
+    let root = repo.working_dir()?;
+    let paused_file = std::fs::canonicalize(paused_file).ok();
+    let is_paused_file = |path: &str| std::fs::canonicalize(root.join(PathBuf::from_slash(path))).ok() == paused_file;
+    if repo.modified_paths()?.iter().any(|p| !self.tolerated.contains(p) && !is_paused_file(p)) {
+      return Ok(());
+    }
+
+    for file in &paused_at.files {
+      let path = root.join(PathBuf::from_slash(&file.path));
+      match &file.content {
+        Some(content) => {
+          let content = BASE64.decode(content).with_context(|| format!("Bad content of {} in pause file.", file.path))?;
+          if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+          }
+          std::fs::write(&path, content).with_context(|| format!("Can't restore {}.", file.path))?;
+        }
+        None => std::fs::remove_file(&path).with_context(|| format!("Can't restore removal of {}.", file.path))?
+      }
+      info!(target: PROGRESS, path = file.path.as_str(), "restored paused file");
+    }
+    Ok(())
+  }
+
+  pub fn resume(&mut self, repo: &Repo, events: &Events) -> Result<()> { self.resume_until(repo, None, events) }
+
+  /// Run the remaining stages of the release, stopping before the `until` stage if it's given.
+  pub fn resume_until(&mut self, repo: &Repo, until: Option<PauseStage>, events: &Events) -> Result<()> {
+    let _span = info_span!(target: PROGRESS, "resume").entered();
+    let runs = |stage: PauseStage| until.map(|until| stage < until).unwrap_or(true);
+
+    if self.stage == PauseStage::Commit && runs(PauseStage::Commit) {
+      // If the release is paused after its commit, the branch is pushed with the tags.
+      self.commit_stage(repo, until.is_some(), events)?;
+      self.stage = PauseStage::Tag;
+    }
+    if self.stage == PauseStage::Tag && runs(PauseStage::Tag) {
+      self.tag_stage(repo)?;
+      self.stage = PauseStage::Push;
+    }
+    if self.stage == PauseStage::Push && runs(PauseStage::Push) {
+      self.push_stage(repo, events)?;
+    }
+    Ok(())
+  }
+
+  fn commit_stage(&mut self, repo: &Repo, defer_push: bool, events: &Events) -> Result<()> {
+    // A setter command may not have changed anything, but then there's nothing modified to commit.
+    let committed = if !self.did_write {
       trace!("No files written, so not committing.");
+      false
+    } else if defer_push {
+      trace!("Wrote files, so committing without pushing.");
+      repo.commit_unpushed(&self.tolerated)?
+    } else {
+      trace!("Wrote files, so committing.");
+      repo.commit(&self.tolerated)?
+    };
+
+    if committed {
+      self.commit = repo.commit_oid_of("HEAD");
+      self.unpushed = defer_push;
     }
+    if let Some(commit) = &self.commit {
+      info!(target: PROGRESS, commit = commit.as_str(), "committed");
+      events.emit("committed", json!({ "commit": commit }))?;
+    }
+    Ok(())
+  }
+
+  fn tag_stage(&mut self, repo: &Repo) -> Result<()> {
+    // Remember which tags are new, so that the release can be rolled back.
+    let mut written: Vec<String> = self.write.tag_head.to_vec();
+    written.extend(self.write.tag_head_or_last.iter().map(|(t, _)| t.clone()));
+    written.extend(self.write.tag_commit.keys().cloned());
+    let is_new = |tag: &&String| repo.commit_oid_of(&format!("refs/tags/{}", tag)).is_none();
+    let created = written.iter().filter(is_new).cloned().collect();
 
     for tag in &self.write.tag_head {
       repo.update_tag_head(tag)?;
+      info!(target: PROGRESS, tag = tag.as_str(), "tagged");
     }
     self.write.tag_head.clear();
 
     for (tag, proj_id) in &self.write.tag_head_or_last {
-      if self.write.proj_writes.contains(proj_id) {
-        repo.update_tag_head(tag)?;
-      } else if let Some(oid) = self.last_commits.get(proj_id) {
-        repo.update_tag(tag, oid)?;
-      } else {
+      let spec = self.write.tag_target(proj_id, &self.last_commits).unwrap_or_else(|| {
         warn!("Latest commit for project {} unknown: tagging head.", proj_id);
-        repo.update_tag_head(tag)?;
+        "HEAD"
+      });
+      match self.write.tag_messages.get(tag) {
+        Some(msg) => repo.update_tag_anno(tag, spec, msg)?,
+        None => repo.update_tag(tag, spec)?
       }
+      info!(target: PROGRESS, project_id = %proj_id, tag = tag.as_str(), "tagged");
     }
     self.write.tag_head_or_last.clear();
+    self.write.tag_messages.clear();
     self.write.proj_writes.clear();
 
     for (tag, oid) in &self.write.tag_commit {
       repo.update_tag(tag, oid)?;
+      info!(target: PROGRESS, tag = tag.as_str(), "tagged");
     }
     self.write.tag_commit.clear();
 
     if self.advance_prev {
+      let record = ReleaseRecord {
+        tags: created,
+        commit: self.commit.clone(),
+        prior_commit: repo.commit_oid_of(&self.prev_tag),
+        prior_versions: self.old_tags.clone()
+      };
       fill_from_old(&self.old_tags, &mut self.write.new_tags);
-      let msg = serde_json::to_string(&PrevTagMessage::new(std::mem::take(&mut self.write.new_tags)))?;
-      repo.update_tag_head_anno(&self.prev_tag, &msg)?;
+      let msg = PrevTagMessage::new(std::mem::take(&mut self.write.new_tags)).with_release(record);
+      repo.update_tag_head_anno(&self.prev_tag, &serde_json::to_string(&msg)?)?;
+      written.push(self.prev_tag.clone());
     }
 
-    repo.finish_tags()?;
+    self.written = written;
+    Ok(())
+  }
+
+  fn push_stage(&mut self, repo: &Repo, events: &Events) -> Result<()> {
+    if take(&mut self.unpushed) {
+      repo.push_branch()?;
+    }
 
+    // The tags may have been made before a pause, so name them all again for the push.
+    repo.push_tags(&self.written)?;
+    for tag in take(&mut self.written) {
+      info!(target: PROGRESS, tag = tag.as_str(), "pushed tag");
+      events.emit("tag_pushed", json!({ "tag": tag }))?;
+    }
     Ok(())
   }
 }
 
+/// The state of the repository when a release was paused. Paths are relative to the base of the repo, so that the
+/// release can be resumed in another clone.
+#[derive(Deserialize, Serialize)]
+struct PausedAt {
+  branch: Option<String>,
+  head: Option<String>,
+  config_hash: Option<String>,
+  #[serde(default)]
+  files: Vec<PausedFile>
+}
+
+/// A file that the paused release changed, and its content in base64 (so that any file can be saved); or no content
+/// if it was removed.
+#[derive(Deserialize, Serialize)]
+struct PausedFile {
+  path: String,
+  content: Option<String>
+}
+
+impl PausedAt {
+  fn capture(repo: &Repo) -> Result<PausedAt> {
+    let branch = repo.branch_name().ok().cloned().flatten();
+    let head = repo.commit_oid_of("HEAD");
+    let config_path = repo.working_dir()?.join(CONFIG_FILENAME);
+    let config_hash = if config_path.exists() { Some(blob_hash(&std::fs::read(config_path)?)?) } else { None };
+    Ok(PausedAt { branch, head, config_hash, files: Vec::new() })
+  }
+
+  /// Also record the content of every file changed by the release.
+  fn with_files(mut self, repo: &Repo, tolerated: &HashSet<String>) -> Result<PausedAt> {
+    let root = repo.working_dir()?;
+    for path in repo.modified_paths()?.into_iter().filter(|p| !tolerated.contains(p)) {
+      let full = root.join(PathBuf::from_slash(&path));
+      let content = if full.exists() {
+        Some(BASE64.encode(std::fs::read(&full).with_context(|| format!("Can't save {} in the pause file.", path))?))
+      } else {
+        None
+      };
+      self.files.push(PausedFile { path, content });
+    }
+    Ok(self)
+  }
+
+  /// List the differences from `other`. Values that couldn't be found (e.g. at a lower VCS level) aren't compared.
+  fn compare(&self, other: &PausedAt) -> Vec<String> {
+    let mut problems = Vec::new();
+    if let (Some(was), Some(is)) = (&self.branch, &other.branch) {
+      if was != is {
+        problems.push(format!("branch was \"{}\", is now \"{}\"", was, is));
+      }
+    }
+    if let (Some(was), Some(is)) = (&self.head, &other.head) {
+      if was != is {
+        problems.push(format!("HEAD was {}, is now {}", was, is));
+      }
+    }
+    if self.config_hash != other.config_hash {
+      problems.push(format!("{} has changed", CONFIG_FILENAME));
+    }
+    problems
+  }
+}
+
 #[derive(Deserialize, Serialize, Default)]
 pub struct PrevTagMessage {
-  versions: HashMap<ProjectId, String>
+  versions: HashMap<ProjectId, String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  release: Option<ReleaseRecord>
 }
 
 impl PrevTagMessage {
-  pub fn new(versions: HashMap<ProjectId, String>) -> PrevTagMessage { PrevTagMessage { versions } }
+  pub fn new(versions: HashMap<ProjectId, String>) -> PrevTagMessage { PrevTagMessage { versions, release: None } }
+
+  /// Parse the annotation of a prev tag. If it doesn't have the expected structure, the error names the offending
+  /// key.
+  pub fn parse(anno: &str) -> Result<PrevTagMessage> {
+    let value: Value = serde_json::from_str(anno).context("Annotation isn't valid JSON.")?;
+    validate_prev_message(&value)?;
+    Ok(serde_json::from_value(value)?)
+  }
+
   pub fn into_versions(self) -> HashMap<ProjectId, String> { self.versions }
+  pub fn into_release(self) -> Option<ReleaseRecord> { self.release }
+
+  pub fn with_release(mut self, release: ReleaseRecord) -> PrevTagMessage {
+    self.release = Some(release);
+    self
+  }
+}
+
+fn validate_prev_message(value: &Value) -> Result<()> {
+  let obj = value.as_object().ok_or_else(|| bad!("Annotation should be an object, not {}.", describe(value)))?;
+  if !obj.contains_key("versions") {
+    bail!("Annotation is missing \"versions\".");
+  }
+
+  for (key, val) in obj {
+    match key.as_str() {
+      "versions" => validate_versions("versions", val)?,
+      "release" => validate_release(val)?,
+      _ => warn!("Ignoring unknown key \"{}\" in annotation.", key)
+    }
+  }
+  Ok(())
+}
+
+fn validate_versions(path: &str, value: &Value) -> Result<()> {
+  let obj = value.as_object().ok_or_else(|| bad!("{} should be an object, not {}.", path, describe(value)))?;
+  for (key, val) in obj {
+    if serde_json::from_value::<ProjectId>(Value::String(key.clone())).is_err() {
+      bail!("{}.\"{}\" isn't a project ID.", path, key);
+    }
+    if !val.is_string() {
+      bail!("{}.\"{}\" should be a version string, not {}.", path, key, describe(val));
+    }
+  }
+  Ok(())
+}
+
+fn validate_release(value: &Value) -> Result<()> {
+  if value.is_null() {
+    return Ok(());
+  }
+  let obj = value.as_object().ok_or_else(|| bad!("release should be an object, not {}.", describe(value)))?;
+  for required in ["tags", "prior_versions"] {
+    if !obj.contains_key(required) {
+      bail!("release is missing \"{}\".", required);
+    }
+  }
+
+  for (key, val) in obj {
+    match key.as_str() {
+      "tags" => {
+        let tags = val.as_array().ok_or_else(|| bad!("release.tags should be an array, not {}.", describe(val)))?;
+        if let Some((i, tag)) = tags.iter().enumerate().find(|(_, t)| !t.is_string()) {
+          bail!("release.tags[{}] should be a string, not {}.", i, describe(tag));
+        }
+      }
+      "commit" | "prior_commit" => {
+        if !val.is_string() && !val.is_null() {
+          bail!("release.{} should be a commit hash or null, not {}.", key, describe(val));
+        }
+      }
+      "prior_versions" => validate_versions("release.prior_versions", val)?,
+      _ => warn!("Ignoring unknown key \"release.{}\" in annotation.", key)
+    }
+  }
+  Ok(())
+}
+
+fn describe(value: &Value) -> &'static str {
+  match value {
+    Value::Null => "null",
+    Value::Bool(_) => "a boolean",
+    Value::Number(_) => "a number",
+    Value::String(_) => "a string",
+    Value::Array(_) => "an array",
+    Value::Object(_) => "an object"
+  }
+}
+
+/// What a release changed in the repo, so that it can be rolled back.
+#[derive(Deserialize, Serialize)]
+pub struct ReleaseRecord {
+  tags: Vec<String>,
+  commit: Option<String>,
+  prior_commit: Option<String>,
+  prior_versions: HashMap<ProjectId, String>
+}
+
+impl ReleaseRecord {
+  pub fn tags(&self) -> &[String] { &self.tags }
+  pub fn commit(&self) -> Option<&str> { self.commit.as_deref() }
+  pub fn prior_commit(&self) -> Option<&str> { self.prior_commit.as_deref() }
+  pub fn prior_versions(&self) -> &HashMap<ProjectId, String> { &self.prior_versions }
 }
 
 #[derive(Deserialize, Serialize)]
@@ -364,6 +896,13 @@ impl FileWrite {
     }
   }
 
+  pub fn path(&self) -> &Path {
+    match self {
+      FileWrite::Write { path, .. } => path,
+      FileWrite::Update { pick, .. } => &pick.file
+    }
+  }
+
   pub fn write(&self) -> Result<()> {
     match self {
       FileWrite::Write { path, val, .. } => {
@@ -376,6 +915,32 @@ impl FileWrite {
       FileWrite::Update { pick, val } => pick.write_value(val)
     }
   }
+
+  /// Like `write`, but write to the same relative path under `dir` instead, and return that path. An update is made
+  /// to that path if it exists, or else to a copy of the original file. Paths that would leave `dir` are refused.
+  pub fn write_at(&self, dir: &Path) -> Result<PathBuf> {
+    let path = self.path();
+    if path.components().any(|c| !matches!(c, Component::Normal(_) | Component::CurDir)) {
+      bail!("Can't write {} outside of {}.", path.display(), dir.display());
+    }
+    let dest = dir.join(path);
+    if let Some(parent) = dest.parent() {
+      std::fs::create_dir_all(parent).with_context(|| format!("Can't create {}.", parent.display()))?;
+    }
+
+    match self {
+      FileWrite::Write { val, .. } => {
+        std::fs::write(&dest, val).with_context(|| format!("Can't write to {}", dest.to_string_lossy()))?
+      }
+      FileWrite::Update { pick, val } => {
+        if !dest.exists() {
+          std::fs::copy(&pick.file, &dest).with_context(|| format!("Can't copy {}.", pick.file.to_string_lossy()))?;
+        }
+        pick.write_value_at(&dest, val)?
+      }
+    }
+    Ok(dest)
+  }
 }
 
 #[derive(Deserialize, Serialize)]
@@ -395,7 +960,11 @@ impl SetCommand {
     if let Some(root) = self.root.as_ref() {
       command.current_dir(root);
     }
-    let full_command = format!("{} {}", self.cmd, self.val);
+    let full_command = if self.cmd.contains("{version}") {
+      self.cmd.replace("{version}", &self.val)
+    } else {
+      format!("{} {}", self.cmd, self.val)
+    };
     let status = command.args(["-e", "-c", &full_command]).status()?;
     if !status.success() {
       bail!("Unable to run hook {}.", self.cmd);
@@ -408,25 +977,321 @@ impl SetCommand {
 #[derive(Deserialize, Serialize)]
 pub struct PickPath {
   file: PathBuf,
-  picker: Picker
+  picker: Picker,
+  #[serde(default)]
+  range: bool
 }
 
 impl PickPath {
-  pub fn new(file: PathBuf, picker: Picker) -> PickPath { PickPath { file, picker } }
+  pub fn new(file: PathBuf, picker: Picker) -> PickPath { PickPath { file, picker, range: false } }
 
-  pub fn write_value(&self, val: &str) -> Result<()> {
-    let data = std::fs::read_to_string(&self.file)
-      .with_context(|| format!("Can't read file {}.", self.file.to_string_lossy()))?;
-    let data = NamedData::new(self.file.clone(), data);
+  /// A path to an npm version range: only the version in the range is replaced, keeping its `^`, `~`, etc.
+  pub fn ranged(file: PathBuf, picker: Picker) -> PickPath { PickPath { file, picker, range: true } }
+
+  pub fn write_value(&self, val: &str) -> Result<()> { self.write_value_at(&self.file, val) }
+
+  /// Write the value into `file`, which has the same format as this path's file.
+  fn write_value_at(&self, file: &Path, val: &str) -> Result<()> {
+    let data =
+      std::fs::read_to_string(file).with_context(|| format!("Can't read file {}.", file.to_string_lossy()))?;
+    let data = NamedData::new(file.to_path_buf(), data);
     let mut mark = self.picker.scan(data)?;
-    mark.write_new_value(val)?;
+    if self.range {
+      match split_range(mark.value()) {
+        Some((prefix, _)) => {
+          let val = format!("{}{}", prefix, val);
+          mark.write_new_value(&val)?;
+        }
+        None => warn!("Not rewriting range \"{}\" in {}.", mark.value(), file.to_string_lossy())
+      }
+    } else {
+      mark.write_new_value(val)?;
+    }
     Ok(())
   }
 }
 
+/// Split a simple npm version range like `^1.2.0`, `~1.2.0`, `>=1.2.0`, or `workspace:^1.2.0` into its prefix and
+/// its version. A range that isn't a single version (like `1.x`, `*`, or `>=1.0.0 <2.0.0`) is `None`.
+pub fn split_range(range: &str) -> Option<(&str, &str)> {
+  let body = range.strip_prefix("workspace:").unwrap_or(range);
+  let op = ["^", "~", ">=", "="].iter().find(|op| body.starts_with(**op)).map(|op| op.len()).unwrap_or(0);
+  let (prefix, version) = range.split_at(range.len() - body.len() + op);
+
+  let semver = Regex::new(r"^\d+\.\d+\.\d+(-[0-9A-Za-z.-]+)?(\+[0-9A-Za-z.-]+)?$").unwrap();
+  if semver.is_match(version) {
+    Some((prefix, version))
+  } else {
+    None
+  }
+}
+
 pub fn read_from_slice<P: AsRef<Path>>(slice: &Slice, path: P) -> Result<String> {
   let path = path.as_ref().to_slash_lossy();
-  let blob = slice.blob(&path)?;
-  let cont: &str = std::str::from_utf8(blob.content()).with_context(|| format!("Not UTF8 content: {}", path))?;
+  let content = slice.blob_content(&path)?;
+  let cont: &str = std::str::from_utf8(&content).with_context(|| format!("Not UTF8 content: {}", path))?;
   Ok(cont.to_string())
 }
+
+#[cfg(test)]
+mod test {
+  use super::{CommitState, HashMap, HashSet, PauseStage, PausedAt, StateWrite};
+  use crate::config::{CommitConfig, ProjectId};
+  use crate::errors::Result;
+  use crate::events::Events;
+  use crate::git::Repo;
+  use crate::vcs::{VcsLevel, VcsState};
+  use git2::{Repository, Signature};
+  use std::fs::{read, read_to_string, remove_file, write, File};
+  use std::path::Path;
+
+  const BINARY: &[u8] = &[0, 0x9f, 0x92, 0x96, 0xff];
+
+  /// Commit a `VERSION` and an `old.txt` in a new repo at `dir`, and then change them as a release would: also add
+  /// a binary `logo.bin`, and an untracked `notes.txt` which the release tolerates.
+  fn released_repo(dir: &Path) -> Repo {
+    let git = Repository::init(dir).unwrap();
+    write(dir.join("VERSION"), "1.0.0\n").unwrap();
+    write(dir.join("old.txt"), "old\n").unwrap();
+    let mut index = git.index().unwrap();
+    index.add_path(Path::new("VERSION")).unwrap();
+    index.add_path(Path::new("old.txt")).unwrap();
+    index.write().unwrap();
+    let tree = git.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = Signature::now("Test", "test@example.com").unwrap();
+    git.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[]).unwrap();
+
+    write(dir.join("VERSION"), "1.1.0\n").unwrap();
+    write(dir.join("logo.bin"), BINARY).unwrap();
+    write(dir.join("notes.txt"), "mine\n").unwrap();
+    remove_file(dir.join("old.txt")).unwrap();
+    Repo::open(dir, VcsState::new(VcsLevel::Local, false), CommitConfig::default()).unwrap()
+  }
+
+  fn tolerated() -> HashSet<String> { ["notes.txt".to_string()].into_iter().collect() }
+
+  #[test]
+  fn test_paused_files() {
+    let dir = std::env::temp_dir().join(format!("versio-paused-files-{}", std::process::id()));
+    let repo = released_repo(&dir);
+    let paused = PausedAt::capture(&repo).and_then(|p| p.with_files(&repo, &tolerated()));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let mut files: Vec<_> = paused.unwrap().files.into_iter().map(|f| (f.path, f.content)).collect();
+    files.sort();
+    assert_eq!(
+      files,
+      [
+        ("VERSION".to_string(), Some("MS4xLjAK".to_string())),
+        ("logo.bin".to_string(), Some("AJ+Slv8=".to_string())),
+        ("old.txt".to_string(), None)
+      ]
+    );
+  }
+
+  #[test]
+  fn test_pause_round_trip() {
+    let dir = std::env::temp_dir().join(format!("versio-pause-trip-{}", std::process::id()));
+    let paused_file = std::env::temp_dir().join(format!("versio-pause-trip-{}.json", std::process::id()));
+    let repo = released_repo(&dir);
+
+    let result = (|| {
+      let (state_write, config) = (StateWrite::new(), CommitConfig::default());
+      let commit = CommitState::new(state_write, true, "prev".into(), HashMap::new(), HashMap::new(), false, config);
+      commit.with_tolerated(tolerated()).pause(&repo, &paused_file)?;
+
+      // As if in a fresh clone at the same commit, except for the tolerated file.
+      write(dir.join("VERSION"), "1.0.0\n")?;
+      write(dir.join("old.txt"), "old\n")?;
+      remove_file(dir.join("logo.bin"))?;
+
+      let resumed: CommitState = serde_json::from_reader(File::open(&paused_file)?)?;
+      resumed.verify_paused(&repo, false)?;
+
+      // Local changes aren't overwritten.
+      write(dir.join("VERSION"), "9.9.9\n")?;
+      resumed.restore_paused(&repo, &paused_file)?;
+      let untouched = (read_to_string(dir.join("VERSION"))?, dir.join("logo.bin").exists());
+
+      write(dir.join("VERSION"), "1.0.0\n")?;
+      resumed.restore_paused(&repo, &paused_file)?;
+      let restored = (read_to_string(dir.join("VERSION"))?, read(dir.join("logo.bin"))?, dir.join("old.txt").exists());
+      Ok::<_, crate::errors::Error>((untouched, restored, read_to_string(dir.join("notes.txt"))?))
+    })();
+    std::fs::remove_dir_all(&dir).unwrap();
+    remove_file(&paused_file).unwrap();
+
+    let (untouched, restored, notes) = result.unwrap();
+    assert_eq!(untouched, ("9.9.9\n".to_string(), false));
+    assert_eq!(restored, ("1.1.0\n".to_string(), BINARY.to_vec(), false));
+    assert_eq!(notes, "mine\n");
+  }
+
+  #[test]
+  fn test_paused_at_compare() {
+    let at = |branch: Option<&str>, head: Option<&str>, config_hash: Option<&str>| PausedAt {
+      branch: branch.map(|b| b.to_string()),
+      head: head.map(|h| h.to_string()),
+      config_hash: config_hash.map(|c| c.to_string()),
+      files: Vec::new()
+    };
+    let paused = at(Some("main"), Some("abc"), Some("123"));
+
+    assert!(paused.compare(&at(Some("main"), Some("abc"), Some("123"))).is_empty());
+    assert_eq!(paused.compare(&at(Some("dev"), Some("abc"), Some("123"))), ["branch was \"main\", is now \"dev\""]);
+    assert_eq!(paused.compare(&at(Some("main"), Some("def"), Some("123"))), ["HEAD was abc, is now def"]);
+    assert_eq!(paused.compare(&at(Some("main"), Some("abc"), Some("456"))), [".versio.yaml has changed"]);
+    assert_eq!(paused.compare(&at(Some("main"), Some("abc"), None)), [".versio.yaml has changed"]);
+    assert_eq!(paused.compare(&at(Some("dev"), Some("def"), Some("456"))).len(), 3);
+
+    // A branch or HEAD that can't be found isn't compared.
+    assert!(paused.compare(&at(None, None, Some("123"))).is_empty());
+    assert!(at(None, None, Some("123")).compare(&paused).is_empty());
+  }
+
+  #[test]
+  fn test_verify_paused() {
+    let dir = std::env::temp_dir().join(format!("versio-verify-paused-{}", std::process::id()));
+    let paused_file = std::env::temp_dir().join(format!("versio-verify-paused-{}.json", std::process::id()));
+    let repo = released_repo(&dir);
+
+    let result = (|| {
+      let (state_write, config) = (StateWrite::new(), CommitConfig::default());
+      let commit = CommitState::new(state_write, true, "prev".into(), HashMap::new(), HashMap::new(), false, config);
+      commit.with_tolerated(tolerated()).pause(&repo, &paused_file)?;
+      let resumed: CommitState = serde_json::from_reader(File::open(&paused_file)?)?;
+      let unchanged = resumed.verify_paused(&repo, false).is_ok();
+
+      write(dir.join(".versio.yaml"), "projects: []\n")?;
+      let config_err = resumed.verify_paused(&repo, false).unwrap_err().to_string();
+      let config_forced = resumed.verify_paused(&repo, true).is_ok();
+
+      let git = Repository::open(&dir)?;
+      let sig = Signature::now("Test", "test@example.com")?;
+      let head = git.head()?.peel_to_commit()?;
+      git.commit(Some("HEAD"), &sig, &sig, "more", &head.tree()?, &[&head])?;
+      let head_err = resumed.verify_paused(&repo, false).unwrap_err().to_string();
+      let head_forced = resumed.verify_paused(&repo, true).is_ok();
+      Ok::<_, crate::errors::Error>((unchanged, config_err, config_forced, head_err, head_forced))
+    })();
+    std::fs::remove_dir_all(&dir).unwrap();
+    remove_file(&paused_file).unwrap();
+
+    let (unchanged, config_err, config_forced, head_err, head_forced) = result.unwrap();
+    assert!(unchanged);
+    assert!(config_err.contains(".versio.yaml has changed") && config_err.contains("--force"), "{}", config_err);
+    assert!(config_forced);
+    assert!(head_err.contains("HEAD was") && head_err.contains(".versio.yaml has changed"), "{}", head_err);
+    assert!(head_forced);
+  }
+
+  /// The commits of the branch and the release tag in the remote.
+  type Pushed = (Option<String>, Option<String>);
+
+  /// In `dir`, release a clone of a bare remote, pausing before the `until` stage and then resuming. Returns what
+  /// was pushed before the release, while it was paused, and after it finished; and the release commit.
+  fn pause_and_resume(dir: &Path, until: PauseStage) -> Result<(Pushed, Pushed, Pushed, String)> {
+    let remote = Repository::init_bare(dir.join("remote.git"))?;
+    let local = Repository::init(dir.join("local"))?;
+    write(dir.join("local/VERSION"), "1.0.0\n")?;
+    let mut index = local.index()?;
+    index.add_path(Path::new("VERSION"))?;
+    index.write()?;
+    let tree = local.find_tree(index.write_tree()?)?;
+    let sig = Signature::now("Test", "test@example.com")?;
+    local.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])?;
+    let branch = local.head()?.name().unwrap().to_string();
+    let url = dir.join("remote.git").to_string_lossy().into_owned();
+    local.remote("origin", &url)?.push(&[format!("{}:{}", branch, branch)], None)?;
+
+    let pushed = || {
+      let find = |name: &str| remote.refname_to_id(name).ok().map(|oid| oid.to_string());
+      (find(&branch), find("refs/tags/proj-v1.1.0"))
+    };
+    let before = pushed();
+
+    write(dir.join("local/VERSION"), "1.1.0\n")?;
+    let repo = Repo::open(dir.join("local"), VcsState::new(VcsLevel::Remote, false), CommitConfig::default())?;
+    let mut state = StateWrite::new();
+    state.tag_head_or_last("1.1.0", "proj-v1.1.0", &ProjectId::from_id(1))?;
+    let config = CommitConfig::default();
+    let mut commit = CommitState::new(state, true, "prev".into(), HashMap::new(), HashMap::new(), false, config);
+    commit.resume_until(&repo, Some(until), &Events::none())?;
+    commit.pause(&repo, &dir.join("paused.json"))?;
+    let paused = pushed();
+
+    let mut resumed: CommitState = serde_json::from_reader(File::open(dir.join("paused.json"))?)?;
+    resumed.verify_paused(&repo, false)?;
+    resumed.resume(&repo, &Events::none())?;
+    Ok((before, paused, pushed(), local.refname_to_id("HEAD")?.to_string()))
+  }
+
+  #[test]
+  fn test_resume_from_tag() {
+    let dir = std::env::temp_dir().join(format!("versio-resume-tag-{}", std::process::id()));
+    let result = pause_and_resume(&dir, PauseStage::Tag);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // Nothing is pushed until the push stage: then the branch is pushed along with the tag made before the pause.
+    let (before, paused, done, head) = result.unwrap();
+    assert_eq!(paused, before);
+    assert_eq!(done, (Some(head.clone()), Some(head)));
+  }
+
+  #[test]
+  fn test_resume_from_push() {
+    let dir = std::env::temp_dir().join(format!("versio-resume-push-{}", std::process::id()));
+    let result = pause_and_resume(&dir, PauseStage::Push);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let (before, paused, done, head) = result.unwrap();
+    assert_eq!(paused, before);
+    assert_eq!(done, (Some(head.clone()), Some(head)));
+  }
+
+  #[test]
+  fn test_preview_inside_dir() {
+    let dir = std::env::temp_dir().join(format!("versio-preview-{}", std::process::id()));
+    let preview = dir.join("preview");
+    let result = (|| -> Result<_> {
+      let mut inside = StateWrite::new();
+      inside.write_shared_file("sub/VERSION".into(), "1.1.0\n")?;
+      let previewed = inside.preview(&preview)?;
+
+      let mut outside = StateWrite::new();
+      outside.write_shared_file("../escaped".into(), "no\n")?;
+      let mut absolute = StateWrite::new();
+      absolute.write_shared_file(dir.join("absolute"), "no\n")?;
+      let refused = (outside.preview(&preview).is_err(), absolute.preview(&preview).is_err());
+
+      let escaped = (dir.join("escaped").exists(), dir.join("absolute").exists());
+      Ok((previewed, read_to_string(preview.join("sub/VERSION"))?, refused, escaped))
+    })();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let (previewed, content, refused, escaped) = result.unwrap();
+    assert_eq!(previewed, vec![preview.join("sub/VERSION")]);
+    assert_eq!(content, "1.1.0\n");
+    assert_eq!(refused, (true, true));
+    assert_eq!(escaped, (false, false));
+  }
+
+  #[test]
+  fn test_planned_commit() {
+    let id = ProjectId::from_id(1);
+    let no_commits = HashMap::new();
+
+    let empty = StateWrite::new();
+    assert_eq!(empty.planned_actions(&no_commits, None)["commit"], false);
+
+    let mut written = StateWrite::new();
+    written.write_file("VERSION".into(), "1.1.0\n", &id, false).unwrap();
+    assert_eq!(written.planned_actions(&no_commits, None)["commit"], true);
+
+    let mut commanded = StateWrite::new();
+    commanded.send_cmd("poetry version {version}".into(), "1.1.0".into(), None, &id).unwrap();
+    let actions = commanded.planned_actions(&no_commits, None);
+    assert_eq!(actions["commit"], true);
+    assert_eq!(actions["files"].as_array().map(|f| f.len()), Some(0));
+  }
+}
@@ -1,15 +1,227 @@
 //! Template and changelog management for Versio.
 
 use crate::bail;
+use crate::config::ProjectId;
 use crate::errors::Result;
+use crate::git::Contributor;
 use crate::mono::{Changelog, ChangelogEntry};
 use crate::output::ProjLine;
-use chrono::prelude::Utc;
+use chrono::format::{Item, StrftimeItems};
+use chrono::prelude::{DateTime, FixedOffset, Local, TimeZone, Utc};
 use hyper::Client;
-use liquid::ParserBuilder;
+use liquid::model::Value;
+use liquid::{Object, ParserBuilder};
 use path_slash::PathBufExt;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
+/// The builtin templates: name, description, and content.
+pub const BUILTIN_TEMPLATES: &[(&str, &str, &str)] = &[
+  ("html", "An expandable HTML changelog, newest release first", include_str!("tmpl/changelog.liquid")),
+  ("markdown", "A Markdown changelog, newest release first", include_str!("tmpl/markdown.liquid")),
+  ("json", "A JSON document of the current release only", include_str!("tmpl/json.liquid")),
+  ("notes", "Markdown release notes of every project in a release", include_str!("tmpl/notes.liquid"))
+];
+
+/// The issue references that are linked by default: Jira-style keys such as `PROJ-123`.
+pub const DEFAULT_ISSUE_PATTERN: &str = r"\b[A-Z][A-Z0-9_]+-[0-9]+\b";
+
+/// The format of changelog dates by default: e.g. `2020-01-31`.
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Links to an issue tracker for the issues referenced in commit summaries. The `{id}` in the URL is replaced by
+/// the first capture group of the pattern, or by the whole match if the pattern has no groups.
+#[derive(Clone, Debug)]
+pub struct IssueLinks {
+  url: String,
+  pattern: Regex
+}
+
+impl IssueLinks {
+  pub fn new(url: String, pattern: &str) -> Result<IssueLinks> {
+    if !url.contains("{id}") {
+      bail!("Issue URL \"{}\" doesn't contain \"{{id}}\".", url);
+    }
+    Ok(IssueLinks { url, pattern: Regex::new(pattern)? })
+  }
+
+  /// The issues referenced in `summary`, as their ID and URL.
+  fn find<'s>(&self, summary: &'s str) -> Vec<(&'s str, String)> {
+    self
+      .pattern
+      .captures_iter(summary)
+      .map(|caps| {
+        let id = caps.get(1).unwrap_or_else(|| caps.get(0).unwrap()).as_str();
+        (id, self.url.replace("{id}", id))
+      })
+      .collect()
+  }
+
+  /// Replace every issue reference in `summary` with an HTML link to the issue.
+  fn link(&self, summary: &str) -> String {
+    let mut linked = String::new();
+    let mut last = 0;
+    for caps in self.pattern.captures_iter(summary) {
+      let whole = caps.get(0).unwrap();
+      let id = caps.get(1).unwrap_or(whole).as_str();
+      linked.push_str(&summary[last .. whole.start()]);
+      linked.push_str(&format!("<a href=\"{}\">{}</a>", self.url.replace("{id}", id), whole.as_str()));
+      last = whole.end();
+    }
+    linked.push_str(&summary[last ..]);
+    linked
+  }
+}
+
+/// How a changelog lists a PR that was also released in other projects: in `full` (as usual), with a `link` to
+/// each other release, or `collapse`d to only those links in all but the first project (by ID) that released it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SharedPrs {
+  #[default]
+  Full,
+  Link,
+  Collapse
+}
+
+/// The timezone that changelog dates are shown in: UTC, the local timezone of the machine running Versio, or a
+/// fixed offset such as `+05:30`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DateZone {
+  #[default]
+  Utc,
+  Local,
+  Fixed(FixedOffset)
+}
+
+impl DateZone {
+  pub fn parse(zone: &str) -> Result<DateZone> {
+    match zone.trim() {
+      z if z.eq_ignore_ascii_case("utc") || z == "Z" => Ok(DateZone::Utc),
+      z if z.eq_ignore_ascii_case("local") => Ok(DateZone::Local),
+      z => match z.parse() {
+        Ok(offset) => Ok(DateZone::Fixed(offset)),
+        Err(_) => bail!("Unknown timezone \"{}\": use \"utc\", \"local\", or an offset like \"+05:30\".", z)
+      }
+    }
+  }
+}
+
+/// How dates are formatted in changelogs: a `strftime`-style format, shown in a timezone.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DateFormat {
+  format: String,
+  zone: DateZone
+}
+
+impl Default for DateFormat {
+  fn default() -> DateFormat { DateFormat { format: DEFAULT_DATE_FORMAT.into(), zone: DateZone::Utc } }
+}
+
+impl DateFormat {
+  pub fn new(format: String, zone: DateZone) -> Result<DateFormat> {
+    if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+      bail!("Invalid date format \"{}\".", format);
+    }
+    Ok(DateFormat { format, zone })
+  }
+
+  pub fn format<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> String {
+    match self.zone {
+      DateZone::Utc => time.with_timezone(&Utc).format(&self.format).to_string(),
+      DateZone::Local => time.with_timezone(&Local).format(&self.format).to_string(),
+      DateZone::Fixed(offset) => time.with_timezone(&offset).format(&self.format).to_string()
+    }
+  }
+
+  fn now(&self) -> String { self.format(&Utc::now()) }
+}
+
+/// Another project that a shared PR is released in, with the new version of that project.
+pub struct CrossLink {
+  id: ProjectId,
+  name: String,
+  version: String
+}
+
+impl CrossLink {
+  pub fn new(id: ProjectId, name: impl ToString, version: String) -> CrossLink {
+    CrossLink { id, name: name.to_string(), version }
+  }
+}
+
+/// The other releases of a shared PR, and whether the PR is collapsed to links to them.
+pub struct SharedPr {
+  also: Vec<CrossLink>,
+  collapsed: bool
+}
+
+impl SharedPr {
+  pub fn new(also: Vec<CrossLink>, collapsed: bool) -> SharedPr { SharedPr { also, collapsed } }
+}
+
+/// The links that are added to a release: to the issues referenced in its commits, to a comparison of the
+/// previous and new release tags, and to the other releases of shared PRs. Also whether curated notes are parsed
+/// from PR descriptions and commit messages, and how dates are formatted.
+#[derive(Default)]
+pub struct ReleaseLinks<'a> {
+  issues: Option<&'a IssueLinks>,
+  compare: Option<String>,
+  shared: SharedPrs,
+  cross: HashMap<u32, SharedPr>,
+  notes: bool,
+  dates: DateFormat
+}
+
+impl<'a> ReleaseLinks<'a> {
+  pub fn new(issues: Option<&'a IssueLinks>, compare: Option<String>, shared: SharedPrs) -> ReleaseLinks<'a> {
+    ReleaseLinks { issues, compare, shared, cross: HashMap::new(), notes: false, dates: DateFormat::default() }
+  }
+
+  /// Format the release date and the PR `closed_at` dates this way.
+  pub fn with_dates(mut self, dates: DateFormat) -> ReleaseLinks<'a> {
+    self.dates = dates;
+    self
+  }
+
+  /// Parse the `## Changelog` section of PR descriptions and commit messages into their `notes`.
+  pub fn with_notes(mut self, notes: bool) -> ReleaseLinks<'a> {
+    self.notes = notes;
+    self
+  }
+
+  /// Add the other releases of the shared PRs, by PR number.
+  pub fn with_cross(mut self, cross: HashMap<u32, SharedPr>) -> ReleaseLinks<'a> {
+    self.cross = cross;
+    self
+  }
+}
+
+/// Extract the body of the first "Changelog" heading (of any level) in `text`, up to the next heading of the same
+/// or a higher level. This is empty if there is no such heading.
+fn changelog_notes(text: &str) -> String {
+  let heading = |line: &str| {
+    let line = line.trim();
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let title = &line[level ..];
+    (level > 0 && (title.is_empty() || title.starts_with(' '))).then(|| (level, title.trim().to_string()))
+  };
+
+  let mut lines =
+    text.lines().skip_while(|l| !matches!(heading(l), Some((_, t)) if t.eq_ignore_ascii_case("changelog")));
+  let level = match lines.next().and_then(heading) {
+    Some((level, _)) => level,
+    None => return String::new()
+  };
+  let notes = lines.take_while(|l| !matches!(heading(l), Some((lvl, _)) if lvl <= level)).collect::<Vec<_>>();
+  notes.join("\n").trim().to_string()
+}
+
+/// The text as a quoted JSON string, for templates that render JSON.
+fn json_string(text: &str) -> String { serde_json::Value::from(text).to_string() }
+
 /// Extract everything in an old changelog between the `BEGIN CONTENT` and `END CONTENT` lines.
 pub fn extract_old_content(path: &Path) -> Result<String> {
   if !path.exists() {
@@ -28,11 +240,121 @@ pub fn extract_old_content(path: &Path) -> Result<String> {
 }
 
 pub fn construct_changelog_html(
-  cl: &Changelog, proj: ProjLine, new_vers: &str, old_content: String, tmpl: String
+  cl: &Changelog, proj: ProjLine, new_vers: &str, links: &ReleaseLinks, old_content: String, tmpl: String
 ) -> Result<String> {
   let tmpl = ParserBuilder::with_stdlib().build()?.parse(&tmpl)?;
   let nowymd = Utc::now().format("%Y-%m-%d").to_string();
 
+  let mut globals = release_globals(cl, proj, new_vers, links);
+  globals.insert("old_content".into(), Value::scalar(old_content));
+  globals.insert("content_marker".into(), Value::scalar(format!("CONTENT {}", nowymd)));
+
+  Ok(tmpl.render(&globals)?)
+}
+
+/// Render the message of an annotated project tag, using the same `project` and `release` variables that are
+/// available to changelog templates.
+pub fn construct_tag_message(
+  cl: &Changelog, proj: ProjLine, new_vers: &str, links: &ReleaseLinks, tmpl: &str
+) -> Result<String> {
+  let tmpl = ParserBuilder::with_stdlib().build()?.parse(tmpl)?;
+  Ok(tmpl.render(&release_globals(cl, proj, new_vers, links))?)
+}
+
+/// Verify that a template parses, and renders using only the variables that are available to changelog
+/// templates. The template is rendered against sample data, so a variable that's only used inside a tag that
+/// never renders (such as an `if` that the sample data doesn't satisfy) isn't checked.
+pub fn validate_template(tmpl: &str) -> Result<()> {
+  let tmpl = ParserBuilder::with_stdlib().build()?.parse(tmpl)?;
+  tmpl.render(&sample_globals())?;
+  Ok(())
+}
+
+/// Render the release notes of every project released at once, using the `date` and a `projects` list of the
+/// same variables that are available to changelog templates. The `date` is formatted as the first project's.
+pub fn construct_release_notes(
+  releases: Vec<(&Changelog, ProjLine, &str, ReleaseLinks)>, tmpl: &str
+) -> Result<String> {
+  let tmpl = ParserBuilder::with_stdlib().build()?.parse(tmpl)?;
+  let nowymd = releases.first().map(|(.., links)| links.dates.now()).unwrap_or_else(|| DateFormat::default().now());
+  let projects: Vec<_> =
+    releases.into_iter().map(|(cl, proj, new_vers, links)| release_globals(cl, proj, new_vers, &links)).collect();
+  Ok(tmpl.render(&liquid::object!({ "date": nowymd, "projects": projects }))?)
+}
+
+/// The same variables as `release_globals` (plus `old_content` and `content_marker`, and the `date` and
+/// `projects` of release notes), with sample values.
+fn sample_globals() -> Object {
+  let mut globals = liquid::object!({
+    "project": {
+      "id": "1",
+      "name": "project",
+      "tag_prefix": "project",
+      "tag_prefix_separator": "-",
+      "version": "1.1.0",
+      "full_version": "project-v1.1.0",
+      "root": "project"
+    },
+    "release": {
+      "date": "2020-01-01",
+      "prs": [{
+        "title": "Add a feature",
+        "body": "Adds a feature.\n\n## Changelog\n\nA new feature.",
+        "notes": "A new feature.",
+        "notes_json": "\"A new feature.\"",
+        "name": "PR 1",
+        "size": "minor",
+        "href": "https://github.com/owner/repo/pull/1",
+        "link": true,
+        "closed_at": "2020-01-01",
+        "commits": [{
+          "href": "https://github.com/owner/repo/commit/0123456789abcdef0123456789abcdef01234567",
+          "link": true,
+          "shorthash": "0123456",
+          "size": "minor",
+          "summary": "feat: add a feature (PROJ-1)",
+          "linked_summary": "feat: add a feature (<a href=\"https://jira.example.com/browse/PROJ-1\">PROJ-1</a>)",
+          "issues": [{
+            "id": "PROJ-1",
+            "href": "https://jira.example.com/browse/PROJ-1"
+          }],
+          "message": "feat: add a feature (PROJ-1)",
+          "notes": "",
+          "notes_json": "\"\""
+        }],
+        "also": [{
+          "id": "3",
+          "name": "other",
+          "version": "2.1.0"
+        }],
+        "collapsed": false
+      }],
+      "deps": [{
+        "id": "2",
+        "name": "dependency",
+        "prev_version": "1.0.0",
+        "version": "1.1.0",
+        "bump_text": "Bumped dependency 1.0.0 → 1.1.0."
+      }],
+      "version": "1.1.0",
+      "compare_href": "https://github.com/owner/repo/compare/project-v1.0.0...project-v1.1.0",
+      "compare_link": true
+    },
+    "old_content": "",
+    "content_marker": "CONTENT 2020-01-01"
+  });
+
+  let project = globals.get("project").cloned().unwrap_or(Value::Nil);
+  let release = globals.get("release").cloned().unwrap_or(Value::Nil);
+  globals.insert("date".into(), Value::scalar("2020-01-01"));
+  globals.insert(
+    "projects".into(),
+    Value::Array(vec![Value::Object(liquid::object!({ "project": project, "release": release }))])
+  );
+  globals
+}
+
+fn release_globals(cl: &Changelog, proj: ProjLine, new_vers: &str, links: &ReleaseLinks) -> Object {
   let pr_count = cl
     .entries()
     .iter()
@@ -44,6 +366,7 @@ pub fn construct_changelog_html(
 
   let mut prs = Vec::new();
   let mut dps = Vec::new();
+  let mut contributors: Vec<&Contributor> = Vec::new();
 
   for entry in cl.entries() {
     match entry {
@@ -54,16 +377,52 @@ pub fn construct_changelog_html(
 
         let mut commits = Vec::new();
         for c in pr.commits().iter().filter(|c| c.included()) {
+          let issues: Vec<_> = links
+            .issues
+            .map(|i| i.find(c.summary()))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(id, href)| liquid::object!({ "id": id, "href": href }))
+            .collect();
+          let linked_summary = links.issues.map(|i| i.link(c.summary())).unwrap_or_else(|| c.summary().to_string());
+          let notes = if links.notes { changelog_notes(c.message()) } else { String::new() };
+          for person in c.contributors() {
+            if !contributors.iter().any(|p| p.same_as(person)) {
+              contributors.push(person);
+            }
+          }
+          let authors: Vec<_> = c
+            .contributors()
+            .iter()
+            .map(|p| liquid::object!({ "name": p.name(), "email": p.email() }))
+            .collect();
           commits.push(liquid::object!({
             "href": c.url().as_deref().unwrap_or(""),
             "link": c.url().is_some(),
             "shorthash": c.oid()[.. 7].to_string(),
             "size": c.size().to_string(),
             "summary": c.summary(),
-            "message": c.message().trim()
+            "linked_summary": linked_summary,
+            "issues": issues,
+            "message": c.message().trim(),
+            "notes_json": json_string(&notes),
+            "notes": notes,
+            "authors": authors
           }));
         }
 
+        let (also, collapsed) = match links.cross.get(&pr.number()).filter(|_| links.shared != SharedPrs::Full) {
+          Some(shared) => {
+            let also: Vec<_> = shared
+              .also
+              .iter()
+              .map(|c| liquid::object!({ "id": c.id.to_string(), "name": c.name, "version": c.version }))
+              .collect();
+            (also, links.shared == SharedPrs::Collapse && shared.collapsed)
+          }
+          None => (Vec::new(), false)
+        };
+
         let pr_name = if pr.number() == 0 {
           if pr_count == 1 {
             "Commits".to_string()
@@ -73,26 +432,39 @@ pub fn construct_changelog_html(
         } else {
           format!("PR {}", pr.number())
         };
+        let notes = if links.notes { changelog_notes(pr.body()) } else { String::new() };
 
         prs.push(liquid::object!({
           "title": pr.title(),
+          "body": pr.body().trim(),
+          "notes_json": json_string(&notes),
+          "notes": notes,
           "name": pr_name,
           "size": size.to_string(),
           "href": pr.url().as_deref().unwrap_or(""),
           "link": pr.number() > 0 && pr.url().is_some(),
-          "commits": commits
+          "closed_at": links.dates.format(pr.closed_at()),
+          "commits": commits,
+          "also": also,
+          "collapsed": collapsed
         }));
       }
-      ChangelogEntry::Dep(proj_id, name) => {
+      ChangelogEntry::Dep(proj_id, name, bump) => {
         dps.push(liquid::object!({
           "id": proj_id.to_string(),
-          "name": name
+          "name": name,
+          "prev_version": bump.prev().unwrap_or(""),
+          "version": bump.next().unwrap_or(""),
+          "bump_text": bump.text(name)
         }));
       }
     }
   }
 
-  let globals = liquid::object!({
+  let credits: Vec<_> =
+    contributors.iter().map(|p| liquid::object!({ "name": p.name(), "email": p.email() })).collect();
+
+  liquid::object!({
     "project": {
       "id": proj.id.to_string(),
       "name": proj.name,
@@ -103,26 +475,24 @@ pub fn construct_changelog_html(
       "root": proj.root.unwrap_or_default(),
     },
     "release": {
-      "date": nowymd,
+      "date": links.dates.now(),
       "prs": prs,
       "deps": dps,
-      "version": new_vers
-    },
-    "old_content": old_content,
-    "content_marker": format!("CONTENT {}", nowymd)
-  });
-
-  Ok(tmpl.render(&globals)?)
+      "contributors": credits,
+      "version": new_vers,
+      "compare_href": links.compare.as_deref().unwrap_or(""),
+      "compare_link": links.compare.is_some()
+    }
+  })
 }
 
 pub async fn read_template(tmpl_url: &str, base_path: Option<&Path>, forward_slash: bool) -> Result<String> {
   let parts: Vec<_> = tmpl_url.splitn(2, ':').collect();
   if parts.len() > 1 {
     match parts[0] {
-      "builtin" => match parts[1] {
-        "html" => Ok(include_str!("tmpl/changelog.liquid").to_string()),
-        "json" => Ok(include_str!("tmpl/json.liquid").to_string()),
-        _ => bail!("Unknown builtin template: {}", parts[1])
+      "builtin" => match BUILTIN_TEMPLATES.iter().find(|(name, ..)| *name == parts[1]) {
+        Some((_, _, content)) => Ok(content.to_string()),
+        None => bail!("Unknown builtin template: {}", parts[1])
       },
       "file" => {
         let path = if forward_slash { PathBuf::from_slash(parts[1]) } else { PathBuf::from(parts[1]) };
@@ -146,3 +516,154 @@ pub async fn read_template(tmpl_url: &str, base_path: Option<&Path>, forward_sla
     bail!("Template URL has no protocol: {}", tmpl_url);
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::{changelog_notes, construct_changelog_html, construct_tag_message, DateFormat, DateZone, IssueLinks,
+              ReleaseLinks, BUILTIN_TEMPLATES, DEFAULT_ISSUE_PATTERN};
+  use crate::config::{ProjectId, Size};
+  use crate::git::Contributor;
+  use crate::mono::{Changelog, LoggedCommit, LoggedPr};
+  use crate::output::ProjLine;
+  use chrono::{DateTime, FixedOffset};
+
+  fn proj_line() -> ProjLine {
+    ProjLine {
+      id: ProjectId::from_id(1),
+      name: "proj".into(),
+      tag_prefix: None,
+      tag_prefix_separator: "-".into(),
+      version: "1.0.0".into(),
+      full_version: None,
+      root: None
+    }
+  }
+
+  fn commit(oid: &str, message: &str, people: &[&str]) -> LoggedCommit {
+    let people = people.iter().filter_map(|p| Contributor::parse(p)).collect();
+    let summary = message.lines().next().unwrap_or_default().to_string();
+    LoggedCommit::new(oid.into(), summary, message.into(), Size::Patch, None, people).with_scoped(true)
+  }
+
+  fn pr(number: u32, body: &str, commits: Vec<LoggedCommit>) -> LoggedPr {
+    let closed_at = DateTime::parse_from_rfc3339("2020-01-31T12:00:00Z").unwrap();
+    let mut pr = LoggedPr::new(number, format!("PR {}", number), body.into(), closed_at, 0, None);
+    for commit in commits {
+      pr.add_commit(commit);
+    }
+    pr
+  }
+
+  #[test]
+  fn test_contributors_dedup() {
+    let mut cl = Changelog::empty();
+    let commits = vec![
+      commit("1111111a", "fix: one", &["Ann <ann@example.com>", "Bob <bob@example.com>"]),
+      commit("2222222b", "fix: two", &["ann <ANN@example.com>"])
+    ];
+    cl.add_entry(pr(1, "", commits), Size::Patch);
+    cl.add_entry(pr(2, "", vec![commit("3333333c", "fix: three", &["Bob <bob@example.com>", "Cy <>"])]), Size::Patch);
+
+    let tmpl = "{% for c in release.contributors %}{{ c.name }} <{{ c.email }}>;{% endfor %}";
+    let credits = construct_tag_message(&cl, proj_line(), "1.0.1", &ReleaseLinks::default(), tmpl).unwrap();
+    assert_eq!(credits, "Ann <ann@example.com>;Bob <bob@example.com>;Cy <>;");
+  }
+
+  #[test]
+  fn test_changelog_notes() {
+    let body = "Some work.\n\n## Changelog\n\nFixed the \"thing\".\n\n### Details\n\nMore.\n\n## Testing\n\nRan it.";
+    assert_eq!(changelog_notes(body), "Fixed the \"thing\".\n\n### Details\n\nMore.");
+    assert_eq!(changelog_notes("### changelog\nShort.\n# Next\nNo."), "Short.");
+    assert_eq!(changelog_notes("Some work.\n\n#Changelog\n\nNot a heading."), "");
+    assert_eq!(changelog_notes("No notes here."), "");
+  }
+
+  #[test]
+  fn test_json_notes() {
+    let mut cl = Changelog::empty();
+    let body = "Some work.\n\n## Changelog\n\nFixed the \"thing\".\nAnd a C:\\ path.";
+    cl.add_entry(pr(1, body, vec![commit("1111111a", "fix: one", &["Ann <ann@example.com>"])]), Size::Patch);
+
+    let tmpl = BUILTIN_TEMPLATES.iter().find(|(name, ..)| *name == "json").unwrap().2.to_string();
+    let links = ReleaseLinks::default().with_notes(true);
+    let rendered = construct_changelog_html(&cl, proj_line(), "1.0.1", &links, String::new(), tmpl).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+    assert_eq!(json["release"]["prs"][0]["notes"], "Fixed the \"thing\".\nAnd a C:\\ path.");
+    assert_eq!(json["release"]["prs"][0]["commits"][0]["notes"], "");
+  }
+
+  #[test]
+  fn test_issue_links() {
+    let jira = IssueLinks::new("https://jira.example.com/browse/{id}".into(), DEFAULT_ISSUE_PATTERN).unwrap();
+    let hash = IssueLinks::new("https://example.com/issues/{id}".into(), r"#([0-9]+)").unwrap();
+    let a = |href: &str, text: &str| format!("<a href=\"{}\">{}</a>", href, text);
+
+    // The links, a summary, the issues found in it, and the linked summary.
+    type Case<'a> = (&'a IssueLinks, &'a str, &'a [(&'a str, &'a str)], String);
+    let cases: &[Case] = &[
+      (&jira, "fix: a bug", &[], "fix: a bug".into()),
+      (&jira, "fix: lowercase proj-1", &[], "fix: lowercase proj-1".into()),
+      (&jira, "fix: no number PROJ-", &[], "fix: no number PROJ-".into()),
+      (&jira, "fix: embedded XPROJ-1x", &[], "fix: embedded XPROJ-1x".into()),
+      (
+        &jira,
+        "fix: a bug (PROJ-12)",
+        &[("PROJ-12", "https://jira.example.com/browse/PROJ-12")],
+        format!("fix: a bug ({})", a("https://jira.example.com/browse/PROJ-12", "PROJ-12"))
+      ),
+      (
+        &jira,
+        "A_1-2 and B2-3",
+        &[("A_1-2", "https://jira.example.com/browse/A_1-2"), ("B2-3", "https://jira.example.com/browse/B2-3")],
+        format!(
+          "{} and {}",
+          a("https://jira.example.com/browse/A_1-2", "A_1-2"),
+          a("https://jira.example.com/browse/B2-3", "B2-3")
+        )
+      ),
+      (&hash, "fix: issue 12", &[], "fix: issue 12".into()),
+      (
+        &hash,
+        "fix: closes #12",
+        &[("12", "https://example.com/issues/12")],
+        format!("fix: closes {}", a("https://example.com/issues/12", "#12"))
+      )
+    ];
+
+    for (links, summary, found, linked) in cases {
+      let expected: Vec<_> = found.iter().map(|(id, href)| (*id, href.to_string())).collect();
+      assert_eq!(links.find(summary), expected, "{}", summary);
+      assert_eq!(&links.link(summary), linked, "{}", summary);
+    }
+
+    assert!(IssueLinks::new("https://example.com/issues".into(), DEFAULT_ISSUE_PATTERN).is_err());
+    assert!(IssueLinks::new("https://example.com/issues/{id}".into(), "(").is_err());
+  }
+
+  #[test]
+  fn test_date_zone_parse() {
+    let east = |secs| DateZone::Fixed(FixedOffset::east_opt(secs).unwrap());
+    assert_eq!(DateZone::parse("utc").unwrap(), DateZone::Utc);
+    assert_eq!(DateZone::parse(" UTC ").unwrap(), DateZone::Utc);
+    assert_eq!(DateZone::parse("Z").unwrap(), DateZone::Utc);
+    assert_eq!(DateZone::parse("Local").unwrap(), DateZone::Local);
+    assert_eq!(DateZone::parse("+05:30").unwrap(), east(5 * 3600 + 30 * 60));
+    assert_eq!(DateZone::parse("-08:00").unwrap(), east(-8 * 3600));
+
+    assert!(DateZone::parse("EST").is_err());
+    assert!(DateZone::parse("").is_err());
+    assert!(DateZone::parse("05:30").is_err());
+  }
+
+  #[test]
+  fn test_date_format_new() {
+    let time = DateTime::parse_from_rfc3339("2021-03-04T20:00:00Z").unwrap();
+    let zone = DateZone::parse("+05:30").unwrap();
+    assert_eq!(DateFormat::new("%Y-%m-%d".into(), DateZone::Utc).unwrap().format(&time), "2021-03-04");
+    assert_eq!(DateFormat::new("%Y-%m-%d %H:%M".into(), zone).unwrap().format(&time), "2021-03-05 01:30");
+    assert_eq!(DateFormat::new("%B %-d, %Y".into(), DateZone::Utc).unwrap().format(&time), "March 4, 2021");
+
+    assert!(DateFormat::new("%Y-%Q".into(), DateZone::Utc).is_err());
+    assert!(DateFormat::new("%Y-%m-%".into(), DateZone::Utc).is_err());
+  }
+}
@@ -0,0 +1,218 @@
+//! Validation of a config file against the JSON schema, for `schema --check`.
+
+use crate::config::{preprocess_config, ConfigFile};
+use crate::errors::Result;
+use jsonschema::JSONSchema;
+use schemars::schema_for;
+use std::collections::HashMap;
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::Marker;
+
+/// A single way in which a config file doesn't match the schema.
+pub struct SchemaError {
+  line: usize,
+  column: usize,
+  path: String,
+  message: String
+}
+
+impl SchemaError {
+  /// The one-based line of the value that doesn't match.
+  pub fn line(&self) -> usize { self.line }
+
+  /// The one-based column of the value that doesn't match.
+  pub fn column(&self) -> usize { self.column }
+
+  /// The JSON pointer to the value that doesn't match, or "/" for the whole file.
+  pub fn path(&self) -> &str { &self.path }
+  pub fn message(&self) -> &str { &self.message }
+}
+
+/// Validate the YAML config data against the config schema, returning every error found.
+pub fn validate_config(data: &str) -> Result<Vec<SchemaError>> {
+  let mut yaml: serde_yaml::Value = match serde_yaml::from_str(data) {
+    Ok(yaml) => yaml,
+    Err(e) => {
+      let (line, column) = e.location().map(|l| (l.line(), l.column())).unwrap_or((1, 1));
+      return Ok(vec![SchemaError { line, column, path: "/".into(), message: e.to_string() }]);
+    }
+  };
+
+  // The schema describes the config as it's deserialized: migrated, and with its project defaults merged.
+  if let Err(e) = preprocess_config(&mut yaml) {
+    return Ok(vec![SchemaError { line: 1, column: 1, path: "/".into(), message: format!("{:#}", e) }]);
+  }
+
+  let instance = serde_json::to_value(&yaml)?;
+  let schema = serde_json::to_value(schema_for!(ConfigFile))?;
+  let compiled = JSONSchema::compile(&schema).map_err(|e| bad!("Invalid config schema: {}", e))?;
+  let positions = Positions::read(data)?;
+
+  let errors = match compiled.validate(&instance) {
+    Ok(()) => return Ok(Vec::new()),
+    Err(errors) => errors
+  };
+
+  let mut found: Vec<SchemaError> = errors
+    .map(|error| {
+      let path = error.instance_path.clone().into_vec();
+      let (line, column) = positions.nearest(&path);
+      SchemaError { line, column, path: format!("/{}", path.join("/")), message: error.to_string() }
+    })
+    .collect();
+  found.sort_by_key(|e| (e.line, e.column));
+  Ok(found)
+}
+
+/// The one-based line and column where each node of a YAML document starts, by its path.
+struct Positions {
+  starts: HashMap<Vec<String>, (usize, usize)>
+}
+
+impl Positions {
+  fn read(data: &str) -> Result<Positions> {
+    let mut rcvr = Receiver { starts: HashMap::new(), stack: Vec::new() };
+    Parser::new(data.chars()).load(&mut rcvr, false)?;
+    Ok(Positions { starts: rcvr.starts })
+  }
+
+  /// The position of the node at the path, or of its closest ancestor that exists.
+  fn nearest(&self, path: &[String]) -> (usize, usize) {
+    (0 ..= path.len()).rev().find_map(|n| self.starts.get(&path[.. n]).copied()).unwrap_or((1, 1))
+  }
+}
+
+enum Frame {
+  Map { path: Vec<String>, key: Option<String> },
+  Seq { path: Vec<String>, index: usize }
+}
+
+struct Receiver {
+  starts: HashMap<Vec<String>, (usize, usize)>,
+  stack: Vec<Frame>
+}
+
+impl Receiver {
+  /// The path of the next value node, or `None` if the next node is a mapping key.
+  fn next_path(&mut self, key: Option<&str>) -> Option<Vec<String>> {
+    match self.stack.last_mut() {
+      None => Some(Vec::new()),
+      Some(Frame::Map { key: next @ None, .. }) => {
+        *next = Some(key.unwrap_or_default().to_string());
+        None
+      }
+      Some(Frame::Map { path, key: Some(key) }) => Some(path.iter().cloned().chain(Some(key.clone())).collect()),
+      Some(Frame::Seq { path, index }) => Some(path.iter().cloned().chain(Some(index.to_string())).collect())
+    }
+  }
+
+  /// Move past the value node that was just completed.
+  fn advance(&mut self) {
+    match self.stack.last_mut() {
+      Some(Frame::Map { key, .. }) => *key = None,
+      Some(Frame::Seq { index, .. }) => *index += 1,
+      None => ()
+    }
+  }
+}
+
+impl MarkedEventReceiver for Receiver {
+  fn on_event(&mut self, ev: Event, mark: Marker) {
+    let key = match &ev {
+      Event::Scalar(val, ..) => Some(val.as_str()),
+      _ => None
+    };
+
+    match &ev {
+      Event::MappingStart(_) | Event::SequenceStart(_) | Event::Scalar(..) | Event::Alias(_) => {
+        let path = match self.next_path(key) {
+          Some(path) => path,
+          None => return
+        };
+        self.starts.entry(path.clone()).or_insert((mark.line(), mark.col() + 1));
+        match &ev {
+          Event::MappingStart(_) => self.stack.push(Frame::Map { path, key: None }),
+          Event::SequenceStart(_) => self.stack.push(Frame::Seq { path, index: 0 }),
+          _ => self.advance()
+        }
+      }
+      Event::MappingEnd | Event::SequenceEnd => {
+        self.stack.pop();
+        self.advance();
+      }
+      _ => ()
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::validate_config;
+
+  #[test]
+  fn test_validate_position() {
+    let config = r#"
+projects:
+  - name: proj
+    id: 1
+    labels: { kind: npm }
+    version:
+      file: "package.json"
+      json: "version"
+"#;
+
+    let errors = validate_config(config).unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].path(), "/projects/0/labels");
+    assert_eq!((errors[0].line(), errors[0].column()), (5, 13));
+  }
+
+  #[test]
+  fn test_validate_ok() {
+    let config = r#"
+projects:
+  - name: proj
+    id: 1
+    version:
+      file: "package.json"
+      json: "version"
+"#;
+
+    assert!(validate_config(config).unwrap().is_empty());
+  }
+
+  #[test]
+  fn test_validate_defaults() {
+    let config = r#"
+project_defaults:
+  version:
+    file: "package.json"
+    json: "version"
+projects:
+  - name: proj
+    id: 1
+    root: proj
+  - name: other
+    id: 2
+    labels: { kind: npm }
+"#;
+
+    let errors = validate_config(config).unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].path(), "/projects/1/labels");
+    assert_eq!((errors[0].line(), errors[0].column()), (12, 13));
+  }
+
+  #[test]
+  fn test_validate_old_version() {
+    let config = r#"
+projects:
+  - name: proj
+    id: 1
+    version:
+      cmd: "poetry version -s"
+"#;
+
+    assert!(validate_config(config).unwrap().is_empty());
+  }
+}
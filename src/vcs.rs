@@ -4,22 +4,138 @@ use crate::bail;
 use crate::errors::Result;
 use crate::git::Repo;
 use std::cmp::{max, min};
+use std::num::NonZeroUsize;
 use std::str::FromStr;
 use tracing::debug;
 
-#[derive(PartialEq, PartialOrd, Eq, Ord, Clone, Copy, Debug)]
+#[derive(PartialEq, PartialOrd, Eq, Ord, Clone, Debug)]
 pub struct VcsState {
   level: VcsLevel,
-  ignore_current: bool
+  ignore_current: bool,
+  allow_dirty: bool,
+  strict_prev: bool,
+  remote: Option<String>,
+  offline: bool,
+  no_cache: bool,
+  jobs: Option<NonZeroUsize>
 }
 
 impl VcsState {
-  pub fn new(level: VcsLevel, ignore_current: bool) -> VcsState { VcsState { level, ignore_current } }
+  pub fn new(level: VcsLevel, ignore_current: bool) -> VcsState {
+    VcsState {
+      level,
+      ignore_current,
+      allow_dirty: false,
+      strict_prev: false,
+      remote: None,
+      offline: false,
+      no_cache: false,
+      jobs: None
+    }
+  }
+
   pub fn level(&self) -> &VcsLevel { &self.level }
   pub fn ignore_current(&self) -> bool { self.ignore_current }
+  pub fn allow_dirty(&self) -> bool { self.allow_dirty }
+  pub fn strict_prev(&self) -> bool { self.strict_prev }
+  pub fn remote(&self) -> Option<&str> { self.remote.as_deref() }
+  pub fn offline(&self) -> bool { self.offline }
+  pub fn no_cache(&self) -> bool { self.no_cache }
+  pub fn jobs(&self) -> Option<NonZeroUsize> { self.jobs }
+
+  /// Only warn about local modifications and untracked files, regardless of the configured `commit.clean`.
+  pub fn with_allow_dirty(mut self, allow_dirty: bool) -> VcsState {
+    self.allow_dirty = allow_dirty;
+    self
+  }
+
+  /// Fail if the prev tag annotation is malformed, instead of using only the versions from the project tags.
+  pub fn with_strict_prev(mut self, strict_prev: bool) -> VcsState {
+    self.strict_prev = strict_prev;
+    self
+  }
+
+  /// Use the named git remote, instead of the one from the environment or the config.
+  pub fn with_remote(mut self, remote: Option<String>) -> VcsState {
+    self.remote = remote;
+    self
+  }
+
+  /// Queue pushes instead of making them, and read fetched URLs only from the cache.
+  pub fn with_offline(mut self, offline: bool) -> VcsState {
+    self.offline = offline;
+    self
+  }
+
+  /// Always ask the API for PRs, instead of re-using the responses cached by earlier runs.
+  pub fn with_no_cache(mut self, no_cache: bool) -> VcsState {
+    self.no_cache = no_cache;
+    self
+  }
+
+  /// Run the given number of things at once, instead of the number of CPUs.
+  pub fn with_jobs(mut self, jobs: Option<NonZeroUsize>) -> VcsState {
+    self.jobs = jobs;
+    self
+  }
 }
 
-#[derive(Debug)]
+/// The VCS preferences given on the command line, which apply to every command.
+#[derive(Clone, Debug, Default)]
+pub struct VcsPrefs {
+  range: Option<VcsRange>,
+  strict_prev: bool,
+  remote: Option<String>,
+  offline: bool,
+  no_cache: bool,
+  jobs: Option<NonZeroUsize>
+}
+
+impl VcsPrefs {
+  pub fn new(range: Option<VcsRange>) -> VcsPrefs {
+    VcsPrefs { range, strict_prev: false, remote: None, offline: false, no_cache: false, jobs: None }
+  }
+
+  pub fn range(&self) -> Option<VcsRange> { self.range }
+  pub fn remote(&self) -> Option<&str> { self.remote.as_deref() }
+
+  pub fn with_strict_prev(mut self, strict_prev: bool) -> VcsPrefs {
+    self.strict_prev = strict_prev;
+    self
+  }
+
+  pub fn with_remote(mut self, remote: Option<String>) -> VcsPrefs {
+    self.remote = remote;
+    self
+  }
+
+  pub fn with_offline(mut self, offline: bool) -> VcsPrefs {
+    self.offline = offline;
+    self
+  }
+
+  pub fn with_no_cache(mut self, no_cache: bool) -> VcsPrefs {
+    self.no_cache = no_cache;
+    self
+  }
+
+  pub fn with_jobs(mut self, jobs: Option<NonZeroUsize>) -> VcsPrefs {
+    self.jobs = jobs;
+    self
+  }
+
+  /// The state to run a command at the given level, according to these preferences.
+  pub fn state(&self, level: VcsLevel, ignore_current: bool) -> VcsState {
+    VcsState::new(level, ignore_current)
+      .with_strict_prev(self.strict_prev)
+      .with_remote(self.remote.clone())
+      .with_offline(self.offline)
+      .with_no_cache(self.no_cache)
+      .with_jobs(self.jobs)
+  }
+}
+
+#[derive(Clone, Copy, Debug)]
 pub struct VcsRange {
   min: VcsLevel,
   max: VcsLevel
@@ -38,9 +154,12 @@ impl VcsRange {
     VcsRange::new(max(self.min(), other.min()), min(self.max(), other.max()))
   }
 
-  pub fn detect() -> Result<VcsRange> { Ok(VcsRange::new(VcsLevel::None, Repo::detect(".")?)) }
+  /// The range of levels that the current directory supports, using the given git remote if any.
+  pub fn detect(remote: Option<&str>) -> Result<VcsRange> {
+    Ok(VcsRange::new(VcsLevel::None, Repo::detect(".", remote)?))
+  }
 
-  pub fn detect_and_combine(pref: &VcsRange, reqd: &VcsRange) -> Result<VcsRange> {
+  pub fn detect_and_combine(pref: &VcsRange, reqd: &VcsRange, remote: Option<&str>) -> Result<VcsRange> {
     if pref.is_empty() {
       bail!("Preferred VCS {:?} is empty.", pref);
     } else if reqd.is_empty() {
@@ -56,7 +175,7 @@ impl VcsRange {
       }
     }
 
-    let dctd = VcsRange::detect()?;
+    let dctd = VcsRange::detect(remote)?;
     let i2 = i1.intersect(&dctd);
     if i2.is_empty() {
       bail!("Couldn't detect {:?} with preferred {:?} required {:?}", dctd, pref, reqd);
@@ -82,6 +201,31 @@ impl VcsLevel {
   pub fn is_network(&self) -> bool { matches!(self, Self::Remote | Self::Smart) }
 }
 
+/// The implementation that talks to the remote, and that creates signed commits and tags.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum VcsBackend {
+  /// Use libgit2 directly, signing with the key file at `versio.keypath`.
+  Libgit2,
+  /// Shell out to the system `git`, which signs with whatever the user has configured.
+  GitCli
+}
+
+impl VcsBackend {
+  pub fn is_git_cli(&self) -> bool { matches!(self, Self::GitCli) }
+}
+
+impl FromStr for VcsBackend {
+  type Err = crate::errors::Error;
+
+  fn from_str(v: &str) -> Result<VcsBackend> {
+    match v {
+      "libgit2" => Ok(VcsBackend::Libgit2),
+      "git-cli" => Ok(VcsBackend::GitCli),
+      other => err!("Illegal vcs backend \"{}\".", other)
+    }
+  }
+}
+
 impl FromStr for VcsLevel {
   type Err = crate::errors::Error;
 